@@ -1,34 +1,49 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
-    StdResult, SubMsgResult, Uint128,
+    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    SubMsgResult, Uint128,
 };
 use cw2::set_contract_version;
 use cw_controllers::{Admin, Hooks};
 use margined_common::validate::{
-    validate_decimal_places, validate_eligible_collateral, validate_margin_ratios, validate_ratio,
+    validate_decimal_places, validate_eligible_collateral, validate_margin_ratios,
+    validate_migration, validate_ratio,
+};
+use margined_perp::margined_engine::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, RiskParams,
 };
-use margined_perp::margined_engine::{ExecuteMsg, InstantiateMsg, QueryMsg};
 
 use crate::error::ContractError;
 use crate::{
     handle::{
-        close_position, deposit_margin, liquidate, open_position, pay_funding, update_config,
-        withdraw_margin,
+        auto_deleverage, batch, batch_step, cancel_order, close_position, deposit_collateral,
+        deposit_margin, execute_order, liquidate, liquidate_collateral, migrate_positions,
+        open_position, pay_funding, place_limit_order, place_trigger_order, receive_cw20,
+        register_collateral, remove_collateral, set_cross_margin, set_leverage_tiers,
+        set_risk_params, update_config, withdraw_collateral, withdraw_margin,
     },
     query::{
-        query_all_positions, query_config, query_cumulative_premium_fraction,
-        query_free_collateral, query_margin_ratio, query_pauser, query_position,
-        query_position_notional_unrealized_pnl, query_state,
-        query_trader_balance_with_funding_payment, query_trader_position_with_funding_payment,
+        query_account_summary, query_adl_ranking, query_all_positions,
+        query_all_positions_for_vamm, query_all_traders, query_collaterals, query_config,
+        query_cross_margin, query_cumulative_premium_fraction, query_free_collateral,
+        query_leverage_tiers, query_liquidatable_positions, query_liquidation_price,
+        query_margin_ratio, query_order, query_orders, query_pauser, query_position,
+        query_position_notional_unrealized_pnl, query_risk_params, query_state,
+        query_trade_history, query_trader_balance_with_funding_payment, query_trader_collateral,
+        query_trader_orders, query_trader_position_with_funding_payment, query_trader_stats,
     },
     reply::{
         close_position_reply, liquidate_reply, partial_close_position_reply,
         partial_liquidation_reply, pay_funding_reply, reverse_position_reply,
         update_position_reply,
     },
-    state::{store_config, store_state, Config, State},
+    simulate::{
+        query_simulate_close_position, query_simulate_deposit_margin, query_simulate_open_position,
+        query_simulate_withdraw_margin,
+    },
+    state::{migrate_config, store_config, store_state, Config, State},
     utils::{
-        add_whitelist, parse_pay_funding, parse_swap, remove_whitelist, set_pause, update_pauser,
+        add_whitelist, decode_reply_id, parse_pay_funding, parse_swap, remove_whitelist, set_pause,
+        update_pauser,
     },
 };
 
@@ -52,6 +67,10 @@ pub const PAY_FUNDING_REPLY_ID: u64 = 8;
 
 pub const TRANSFER_FAILURE_REPLY_ID: u64 = 9;
 
+/// Number of low bits of a reply id holding the reply kind, the remaining bits hold
+/// the nonce of the operation's reply context
+pub const REPLY_KIND_BITS: u64 = 8;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -94,6 +113,7 @@ pub fn instantiate(
         maintenance_margin_ratio: msg.maintenance_margin_ratio,
         partial_liquidation_ratio: Uint128::zero(), // set as zero by default
         liquidation_fee: msg.liquidation_fee,
+        keeper_fee: Uint128::zero(), // set as zero by default
     };
 
     store_config(deps.storage, &config)?;
@@ -114,8 +134,14 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
     match msg {
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
         ExecuteMsg::UpdateConfig {
             owner,
             insurance_fund,
@@ -124,6 +150,7 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             maintenance_margin_ratio,
             partial_liquidation_ratio,
             liquidation_fee,
+            keeper_fee,
         } => update_config(
             deps,
             info,
@@ -134,6 +161,7 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             maintenance_margin_ratio,
             partial_liquidation_ratio,
             liquidation_fee,
+            keeper_fee,
         ),
         ExecuteMsg::UpdatePauser { pauser } => update_pauser(deps, info, pauser),
         ExecuteMsg::AddWhitelist { address } => add_whitelist(deps, info, address),
@@ -169,11 +197,104 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             withdraw_margin(deps, env, info, vamm, amount)
         }
         ExecuteMsg::SetPause { pause } => set_pause(deps, env, info, pause),
+        ExecuteMsg::SetCrossMargin { enabled } => set_cross_margin(deps, info, enabled),
+        ExecuteMsg::PlaceLimitOrder {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+            trigger_price,
+            trigger_option,
+        } => place_limit_order(
+            deps,
+            env,
+            info,
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+            trigger_price,
+            trigger_option,
+        ),
+        ExecuteMsg::PlaceTriggerOrder {
+            vamm,
+            order_type,
+            quote_asset_limit,
+            trigger_price,
+            trigger_option,
+        } => place_trigger_order(
+            deps,
+            env,
+            info,
+            vamm,
+            order_type,
+            quote_asset_limit,
+            trigger_price,
+            trigger_option,
+        ),
+        ExecuteMsg::CancelOrder { vamm, order_id } => cancel_order(deps, info, vamm, order_id),
+        ExecuteMsg::ExecuteOrder { vamm, order_id } => {
+            execute_order(deps, env, info, vamm, order_id)
+        }
+        ExecuteMsg::MigratePositions { limit } => migrate_positions(deps, info, limit),
+        ExecuteMsg::Batch { ops } => batch(deps, env, info, ops),
+        ExecuteMsg::BatchStep { batch_id } => batch_step(deps, env, info, batch_id),
+        ExecuteMsg::RegisterCollateral {
+            asset,
+            weight,
+            pricefeed,
+            price_key,
+            price_decimals,
+        } => register_collateral(
+            deps,
+            info,
+            asset,
+            weight,
+            pricefeed,
+            price_key,
+            price_decimals,
+        ),
+        ExecuteMsg::RemoveCollateral { asset } => remove_collateral(deps, info, asset),
+        ExecuteMsg::DepositCollateral {} => deposit_collateral(deps, info),
+        ExecuteMsg::WithdrawCollateral { asset, amount } => {
+            withdraw_collateral(deps, info, asset, amount)
+        }
+        ExecuteMsg::LiquidateCollateral {
+            vamm,
+            trader,
+            asset,
+            amount,
+        } => liquidate_collateral(deps, env, info, vamm, trader, asset, amount),
+        ExecuteMsg::SetRiskParams {
+            vamm,
+            initial_margin_ratio,
+            maintenance_margin_ratio,
+            partial_liquidation_ratio,
+            liquidation_fee,
+        } => set_risk_params(
+            deps,
+            info,
+            vamm,
+            RiskParams {
+                initial_margin_ratio,
+                maintenance_margin_ratio,
+                partial_liquidation_ratio,
+                liquidation_fee,
+            },
+        ),
+        ExecuteMsg::SetLeverageTiers { vamm, tiers } => set_leverage_tiers(deps, info, vamm, tiers),
+        ExecuteMsg::AutoDeleverage {
+            vamm,
+            trader,
+            limit,
+        } => auto_deleverage(deps, env, vamm, trader, limit),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::State {} => to_binary(&query_state(deps)?),
@@ -207,101 +328,213 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::PositionWithFundingPayment { vamm, trader } => to_binary(
             &query_trader_position_with_funding_payment(deps, vamm, trader)?,
         ),
+        QueryMsg::IsCrossMargin { trader } => to_binary(&query_cross_margin(deps, trader)?),
+        QueryMsg::Order { vamm, order_id } => to_binary(&query_order(deps, vamm, order_id)?),
+        QueryMsg::Orders {
+            vamm,
+            start_after,
+            limit,
+        } => to_binary(&query_orders(deps, vamm, start_after, limit)?),
+        QueryMsg::TraderOrders {
+            vamm,
+            trader,
+            start_after,
+            limit,
+        } => to_binary(&query_trader_orders(
+            deps,
+            vamm,
+            trader,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::AllPositionsForVamm {
+            vamm,
+            start_after,
+            limit,
+        } => to_binary(&query_all_positions_for_vamm(
+            deps,
+            vamm,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::AllTraders { start_after, limit } => {
+            to_binary(&query_all_traders(deps, start_after, limit)?)
+        }
+        QueryMsg::LiquidatablePositions {
+            vamm,
+            start_after,
+            limit,
+        } => to_binary(&query_liquidatable_positions(
+            deps,
+            vamm,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::LiquidationPrice { vamm, trader } => {
+            to_binary(&query_liquidation_price(deps, vamm, trader)?)
+        }
+        QueryMsg::AccountSummary { trader } => to_binary(&query_account_summary(deps, trader)?),
+        QueryMsg::TradeHistory {
+            vamm,
+            trader,
+            start_after,
+            limit,
+        } => to_binary(&query_trade_history(
+            deps,
+            vamm,
+            trader,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::TraderStats { trader, vamm } => {
+            to_binary(&query_trader_stats(deps, trader, vamm)?)
+        }
+        QueryMsg::Collaterals {} => to_binary(&query_collaterals(deps)?),
+        QueryMsg::TraderCollateral { trader } => to_binary(&query_trader_collateral(deps, trader)?),
+        QueryMsg::RiskParams { vamm } => to_binary(&query_risk_params(deps, vamm)?),
+        QueryMsg::LeverageTiers { vamm } => to_binary(&query_leverage_tiers(deps, vamm)?),
+        QueryMsg::AdlRanking {
+            vamm,
+            trader,
+            start_after,
+            limit,
+        } => to_binary(&query_adl_ranking(deps, vamm, trader, start_after, limit)?),
+        QueryMsg::SimulateOpenPosition {
+            vamm,
+            trader,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+        } => to_binary(&query_simulate_open_position(
+            deps,
+            env,
+            vamm,
+            trader,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+        )?),
+        QueryMsg::SimulateClosePosition {
+            vamm,
+            trader,
+            quote_asset_limit,
+        } => to_binary(&query_simulate_close_position(
+            deps,
+            env,
+            vamm,
+            trader,
+            quote_asset_limit,
+        )?),
+        QueryMsg::SimulateDepositMargin {
+            vamm,
+            trader,
+            amount,
+        } => to_binary(&query_simulate_deposit_margin(deps, vamm, trader, amount)?),
+        QueryMsg::SimulateWithdrawMargin {
+            vamm,
+            trader,
+            amount,
+        } => to_binary(&query_simulate_withdraw_margin(deps, vamm, trader, amount)?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    // the reply id carries the nonce of the context of the operation, zero if there is none
+    let (nonce, id) = decode_reply_id(msg.id);
+
     match msg.result {
-        SubMsgResult::Ok(response) => match msg.id {
+        SubMsgResult::Ok(response) => match id {
             INCREASE_POSITION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response =
-                    update_position_reply(deps, env, input, output, INCREASE_POSITION_REPLY_ID)?;
+                let (input, output) = parse_swap(response)?;
+                let response = update_position_reply(
+                    deps,
+                    env,
+                    input,
+                    output,
+                    nonce,
+                    INCREASE_POSITION_REPLY_ID,
+                )?;
                 Ok(response)
             }
             DECREASE_POSITION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response =
-                    update_position_reply(deps, env, input, output, DECREASE_POSITION_REPLY_ID)?;
+                let (input, output) = parse_swap(response)?;
+                let response = update_position_reply(
+                    deps,
+                    env,
+                    input,
+                    output,
+                    nonce,
+                    DECREASE_POSITION_REPLY_ID,
+                )?;
                 Ok(response)
             }
             REVERSE_POSITION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response = reverse_position_reply(deps, env, input, output)?;
+                let (input, output) = parse_swap(response)?;
+                let response = reverse_position_reply(deps, env, input, output, nonce)?;
                 Ok(response)
             }
             CLOSE_POSITION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response = close_position_reply(deps, env, input, output)?;
+                let (input, output) = parse_swap(response)?;
+                let response = close_position_reply(deps, env, input, output, nonce)?;
                 Ok(response)
             }
             PARTIAL_CLOSE_POSITION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response = partial_close_position_reply(deps, env, input, output)?;
+                let (input, output) = parse_swap(response)?;
+                let response = partial_close_position_reply(deps, env, input, output, nonce)?;
                 Ok(response)
             }
             LIQUIDATION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response = liquidate_reply(deps, env, input, output)?;
+                let (input, output) = parse_swap(response)?;
+                let response = liquidate_reply(deps, env, input, output, nonce)?;
                 Ok(response)
             }
             PARTIAL_LIQUIDATION_REPLY_ID => {
-                let (input, output) = parse_swap(response).unwrap();
-                let response = partial_liquidation_reply(deps, env, input, output)?;
+                let (input, output) = parse_swap(response)?;
+                let response = partial_liquidation_reply(deps, env, input, output, nonce)?;
                 Ok(response)
             }
             PAY_FUNDING_REPLY_ID => {
-                let (premium_fraction, sender) = parse_pay_funding(response).unwrap();
+                let (premium_fraction, sender) = parse_pay_funding(response)?;
                 let response = pay_funding_reply(deps, env, premium_fraction, sender)?;
                 Ok(response)
             }
-            _ => Err(StdError::generic_err(format!(
-                "reply (id {:?}) invalid",
-                msg.id
-            ))),
-        },
-        SubMsgResult::Err(e) => match msg.id {
-            TRANSFER_FAILURE_REPLY_ID => Err(StdError::generic_err(format!(
-                "transfer failure - reply (id {:?})",
-                msg.id
-            ))),
-            INCREASE_POSITION_REPLY_ID => Err(StdError::generic_err(format!(
-                "increase position failure - reply (id {:?})",
-                msg.id
-            ))),
-            DECREASE_POSITION_REPLY_ID => Err(StdError::generic_err(format!(
-                "decrease position failure - reply (id {:?})",
-                msg.id
-            ))),
-            REVERSE_POSITION_REPLY_ID => Err(StdError::generic_err(format!(
-                "reverse position failure - reply (id {:?})",
-                msg.id
-            ))),
-            CLOSE_POSITION_REPLY_ID => Err(StdError::generic_err(format!(
-                "close position failure - reply (id {:?})",
-                msg.id
-            ))),
-            PARTIAL_CLOSE_POSITION_REPLY_ID => Err(StdError::generic_err(format!(
-                "partial close position failure - reply (id {:?})",
-                msg.id
-            ))),
-            LIQUIDATION_REPLY_ID => Err(StdError::generic_err(format!(
-                "liquidation failure - reply (id {:?})",
-                msg.id
-            ))),
-            PARTIAL_LIQUIDATION_REPLY_ID => Err(StdError::generic_err(format!(
-                "partial liquidation failure - reply (id {:?})",
-                msg.id
-            ))),
-            PAY_FUNDING_REPLY_ID => Err(StdError::generic_err(format!(
-                "funding payment failure - reply (id {:?})",
-                msg.id
-            ))),
-            _ => Err(StdError::generic_err(format!(
-                "reply (id {:?}) error {:?}",
-                msg.id, e
-            ))),
+            _ => Err(ContractError::InvalidReply { id: msg.id }),
         },
+        SubMsgResult::Err(_) => {
+            // returning an error reverts the whole transaction, the context stored
+            // for the operation is discarded along with it
+            let action = match id {
+                TRANSFER_FAILURE_REPLY_ID => "transfer",
+                INCREASE_POSITION_REPLY_ID => "increase position",
+                DECREASE_POSITION_REPLY_ID => "decrease position",
+                REVERSE_POSITION_REPLY_ID => "reverse position",
+                CLOSE_POSITION_REPLY_ID => "close position",
+                PARTIAL_CLOSE_POSITION_REPLY_ID => "partial close position",
+                LIQUIDATION_REPLY_ID => "liquidation",
+                PARTIAL_LIQUIDATION_REPLY_ID => "partial liquidation",
+                PAY_FUNDING_REPLY_ID => "funding payment",
+                _ => return Err(ContractError::InvalidReply { id: msg.id }),
+            };
+
+            Err(ContractError::ReplyFailure {
+                action: action.to_string(),
+                id,
+            })
+        }
     }
 }
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // transform any state stored in a previous layout
+    migrate_config(deps.storage)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
@@ -1,4 +1,6 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{DivideByZeroError, OverflowError, StdError, Uint128};
+use cw_controllers::{AdminError, HookError};
+use margined_common::integer::Integer;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +8,18 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    DivideByZero(#[from] DivideByZeroError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -14,4 +28,169 @@ pub enum ContractError {
 
     #[error("User position is zero")]
     UserPositionZero,
+
+    #[error("Margin engine is paused")]
+    Paused {},
+
+    #[error("vAMM {vamm} is not registered")]
+    VammNotRegistered { vamm: String },
+
+    #[error("vAMM {vamm} is not open")]
+    VammNotOpen { vamm: String },
+
+    #[error("Less than minimum base asset amount limit")]
+    BaseAssetBelowLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("Greater than maximum base asset amount limit")]
+    BaseAssetAboveLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("Less than minimum quote asset amount limit")]
+    QuoteAssetBelowLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("Greater than maximum quote asset amount limit")]
+    QuoteAssetAboveLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("No position found")]
+    NoPosition {},
+
+    #[error("Input must be non-zero")]
+    ZeroInput {},
+
+    #[error("Leverage must be greater than 1, got {leverage}")]
+    InvalidLeverage { leverage: Uint128 },
+
+    #[error("Insufficient margin, bad debt of {bad_debt}")]
+    InsufficientMargin { bad_debt: Uint128 },
+
+    #[error("Position is undercollateralized, margin ratio {margin_ratio} is below {required}")]
+    Undercollateralized {
+        margin_ratio: Integer,
+        required: Uint128,
+    },
+
+    #[error("Position is overcollateralized, margin ratio {margin_ratio} is above {maintenance}")]
+    Overcollateralized {
+        margin_ratio: Integer,
+        maintenance: Uint128,
+    },
+
+    #[error("Only one action allowed")]
+    OnlyOneAction {},
+
+    #[error("Insufficient collateral, {free_collateral} free but {amount} requested")]
+    InsufficientCollateral {
+        free_collateral: Integer,
+        amount: Uint128,
+    },
+
+    #[error("Cannot change margin mode with open positions")]
+    OpenPositionsExist {},
+
+    #[error("Open interest {open_interest} exceeds cap {cap}")]
+    OpenInterestCapExceeded {
+        open_interest: Uint128,
+        cap: Uint128,
+    },
+
+    #[error("Base asset holding {size} exceeds cap {cap}")]
+    BaseAssetHoldingCapExceeded { size: Uint128, cap: Uint128 },
+
+    #[error("Cannot close position - bad debt of {bad_debt}")]
+    BadDebt { bad_debt: Uint128 },
+
+    #[error("Limit orders must be placed with place_limit_order")]
+    InvalidTriggerOrder {},
+
+    #[error("Order trigger price not reached")]
+    OrderNotTriggered {},
+
+    #[error("Limit order cannot reverse an existing position")]
+    OrderReversesPosition {},
+
+    #[error("Order escrow {escrow} is below the required {required}")]
+    InsufficientOrderEscrow { escrow: Uint128, required: Uint128 },
+
+    #[error("Position direction has changed")]
+    PositionDirectionChanged {},
+
+    #[error("Batch must contain at least one operation")]
+    EmptyBatch {},
+
+    #[error("Token {token} is not the eligible collateral")]
+    IneligibleCollateral { token: String },
+
+    #[error("Collateral {asset} is not registered")]
+    CollateralNotRegistered { asset: String },
+
+    #[error("The eligible collateral cannot be registered as collateral")]
+    CollateralIsEligible {},
+
+    #[error("Exactly one collateral coin must be sent")]
+    InvalidCollateralFunds {},
+
+    #[error("Collateral balance of {balance} is below {amount}")]
+    InsufficientCollateralBalance { balance: Uint128, amount: Uint128 },
+
+    #[error("Collateral only backs cross margined accounts")]
+    NotCrossMargined {},
+
+    #[error("Maintenance margin ratio of vAMM {vamm} would exceed its initial margin ratio")]
+    InconsistentMarginRatios { vamm: String },
+
+    #[error("Leverage tiers must increase in notional and margin ratios")]
+    InvalidLeverageTiers {},
+
+    #[error("Position is not bankrupt")]
+    NotBankrupt {},
+
+    #[error("Insurance fund balance of {balance} covers the bad debt of {bad_debt}")]
+    InsuranceFundNotExhausted { balance: Uint128, bad_debt: Uint128 },
+
+    #[error("Insurance fund balance of {balance} cannot cover a shortfall of {shortfall}")]
+    InsuranceFundExhausted {
+        balance: Uint128,
+        shortfall: Uint128,
+    },
+
+    #[error("Sent funds are excessive")]
+    ExcessiveFunds {},
+
+    #[error("Sent funds are insufficient")]
+    InsufficientFunds {},
+
+    #[error("Order {order_id} not found")]
+    OrderNotFound { order_id: u64 },
+
+    #[error("No reply context for nonce {nonce}")]
+    MissingReplyContext { nonce: u64 },
+
+    #[error("No sent funds in reply context")]
+    MissingSentFunds {},
+
+    #[error("No liquidator in reply context")]
+    MissingLiquidator {},
+
+    #[error("Cannot parse swap")]
+    CannotParseSwap {},
+
+    #[error("No event found: {key}")]
+    EventNotFound { key: String },
+
+    #[error("{action} failure - reply (id {id})")]
+    ReplyFailure { action: String, id: u64 },
+
+    #[error("Invalid reply (id {id})")]
+    InvalidReply { id: u64 },
+}
+
+// queries and simulations share the checks of the handlers but have to return a `StdError`
+impl From<ContractError> for StdError {
+    fn from(error: ContractError) -> Self {
+        match error {
+            ContractError::Std(error) => error,
+            ContractError::Overflow(error) => error.into(),
+            ContractError::DivideByZero(error) => error.into(),
+            error => StdError::generic_err(error.to_string()),
+        }
+    }
 }
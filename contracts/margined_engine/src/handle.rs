@@ -1,7 +1,8 @@
 use cosmwasm_std::{
-    to_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, ReplyOn, Response, StdError, StdResult,
+    from_binary, to_binary, Addr, CosmosMsg, Deps, DepsMut, Env, MessageInfo, ReplyOn, Response,
     SubMsg, Uint128, WasmMsg,
 };
+use cw20::Cw20ReceiveMsg;
 
 use crate::{
     contract::{
@@ -9,32 +10,50 @@ use crate::{
         LIQUIDATION_REPLY_ID, PARTIAL_CLOSE_POSITION_REPLY_ID, PARTIAL_LIQUIDATION_REPLY_ID,
         PAY_FUNDING_REPLY_ID, REVERSE_POSITION_REPLY_ID,
     },
-    messages::{execute_transfer_from, withdraw},
-    querier::{
-        query_is_over_fluctuation_limit, query_vamm_output_amount, query_vamm_over_spread_limit,
-    },
-    query::{query_free_collateral, query_margin_ratio},
+    error::ContractError,
+    messages::{execute_collateral_transfer, execute_transfer, execute_transfer_from, withdraw},
+    querier::{query_is_over_fluctuation_limit, query_vamm_calc_fee, query_vamm_output_amount},
+    query::{calc_account_free_collateral, query_all_positions, query_free_collateral},
     state::{
-        read_config, read_position, read_state, store_config, store_position, store_sent_funds,
-        store_state, store_tmp_liquidator, store_tmp_swap, Config, SentFunds, State, TmpSwapInfo,
+        migrate_legacy_positions, next_batch_id, next_order_id, read_adl_scan, read_batch,
+        read_collateral, read_config, read_cross_margin, read_market_config, read_order,
+        read_position, read_position_config, read_state, read_trader_collateral, read_vamm_map,
+        read_vamm_positions, read_vamms_risk_params, remove_adl_scan, remove_batch,
+        remove_collateral as remove_stored_collateral, remove_order, remove_position,
+        store_adl_scan, store_batch, store_collateral, store_config, store_cross_margin,
+        store_new_reply_context, store_order, store_position, store_state, store_trade,
+        store_trader_collateral, store_vamm_map, Batch, Config, ReplyContext, SentFunds, State,
+        TmpSwapInfo,
     },
     utils::{
-        calc_remain_margin_with_funding_payment, direction_to_side, get_asset,
-        get_margin_ratio_calc_option, get_position, get_position_notional_unrealized_pnl,
-        position_to_side, require_additional_margin, require_bad_debt, require_insufficient_margin,
+        calc_adl_score, calc_collateral_value, calc_remain_margin_with_funding_payment,
+        calc_weighted_collateral_value, cancel_all_orders, direction_to_side, encode_reply_id,
+        get_asset, get_liquidation_margin_ratio, get_margin_account_positions, get_position,
+        get_position_notional_unrealized_pnl, is_order_triggered, is_partial_liquidation,
+        new_trade, position_to_side, require_bad_debt, require_insufficient_margin,
         require_non_zero_input, require_not_paused, require_not_restriction_mode,
-        require_position_not_zero, require_vamm, side_to_direction,
+        require_position_not_zero, require_valid_leverage, require_vamm, side_to_direction,
+        update_open_interest_notional,
     },
 };
 use margined_common::{
-    asset::{Asset, AssetInfo},
+    asset::{native_asset, token_asset, Asset, AssetInfo},
     integer::Integer,
-    validate::{validate_margin_ratios, validate_ratio},
+    validate::{validate_decimal_places, validate_margin_ratios, validate_ratio},
 };
 use margined_perp::margined_engine::{
-    PnlCalcOption, Position, PositionUnrealizedPnlResponse, Side,
+    CollateralAsset, Cw20HookMsg, EngineOp, ExecuteMsg as EngineExecuteMsg, LeverageTier, Order,
+    OrderType, PnlCalcOption, Position, PositionUnrealizedPnlResponse, RiskParams, Side, Trade,
+    TriggerPriceOption,
 };
-use margined_perp::margined_vamm::{Direction, ExecuteMsg};
+use margined_perp::margined_vamm::{CalcFeeResponse, Direction, ExecuteMsg};
+use margined_perp::querier::query_token_balance;
+
+const DEFAULT_MIGRATION_LIMIT: u32 = 30u32;
+const MAX_MIGRATION_LIMIT: u32 = 100u32;
+const DEFAULT_ADL_SCAN_LIMIT: u32 = 30u32;
+const MAX_ADL_SCAN_LIMIT: u32 = 100u32;
+const MAX_ADL_COUNTERPARTIES: usize = 10usize;
 
 #[allow(clippy::too_many_arguments)]
 pub fn update_config(
@@ -47,12 +66,13 @@ pub fn update_config(
     maintenance_margin_ratio: Option<Uint128>,
     partial_liquidation_ratio: Option<Uint128>,
     liquidation_fee: Option<Uint128>,
-) -> StdResult<Response> {
+    keeper_fee: Option<Uint128>,
+) -> Result<Response, ContractError> {
     let mut config = read_config(deps.storage)?;
 
     // check permission
     if info.sender != config.owner {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
 
     // change owner of engine
@@ -86,6 +106,24 @@ pub fn update_config(
         config.maintenance_margin_ratio = maintenance_margin_ratio;
     }
 
+    // vamms overriding one of the margin ratios must stay consistent with the other
+    if initial_margin_ratio.is_some() || maintenance_margin_ratio.is_some() {
+        for (vamm, risk_params) in read_vamms_risk_params(deps.storage)? {
+            let initial_margin_ratio = risk_params
+                .initial_margin_ratio
+                .unwrap_or(config.initial_margin_ratio);
+            let maintenance_margin_ratio = risk_params
+                .maintenance_margin_ratio
+                .unwrap_or(config.maintenance_margin_ratio);
+
+            if maintenance_margin_ratio > initial_margin_ratio {
+                return Err(ContractError::InconsistentMarginRatios {
+                    vamm: vamm.to_string(),
+                });
+            }
+        }
+    }
+
     // update partial liquidation ratio
     if let Some(partial_liquidation_ratio) = partial_liquidation_ratio {
         validate_ratio(partial_liquidation_ratio, config.decimals)?;
@@ -98,104 +136,138 @@ pub fn update_config(
         config.liquidation_fee = liquidation_fee;
     }
 
+    // update keeper fee paid for executing orders
+    if let Some(keeper_fee) = keeper_fee {
+        config.keeper_fee = keeper_fee;
+    }
+
     store_config(deps.storage, &config)?;
 
     Ok(Response::default().add_attribute("action", "update_config"))
 }
 
-// Opens a position
-#[allow(clippy::too_many_arguments)]
-pub fn open_position(
+/// Sets the risk parameters of a vamm, those not given fall back to the engine config
+pub fn set_risk_params(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
     vamm: String,
-    side: Side,
-    margin_amount: Uint128,
-    leverage: Uint128,
-    base_asset_limit: Uint128,
-) -> StdResult<Response> {
+    risk_params: RiskParams,
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
-    let state: State = read_state(deps.storage)?;
 
-    // validate address inputs
-    let vamm = deps.api.addr_validate(&vamm)?;
-    let trader = info.sender.clone();
+    // check permission
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    require_not_paused(state.pause)?;
+    let vamm = deps.api.addr_validate(&vamm)?;
     require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
-    require_not_restriction_mode(deps.storage, &vamm, &trader, env.block.height)?;
-    require_non_zero_input(margin_amount)?;
-    require_non_zero_input(leverage)?;
 
-    if leverage < config.decimals {
-        return Err(StdError::generic_err("Leverage must be greater than 1"));
+    for ratio in vec![
+        risk_params.initial_margin_ratio,
+        risk_params.maintenance_margin_ratio,
+        risk_params.partial_liquidation_ratio,
+        risk_params.liquidation_fee,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        validate_ratio(ratio, config.decimals)?;
     }
 
-    // calculate the margin ratio of new position wrt to leverage
-    let margin_ratio = config
-        .decimals
-        .checked_mul(config.decimals)?
-        .checked_div(leverage)?;
-    require_additional_margin(Integer::from(margin_ratio), config.initial_margin_ratio)?;
+    // the effective margin ratios must be consistent with each other
+    validate_margin_ratios(
+        risk_params
+            .initial_margin_ratio
+            .unwrap_or(config.initial_margin_ratio),
+        risk_params
+            .maintenance_margin_ratio
+            .unwrap_or(config.maintenance_margin_ratio),
+    )?;
 
-    // retrieves existing position or creates a new one
-    let position: Position = get_position(env, deps.storage, &vamm, &trader, side.clone());
+    let mut vamm_map = read_vamm_map(deps.storage, vamm.clone())?;
+    vamm_map.risk_params = risk_params;
+    store_vamm_map(deps.storage, vamm.clone(), &vamm_map)?;
 
-    // if direction and side are same way then increasing else we are reversing
-    let is_increase: bool = position.direction == Direction::AddToAmm && side == Side::Buy
-        || position.direction == Direction::RemoveFromAmm && side == Side::Sell;
+    Ok(Response::new()
+        .add_attribute("action", "set_risk_params")
+        .add_attribute("vamm", vamm))
+}
 
-    // calculate the position notional
-    let open_notional = margin_amount
-        .checked_mul(leverage)?
-        .checked_div(config.decimals)?;
+/// Sets the leverage tiers of a vamm, the margin ratios must step up with the notional
+pub fn set_leverage_tiers(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm: String,
+    tiers: Vec<LeverageTier>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
 
-    // check if the position is new or being increased, else position is being reversed
-    let msg: SubMsg = if is_increase {
-        internal_increase_position(vamm.clone(), side.clone(), open_notional, base_asset_limit)
-            .unwrap()
-    } else {
-        open_reverse_position(
-            &deps,
-            position.clone(),
-            side.clone(),
-            open_notional,
-            base_asset_limit,
-            false,
-            None,
-        )
-        .unwrap()
-    };
+    // check permission
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let PositionUnrealizedPnlResponse {
-        position_notional,
-        unrealized_pnl,
-    } = get_position_notional_unrealized_pnl(deps.as_ref(), &position, PnlCalcOption::SpotPrice)
-        .unwrap();
+    let vamm = deps.api.addr_validate(&vamm)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
 
-    store_tmp_swap(
-        deps.storage,
-        &TmpSwapInfo {
-            vamm: vamm.clone(),
-            trader: trader.clone(),
-            side,
-            margin_amount,
-            leverage,
-            open_notional,
-            position_notional,
-            unrealized_pnl,
-            margin_to_vault: Integer::zero(),
-            fees_paid: false,
-        },
-    )?;
+    for tier in tiers.iter() {
+        validate_ratio(tier.initial_margin_ratio, config.decimals)?;
+        validate_ratio(tier.maintenance_margin_ratio, config.decimals)?;
+        validate_margin_ratios(tier.initial_margin_ratio, tier.maintenance_margin_ratio)?;
+    }
 
-    store_sent_funds(
-        deps.storage,
-        &SentFunds {
+    for pair in tiers.windows(2) {
+        if pair[1].notional <= pair[0].notional
+            || pair[1].initial_margin_ratio < pair[0].initial_margin_ratio
+            || pair[1].maintenance_margin_ratio < pair[0].maintenance_margin_ratio
+        {
+            return Err(ContractError::InvalidLeverageTiers {});
+        }
+    }
+
+    let mut vamm_map = read_vamm_map(deps.storage, vamm.clone())?;
+    vamm_map.leverage_tiers = tiers;
+    store_vamm_map(deps.storage, vamm.clone(), &vamm_map)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_leverage_tiers")
+        .add_attribute("vamm", vamm))
+}
+
+// Opens a position
+#[allow(clippy::too_many_arguments)]
+pub fn open_position(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vamm: String,
+    side: Side,
+    margin_amount: Uint128,
+    leverage: Uint128,
+    base_asset_limit: Uint128,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = info.sender.clone();
+
+    let msg: SubMsg = internal_open_position(
+        deps.branch(),
+        env,
+        &vamm,
+        &trader,
+        side,
+        margin_amount,
+        leverage,
+        base_asset_limit,
+        false,
+        SentFunds {
             asset: get_asset(info, config.eligible_collateral),
             required: Uint128::zero(),
         },
+        None,
     )?;
 
     Ok(Response::new().add_submessage(msg).add_attributes(vec![
@@ -213,14 +285,15 @@ pub fn close_position(
     info: MessageInfo,
     vamm: String,
     quote_amount_limit: Uint128,
-) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+) -> Result<Response, ContractError> {
     let state: State = read_state(deps.storage)?;
 
     // validate address inputs
     let vamm = deps.api.addr_validate(&vamm)?;
     let trader = info.sender;
 
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
     // read the position for the trader from vamm
     let position = read_position(deps.storage, &vamm, &trader).unwrap();
 
@@ -273,19 +346,25 @@ pub fn close_position(
             )
             .unwrap();
 
-            store_tmp_swap(
+            let nonce = store_new_reply_context(
                 deps.storage,
-                &TmpSwapInfo {
-                    vamm: position.vamm.clone(),
-                    trader: position.trader.clone(),
-                    side: side.clone(),
-                    margin_amount: position.size.value,
-                    leverage: config.decimals,
-                    open_notional: partial_close_notional,
-                    position_notional,
-                    unrealized_pnl,
-                    margin_to_vault: Integer::zero(),
-                    fees_paid: false,
+                &ReplyContext {
+                    swap: TmpSwapInfo {
+                        vamm: position.vamm.clone(),
+                        trader: position.trader.clone(),
+                        side: side.clone(),
+                        margin_amount: position.size.value,
+                        leverage: config.decimals,
+                        open_notional: partial_close_notional,
+                        position_notional,
+                        unrealized_pnl,
+                        margin_to_vault: Integer::zero(),
+                        fees_paid: false,
+                        escrowed: false,
+                    },
+                    funds: None,
+                    liquidator: None,
+                    batch: None,
                 },
             )?;
 
@@ -295,10 +374,16 @@ pub fn close_position(
                 partial_close_notional,
                 Uint128::zero(),
                 true,
-                PARTIAL_CLOSE_POSITION_REPLY_ID,
+                encode_reply_id(nonce, PARTIAL_CLOSE_POSITION_REPLY_ID),
             )?
         } else {
-            internal_close_position(deps, &position, quote_amount_limit, CLOSE_POSITION_REPLY_ID)?
+            internal_close_position(
+                deps,
+                &position,
+                quote_amount_limit,
+                None,
+                CLOSE_POSITION_REPLY_ID,
+            )?
         };
 
     Ok(Response::new().add_submessage(msg).add_attributes(vec![
@@ -315,48 +400,47 @@ pub fn liquidate(
     vamm: String,
     trader: String,
     quote_asset_limit: Uint128,
-) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
-
+) -> Result<Response, ContractError> {
     // validate address inputs
     let vamm = deps.api.addr_validate(&vamm)?;
     let trader = deps.api.addr_validate(&trader)?;
 
-    // store the liquidator
-    store_tmp_liquidator(deps.storage, &info.sender)?;
-
-    // retrieve the existing margin ratio of the position
-    let mut margin_ratio = query_margin_ratio(deps.as_ref(), vamm.to_string(), trader.to_string())?;
+    let config: Config = read_market_config(deps.storage, &vamm)?;
 
-    if query_vamm_over_spread_limit(&deps.as_ref(), vamm.to_string())? {
-        let oracle_margin_ratio = get_margin_ratio_calc_option(
-            deps.as_ref(),
-            vamm.to_string(),
-            trader.to_string(),
-            PnlCalcOption::Oracle,
-        )?;
-
-        if oracle_margin_ratio.checked_sub(margin_ratio)? > Integer::zero() {
-            margin_ratio = oracle_margin_ratio
-        }
-    }
+    // retrieve the margin ratio of the position, using the oracle price if over the spread limit
+    let margin_ratio =
+        get_liquidation_margin_ratio(deps.as_ref(), vamm.to_string(), trader.to_string())?;
 
     require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
-    require_insufficient_margin(margin_ratio, config.maintenance_margin_ratio)?;
 
     // read the position for the trader from vamm
     let position = read_position(deps.storage, &vamm, &trader).unwrap();
 
+    // the maintenance margin ratio steps up with the leverage tier of the position
+    let tier_config = read_position_config(deps.storage, &vamm, position.notional)?;
+    require_insufficient_margin(margin_ratio, tier_config.maintenance_margin_ratio)?;
+
     // check the position isn't zero
     require_position_not_zero(position.size.value)?;
 
     // first see if this is a partial liquidation, else get rekt
-    let msg = if margin_ratio.value > config.liquidation_fee
-        && !config.partial_liquidation_ratio.is_zero()
-    {
-        partial_liquidation(deps, env, vamm.clone(), trader.clone(), quote_asset_limit)?
+    let msg = if is_partial_liquidation(&config, margin_ratio) {
+        partial_liquidation(
+            deps,
+            env,
+            vamm.clone(),
+            trader.clone(),
+            quote_asset_limit,
+            info.sender,
+        )?
     } else {
-        internal_close_position(deps, &position, quote_asset_limit, LIQUIDATION_REPLY_ID)?
+        internal_close_position(
+            deps,
+            &position,
+            quote_asset_limit,
+            Some(info.sender),
+            LIQUIDATION_REPLY_ID,
+        )?
     };
 
     Ok(Response::new().add_submessage(msg).add_attributes(vec![
@@ -372,7 +456,7 @@ pub fn pay_funding(
     _env: Env,
     _info: MessageInfo,
     vamm: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
 
     // validate address inputs
@@ -404,7 +488,7 @@ pub fn deposit_margin(
     info: MessageInfo,
     vamm: String,
     amount: Uint128,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let state: State = read_state(deps.storage)?;
 
@@ -414,30 +498,43 @@ pub fn deposit_margin(
     require_not_paused(state.pause)?;
     require_non_zero_input(amount)?;
 
-    // first try to execute the transfer
-    let mut response: Response = Response::new();
-    match config.eligible_collateral.clone() {
-        AssetInfo::NativeToken { .. } => {
-            let token = Asset {
-                info: config.eligible_collateral,
-                amount,
-            };
+    if let AssetInfo::NativeToken { .. } = config.eligible_collateral {
+        let token = Asset {
+            info: config.eligible_collateral,
+            amount,
+        };
 
-            token.assert_sent_native_token_balance(&info)?;
-        }
+        token.assert_sent_native_token_balance(&info)?;
+    }
 
-        AssetInfo::Token { .. } => {
-            let msg: SubMsg =
-                execute_transfer_from(deps.storage, &trader, &env.contract.address, amount)?;
-            response = response.clone().add_submessage(msg);
-        }
-    };
+    internal_deposit_margin(deps, env, vamm, trader, amount, false)
+}
+
+// Adds margin to the position, native tokens must have been checked by the caller and
+// if escrowed the tokens are already held by the engine
+fn internal_deposit_margin(
+    deps: DepsMut,
+    env: Env,
+    vamm: Addr,
+    trader: Addr,
+    amount: Uint128,
+    escrowed: bool,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    // first try to execute the transfer
+    let mut response: Response = Response::new();
+    if matches!(config.eligible_collateral, AssetInfo::Token { .. }) && !escrowed {
+        let msg: SubMsg =
+            execute_transfer_from(deps.storage, &trader, &env.contract.address, amount)?;
+        response = response.add_submessage(msg);
+    }
 
     // read the position for the trader from vamm
     let mut position = read_position(deps.storage, &vamm, &trader).unwrap();
 
     if position.trader != trader {
-        return Err(StdError::generic_err("No position found"));
+        return Err(ContractError::NoPosition {});
     }
 
     position.margin = position.margin.checked_add(amount)?;
@@ -458,7 +555,7 @@ pub fn withdraw_margin(
     info: MessageInfo,
     vamm: String,
     amount: Uint128,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let mut state: State = read_state(deps.storage)?;
 
@@ -471,7 +568,7 @@ pub fn withdraw_margin(
     require_non_zero_input(amount)?;
 
     // read the position for the trader from vamm
-    let mut position = read_position(deps.storage, &vamm, &trader).unwrap();
+    let mut position = read_position(deps.storage, &vamm, &trader)?;
 
     let remain_margin = calc_remain_margin_with_funding_payment(
         deps.as_ref(),
@@ -490,7 +587,10 @@ pub fn withdraw_margin(
         .checked_sub(Integer::new_positive(amount))?
         .is_negative()
     {
-        return Err(StdError::generic_err("Insufficient collateral"));
+        return Err(ContractError::InsufficientCollateral {
+            free_collateral,
+            amount,
+        });
     }
 
     // withdraw margin
@@ -502,8 +602,7 @@ pub fn withdraw_margin(
         config.eligible_collateral,
         amount,
         Uint128::zero(),
-    )
-    .unwrap();
+    )?;
 
     store_position(deps.storage, &position)?;
     store_state(deps.storage, &state)?;
@@ -515,124 +614,1444 @@ pub fn withdraw_margin(
     ]))
 }
 
-// Increase the position through a swap
-pub fn internal_increase_position(
-    vamm: Addr,
-    side: Side,
-    open_notional: Uint128,
-    base_asset_limit: Uint128,
-) -> StdResult<SubMsg> {
-    swap_input(
-        &vamm,
-        side,
-        open_notional,
-        base_asset_limit,
-        false,
-        INCREASE_POSITION_REPLY_ID,
-    )
-}
+/// Opens a position, deposits margin or executes a batch with the cw20 collateral
+/// sent, the amount received must exactly cover the margin and fees required
+pub fn receive_cw20(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let trader = deps.api.addr_validate(&cw20_msg.sender)?;
+    let hook: Cw20HookMsg = from_binary(&cw20_msg.msg)?;
+
+    // only the eligible collateral can be received, except for collateral deposits
+    // which are checked against the registry
+    let is_eligible = matches!(
+        &config.eligible_collateral,
+        AssetInfo::Token { contract_addr } if *contract_addr == info.sender
+    );
+    if !is_eligible && !matches!(hook, Cw20HookMsg::DepositCollateral {}) {
+        return Err(ContractError::IneligibleCollateral {
+            token: info.sender.to_string(),
+        });
+    }
 
-pub fn internal_close_position(
-    deps: DepsMut,
-    position: &Position,
-    quote_asset_limit: Uint128,
-    id: u64,
-) -> StdResult<SubMsg> {
-    store_tmp_swap(
-        deps.storage,
-        &TmpSwapInfo {
-            vamm: position.vamm.clone(),
-            trader: position.trader.clone(),
-            side: direction_to_side(position.direction.clone()),
-            margin_amount: position.size.value,
-            leverage: Uint128::zero(),
-            open_notional: position.notional,
-            position_notional: Uint128::zero(),
-            unrealized_pnl: Integer::zero(),
-            margin_to_vault: Integer::zero(),
-            fees_paid: false,
+    let funds = SentFunds {
+        asset: Asset {
+            info: config.eligible_collateral,
+            amount: cw20_msg.amount,
         },
-    )?;
+        required: Uint128::zero(),
+    };
 
-    swap_output(
-        &position.vamm.clone(),
-        direction_to_side(position.direction.clone()),
-        position.size.value,
-        quote_asset_limit,
-        id,
-    )
-}
+    match hook {
+        Cw20HookMsg::OpenPosition {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+        } => {
+            let vamm = deps.api.addr_validate(&vamm)?;
+
+            let msg: SubMsg = internal_open_position(
+                deps.branch(),
+                env,
+                &vamm,
+                &trader,
+                side,
+                margin_amount,
+                leverage,
+                base_asset_limit,
+                true,
+                funds,
+                None,
+            )?;
 
-#[allow(clippy::too_many_arguments)]
-fn open_reverse_position(
-    deps: &DepsMut,
-    position: Position,
-    side: Side,
-    notional_amount: Uint128,
-    base_asset_limit: Uint128,
-    can_go_over_fluctuation: bool,
-    reply_id: Option<u64>,
-) -> StdResult<SubMsg> {
-    let PositionUnrealizedPnlResponse {
-        position_notional,
-        unrealized_pnl: _,
-    } = get_position_notional_unrealized_pnl(deps.as_ref(), &position, PnlCalcOption::SpotPrice)
-        .unwrap();
+            Ok(Response::new().add_submessage(msg).add_attributes(vec![
+                ("action", "open_position"),
+                ("vamm", vamm.as_ref()),
+                ("trader", trader.as_ref()),
+                ("margin_amount", &margin_amount.to_string()),
+                ("leverage", &leverage.to_string()),
+            ]))
+        }
+        Cw20HookMsg::DepositMargin { vamm } => {
+            let state: State = read_state(deps.storage)?;
 
-    // reduce position if old position is larger
-    let msg: SubMsg = if position_notional > notional_amount {
-        let reply_id = match reply_id {
-            Some(id) => id,
-            None => DECREASE_POSITION_REPLY_ID,
-        };
+            let vamm = deps.api.addr_validate(&vamm)?;
 
-        swap_input(
-            &position.vamm,
-            side,
-            notional_amount,
-            base_asset_limit,
-            can_go_over_fluctuation,
-            reply_id,
-        )
-        .unwrap()
-    } else {
-        // first close position swap out the entire position
-        let reply_id = match reply_id {
-            Some(id) => id,
-            None => REVERSE_POSITION_REPLY_ID,
-        };
+            require_not_paused(state.pause)?;
+            require_non_zero_input(cw20_msg.amount)?;
 
-        swap_output(
-            &position.vamm,
-            direction_to_side(position.direction.clone()),
-            position.size.value,
-            Uint128::zero(),
-            reply_id,
-        )
-        .unwrap()
+            internal_deposit_margin(deps, env, vamm, trader, cw20_msg.amount, true)
+        }
+        Cw20HookMsg::Batch { ops } => internal_batch(deps, env, trader, ops, funds, true),
+        Cw20HookMsg::DepositCollateral {} => {
+            internal_deposit_collateral(deps, trader, token_asset(info.sender, cw20_msg.amount))
+        }
+    }
+}
+
+/// Executes a sequence of operations for the sender atomically, native tokens sent
+/// must cover the funds required by all of the operations
+pub fn batch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ops: Vec<EngineOp>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    let trader = info.sender.clone();
+    let funds = SentFunds {
+        asset: get_asset(info, config.eligible_collateral),
+        required: Uint128::zero(),
     };
 
-    Ok(msg)
+    internal_batch(deps, env, trader, ops, funds, false)
 }
 
-fn partial_liquidation(
+// Stores the batch and executes its first operation, if escrowed the funds of every
+// operation are paid from the tokens already held by the engine
+fn internal_batch(
     deps: DepsMut,
-    _env: Env,
-    vamm: Addr,
+    env: Env,
     trader: Addr,
-    quote_asset_limit: Uint128,
-) -> StdResult<SubMsg> {
-    let config: Config = read_config(deps.storage).unwrap();
-
-    let position: Position = read_position(deps.storage, &vamm, &trader).unwrap();
+    ops: Vec<EngineOp>,
+    funds: SentFunds,
+    escrowed: bool,
+) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
 
-    let partial_position_size = position
-        .size
-        .value
-        .checked_mul(config.partial_liquidation_ratio)
-        .unwrap()
-        .checked_div(config.decimals)
-        .unwrap();
+    require_not_paused(state.pause)?;
+    if ops.is_empty() {
+        return Err(ContractError::EmptyBatch {});
+    }
+
+    let batch_id = next_batch_id(deps.storage)?;
+    store_batch(
+        deps.storage,
+        batch_id,
+        &Batch {
+            trader,
+            ops,
+            funds,
+            escrowed,
+        },
+    )?;
+
+    let response = internal_batch_step(deps, env, batch_id)?;
+
+    Ok(response.add_attribute("action", "batch"))
+}
+
+/// Executes the next operation of a batch, each operation is executed once the swaps
+/// of the previous one have been replied to so that it sees the updated positions
+pub fn batch_step(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    batch_id: u64,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    internal_batch_step(deps, env, batch_id)
+}
+
+fn internal_batch_step(
+    mut deps: DepsMut,
+    env: Env,
+    batch_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let mut batch = read_batch(deps.storage, batch_id)?;
+
+    // once every operation is executed the funds sent are reconciled
+    if batch.ops.is_empty() {
+        if batch.escrowed || matches!(config.eligible_collateral, AssetInfo::NativeToken { .. }) {
+            batch.funds.are_sufficient()?;
+        }
+
+        remove_batch(deps.storage, batch_id);
+
+        return Ok(Response::new().add_attributes(vec![
+            ("action", "settle_batch"),
+            ("batch_id", &batch_id.to_string()),
+            ("trader", batch.trader.as_ref()),
+        ]));
+    }
+
+    let op = batch.ops.remove(0);
+    let trader = batch.trader.clone();
+
+    // the caller of the operation handlers is the trader, without any funds
+    let info = MessageInfo {
+        sender: trader.clone(),
+        funds: vec![],
+    };
+
+    let response = match op {
+        EngineOp::OpenPosition {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+        } => {
+            let vamm = deps.api.addr_validate(&vamm)?;
+            let msg: SubMsg = internal_open_position(
+                deps.branch(),
+                env.clone(),
+                &vamm,
+                &trader,
+                side,
+                margin_amount,
+                leverage,
+                base_asset_limit,
+                batch.escrowed,
+                SentFunds {
+                    asset: Asset {
+                        info: config.eligible_collateral.clone(),
+                        amount: Uint128::zero(),
+                    },
+                    required: Uint128::zero(),
+                },
+                Some(batch_id),
+            )?;
+
+            Response::new().add_submessage(msg).add_attributes(vec![
+                ("action", "open_position"),
+                ("vamm", vamm.as_ref()),
+                ("trader", trader.as_ref()),
+                ("margin_amount", &margin_amount.to_string()),
+                ("leverage", &leverage.to_string()),
+            ])
+        }
+        EngineOp::ClosePosition {
+            vamm,
+            quote_asset_limit,
+        } => close_position(deps.branch(), env.clone(), info, vamm, quote_asset_limit)?,
+        EngineOp::DepositMargin { vamm, amount } => {
+            let state: State = read_state(deps.storage)?;
+
+            let vamm = deps.api.addr_validate(&vamm)?;
+
+            require_not_paused(state.pause)?;
+            require_non_zero_input(amount)?;
+
+            batch.funds.required = batch.funds.required.checked_add(amount)?;
+
+            internal_deposit_margin(
+                deps.branch(),
+                env.clone(),
+                vamm,
+                trader,
+                amount,
+                batch.escrowed,
+            )?
+        }
+        EngineOp::WithdrawMargin { vamm, amount } => {
+            withdraw_margin(deps.branch(), env.clone(), info, vamm, amount)?
+        }
+    };
+
+    store_batch(deps.storage, batch_id, &batch)?;
+
+    // the next step is executed after the messages of this operation
+    let msg = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        funds: vec![],
+        msg: to_binary(&EngineExecuteMsg::BatchStep { batch_id })?,
+    };
+
+    Ok(response
+        .add_message(msg)
+        .add_attribute("batch_id", batch_id.to_string()))
+}
+
+/// Enables a trader to switch between isolated and cross margin, all positions must be closed
+pub fn set_cross_margin(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let trader = info.sender;
+
+    let has_open_positions = query_all_positions(deps.as_ref(), trader.to_string())?
+        .iter()
+        .any(|position| !position.size.is_zero());
+    if has_open_positions {
+        return Err(ContractError::OpenPositionsExist {});
+    }
+
+    store_cross_margin(deps.storage, &trader, enabled)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_cross_margin"),
+        ("trader", trader.as_ref()),
+        ("enabled", &enabled.to_string()),
+    ]))
+}
+
+/// Registers an asset as collateral or updates its weight and price source, only the owner
+pub fn register_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: AssetInfo,
+    weight: Uint128,
+    pricefeed: String,
+    price_key: String,
+    price_decimals: u8,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    asset.check(deps.api)?;
+    if asset.equal(&config.eligible_collateral) {
+        return Err(ContractError::CollateralIsEligible {});
+    }
+
+    // the weight is the share of the collateral value counted as margin
+    validate_ratio(weight, config.decimals)?;
+
+    // prices are converted from the decimals of the feed into the engine decimals
+    let price_decimals = validate_decimal_places(price_decimals)?;
+
+    let collateral = CollateralAsset {
+        decimals: Uint128::from(10u128.pow(asset.get_decimals(deps.as_ref())? as u32)),
+        asset,
+        weight,
+        pricefeed: deps.api.addr_validate(&pricefeed)?,
+        price_key,
+        price_decimals,
+    };
+
+    store_collateral(deps.storage, &collateral)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "register_collateral"),
+        ("asset", &collateral.asset.to_string()),
+        ("weight", &weight.to_string()),
+    ]))
+}
+
+/// Removes an asset from the collateral registry, deposits of the asset can still be
+/// withdrawn but no longer count as margin
+pub fn remove_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: AssetInfo,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if read_collateral(deps.storage, &asset)?.is_none() {
+        return Err(ContractError::CollateralNotRegistered {
+            asset: asset.to_string(),
+        });
+    }
+
+    remove_stored_collateral(deps.storage, &asset);
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "remove_collateral"),
+        ("asset", &asset.to_string()),
+    ]))
+}
+
+/// Deposits the registered native collateral sent
+pub fn deposit_collateral(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let deposit = match info.funds.as_slice() {
+        [coin] => native_asset(coin.denom.clone(), coin.amount),
+        _ => return Err(ContractError::InvalidCollateralFunds {}),
+    };
+
+    internal_deposit_collateral(deps, info.sender, deposit)
+}
+
+// Adds the deposit to the trader's collateral, the tokens are already held by the engine
+fn internal_deposit_collateral(
+    deps: DepsMut,
+    trader: Addr,
+    deposit: Asset,
+) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+
+    require_not_paused(state.pause)?;
+    require_non_zero_input(deposit.amount)?;
+
+    if read_collateral(deps.storage, &deposit.info)?.is_none() {
+        return Err(ContractError::CollateralNotRegistered {
+            asset: deposit.info.to_string(),
+        });
+    }
+
+    let balance = read_trader_collateral(deps.storage, &trader, &deposit.info)?;
+    store_trader_collateral(
+        deps.storage,
+        &trader,
+        &Asset {
+            info: deposit.info.clone(),
+            amount: balance.checked_add(deposit.amount)?,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "deposit_collateral"),
+        ("trader", trader.as_ref()),
+        ("asset", &deposit.info.to_string()),
+        ("amount", &deposit.amount.to_string()),
+    ]))
+}
+
+/// Withdraws deposited collateral, the weighted value withdrawn from a cross margined
+/// account cannot exceed its free collateral
+pub fn withdraw_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: AssetInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+    let trader = info.sender;
+
+    require_not_paused(state.pause)?;
+    require_non_zero_input(amount)?;
+
+    let balance = read_trader_collateral(deps.storage, &trader, &asset)?;
+    if balance < amount {
+        return Err(ContractError::InsufficientCollateralBalance { balance, amount });
+    }
+
+    // collateral removed from the registry no longer backs the account
+    if let Some(collateral) = read_collateral(deps.storage, &asset)? {
+        if read_cross_margin(deps.storage, &trader)? {
+            let value =
+                calc_weighted_collateral_value(deps.as_ref(), &config, &collateral, amount)?;
+
+            let free_collateral = calc_account_free_collateral(deps.as_ref(), &trader)?;
+            if free_collateral
+                .checked_sub(Integer::new_positive(value))?
+                .is_negative()
+            {
+                return Err(ContractError::InsufficientCollateral {
+                    free_collateral,
+                    amount: value,
+                });
+            }
+        }
+    }
+
+    let withdrawal = Asset {
+        info: asset,
+        amount,
+    };
+
+    store_trader_collateral(
+        deps.storage,
+        &trader,
+        &Asset {
+            info: withdrawal.info.clone(),
+            amount: balance.checked_sub(amount)?,
+        },
+    )?;
+
+    let msg = execute_collateral_transfer(&withdrawal, &trader)?;
+
+    Ok(Response::new().add_submessage(msg).add_attributes(vec![
+        ("action", "withdraw_collateral"),
+        ("trader", trader.as_ref()),
+        ("asset", &withdrawal.info.to_string()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+/// Sells the collateral of a cross margined account below the maintenance margin ratio to
+/// the liquidator, at the pricefeed price discounted by the liquidation fee. The proceeds
+/// are added to the margin of the trader's position in the vamm
+#[allow(clippy::too_many_arguments)]
+pub fn liquidate_collateral(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vamm: String,
+    trader: String,
+    asset: AssetInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+    let liquidator = info.sender.clone();
+
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
+    require_not_paused(state.pause)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+    require_non_zero_input(amount)?;
+
+    if !read_cross_margin(deps.storage, &trader)? {
+        return Err(ContractError::NotCrossMargined {});
+    }
+
+    let collateral = read_collateral(deps.storage, &asset)?.ok_or_else(|| {
+        ContractError::CollateralNotRegistered {
+            asset: asset.to_string(),
+        }
+    })?;
+
+    // the proceeds are added to this position
+    let mut position = read_position(deps.storage, &vamm, &trader)?;
+    require_position_not_zero(position.size.value)?;
+
+    let margin_ratio =
+        get_liquidation_margin_ratio(deps.as_ref(), vamm.to_string(), trader.to_string())?;
+    let tier_config = read_position_config(deps.storage, &vamm, position.notional)?;
+    require_insufficient_margin(margin_ratio, tier_config.maintenance_margin_ratio)?;
+
+    let balance = read_trader_collateral(deps.storage, &trader, &asset)?;
+    if balance < amount {
+        return Err(ContractError::InsufficientCollateralBalance { balance, amount });
+    }
+
+    // the liquidator is rewarded by buying the collateral at a discount
+    let payment = calc_collateral_value(deps.as_ref(), &config, &collateral, amount)?
+        .multiply_ratio(
+            config.decimals.checked_sub(config.liquidation_fee)?,
+            config.decimals,
+        );
+    require_non_zero_input(payment)?;
+
+    let mut response = Response::new();
+    match config.eligible_collateral {
+        AssetInfo::NativeToken { .. } => {
+            let token = Asset {
+                info: config.eligible_collateral,
+                amount: payment,
+            };
+
+            token.assert_sent_native_token_balance(&info)?;
+        }
+        AssetInfo::Token { .. } => {
+            let msg =
+                execute_transfer_from(deps.storage, &liquidator, &env.contract.address, payment)?;
+            response = response.add_submessage(msg);
+        }
+    }
+
+    position.margin = position.margin.checked_add(payment)?;
+    store_position(deps.storage, &position)?;
+
+    let sold = Asset {
+        info: asset,
+        amount,
+    };
+
+    store_trader_collateral(
+        deps.storage,
+        &trader,
+        &Asset {
+            info: sold.info.clone(),
+            amount: balance.checked_sub(amount)?,
+        },
+    )?;
+
+    let msg = execute_collateral_transfer(&sold, &liquidator)?;
+
+    Ok(response.add_submessage(msg).add_attributes(vec![
+        ("action", "liquidate_collateral"),
+        ("vamm", vamm.as_ref()),
+        ("trader", trader.as_ref()),
+        ("asset", &sold.info.to_string()),
+        ("amount", &amount.to_string()),
+        ("payment", &payment.to_string()),
+    ]))
+}
+
+/// Closes a bankrupt position the insurance fund cannot cover against the highest scored
+/// profitable positions on the other side. The positions of the vamm are ranked by a scan
+/// resumed by each call, the position is closed by the call that completes it. Both sides
+/// are settled at the bankruptcy price so the loss is taken out of the counterparties' profit
+pub fn auto_deleverage(
+    deps: DepsMut,
+    env: Env,
+    vamm: String,
+    trader: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    require_not_paused(state.pause)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_ADL_SCAN_LIMIT)
+        .min(MAX_ADL_SCAN_LIMIT) as usize;
+
+    let mut position = read_position(deps.storage, &vamm, &trader)?;
+    require_position_not_zero(position.size.value)?;
+
+    let PositionUnrealizedPnlResponse {
+        position_notional,
+        unrealized_pnl,
+    } = get_position_notional_unrealized_pnl(deps.as_ref(), &position, PnlCalcOption::SpotPrice)?;
+
+    let remain_margin =
+        calc_remain_margin_with_funding_payment(deps.as_ref(), position.clone(), unrealized_pnl)?;
+    if remain_margin.bad_debt.is_zero() {
+        return Err(ContractError::NotBankrupt {});
+    }
+
+    // the bad debt a liquidation would realize, including the liquidation fee and
+    // less what the trader's other cross margined positions absorb
+    let liquidation_fee = position_notional
+        .checked_mul(config.liquidation_fee)?
+        .checked_div(config.decimals)?
+        .checked_div(Uint128::from(2u64))?;
+    let mut bad_debt = remain_margin
+        .bad_debt
+        .checked_add(liquidation_fee.saturating_sub(remain_margin.margin))?;
+    for sibling in get_margin_account_positions(deps.as_ref(), &vamm, &trader)? {
+        if sibling.vamm != vamm {
+            bad_debt = bad_debt.saturating_sub(sibling.margin);
+        }
+    }
+
+    // deleveraging is the last resort once the insurance fund cannot cover the shortfall
+    // that realize_bad_debt would withdraw, the bad debt prepaid by the fund is used first
+    let shortfall = bad_debt.saturating_sub(state.prepaid_bad_debt);
+    let balance = query_token_balance(
+        deps.as_ref(),
+        config.eligible_collateral.clone(),
+        config.insurance_fund.clone(),
+    )?;
+    if balance >= shortfall {
+        return Err(ContractError::InsuranceFundNotExhausted {
+            balance,
+            bad_debt: shortfall,
+        });
+    }
+
+    // the bankruptcy price is where the remaining margin of the position is exactly lost
+    let remain_margin =
+        calc_remain_margin_with_funding_payment(deps.as_ref(), position.clone(), Integer::zero())?;
+    let bankruptcy_notional = match position.direction {
+        Direction::AddToAmm => position.notional.saturating_sub(remain_margin.margin),
+        Direction::RemoveFromAmm => position.notional.checked_add(remain_margin.margin)?,
+    };
+    let bankruptcy_price = bankruptcy_notional.multiply_ratio(config.decimals, position.size.value);
+
+    // the next page of positions is ranked along with the counterparties kept so far, which
+    // are scored again as their positions may have changed since they were scanned
+    let mut scan = read_adl_scan(deps.storage, &vamm, &trader)?;
+    let page = read_vamm_positions(deps.storage, &vamm, scan.last_trader.clone(), limit)?;
+
+    let mut candidates: Vec<Position> = vec![];
+    for counterparty in scan.counterparties.iter() {
+        candidates.push(read_position(deps.storage, &vamm, counterparty)?);
+    }
+    candidates.extend(
+        page.iter()
+            .filter(|other| !scan.counterparties.contains(&other.trader))
+            .cloned(),
+    );
+
+    // the counterparties must be profitable positions on the other side
+    let mut ranked: Vec<(Integer, Position)> = vec![];
+    for candidate in candidates {
+        if candidate.size.is_zero() || candidate.direction == position.direction {
+            continue;
+        }
+
+        let score = calc_adl_score(deps.as_ref(), &candidate)?;
+        if score > Integer::zero() {
+            ranked.push((score, candidate));
+        }
+    }
+    ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    ranked.truncate(MAX_ADL_COUNTERPARTIES);
+
+    // positions are left to scan, the position is closed once the scan completes
+    if page.len() == limit {
+        scan.last_trader = page.last().map(|last| last.trader.clone());
+        scan.counterparties = ranked
+            .into_iter()
+            .map(|(_, counterparty)| counterparty.trader)
+            .collect();
+        store_adl_scan(deps.storage, &vamm, &trader, &scan)?;
+
+        return Ok(Response::new().add_attributes(vec![
+            ("action", "auto_deleverage"),
+            ("vamm", vamm.as_ref()),
+            ("trader", trader.as_ref()),
+            ("scanned", &page.len().to_string()),
+        ]));
+    }
+
+    // the next call starts a new scan, whether or not the position could be closed
+    remove_adl_scan(deps.storage, &vamm, &trader);
+
+    let mut msgs: Vec<SubMsg> = vec![];
+    let mut filled = Uint128::zero();
+    for (_, mut counterparty) in ranked {
+        let remaining = position.size.value.checked_sub(filled)?;
+        if remaining.is_zero() {
+            break;
+        }
+
+        let size = remaining.min(counterparty.size.value);
+        let closed_notional = counterparty
+            .notional
+            .multiply_ratio(size, counterparty.size.value);
+        let exit_notional = size.multiply_ratio(bankruptcy_price, config.decimals);
+
+        let realized_pnl = match counterparty.direction {
+            Direction::AddToAmm => {
+                Integer::new_positive(exit_notional) - Integer::new_positive(closed_notional)
+            }
+            Direction::RemoveFromAmm => {
+                Integer::new_positive(closed_notional) - Integer::new_positive(exit_notional)
+            }
+        };
+
+        // a counterparty that would be left with bad debt cannot take the position
+        let counterparty_margin = calc_remain_margin_with_funding_payment(
+            deps.as_ref(),
+            counterparty.clone(),
+            realized_pnl,
+        )?;
+        if !counterparty_margin.bad_debt.is_zero() {
+            continue;
+        }
+
+        let trade = Trade {
+            realized_pnl,
+            funding_paid: counterparty_margin.funding_payment,
+            ..new_trade(
+                deps.storage,
+                &env,
+                &vamm,
+                &counterparty.trader,
+                if counterparty.size.is_positive() {
+                    Integer::new_negative(size)
+                } else {
+                    Integer::new_positive(size)
+                },
+                exit_notional,
+            )?
+        };
+        store_trade(deps.storage, &trade)?;
+
+        if size == counterparty.size.value {
+            remove_position(deps.storage, &counterparty);
+
+            if !counterparty_margin.margin.is_zero() {
+                msgs.append(&mut withdraw(
+                    deps.as_ref(),
+                    env.clone(),
+                    &mut state,
+                    &counterparty.trader,
+                    config.eligible_collateral.clone(),
+                    counterparty_margin.margin,
+                    Uint128::zero(),
+                )?);
+            }
+        } else {
+            counterparty.size.value = counterparty.size.value.checked_sub(size)?;
+            counterparty.notional = counterparty.notional.checked_sub(closed_notional)?;
+            counterparty.margin = counterparty_margin.margin;
+            counterparty.last_updated_premium_fraction =
+                counterparty_margin.latest_premium_fraction;
+            counterparty.block_number = env.block.height;
+
+            store_position(deps.storage, &counterparty)?;
+        }
+
+        // orders placed for the position no longer match what is left of it
+        msgs.append(&mut cancel_all_orders(
+            deps.storage,
+            &vamm,
+            &counterparty.trader,
+        )?);
+
+        update_open_interest_notional(
+            &deps.as_ref(),
+            &mut state,
+            vamm.clone(),
+            Integer::new_negative(closed_notional),
+            counterparty.trader,
+        )?;
+
+        filled = filled.checked_add(size)?;
+    }
+
+    if filled.is_zero() {
+        return Ok(Response::new().add_attributes(vec![
+            ("action", "auto_deleverage"),
+            ("vamm", vamm.as_ref()),
+            ("trader", trader.as_ref()),
+            ("size", "0"),
+        ]));
+    }
+
+    // the bankrupt side loses its margin in proportion to the size that was filled
+    let closed_notional = position
+        .notional
+        .multiply_ratio(filled, position.size.value);
+    let lost_margin = remain_margin
+        .margin
+        .multiply_ratio(filled, position.size.value);
+
+    let trade = Trade {
+        realized_pnl: Integer::new_negative(lost_margin),
+        funding_paid: remain_margin.funding_payment,
+        liquidation: true,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &vamm,
+            &trader,
+            if position.size.is_positive() {
+                Integer::new_negative(filled)
+            } else {
+                Integer::new_positive(filled)
+            },
+            filled.multiply_ratio(bankruptcy_price, config.decimals),
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
+
+    if filled == position.size.value {
+        remove_position(deps.storage, &position);
+    } else {
+        position.size.value = position.size.value.checked_sub(filled)?;
+        position.notional = position.notional.checked_sub(closed_notional)?;
+        position.margin = remain_margin.margin.checked_sub(lost_margin)?;
+        position.last_updated_premium_fraction = remain_margin.latest_premium_fraction;
+        position.block_number = env.block.height;
+
+        store_position(deps.storage, &position)?;
+    }
+
+    msgs.append(&mut cancel_all_orders(deps.storage, &vamm, &trader)?);
+
+    update_open_interest_notional(
+        &deps.as_ref(),
+        &mut state,
+        vamm.clone(),
+        Integer::new_negative(closed_notional),
+        trader.clone(),
+    )?;
+
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_submessages(msgs).add_attributes(vec![
+        ("action", "auto_deleverage"),
+        ("vamm", vamm.as_ref()),
+        ("trader", trader.as_ref()),
+        ("size", &filled.to_string()),
+        ("bankruptcy_price", &bankruptcy_price.to_string()),
+        ("bad_debt", &bad_debt.to_string()),
+    ]))
+}
+
+/// Moves a batch of positions stored under the legacy hashed keys into the
+/// position indexes, can be called repeatedly until nothing is left to migrate
+pub fn migrate_positions(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = read_config(deps.storage)?;
+
+    // check permission
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let limit = limit
+        .unwrap_or(DEFAULT_MIGRATION_LIMIT)
+        .min(MAX_MIGRATION_LIMIT) as usize;
+
+    let migrated = migrate_legacy_positions(deps.storage, limit)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate_positions"),
+        ("migrated", &migrated.to_string()),
+    ]))
+}
+
+/// Places a limit order, escrowing the margin, trading fees and keeper fee
+#[allow(clippy::too_many_arguments)]
+pub fn place_limit_order(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vamm: String,
+    side: Side,
+    margin_amount: Uint128,
+    leverage: Uint128,
+    base_asset_limit: Uint128,
+    trigger_price: Uint128,
+    trigger_option: TriggerPriceOption,
+) -> Result<Response, ContractError> {
+    let state: State = read_state(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = info.sender.clone();
+
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
+    require_not_paused(state.pause)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+    require_non_zero_input(margin_amount)?;
+    require_non_zero_input(leverage)?;
+    require_non_zero_input(trigger_price)?;
+    require_valid_leverage(leverage, config.decimals, config.initial_margin_ratio)?;
+
+    // escrow the margin and fees that the position will require once opened
+    let escrow = calc_order_escrow(deps.as_ref(), &vamm, margin_amount, leverage)?;
+
+    let msgs = collect_order_funds(
+        deps.as_ref(),
+        &env,
+        &info,
+        escrow.checked_add(config.keeper_fee)?,
+    )?;
+
+    let order = Order {
+        order_id: next_order_id(deps.storage)?,
+        vamm: vamm.clone(),
+        trader: trader.clone(),
+        order_type: OrderType::Limit,
+        side,
+        margin_amount,
+        leverage,
+        asset_limit: base_asset_limit,
+        trigger_price,
+        trigger_option,
+        escrow,
+        keeper_fee: config.keeper_fee,
+        block_number: env.block.height,
+    };
+
+    store_order(deps.storage, &order)?;
+
+    Ok(Response::new().add_submessages(msgs).add_attributes(vec![
+        ("action", "place_limit_order"),
+        ("vamm", vamm.as_ref()),
+        ("trader", trader.as_ref()),
+        ("order_id", &order.order_id.to_string()),
+    ]))
+}
+
+/// Places a stop loss or take profit order that closes the existing position
+#[allow(clippy::too_many_arguments)]
+pub fn place_trigger_order(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vamm: String,
+    order_type: OrderType,
+    quote_asset_limit: Uint128,
+    trigger_price: Uint128,
+    trigger_option: TriggerPriceOption,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = info.sender.clone();
+
+    require_not_paused(state.pause)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, &vamm)?;
+    require_non_zero_input(trigger_price)?;
+
+    if order_type == OrderType::Limit {
+        return Err(ContractError::InvalidTriggerOrder {});
+    }
+
+    // the order closes the position so it must exist
+    let position = read_position(deps.storage, &vamm, &trader)?;
+    require_position_not_zero(position.size.value)?;
+
+    let msgs = collect_order_funds(deps.as_ref(), &env, &info, config.keeper_fee)?;
+
+    let order = Order {
+        order_id: next_order_id(deps.storage)?,
+        vamm: vamm.clone(),
+        trader: trader.clone(),
+        order_type,
+        side: direction_to_side(position.direction.clone()),
+        margin_amount: Uint128::zero(),
+        leverage: Uint128::zero(),
+        asset_limit: quote_asset_limit,
+        trigger_price,
+        trigger_option,
+        escrow: Uint128::zero(),
+        keeper_fee: config.keeper_fee,
+        block_number: env.block.height,
+    };
+
+    store_order(deps.storage, &order)?;
+
+    Ok(Response::new().add_submessages(msgs).add_attributes(vec![
+        ("action", "place_trigger_order"),
+        ("vamm", vamm.as_ref()),
+        ("trader", trader.as_ref()),
+        ("order_id", &order.order_id.to_string()),
+    ]))
+}
+
+/// Cancels an order and refunds the escrowed funds to the trader
+pub fn cancel_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm: String,
+    order_id: u64,
+) -> Result<Response, ContractError> {
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    let order = read_order(deps.storage, &vamm, order_id)?;
+
+    // check permission
+    if info.sender != order.trader {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    remove_order(deps.storage, &order);
+
+    let mut msgs: Vec<SubMsg> = vec![];
+
+    let refund = order.escrow.checked_add(order.keeper_fee)?;
+    if !refund.is_zero() {
+        msgs.push(execute_transfer(deps.storage, &order.trader, refund)?);
+    }
+
+    Ok(Response::new().add_submessages(msgs).add_attributes(vec![
+        ("action", "cancel_order"),
+        ("vamm", vamm.as_ref()),
+        ("trader", order.trader.as_ref()),
+        ("order_id", &order_id.to_string()),
+    ]))
+}
+
+/// Executes an order whose trigger price has been reached, the caller receives the keeper fee
+pub fn execute_order(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vamm: String,
+    order_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    require_not_paused(state.pause)?;
+
+    let order = read_order(deps.storage, &vamm, order_id)?;
+
+    if !is_order_triggered(deps.as_ref(), &order)? {
+        return Err(ContractError::OrderNotTriggered {});
+    }
+
+    remove_order(deps.storage, &order);
+
+    let position = read_position(deps.storage, &vamm, &order.trader)?;
+
+    let mut msgs: Vec<SubMsg> = vec![];
+
+    match order.order_type {
+        OrderType::Limit => {
+            // limit orders may open or increase a position but never reverse one
+            if !position.size.is_zero()
+                && direction_to_side(position.direction.clone()) != order.side
+            {
+                return Err(ContractError::OrderReversesPosition {});
+            }
+
+            // the fees may have changed since the order was placed
+            let required =
+                calc_order_escrow(deps.as_ref(), &vamm, order.margin_amount, order.leverage)?;
+            if required > order.escrow {
+                return Err(ContractError::InsufficientOrderEscrow {
+                    escrow: order.escrow,
+                    required,
+                });
+            }
+
+            msgs.push(internal_open_position(
+                deps.branch(),
+                env,
+                &vamm,
+                &order.trader,
+                order.side.clone(),
+                order.margin_amount,
+                order.leverage,
+                order.asset_limit,
+                true,
+                SentFunds {
+                    asset: Asset {
+                        info: config.eligible_collateral,
+                        amount: required,
+                    },
+                    required: Uint128::zero(),
+                },
+                None,
+            )?);
+
+            // return any excess escrow to the trader
+            let refund = order.escrow.checked_sub(required)?;
+            if !refund.is_zero() {
+                msgs.push(execute_transfer(deps.storage, &order.trader, refund)?);
+            }
+        }
+        OrderType::StopLoss | OrderType::TakeProfit => {
+            // the position must be the one the order was placed against
+            require_position_not_zero(position.size.value)?;
+            if direction_to_side(position.direction.clone()) != order.side {
+                return Err(ContractError::PositionDirectionChanged {});
+            }
+
+            require_not_restriction_mode(deps.storage, &vamm, &order.trader, env.block.height)?;
+
+            msgs.push(internal_close_position(
+                deps.branch(),
+                &position,
+                order.asset_limit,
+                None,
+                CLOSE_POSITION_REPLY_ID,
+            )?);
+        }
+    }
+
+    // pay the keeper for executing the order
+    if !order.keeper_fee.is_zero() {
+        msgs.push(execute_transfer(
+            deps.storage,
+            &info.sender,
+            order.keeper_fee,
+        )?);
+    }
+
+    Ok(Response::new().add_submessages(msgs).add_attributes(vec![
+        ("action", "execute_order"),
+        ("vamm", vamm.as_ref()),
+        ("trader", order.trader.as_ref()),
+        ("order_id", &order_id.to_string()),
+        ("keeper", info.sender.as_ref()),
+    ]))
+}
+
+// returns the margin and trading fees required to open the position of an order
+fn calc_order_escrow(
+    deps: Deps,
+    vamm: &Addr,
+    margin_amount: Uint128,
+    leverage: Uint128,
+) -> Result<Uint128, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    let open_notional = margin_amount
+        .checked_mul(leverage)?
+        .checked_div(config.decimals)?;
+
+    // the margin is recalculated from the notional as done when the position is updated
+    let margin = open_notional
+        .checked_mul(config.decimals)?
+        .checked_div(leverage)?;
+
+    let CalcFeeResponse {
+        spread_fee,
+        toll_fee,
+    } = query_vamm_calc_fee(&deps, vamm.to_string(), open_notional)?;
+
+    Ok(margin.checked_add(spread_fee)?.checked_add(toll_fee)?)
+}
+
+// transfers funds for an order into the engine, native tokens must be sent with the message
+fn collect_order_funds(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    amount: Uint128,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    let mut msgs: Vec<SubMsg> = vec![];
+    match config.eligible_collateral.clone() {
+        AssetInfo::NativeToken { .. } => {
+            if amount.is_zero() {
+                if !get_asset(info.clone(), config.eligible_collateral)
+                    .amount
+                    .is_zero()
+                {
+                    return Err(ContractError::ExcessiveFunds {});
+                }
+            } else {
+                let token = Asset {
+                    info: config.eligible_collateral,
+                    amount,
+                };
+
+                token.assert_sent_native_token_balance(info)?;
+            }
+        }
+        AssetInfo::Token { .. } => {
+            if !amount.is_zero() {
+                msgs.push(execute_transfer_from(
+                    deps.storage,
+                    &info.sender,
+                    &env.contract.address,
+                    amount,
+                )?);
+            }
+        }
+    };
+
+    Ok(msgs)
+}
+
+// Increase the position through a swap, replying to the context of the nonce
+pub fn internal_increase_position(
+    vamm: Addr,
+    side: Side,
+    open_notional: Uint128,
+    base_asset_limit: Uint128,
+    nonce: u64,
+) -> Result<SubMsg, ContractError> {
+    swap_input(
+        &vamm,
+        side,
+        open_notional,
+        base_asset_limit,
+        false,
+        encode_reply_id(nonce, INCREASE_POSITION_REPLY_ID),
+    )
+}
+
+// Validates and stores the swap to open, increase or reverse a position together with
+// the funds sent for it, if escrowed the margin and fees are already held by the engine
+// and if part of a batch the funds are reconciled by the batch
+#[allow(clippy::too_many_arguments)]
+pub fn internal_open_position(
+    deps: DepsMut,
+    env: Env,
+    vamm: &Addr,
+    trader: &Addr,
+    side: Side,
+    margin_amount: Uint128,
+    leverage: Uint128,
+    base_asset_limit: Uint128,
+    escrowed: bool,
+    funds: SentFunds,
+    batch: Option<u64>,
+) -> Result<SubMsg, ContractError> {
+    let config: Config = read_market_config(deps.storage, vamm)?;
+    let state: State = read_state(deps.storage)?;
+
+    require_not_paused(state.pause)?;
+    require_vamm(deps.as_ref(), &config.insurance_fund, vamm)?;
+    require_not_restriction_mode(deps.storage, vamm, trader, env.block.height)?;
+    require_non_zero_input(margin_amount)?;
+    require_non_zero_input(leverage)?;
+
+    // retrieves existing position or creates a new one
+    let position: Position = get_position(env, deps.storage, vamm, trader, side.clone());
+
+    // if direction and side are same way then increasing else we are reversing
+    let is_increase: bool = position.direction == Direction::AddToAmm && side == Side::Buy
+        || position.direction == Direction::RemoveFromAmm && side == Side::Sell;
+
+    // calculate the position notional
+    let open_notional = margin_amount
+        .checked_mul(leverage)?
+        .checked_div(config.decimals)?;
+
+    let PositionUnrealizedPnlResponse {
+        position_notional,
+        unrealized_pnl,
+    } = get_position_notional_unrealized_pnl(deps.as_ref(), &position, PnlCalcOption::SpotPrice)
+        .unwrap();
+
+    // the leverage allowed depends on the tier of the notional after the trade
+    let notional = if is_increase {
+        position.notional.checked_add(open_notional)?
+    } else if position_notional > open_notional {
+        position_notional.checked_sub(open_notional)?
+    } else {
+        open_notional.checked_sub(position_notional)?
+    };
+    let tier_config = read_position_config(deps.storage, vamm, notional)?;
+    require_valid_leverage(leverage, config.decimals, tier_config.initial_margin_ratio)?;
+
+    let nonce = store_new_reply_context(
+        deps.storage,
+        &ReplyContext {
+            swap: TmpSwapInfo {
+                vamm: vamm.clone(),
+                trader: trader.clone(),
+                side: side.clone(),
+                margin_amount,
+                leverage,
+                open_notional,
+                position_notional,
+                unrealized_pnl,
+                margin_to_vault: Integer::zero(),
+                fees_paid: false,
+                escrowed,
+            },
+            funds: Some(funds),
+            liquidator: None,
+            batch,
+        },
+    )?;
+
+    // check if the position is new or being increased, else position is being reversed
+    let msg: SubMsg = if is_increase {
+        internal_increase_position(vamm.clone(), side, open_notional, base_asset_limit, nonce)
+            .unwrap()
+    } else {
+        open_reverse_position(
+            &deps,
+            position,
+            side,
+            open_notional,
+            base_asset_limit,
+            false,
+            nonce,
+        )
+        .unwrap()
+    };
+
+    Ok(msg)
+}
+
+// Closes the whole position, the liquidator is only given when liquidating
+pub fn internal_close_position(
+    deps: DepsMut,
+    position: &Position,
+    quote_asset_limit: Uint128,
+    liquidator: Option<Addr>,
+    id: u64,
+) -> Result<SubMsg, ContractError> {
+    let nonce = store_new_reply_context(
+        deps.storage,
+        &ReplyContext {
+            swap: TmpSwapInfo {
+                vamm: position.vamm.clone(),
+                trader: position.trader.clone(),
+                side: direction_to_side(position.direction.clone()),
+                margin_amount: position.size.value,
+                leverage: Uint128::zero(),
+                open_notional: position.notional,
+                position_notional: Uint128::zero(),
+                unrealized_pnl: Integer::zero(),
+                margin_to_vault: Integer::zero(),
+                fees_paid: false,
+                escrowed: false,
+            },
+            funds: None,
+            liquidator,
+            batch: None,
+        },
+    )?;
+
+    swap_output(
+        &position.vamm.clone(),
+        direction_to_side(position.direction.clone()),
+        position.size.value,
+        quote_asset_limit,
+        encode_reply_id(nonce, id),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_reverse_position(
+    deps: &DepsMut,
+    position: Position,
+    side: Side,
+    notional_amount: Uint128,
+    base_asset_limit: Uint128,
+    can_go_over_fluctuation: bool,
+    nonce: u64,
+) -> Result<SubMsg, ContractError> {
+    let PositionUnrealizedPnlResponse {
+        position_notional,
+        unrealized_pnl: _,
+    } = get_position_notional_unrealized_pnl(deps.as_ref(), &position, PnlCalcOption::SpotPrice)
+        .unwrap();
+
+    // reduce position if old position is larger
+    let msg: SubMsg = if position_notional > notional_amount {
+        swap_input(
+            &position.vamm,
+            side,
+            notional_amount,
+            base_asset_limit,
+            can_go_over_fluctuation,
+            encode_reply_id(nonce, DECREASE_POSITION_REPLY_ID),
+        )
+        .unwrap()
+    } else {
+        // first close position swap out the entire position
+        swap_output(
+            &position.vamm,
+            direction_to_side(position.direction.clone()),
+            position.size.value,
+            Uint128::zero(),
+            encode_reply_id(nonce, REVERSE_POSITION_REPLY_ID),
+        )
+        .unwrap()
+    };
+
+    Ok(msg)
+}
+
+fn partial_liquidation(
+    deps: DepsMut,
+    _env: Env,
+    vamm: Addr,
+    trader: Addr,
+    quote_asset_limit: Uint128,
+    liquidator: Addr,
+) -> Result<SubMsg, ContractError> {
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
+    let position: Position = read_position(deps.storage, &vamm, &trader).unwrap();
+
+    let partial_position_size = position
+        .size
+        .value
+        .checked_mul(config.partial_liquidation_ratio)
+        .unwrap()
+        .checked_div(config.decimals)
+        .unwrap();
 
     let partial_asset_limit = quote_asset_limit
         .checked_mul(config.partial_liquidation_ratio)
@@ -656,22 +2075,27 @@ fn partial_liquidation(
 
     let side = position_to_side(position.size);
 
-    store_tmp_swap(
+    let nonce = store_new_reply_context(
         deps.storage,
-        &TmpSwapInfo {
-            vamm: position.vamm.clone(),
-            trader: position.trader.clone(),
-            side,
-            margin_amount: partial_position_size,
-            leverage: Uint128::zero(),
-            open_notional: current_notional,
-            position_notional: Uint128::zero(),
-            unrealized_pnl,
-            margin_to_vault: Integer::zero(),
-            fees_paid: false,
+        &ReplyContext {
+            swap: TmpSwapInfo {
+                vamm: position.vamm.clone(),
+                trader: position.trader.clone(),
+                side,
+                margin_amount: partial_position_size,
+                leverage: Uint128::zero(),
+                open_notional: current_notional,
+                position_notional: Uint128::zero(),
+                unrealized_pnl,
+                margin_to_vault: Integer::zero(),
+                fees_paid: false,
+                escrowed: false,
+            },
+            funds: None,
+            liquidator: Some(liquidator),
+            batch: None,
         },
-    )
-    .unwrap();
+    )?;
 
     let msg: SubMsg = if current_notional > position.notional {
         swap_input(
@@ -680,7 +2104,7 @@ fn partial_liquidation(
             position.notional,
             Uint128::zero(),
             true,
-            PARTIAL_LIQUIDATION_REPLY_ID,
+            encode_reply_id(nonce, PARTIAL_LIQUIDATION_REPLY_ID),
         )
         .unwrap()
     } else {
@@ -689,7 +2113,7 @@ fn partial_liquidation(
             direction_to_side(position.direction),
             partial_position_size,
             partial_asset_limit,
-            PARTIAL_LIQUIDATION_REPLY_ID,
+            encode_reply_id(nonce, PARTIAL_LIQUIDATION_REPLY_ID),
         )
         .unwrap()
     };
@@ -704,7 +2128,7 @@ fn swap_input(
     base_asset_limit: Uint128,
     can_go_over_fluctuation: bool,
     id: u64,
-) -> StdResult<SubMsg> {
+) -> Result<SubMsg, ContractError> {
     let direction: Direction = side_to_direction(side);
 
     let msg = WasmMsg::Execute {
@@ -734,7 +2158,7 @@ fn swap_output(
     open_notional: Uint128,
     quote_asset_limit: Uint128,
     id: u64,
-) -> StdResult<SubMsg> {
+) -> Result<SubMsg, ContractError> {
     let direction: Direction = side_to_direction(side);
 
     let swap_msg = WasmMsg::Execute {
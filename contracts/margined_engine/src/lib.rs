@@ -1,10 +1,11 @@
 pub mod contract;
-mod error;
+pub mod error;
 mod handle;
 mod messages;
 mod querier;
 mod query;
 mod reply;
+mod simulate;
 mod state;
 mod utils;
 
@@ -6,12 +6,16 @@ use cw20::Cw20ExecuteMsg;
 
 use crate::{
     contract::TRANSFER_FAILURE_REPLY_ID,
+    error::ContractError,
     querier::query_vamm_calc_fee,
     state::{read_config, State},
 };
 
-use margined_common::asset::AssetInfo;
+use margined_common::asset::{Asset, AssetInfo};
 use margined_perp::margined_engine::TransferResponse;
+use margined_perp::margined_fee_pool::{
+    Cw20HookMsg as FeePoolHookMsg, ExecuteMsg as FeePoolExecuteMessage,
+};
 use margined_perp::margined_insurance_fund::ExecuteMsg as InsuranceFundExecuteMessage;
 use margined_perp::margined_vamm::CalcFeeResponse;
 use margined_perp::querier::query_token_balance;
@@ -82,6 +86,85 @@ pub fn execute_transfer(
     Ok(transfer_msg)
 }
 
+// Transfers a collateral asset held by the engine, which need not be the eligible collateral
+pub fn execute_collateral_transfer(asset: &Asset, receiver: &Addr) -> StdResult<SubMsg> {
+    let msg: CosmosMsg = match &asset.info {
+        AssetInfo::NativeToken { denom } => CosmosMsg::Bank(BankMsg::Send {
+            to_address: receiver.to_string(),
+            amount: vec![Coin {
+                denom: denom.to_string(),
+                amount: asset.amount,
+            }],
+        }),
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: receiver.to_string(),
+                amount: asset.amount,
+            })?,
+        }),
+    };
+
+    let transfer_msg = SubMsg {
+        msg,
+        gas_limit: None,
+        id: TRANSFER_FAILURE_REPLY_ID,
+        reply_on: ReplyOn::Error,
+    };
+
+    Ok(transfer_msg)
+}
+
+// Deposits fees in the fee pool so that they are recorded against the engine,
+// cw20 fees are sent from the owner if given else from the engine
+pub fn execute_fee_pool_deposit(
+    storage: &dyn Storage,
+    owner: Option<&Addr>,
+    amount: Uint128,
+) -> StdResult<SubMsg> {
+    let config = read_config(storage)?;
+
+    let msg: CosmosMsg = match config.eligible_collateral {
+        AssetInfo::NativeToken { denom } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: config.fee_pool.to_string(),
+            funds: vec![Coin { denom, amount }],
+            msg: to_binary(&FeePoolExecuteMessage::Deposit {})?,
+        }),
+        AssetInfo::Token { contract_addr } => {
+            let hook = to_binary(&FeePoolHookMsg::Deposit {})?;
+            let msg = match owner {
+                Some(owner) => Cw20ExecuteMsg::SendFrom {
+                    owner: owner.to_string(),
+                    contract: config.fee_pool.to_string(),
+                    amount,
+                    msg: hook,
+                },
+                None => Cw20ExecuteMsg::Send {
+                    contract: config.fee_pool.to_string(),
+                    amount,
+                    msg: hook,
+                },
+            };
+
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                funds: vec![],
+                msg: to_binary(&msg)?,
+            })
+        }
+    };
+
+    let transfer_msg = SubMsg {
+        msg,
+        gas_limit: None,
+        id: TRANSFER_FAILURE_REPLY_ID,
+        reply_on: ReplyOn::Error,
+    };
+
+    Ok(transfer_msg)
+}
+
 pub fn execute_transfer_to_insurance_fund(
     deps: Deps,
     env: Env,
@@ -126,6 +209,22 @@ pub fn execute_insurance_fund_withdrawal(deps: Deps, amount: Uint128) -> StdResu
     Ok(transfer_msg)
 }
 
+// withdraws a shortfall of the engine from the insurance fund, once the fund balance can
+// no longer cover it the bad debt can only be settled by auto-deleveraging
+pub fn execute_insurance_fund_cover(
+    deps: Deps,
+    shortfall: Uint128,
+) -> Result<SubMsg, ContractError> {
+    let config = read_config(deps.storage)?;
+
+    let balance = query_token_balance(deps, config.eligible_collateral, config.insurance_fund)?;
+    if balance < shortfall {
+        return Err(ContractError::InsuranceFundExhausted { balance, shortfall });
+    }
+
+    Ok(execute_insurance_fund_withdrawal(deps, shortfall)?)
+}
+
 // Transfers the toll and spread fees to the the insurance fund and fee pool
 pub fn transfer_fees(
     deps: Deps,
@@ -149,8 +248,45 @@ pub fn transfer_fees(
     };
 
     if !toll_fee.is_zero() {
-        let msg = execute_transfer_from(deps.storage, &from, &config.fee_pool, toll_fee).unwrap();
-        messages.push(msg);
+        messages.push(execute_fee_pool_deposit(
+            deps.storage,
+            Some(&from),
+            toll_fee,
+        )?);
+    };
+
+    Ok(TransferResponse {
+        messages,
+        spread_fee,
+        toll_fee,
+    })
+}
+
+// Transfers the toll and spread fees already held by the engine, e.g. escrowed by an order
+pub fn transfer_escrowed_fees(
+    deps: Deps,
+    vamm: Addr,
+    notional: Uint128,
+) -> StdResult<TransferResponse> {
+    let config = read_config(deps.storage)?;
+
+    let CalcFeeResponse {
+        spread_fee,
+        toll_fee,
+    } = query_vamm_calc_fee(&deps, vamm.into_string(), notional)?;
+
+    let mut messages: Vec<SubMsg> = vec![];
+
+    if !spread_fee.is_zero() {
+        messages.push(execute_transfer(
+            deps.storage,
+            &config.insurance_fund,
+            spread_fee,
+        )?);
+    };
+
+    if !toll_fee.is_zero() {
+        messages.push(execute_fee_pool_deposit(deps.storage, None, toll_fee)?);
     };
 
     Ok(TransferResponse {
@@ -168,7 +304,7 @@ pub fn withdraw(
     eligible_collateral: AssetInfo,
     amount: Uint128,
     pre_paid_shortfall: Uint128,
-) -> StdResult<Vec<SubMsg>> {
+) -> Result<Vec<SubMsg>, ContractError> {
     let token_balance = query_token_balance(deps, eligible_collateral, env.contract.address)?;
 
     let mut messages: Vec<SubMsg> = vec![];
@@ -179,10 +315,10 @@ pub fn withdraw(
         // add any shortfall to bad_debt
         state.prepaid_bad_debt = state.prepaid_bad_debt.checked_add(shortfall)?;
 
-        messages.push(execute_insurance_fund_withdrawal(deps, shortfall).unwrap());
+        messages.push(execute_insurance_fund_cover(deps, shortfall)?);
     }
 
-    messages.push(execute_transfer(deps.storage, receiver, amount).unwrap());
+    messages.push(execute_transfer(deps.storage, receiver, amount)?);
 
     Ok(messages)
 }
@@ -2,7 +2,8 @@
 use cosmwasm_std::{to_binary, Deps, QueryRequest, StdResult, Uint128, WasmQuery};
 
 use margined_perp::{
-    margined_insurance_fund::{AllVammResponse, QueryMsg as InsuranceFundQueryMsg, VammResponse},
+    margined_insurance_fund::{QueryMsg as InsuranceFundQueryMsg, VammResponse},
+    margined_pricefeed::QueryMsg as PricefeedQueryMsg,
     margined_vamm::{CalcFeeResponse, ConfigResponse, Direction, QueryMsg, StateResponse},
 };
 
@@ -63,6 +64,22 @@ pub fn query_vamm_calc_fee(
     }))
 }
 
+// returns the spot price of the vamm
+pub fn query_vamm_spot_price(deps: &Deps, address: String) -> StdResult<Uint128> {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: address,
+        msg: to_binary(&QueryMsg::SpotPrice {})?,
+    }))
+}
+
+// returns the twap price of the vamm over the interval
+pub fn query_vamm_twap_price(deps: &Deps, address: String, interval: u64) -> StdResult<Uint128> {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: address,
+        msg: to_binary(&QueryMsg::TwapPrice { interval })?,
+    }))
+}
+
 // returns bool if vamm is over spread limit
 pub fn query_vamm_over_spread_limit(deps: &Deps, address: String) -> StdResult<bool> {
     deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
@@ -91,18 +108,6 @@ pub fn query_insurance_is_vamm(
     }))
 }
 
-// returns all vamm registered in the insurance contract
-pub fn query_insurance_all_vamm(
-    deps: &Deps,
-    insurance: String,
-    limit: Option<u32>,
-) -> StdResult<AllVammResponse> {
-    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-        contract_addr: insurance,
-        msg: to_binary(&InsuranceFundQueryMsg::GetAllVamm { limit })?,
-    }))
-}
-
 // returns bool if swap is over fluctuation limit
 pub fn query_is_over_fluctuation_limit(
     deps: &Deps,
@@ -118,3 +123,11 @@ pub fn query_is_over_fluctuation_limit(
         })?,
     }))
 }
+
+// returns the latest price of the key in the pricefeed
+pub fn query_pricefeed_price(deps: &Deps, pricefeed: String, key: String) -> StdResult<Uint128> {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: pricefeed,
+        msg: to_binary(&PricefeedQueryMsg::GetPrice { key })?,
+    }))
+}
@@ -1,20 +1,34 @@
-use cosmwasm_std::{Deps, StdError, StdResult, Uint128};
+use cosmwasm_std::{Addr, Deps, StdError, StdResult, Uint128};
 use margined_common::integer::Integer;
 use margined_perp::margined_engine::{
-    ConfigResponse, PauserResponse, PnlCalcOption, Position, PositionUnrealizedPnlResponse,
-    StateResponse,
+    AccountSummaryResponse, AdlRankingResponse, CollateralBalance, CollateralsResponse,
+    ConfigResponse, LeverageTiersResponse, LiquidatablePosition, LiquidatablePositionsResponse,
+    LiquidationType, Order, OrdersResponse, PauserResponse, PnlCalcOption, Position,
+    PositionSummary, PositionUnrealizedPnlResponse, PositionsResponse, RiskParamsResponse,
+    StateResponse, TraderCollateralResponse, TraderStats, TradersResponse, TradesResponse,
 };
 
 use crate::{
     contract::PAUSER,
-    querier::query_insurance_all_vamm,
-    state::{read_config, read_position, read_state, read_vamm_map, Config, State},
+    error::ContractError,
+    state::{
+        read_collateral, read_collaterals, read_config, read_cross_margin, read_market_config,
+        read_order, read_orders, read_position, read_position_config, read_state,
+        read_trader_collaterals, read_trader_orders, read_trader_positions, read_trader_stats,
+        read_traders, read_trades, read_vamm_map, read_vamm_positions, Config, State,
+    },
     utils::{
-        calc_funding_payment, calc_remain_margin_with_funding_payment,
-        get_position_notional_unrealized_pnl,
+        calc_adl_score, calc_funding_payment, calc_liquidation_price,
+        calc_remain_margin_with_funding_payment, calc_weighted_collateral_value,
+        get_account_collateral_value, get_liquidation_margin_ratio, get_margin_account_positions,
+        get_position_notional_unrealized_pnl, is_partial_liquidation, require_insufficient_margin,
+        require_vamm,
     },
 };
 
+const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
+const MAX_PAGINATION_LIMIT: u32 = 30u32;
+
 /// Queries contract Config
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config: Config = read_config(deps.storage)?;
@@ -29,6 +43,7 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         maintenance_margin_ratio: config.maintenance_margin_ratio,
         partial_liquidation_ratio: config.partial_liquidation_ratio,
         liquidation_fee: config.liquidation_fee,
+        keeper_fee: config.keeper_fee,
     })
 }
 
@@ -62,7 +77,7 @@ pub fn query_position(deps: Deps, vamm: String, trader: String) -> StdResult<Pos
 
     // a default is returned if no position found with no trader set
     if position.trader != trader {
-        return Err(StdError::generic_err("No position found"));
+        return Err(ContractError::NoPosition {}.into());
     }
 
     Ok(position)
@@ -70,22 +85,7 @@ pub fn query_position(deps: Deps, vamm: String, trader: String) -> StdResult<Pos
 
 /// Queries and returns users position for all registered vamms
 pub fn query_all_positions(deps: Deps, trader: String) -> StdResult<Vec<Position>> {
-    let config = read_config(deps.storage).unwrap();
-
-    let mut response: Vec<Position> = vec![];
-
-    let vamms = query_insurance_all_vamm(&deps, config.insurance_fund.to_string(), None)?.vamm_list;
-    for vamm in vamms.iter() {
-        let position =
-            read_position(deps.storage, vamm, &deps.api.addr_validate(&trader)?).unwrap();
-
-        // a default is returned if no position found with no trader set
-        if position.trader == trader {
-            response.push(position)
-        }
-    }
-
-    Ok(response)
+    read_trader_positions(deps.storage, &deps.api.addr_validate(&trader)?)
 }
 
 /// Queries user position
@@ -123,13 +123,15 @@ pub fn query_cumulative_premium_fraction(deps: Deps, vamm: String) -> StdResult<
 
 /// Queries traders balance across all vamms with funding payment
 pub fn query_trader_balance_with_funding_payment(deps: Deps, trader: String) -> StdResult<Uint128> {
-    let config = read_config(deps.storage).unwrap();
+    let valid_trader = deps.api.addr_validate(&trader)?;
 
     let mut margin = Uint128::zero();
-    let vamms = query_insurance_all_vamm(&deps, config.insurance_fund.to_string(), None)?.vamm_list;
-    for vamm in vamms.iter() {
-        let position =
-            query_trader_position_with_funding_payment(deps, vamm.to_string(), trader.clone())?;
+    for position in read_trader_positions(deps.storage, &valid_trader)? {
+        let position = query_trader_position_with_funding_payment(
+            deps,
+            position.vamm.to_string(),
+            trader.clone(),
+        )?;
         margin = margin.checked_add(position.margin)?;
     }
 
@@ -170,89 +172,83 @@ pub fn query_trader_position_with_funding_payment(
     Ok(position)
 }
 
-/// Queries the margin ratio of a trader
+/// Queries the margin ratio of a trader, computed over the whole account if cross margined
 pub fn query_margin_ratio(deps: Deps, vamm: String, trader: String) -> StdResult<Integer> {
     let config: Config = read_config(deps.storage)?;
 
-    // retrieve the latest position
-    let position = read_position(
-        deps.storage,
-        &deps.api.addr_validate(&vamm)?,
-        &deps.api.addr_validate(&trader)?,
-    )
-    .unwrap();
+    // retrieve the latest positions sharing margin
+    let trader = deps.api.addr_validate(&trader)?;
+    let positions = get_margin_account_positions(deps, &deps.api.addr_validate(&vamm)?, &trader)?;
+
+    let mut account_margin = Integer::zero();
+    let mut account_notional = Uint128::zero();
+    for position in positions {
+        let PositionUnrealizedPnlResponse {
+            position_notional,
+            unrealized_pnl,
+        } = get_least_beneficial_unrealized_pnl(deps, &position)?;
+
+        let remain_margin =
+            calc_remain_margin_with_funding_payment(deps, position, unrealized_pnl)?;
+
+        account_margin += Integer::new_positive(remain_margin.margin)
+            - Integer::new_positive(remain_margin.bad_debt);
+        account_notional = account_notional.checked_add(position_notional)?;
+    }
 
-    if position.size.is_zero() {
+    if account_notional.is_zero() {
         return Ok(Integer::zero());
     }
 
-    let PositionUnrealizedPnlResponse {
-        position_notional: spot_notional,
-        unrealized_pnl: spot_pnl,
-    } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::SpotPrice)?;
-    let PositionUnrealizedPnlResponse {
-        position_notional: twap_notional,
-        unrealized_pnl: twap_pnl,
-    } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::Twap)?;
+    // collateral deposited by cross margined traders backs the whole account
+    account_margin += Integer::new_positive(get_account_collateral_value(deps, &trader)?);
 
-    // calculate and return margin
-    let PositionUnrealizedPnlResponse {
-        position_notional,
-        unrealized_pnl,
-    } = if spot_pnl.abs() > twap_pnl.abs() {
-        PositionUnrealizedPnlResponse {
-            position_notional: twap_notional,
-            unrealized_pnl: twap_pnl,
-        }
-    } else {
-        PositionUnrealizedPnlResponse {
-            position_notional: spot_notional,
-            unrealized_pnl: spot_pnl,
-        }
-    };
-
-    let remain_margin = calc_remain_margin_with_funding_payment(deps, position, unrealized_pnl)?;
-
-    let margin_ratio = ((Integer::new_positive(remain_margin.margin)
-        - Integer::new_positive(remain_margin.bad_debt))
-        * Integer::new_positive(config.decimals))
-        / Integer::new_positive(position_notional);
+    let margin_ratio = (account_margin * Integer::new_positive(config.decimals))
+        / Integer::new_positive(account_notional);
 
     Ok(margin_ratio)
 }
 
-/// Queries the withdrawable collateral of a trader
+/// Queries the withdrawable collateral of a trader, computed over the whole account if cross margined
 pub fn query_free_collateral(deps: Deps, vamm: String, trader: String) -> StdResult<Integer> {
-    let config: Config = read_config(deps.storage)?;
+    let valid_trader = deps.api.addr_validate(&trader)?;
+
+    if !read_cross_margin(deps.storage, &valid_trader)? {
+        return calc_free_collateral(deps, vamm, trader);
+    }
+
+    calc_account_free_collateral(deps, &valid_trader)
+}
+
+/// Returns the free collateral of a cross margined account, the free collateral of each
+/// position plus the weighted value of the collateral deposited
+pub fn calc_account_free_collateral(deps: Deps, trader: &Addr) -> StdResult<Integer> {
+    let mut free_collateral = Integer::new_positive(get_account_collateral_value(deps, trader)?);
+    for position in query_all_positions(deps, trader.to_string())? {
+        free_collateral +=
+            calc_free_collateral(deps, position.vamm.to_string(), trader.to_string())?;
+    }
+
+    Ok(free_collateral)
+}
 
+/// Queries whether the positions of a trader are cross margined
+pub fn query_cross_margin(deps: Deps, trader: String) -> StdResult<bool> {
+    read_cross_margin(deps.storage, &deps.api.addr_validate(&trader)?)
+}
+
+// returns the free collateral of a single position
+fn calc_free_collateral(deps: Deps, vamm: String, trader: String) -> StdResult<Integer> {
     // retrieve the latest position
     let position = query_trader_position_with_funding_payment(deps, vamm, trader)?;
 
-    // get trader's unrealized PnL and choose the least beneficial one for the trader
-    let PositionUnrealizedPnlResponse {
-        position_notional: spot_notional,
-        unrealized_pnl: spot_pnl,
-    } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::SpotPrice)?;
-    let PositionUnrealizedPnlResponse {
-        position_notional: twap_notional,
-        unrealized_pnl: twap_pnl,
-    } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::Twap)?;
+    let config: Config = read_position_config(deps.storage, &position.vamm, position.notional)?;
 
-    // calculate and return margin
+    // get trader's unrealized PnL and choose the least beneficial one for the trader
     let PositionUnrealizedPnlResponse {
         position_notional,
         unrealized_pnl,
-    } = if spot_pnl.abs() > twap_pnl.abs() {
-        PositionUnrealizedPnlResponse {
-            position_notional: twap_notional,
-            unrealized_pnl: twap_pnl,
-        }
-    } else {
-        PositionUnrealizedPnlResponse {
-            position_notional: spot_notional,
-            unrealized_pnl: spot_pnl,
-        }
-    };
+    } = get_least_beneficial_unrealized_pnl(deps, &position)?;
 
     // min(margin + funding, margin + funding + unrealized PnL) - position value * initMarginRatio
     let account_value = unrealized_pnl.checked_add(Integer::new_positive(position.margin))?;
@@ -278,3 +274,427 @@ pub fn query_free_collateral(deps: Deps, vamm: String, trader: String) -> StdRes
 
     Ok(minimum_collateral.checked_sub(Integer::new_positive(margin_requirement))?)
 }
+
+// returns the notional and unrealized pnl from the spot or twap price, whichever has the smaller pnl
+fn get_least_beneficial_unrealized_pnl(
+    deps: Deps,
+    position: &Position,
+) -> StdResult<PositionUnrealizedPnlResponse> {
+    let PositionUnrealizedPnlResponse {
+        position_notional: spot_notional,
+        unrealized_pnl: spot_pnl,
+    } = get_position_notional_unrealized_pnl(deps, position, PnlCalcOption::SpotPrice)?;
+    let PositionUnrealizedPnlResponse {
+        position_notional: twap_notional,
+        unrealized_pnl: twap_pnl,
+    } = get_position_notional_unrealized_pnl(deps, position, PnlCalcOption::Twap)?;
+
+    if spot_pnl.abs() > twap_pnl.abs() {
+        Ok(PositionUnrealizedPnlResponse {
+            position_notional: twap_notional,
+            unrealized_pnl: twap_pnl,
+        })
+    } else {
+        Ok(PositionUnrealizedPnlResponse {
+            position_notional: spot_notional,
+            unrealized_pnl: spot_pnl,
+        })
+    }
+}
+
+/// Queries the vamm spot price at which the position of a trader would be liquidated
+pub fn query_liquidation_price(deps: Deps, vamm: String, trader: String) -> StdResult<Uint128> {
+    let position = query_position(deps, vamm, trader)?;
+
+    calc_liquidation_price(deps, &position)
+}
+
+/// Queries every position of a trader with its pnl, funding, margin ratio and liquidation price
+pub fn query_account_summary(deps: Deps, trader: String) -> StdResult<AccountSummaryResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let valid_trader = deps.api.addr_validate(&trader)?;
+
+    let mut positions: Vec<PositionSummary> = vec![];
+    let mut free_collateral = Integer::zero();
+    for position in query_all_positions(deps, trader.clone())? {
+        let PositionUnrealizedPnlResponse {
+            position_notional,
+            unrealized_pnl: spot_unrealized_pnl,
+        } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::SpotPrice)?;
+        let twap_unrealized_pnl =
+            get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::Twap)?
+                .unrealized_pnl;
+        let oracle_unrealized_pnl =
+            get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::Oracle)?
+                .unrealized_pnl;
+
+        let pending_funding = calc_funding_payment(
+            position.clone(),
+            query_cumulative_premium_fraction(deps, position.vamm.to_string())?,
+            config.decimals,
+        );
+
+        let position_free_collateral =
+            calc_free_collateral(deps, position.vamm.to_string(), trader.clone())?;
+        free_collateral += position_free_collateral;
+
+        positions.push(PositionSummary {
+            position_notional,
+            spot_unrealized_pnl,
+            twap_unrealized_pnl,
+            oracle_unrealized_pnl,
+            pending_funding,
+            margin_ratio: query_margin_ratio(deps, position.vamm.to_string(), trader.clone())?,
+            free_collateral: position_free_collateral,
+            liquidation_price: calc_liquidation_price(deps, &position)?,
+            position,
+        });
+    }
+
+    // deposited collateral only counts towards cross margined accounts
+    free_collateral += Integer::new_positive(get_account_collateral_value(deps, &valid_trader)?);
+
+    Ok(AccountSummaryResponse {
+        cross_margin: read_cross_margin(deps.storage, &valid_trader)?,
+        trader: valid_trader,
+        positions,
+        free_collateral,
+    })
+}
+
+/// Queries the trades of a trader in a vamm, oldest first
+pub fn query_trade_history(
+    deps: Deps,
+    vamm: String,
+    trader: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TradesResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    let trades = read_trades(
+        deps.storage,
+        &deps.api.addr_validate(&vamm)?,
+        &deps.api.addr_validate(&trader)?,
+        start_after,
+        limit,
+    )?;
+
+    Ok(TradesResponse { trades })
+}
+
+/// Queries the running totals of a trader in a vamm, or across every vamm if none is given
+pub fn query_trader_stats(
+    deps: Deps,
+    trader: String,
+    vamm: Option<String>,
+) -> StdResult<TraderStats> {
+    let vamm = vamm.map(|vamm| deps.api.addr_validate(&vamm)).transpose()?;
+
+    read_trader_stats(
+        deps.storage,
+        &deps.api.addr_validate(&trader)?,
+        vamm.as_ref(),
+    )
+}
+
+/// Queries an open order
+pub fn query_order(deps: Deps, vamm: String, order_id: u64) -> StdResult<Order> {
+    Ok(read_order(
+        deps.storage,
+        &deps.api.addr_validate(&vamm)?,
+        order_id,
+    )?)
+}
+
+/// Queries the open orders of a vamm
+pub fn query_orders(
+    deps: Deps,
+    vamm: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    let orders = read_orders(
+        deps.storage,
+        &deps.api.addr_validate(&vamm)?,
+        start_after,
+        limit,
+    )?;
+
+    Ok(OrdersResponse { orders })
+}
+
+/// Queries the open orders of a trader in a vamm
+pub fn query_trader_orders(
+    deps: Deps,
+    vamm: String,
+    trader: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    let orders = read_trader_orders(
+        deps.storage,
+        &deps.api.addr_validate(&vamm)?,
+        &deps.api.addr_validate(&trader)?,
+        start_after,
+        limit,
+    )?;
+
+    Ok(OrdersResponse { orders })
+}
+
+/// Queries the positions in a vamm, ordered by trader
+pub fn query_all_positions_for_vamm(
+    deps: Deps,
+    vamm: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PositionsResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|trader| deps.api.addr_validate(&trader))
+        .transpose()?;
+
+    let positions = read_vamm_positions(
+        deps.storage,
+        &deps.api.addr_validate(&vamm)?,
+        start_after,
+        limit,
+    )?;
+
+    Ok(PositionsResponse { positions })
+}
+
+/// Queries the traders with a position in any vamm
+pub fn query_all_traders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TradersResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|trader| deps.api.addr_validate(&trader))
+        .transpose()?;
+
+    let traders = read_traders(deps.storage, start_after, limit)?;
+
+    Ok(TradersResponse { traders })
+}
+
+/// Queries the positions in a vamm that can be liquidated, evaluating `limit` positions
+/// after `start_after` with the same margin ratio as a liquidation
+pub fn query_liquidatable_positions(
+    deps: Deps,
+    vamm: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<LiquidatablePositionsResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|trader| deps.api.addr_validate(&trader))
+        .transpose()?;
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+
+    let candidates = read_vamm_positions(deps.storage, &vamm, start_after, limit)?;
+
+    let mut positions: Vec<LiquidatablePosition> = vec![];
+    for position in candidates.iter() {
+        if position.size.is_zero() {
+            continue;
+        }
+
+        let maintenance_margin_ratio =
+            read_position_config(deps.storage, &vamm, position.notional)?.maintenance_margin_ratio;
+        let margin_ratio =
+            get_liquidation_margin_ratio(deps, vamm.to_string(), position.trader.to_string())?;
+        if require_insufficient_margin(margin_ratio, maintenance_margin_ratio).is_err() {
+            continue;
+        }
+
+        // the shortfall is measured against the spot notional of the margin account
+        let mut account_notional = Uint128::zero();
+        for account_position in get_margin_account_positions(deps, &vamm, &position.trader)? {
+            let PositionUnrealizedPnlResponse {
+                position_notional, ..
+            } = get_position_notional_unrealized_pnl(
+                deps,
+                &account_position,
+                PnlCalcOption::SpotPrice,
+            )?;
+            account_notional = account_notional.checked_add(position_notional)?;
+        }
+
+        let missing_ratio = if margin_ratio.negative {
+            maintenance_margin_ratio + margin_ratio.value
+        } else {
+            maintenance_margin_ratio - margin_ratio.value
+        };
+
+        positions.push(LiquidatablePosition {
+            trader: position.trader.clone(),
+            margin_ratio,
+            shortfall: account_notional.multiply_ratio(missing_ratio, config.decimals),
+            liquidation_type: if is_partial_liquidation(&config, margin_ratio) {
+                LiquidationType::Partial
+            } else {
+                LiquidationType::Full
+            },
+        });
+    }
+
+    Ok(LiquidatablePositionsResponse {
+        positions,
+        last_trader: candidates.last().map(|position| position.trader.clone()),
+    })
+}
+
+/// Queries the assets accepted as collateral besides the eligible collateral
+pub fn query_collaterals(deps: Deps) -> StdResult<CollateralsResponse> {
+    Ok(CollateralsResponse {
+        collaterals: read_collaterals(deps.storage)?,
+    })
+}
+
+/// Queries the collateral deposited by a trader with its weighted value, assets no longer
+/// registered have no value
+pub fn query_trader_collateral(deps: Deps, trader: String) -> StdResult<TraderCollateralResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    let mut balances: Vec<CollateralBalance> = vec![];
+    let mut total_value = Uint128::zero();
+    for balance in read_trader_collaterals(deps.storage, &trader)? {
+        let value = match read_collateral(deps.storage, &balance.info)? {
+            Some(collateral) => {
+                calc_weighted_collateral_value(deps, &config, &collateral, balance.amount)?
+            }
+            None => Uint128::zero(),
+        };
+        total_value = total_value.checked_add(value)?;
+
+        balances.push(CollateralBalance {
+            asset: balance.info,
+            amount: balance.amount,
+            value,
+        });
+    }
+
+    Ok(TraderCollateralResponse {
+        trader,
+        balances,
+        total_value,
+    })
+}
+
+/// Queries the risk parameters in effect for a vamm and the overrides it sets
+pub fn query_risk_params(deps: Deps, vamm: String) -> StdResult<RiskParamsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+
+    let market_config = read_market_config(deps.storage, &vamm)?;
+
+    Ok(RiskParamsResponse {
+        initial_margin_ratio: market_config.initial_margin_ratio,
+        maintenance_margin_ratio: market_config.maintenance_margin_ratio,
+        partial_liquidation_ratio: market_config.partial_liquidation_ratio,
+        liquidation_fee: market_config.liquidation_fee,
+        overrides: read_vamm_map(deps.storage, vamm.clone())?.risk_params,
+        vamm,
+    })
+}
+
+/// Queries the leverage tiers of a vamm
+pub fn query_leverage_tiers(deps: Deps, vamm: String) -> StdResult<LeverageTiersResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+
+    Ok(LeverageTiersResponse {
+        tiers: read_vamm_map(deps.storage, vamm.clone())?.leverage_tiers,
+        vamm,
+    })
+}
+
+/// Queries the score of a position and the number of positions in a page of the vamm
+/// that are ahead of it in the auto-deleveraging queue of its side
+pub fn query_adl_ranking(
+    deps: Deps,
+    vamm: String,
+    trader: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AdlRankingResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|trader| deps.api.addr_validate(&trader))
+        .transpose()?;
+    let config: Config = read_config(deps.storage)?;
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+
+    let position = read_position(deps.storage, &vamm, &trader)?;
+    if position.size.is_zero() {
+        return Err(StdError::NotFound {
+            kind: "No position found".to_string(),
+        });
+    }
+
+    let score = calc_adl_score(deps, &position)?;
+
+    let page = read_vamm_positions(deps.storage, &vamm, start_after, limit)?;
+
+    // losing positions are not in the queue so nothing is ahead of them
+    let mut ahead = 0u32;
+    if score > Integer::zero() {
+        for other in page.iter() {
+            if other.trader == trader
+                || other.size.is_zero()
+                || other.direction != position.direction
+            {
+                continue;
+            }
+
+            if calc_adl_score(deps, other)? > score {
+                ahead += 1;
+            }
+        }
+    }
+
+    Ok(AdlRankingResponse {
+        trader,
+        score,
+        ahead,
+        last_trader: if page.len() < limit {
+            None
+        } else {
+            page.last().map(|last| last.trader.clone())
+        },
+    })
+}
@@ -1,30 +1,31 @@
-use cosmwasm_std::{DepsMut, Env, Response, StdError, StdResult, SubMsg, Uint128};
+use cosmwasm_std::{Addr, DepsMut, Env, Response, Storage, SubMsg, Uint128};
 
 use crate::{
     contract::INCREASE_POSITION_REPLY_ID,
+    error::ContractError,
     handle::internal_increase_position,
     messages::{
         execute_insurance_fund_withdrawal, execute_transfer, execute_transfer_from,
-        execute_transfer_to_insurance_fund, transfer_fees, withdraw,
+        execute_transfer_to_insurance_fund, transfer_escrowed_fees, transfer_fees, withdraw,
     },
     querier::query_vamm_state,
     query::query_margin_ratio,
     state::{
-        append_cumulative_premium_fraction, enter_restriction_mode, read_config, read_sent_funds,
-        read_state, read_tmp_liquidator, read_tmp_swap, remove_position, remove_sent_funds,
-        remove_tmp_liquidator, remove_tmp_swap, store_position, store_sent_funds, store_state,
-        store_tmp_swap, Config, State, TmpSwapInfo,
+        add_batch_required_funds, append_cumulative_premium_fraction, enter_restriction_mode,
+        read_config, read_market_config, read_position_config, read_reply_context, read_state,
+        remove_position, remove_reply_context, store_position, store_reply_context, store_state,
+        store_trade, Config, ReplyContext, SentFunds, State, TmpSwapInfo,
     },
     utils::{
-        calc_remain_margin_with_funding_payment, check_base_asset_holding_cap, clear_position,
-        get_position, realize_bad_debt, require_additional_margin, side_to_direction,
-        update_open_interest_notional,
+        absorb_account_bad_debt, calc_remain_margin_with_funding_payment, cancel_trigger_orders,
+        check_base_asset_holding_cap, clear_position, get_position, new_trade, realize_bad_debt,
+        require_additional_margin, side_to_direction, update_open_interest_notional,
     },
 };
 
 use margined_common::{asset::AssetInfo, integer::Integer};
 use margined_perp::{
-    margined_engine::{Position, RemainMarginResponse, Side},
+    margined_engine::{Position, RemainMarginResponse, Side, Trade},
     margined_vamm::Direction,
 };
 
@@ -34,13 +35,16 @@ pub fn update_position_reply(
     env: Env,
     input: Uint128,
     output: Uint128,
+    nonce: u64,
     reply_id: u64,
-) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+) -> Result<Response, ContractError> {
     let mut state: State = read_state(deps.storage)?;
 
-    let mut swap = read_tmp_swap(deps.storage)?;
-    let mut funds = read_sent_funds(deps.storage)?;
+    let context = read_reply_context(deps.storage, nonce)?;
+    let mut funds = read_context_funds(&context)?;
+    let mut swap = context.swap;
+
+    let config: Config = read_market_config(deps.storage, &swap.vamm)?;
 
     let mut position = get_position(
         env.clone(),
@@ -73,6 +77,7 @@ pub fn update_position_reply(
     let margin_delta: Integer;
     let new_direction: Direction;
     let new_notional: Uint128;
+    let realized_pnl: Integer;
 
     // calculate margin needed given swap
     match reply_id {
@@ -89,13 +94,14 @@ pub fn update_position_reply(
             margin_delta = Integer::new_positive(swap_margin);
             new_direction = side_to_direction(swap.side);
             new_notional = position.notional.checked_add(swap.open_notional)?;
+            realized_pnl = Integer::zero();
         }
         // DECREASE_POSITION_REPLY
         _ => {
             swap_margin = Uint128::zero();
 
             // realized_pnl = unrealized_pnl * close_ratio
-            let realized_pnl = if !position.size.is_zero() {
+            realized_pnl = if !position.size.is_zero() {
                 swap.unrealized_pnl.checked_mul(signed_output.abs())? / position.size.abs()
             } else {
                 Integer::zero()
@@ -120,7 +126,7 @@ pub fn update_position_reply(
 
     // calculate the remaining margin
     let RemainMarginResponse {
-        funding_payment: _,
+        funding_payment,
         margin,
         bad_debt: _,
         latest_premium_fraction,
@@ -152,7 +158,7 @@ pub fn update_position_reply(
         msgs.append(
             &mut withdraw(
                 deps.as_ref(),
-                env,
+                env.clone(),
                 &mut state,
                 &swap.trader,
                 config.eligible_collateral.clone(),
@@ -166,6 +172,9 @@ pub fn update_position_reply(
             AssetInfo::NativeToken { .. } => {
                 funds.required = funds.required.checked_add(swap_margin)?;
             }
+            AssetInfo::Token { .. } if swap.escrowed => {
+                funds.required = funds.required.checked_add(swap_margin)?;
+            }
             AssetInfo::Token { .. } => {
                 msgs.push(
                     execute_transfer_from(
@@ -185,8 +194,11 @@ pub fn update_position_reply(
 
     // create messages to pay for toll and spread fees, check flag is true if this follows a reverse
     if !swap.fees_paid {
-        let mut fees =
-            transfer_fees(deps.as_ref(), swap.trader, swap.vamm, swap.open_notional).unwrap();
+        let mut fees = if swap.escrowed {
+            transfer_escrowed_fees(deps.as_ref(), swap.vamm, swap.open_notional)?
+        } else {
+            transfer_fees(deps.as_ref(), swap.trader, swap.vamm, swap.open_notional).unwrap()
+        };
 
         // add the fee transfer messages
         msgs.append(&mut fees.messages);
@@ -201,10 +213,8 @@ pub fn update_position_reply(
         fees_amount[1] = fees.toll_fee;
     };
 
-    // check if native tokens are sufficient
-    if let AssetInfo::NativeToken { .. } = config.eligible_collateral {
-        funds.are_sufficient()?;
-    }
+    // check if native or escrowed tokens are sufficient
+    check_sent_funds(deps.storage, &config, swap.escrowed, &funds, context.batch)?;
 
     // check that the maintenance margin is correct
     let margin_ratio = query_margin_ratio(
@@ -213,12 +223,29 @@ pub fn update_position_reply(
         position.trader.to_string(),
     )?;
 
-    require_additional_margin(margin_ratio, config.maintenance_margin_ratio)?;
+    let tier_config = read_position_config(deps.storage, &position.vamm, position.notional)?;
+    require_additional_margin(margin_ratio, tier_config.maintenance_margin_ratio)?;
+
+    // record the trade in the trader's history
+    let trade = Trade {
+        spread_fee: fees_amount[0],
+        toll_fee: fees_amount[1],
+        realized_pnl,
+        funding_paid: funding_payment,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &position.vamm,
+            &position.trader,
+            signed_output,
+            input,
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
 
     store_state(deps.storage, &state)?;
 
-    remove_tmp_swap(deps.storage);
-    remove_sent_funds(deps.storage);
+    remove_reply_context(deps.storage, nonce);
 
     Ok(Response::new().add_submessages(msgs).add_attributes(vec![
         ("action", "update_position_reply"),
@@ -233,11 +260,13 @@ pub fn reverse_position_reply(
     env: Env,
     _input: Uint128,
     output: Uint128,
-) -> StdResult<Response> {
+    nonce: u64,
+) -> Result<Response, ContractError> {
     let config = read_config(deps.storage)?;
     let mut state = read_state(deps.storage)?;
-    let mut swap = read_tmp_swap(deps.storage)?;
-    let mut funds = read_sent_funds(deps.storage)?;
+    let context = read_reply_context(deps.storage, nonce)?;
+    let mut funds = read_context_funds(&context)?;
+    let mut swap = context.swap;
 
     let mut position = get_position(
         env.clone(),
@@ -256,9 +285,10 @@ pub fn reverse_position_reply(
     )?;
 
     let previous_margin = Integer::new_negative(position.margin);
+    let previous_size = position.size;
 
     // reset the position in order to reverse
-    position = clear_position(env, position)?;
+    position = clear_position(env.clone(), position)?;
 
     // now increase the position again if there is additional position
     let current_open_notional = swap.open_notional;
@@ -269,17 +299,44 @@ pub fn reverse_position_reply(
     };
 
     // create messages to pay for toll and spread fees
-    let fees = transfer_fees(
-        deps.as_ref(),
-        swap.trader.clone(),
-        swap.vamm.clone(),
-        current_open_notional,
-    )
-    .unwrap();
+    let fees = if swap.escrowed {
+        transfer_escrowed_fees(deps.as_ref(), swap.vamm.clone(), current_open_notional)?
+    } else {
+        transfer_fees(
+            deps.as_ref(),
+            swap.trader.clone(),
+            swap.vamm.clone(),
+            current_open_notional,
+        )
+        .unwrap()
+    };
+
+    // record the closing trade, the new position is recorded once increased
+    let trade = Trade {
+        spread_fee: fees.spread_fee,
+        toll_fee: fees.toll_fee,
+        realized_pnl: swap.unrealized_pnl,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &swap.vamm,
+            &swap.trader,
+            previous_size.invert_sign(),
+            output,
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
 
     // add the fee transfer messages
     let mut msgs: Vec<SubMsg> = fees.messages;
 
+    // the orders of the reversed position no longer apply
+    msgs.append(&mut cancel_trigger_orders(
+        deps.storage,
+        &swap.vamm,
+        &swap.trader,
+    )?);
+
     // add the total fees (spread + toll) to the required funds counter
     funds.required = funds
         .required
@@ -295,12 +352,9 @@ pub fn reverse_position_reply(
         msgs.push(execute_transfer(deps.storage, &swap.trader, margin.value).unwrap());
 
         // check if native tokens are sufficient
-        if let AssetInfo::NativeToken { .. } = config.eligible_collateral {
-            funds.are_sufficient()?;
-        }
+        check_sent_funds(deps.storage, &config, swap.escrowed, &funds, context.batch)?;
 
-        remove_sent_funds(deps.storage);
-        remove_tmp_swap(deps.storage);
+        remove_reply_context(deps.storage, nonce);
     } else {
         // determine new position
         swap.margin_to_vault = previous_margin.checked_sub(swap.unrealized_pnl)?;
@@ -324,10 +378,20 @@ pub fn reverse_position_reply(
             swap.side.clone(),
             swap.open_notional,
             Uint128::zero(),
+            nonce,
         )?);
 
-        store_tmp_swap(deps.storage, &swap)?;
-        store_sent_funds(deps.storage, &funds)?;
+        // the increase replies to the same context
+        store_reply_context(
+            deps.storage,
+            nonce,
+            &ReplyContext {
+                swap,
+                funds: Some(funds),
+                liquidator: None,
+                batch: context.batch,
+            },
+        )?;
     }
 
     store_position(deps.storage, &position)?;
@@ -342,14 +406,15 @@ pub fn reverse_position_reply(
 
 // Closes position after successful execution of the swap
 pub fn close_position_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     _input: Uint128,
     output: Uint128,
-) -> StdResult<Response> {
+    nonce: u64,
+) -> Result<Response, ContractError> {
     let config = read_config(deps.storage)?;
     let mut state = read_state(deps.storage)?;
-    let swap = read_tmp_swap(deps.storage)?;
+    let swap = read_reply_context(deps.storage, nonce)?.swap;
 
     let position = get_position(
         env.clone(),
@@ -379,16 +444,21 @@ pub fn close_position_reply(
 
     let mut msgs: Vec<SubMsg> = vec![];
 
+    // cross margined positions are covered by the rest of the trader's account
+    let uncovered_bad_debt = absorb_account_bad_debt(deps.branch(), &position, bad_debt)?;
+
     // to prevent attacker to leverage the bad debt to withdraw extra token from insurance fund
-    if !bad_debt.is_zero() {
-        return Err(StdError::generic_err("Cannot close position - bad debt"));
+    if !uncovered_bad_debt.is_zero() {
+        return Err(ContractError::BadDebt {
+            bad_debt: uncovered_bad_debt,
+        });
     }
 
     if !withdraw_amount.is_zero() {
         msgs.append(
             &mut withdraw(
                 deps.as_ref(),
-                env,
+                env.clone(),
                 &mut state,
                 &swap.trader,
                 config.eligible_collateral,
@@ -417,6 +487,23 @@ pub fn close_position_reply(
         msgs.append(&mut fees.messages);
     }
 
+    // record the trade in the trader's history
+    let trade = Trade {
+        spread_fee: fees_amount[0],
+        toll_fee: fees_amount[1],
+        realized_pnl: margin_delta,
+        funding_paid: funding_payment,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &position.vamm,
+            &position.trader,
+            position.size.invert_sign(),
+            output,
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
+
     let value =
         margin_delta + Integer::new_positive(bad_debt) + Integer::new_positive(position.notional);
 
@@ -429,10 +516,15 @@ pub fn close_position_reply(
     )?;
 
     remove_position(deps.storage, &position);
+    msgs.append(&mut cancel_trigger_orders(
+        deps.storage,
+        &position.vamm,
+        &position.trader,
+    )?);
 
     store_state(deps.storage, &state)?;
 
-    remove_tmp_swap(deps.storage);
+    remove_reply_context(deps.storage, nonce);
 
     Ok(Response::new().add_submessages(msgs).add_attributes(vec![
         ("action", "close_position_reply"),
@@ -449,10 +541,11 @@ pub fn partial_close_position_reply(
     env: Env,
     input: Uint128,
     output: Uint128,
-) -> StdResult<Response> {
+    nonce: u64,
+) -> Result<Response, ContractError> {
     let mut state: State = read_state(deps.storage)?;
 
-    let swap: TmpSwapInfo = read_tmp_swap(deps.storage)?;
+    let swap: TmpSwapInfo = read_reply_context(deps.storage, nonce)?.swap;
 
     let mut position: Position = get_position(
         env.clone(),
@@ -502,7 +595,30 @@ pub fn partial_close_position_reply(
     };
 
     // calculate the fees
-    let fees = transfer_fees(deps.as_ref(), swap.trader, swap.vamm, swap.open_notional).unwrap();
+    let fees = transfer_fees(
+        deps.as_ref(),
+        swap.trader.clone(),
+        swap.vamm.clone(),
+        swap.open_notional,
+    )
+    .unwrap();
+
+    // record the trade in the trader's history
+    let trade = Trade {
+        spread_fee: fees.spread_fee,
+        toll_fee: fees.toll_fee,
+        realized_pnl,
+        funding_paid: funding_payment,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &swap.vamm,
+            &swap.trader,
+            signed_output,
+            input,
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
 
     // set the new position
     position.size += signed_output;
@@ -516,11 +632,11 @@ pub fn partial_close_position_reply(
 
     // to prevent attacker to leverage the bad debt to withdraw extra token from insurance fund
     if !bad_debt.is_zero() {
-        return Err(StdError::generic_err("Cannot close position - bad debt"));
+        return Err(ContractError::BadDebt { bad_debt });
     }
 
-    // remove the tmp position
-    remove_tmp_swap(deps.storage);
+    // remove the reply context
+    remove_reply_context(deps.storage, nonce);
 
     Ok(Response::new()
         .add_submessages(fees.messages)
@@ -535,16 +651,19 @@ pub fn partial_close_position_reply(
 
 // Liquidates position after successful execution of the swap
 pub fn liquidate_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     _input: Uint128,
     output: Uint128,
-) -> StdResult<Response> {
-    let config = read_config(deps.storage)?;
+    nonce: u64,
+) -> Result<Response, ContractError> {
     let mut state = read_state(deps.storage)?;
 
-    let swap = read_tmp_swap(deps.storage)?;
-    let liquidator = read_tmp_liquidator(deps.storage)?;
+    let context = read_reply_context(deps.storage, nonce)?;
+    let liquidator = read_context_liquidator(&context)?;
+    let swap = context.swap;
+
+    let config = read_market_config(deps.storage, &swap.vamm)?;
 
     let position = get_position(
         env.clone(),
@@ -584,10 +703,14 @@ pub fn liquidate_reply(
         remain_margin.margin = remain_margin.margin.checked_sub(liquidation_fee)?;
     }
 
+    // cross margined positions are covered by the rest of the trader's account
+    remain_margin.bad_debt =
+        absorb_account_bad_debt(deps.branch(), &position, remain_margin.bad_debt)?;
+
     let mut msgs: Vec<SubMsg> = vec![];
 
     let pre_paid_shortfall: Uint128 = if !remain_margin.bad_debt.is_zero() {
-        realize_bad_debt(deps.as_ref(), remain_margin.bad_debt, &mut msgs, &mut state)
+        realize_bad_debt(deps.as_ref(), remain_margin.bad_debt, &mut msgs, &mut state)?
     } else {
         Uint128::zero()
     };
@@ -612,11 +735,31 @@ pub fn liquidate_reply(
         .unwrap(),
     );
 
+    // record the liquidation in the trader's history
+    let trade = Trade {
+        realized_pnl: margin_delta,
+        funding_paid: remain_margin.funding_payment,
+        liquidation: true,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &position.vamm,
+            &position.trader,
+            position.size.invert_sign(),
+            output,
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
+
     store_state(deps.storage, &state)?;
 
     remove_position(deps.storage, &position);
-    remove_tmp_swap(deps.storage);
-    remove_tmp_liquidator(deps.storage);
+    msgs.append(&mut cancel_trigger_orders(
+        deps.storage,
+        &position.vamm,
+        &position.trader,
+    )?);
+    remove_reply_context(deps.storage, nonce);
 
     enter_restriction_mode(deps.storage, swap.vamm, env.block.height)?;
 
@@ -638,13 +781,15 @@ pub fn partial_liquidation_reply(
     env: Env,
     input: Uint128,
     output: Uint128,
-) -> StdResult<Response> {
-    let config = read_config(deps.storage)?;
+    nonce: u64,
+) -> Result<Response, ContractError> {
     let mut state = read_state(deps.storage)?;
 
-    let swap = read_tmp_swap(deps.storage)?;
+    let context = read_reply_context(deps.storage, nonce)?;
+    let liquidator = read_context_liquidator(&context)?;
+    let swap = context.swap;
 
-    let liquidator = read_tmp_liquidator(deps.storage)?;
+    let config = read_market_config(deps.storage, &swap.vamm)?;
 
     let mut position = get_position(
         env.clone(),
@@ -664,6 +809,7 @@ pub fn partial_liquidation_reply(
         .checked_div(config.decimals)?;
 
     let liquidation_fee: Uint128 = liquidation_penalty.checked_div(Uint128::from(2u64))?;
+    let previous_size = position.size;
 
     if position.size < Integer::zero() {
         position.size += Integer::new_positive(input);
@@ -716,11 +862,25 @@ pub fn partial_liquidation_reply(
         );
     }
 
+    // record the liquidation in the trader's history
+    let trade = Trade {
+        realized_pnl,
+        liquidation: true,
+        ..new_trade(
+            deps.storage,
+            &env,
+            &position.vamm,
+            &position.trader,
+            position.size - previous_size,
+            output,
+        )?
+    };
+    store_trade(deps.storage, &trade)?;
+
     store_position(deps.storage, &position)?;
     store_state(deps.storage, &state)?;
 
-    remove_tmp_swap(deps.storage);
-    remove_tmp_liquidator(deps.storage);
+    remove_reply_context(deps.storage, nonce);
 
     enter_restriction_mode(deps.storage, swap.vamm, env.block.height)?;
 
@@ -740,7 +900,7 @@ pub fn pay_funding_reply(
     env: Env,
     premium_fraction: Integer,
     sender: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let config = read_config(deps.storage)?;
     let vamm = deps.api.addr_validate(&sender)?;
 
@@ -768,3 +928,37 @@ pub fn pay_funding_reply(
         ("funding_payment", &funding_payment.to_string()),
     ]))
 }
+
+// the funds sent with the operation, only stored when opening or increasing a position
+fn read_context_funds(context: &ReplyContext) -> Result<SentFunds, ContractError> {
+    context
+        .funds
+        .clone()
+        .ok_or(ContractError::MissingSentFunds {})
+}
+
+// the liquidator of the position, only stored when liquidating
+fn read_context_liquidator(context: &ReplyContext) -> Result<Addr, ContractError> {
+    context
+        .liquidator
+        .clone()
+        .ok_or(ContractError::MissingLiquidator {})
+}
+
+// checks the native or escrowed funds sent cover the funds required, if the swap is part
+// of a batch the funds required are added to the batch and reconciled once it is finished
+fn check_sent_funds(
+    storage: &mut dyn Storage,
+    config: &Config,
+    escrowed: bool,
+    funds: &SentFunds,
+    batch: Option<u64>,
+) -> Result<(), ContractError> {
+    if let Some(batch_id) = batch {
+        add_batch_required_funds(storage, batch_id, funds.required)?;
+    } else if escrowed || matches!(config.eligible_collateral, AssetInfo::NativeToken { .. }) {
+        funds.are_sufficient()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,711 @@
+// Dry-run simulations of the position handlers, these follow the math of the
+// execute handlers and their replies but swap against a copy of the vamm reserves
+use cosmwasm_std::{Addr, Deps, Env, StdResult, Uint128};
+use margined_common::integer::Integer;
+use margined_perp::{
+    amm::{calc_input_amount, calc_output_amount},
+    margined_engine::{
+        PnlCalcOption, Position, PositionUnrealizedPnlResponse, RemainMarginResponse, Side,
+        SimulationResponse,
+    },
+    margined_vamm::{CalcFeeResponse, Direction},
+};
+
+use crate::{
+    error::ContractError,
+    querier::{
+        query_is_over_fluctuation_limit, query_vamm_calc_fee, query_vamm_config, query_vamm_state,
+    },
+    query::query_free_collateral,
+    state::{
+        read_config, read_market_config, read_position, read_position_config, read_state, Config,
+        State,
+    },
+    utils::{
+        calc_remain_margin_with_funding_payment, check_base_asset_holding_cap, clear_position,
+        get_position, get_position_notional_unrealized_pnl, position_to_side,
+        require_additional_margin, require_bad_debt, require_non_zero_input, require_not_paused,
+        require_not_restriction_mode, require_position_not_zero, require_valid_leverage,
+        require_vamm, side_to_direction, update_open_interest_notional,
+    },
+};
+
+// copy of the vamm reserves, swaps follow the rounding of the vamm
+struct SimulatedVamm {
+    quote_asset_reserve: Uint128,
+    base_asset_reserve: Uint128,
+    decimals: Uint128,
+}
+
+impl SimulatedVamm {
+    fn new(deps: Deps, vamm: &Addr) -> StdResult<Self> {
+        let state = query_vamm_state(&deps, vamm.to_string())?;
+        let config = query_vamm_config(&deps, vamm.to_string())?;
+
+        Ok(SimulatedVamm {
+            quote_asset_reserve: state.quote_asset_reserve,
+            base_asset_reserve: state.base_asset_reserve,
+            decimals: config.decimals,
+        })
+    }
+
+    // returns the base asset exchanged for the quote asset amount
+    fn input_amount(
+        &self,
+        direction: &Direction,
+        quote_asset_amount: Uint128,
+    ) -> StdResult<Uint128> {
+        calc_input_amount(
+            direction,
+            quote_asset_amount,
+            self.quote_asset_reserve,
+            self.base_asset_reserve,
+            self.decimals,
+        )
+    }
+
+    // returns the quote asset exchanged for the base asset amount
+    fn output_amount(
+        &self,
+        direction: &Direction,
+        base_asset_amount: Uint128,
+    ) -> StdResult<Uint128> {
+        calc_output_amount(
+            direction,
+            base_asset_amount,
+            self.quote_asset_reserve,
+            self.base_asset_reserve,
+            self.decimals,
+        )
+    }
+
+    // swaps quote for base asset, as `SwapInput` on the vamm
+    fn swap_input(
+        &mut self,
+        direction: Direction,
+        quote_asset_amount: Uint128,
+        base_asset_limit: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        let base_asset_amount = self.input_amount(&direction, quote_asset_amount)?;
+
+        if !base_asset_limit.is_zero() {
+            if direction == Direction::AddToAmm && base_asset_amount < base_asset_limit {
+                return Err(ContractError::BaseAssetBelowLimit {
+                    amount: base_asset_amount,
+                    limit: base_asset_limit,
+                });
+            } else if direction == Direction::RemoveFromAmm && base_asset_amount > base_asset_limit
+            {
+                return Err(ContractError::BaseAssetAboveLimit {
+                    amount: base_asset_amount,
+                    limit: base_asset_limit,
+                });
+            }
+        }
+
+        self.update_reserve(&direction, quote_asset_amount, base_asset_amount)?;
+
+        Ok(base_asset_amount)
+    }
+
+    // swaps base for quote asset, as `SwapOutput` on the vamm
+    fn swap_output(
+        &mut self,
+        direction: Direction,
+        base_asset_amount: Uint128,
+        quote_asset_limit: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        let quote_asset_amount = self.output_amount(&direction, base_asset_amount)?;
+
+        // flip direction when updating reserve
+        let update_direction = match direction {
+            Direction::AddToAmm => Direction::RemoveFromAmm,
+            Direction::RemoveFromAmm => Direction::AddToAmm,
+        };
+
+        if !quote_asset_limit.is_zero() {
+            if update_direction == Direction::RemoveFromAmm
+                && quote_asset_amount < quote_asset_limit
+            {
+                return Err(ContractError::QuoteAssetBelowLimit {
+                    amount: quote_asset_amount,
+                    limit: quote_asset_limit,
+                });
+            } else if update_direction == Direction::AddToAmm
+                && quote_asset_amount > quote_asset_limit
+            {
+                return Err(ContractError::QuoteAssetAboveLimit {
+                    amount: quote_asset_amount,
+                    limit: quote_asset_limit,
+                });
+            }
+        }
+
+        self.update_reserve(&update_direction, quote_asset_amount, base_asset_amount)?;
+
+        Ok(quote_asset_amount)
+    }
+
+    fn update_reserve(
+        &mut self,
+        direction: &Direction,
+        quote_asset_amount: Uint128,
+        base_asset_amount: Uint128,
+    ) -> StdResult<()> {
+        match direction {
+            Direction::AddToAmm => {
+                self.quote_asset_reserve =
+                    self.quote_asset_reserve.checked_add(quote_asset_amount)?;
+                self.base_asset_reserve = self.base_asset_reserve.checked_sub(base_asset_amount)?;
+            }
+            Direction::RemoveFromAmm => {
+                self.base_asset_reserve = self.base_asset_reserve.checked_add(base_asset_amount)?;
+                self.quote_asset_reserve =
+                    self.quote_asset_reserve.checked_sub(quote_asset_amount)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Simulates opening, increasing, reducing or reversing a position
+#[allow(clippy::too_many_arguments)]
+pub fn query_simulate_open_position(
+    deps: Deps,
+    env: Env,
+    vamm: String,
+    trader: String,
+    side: Side,
+    margin_amount: Uint128,
+    leverage: Uint128,
+    base_asset_limit: Uint128,
+) -> StdResult<SimulationResponse> {
+    let mut state: State = read_state(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
+    require_not_paused(state.pause)?;
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+    require_not_restriction_mode(deps.storage, &vamm, &trader, env.block.height)?;
+    require_non_zero_input(margin_amount)?;
+    require_non_zero_input(leverage)?;
+
+    let mut amm = SimulatedVamm::new(deps, &vamm)?;
+    let mut position: Position =
+        get_position(env.clone(), deps.storage, &vamm, &trader, side.clone());
+    let previous_size = position.size;
+
+    // if direction and side are same way then increasing else we are reversing
+    let is_increase: bool = position.direction == Direction::AddToAmm && side == Side::Buy
+        || position.direction == Direction::RemoveFromAmm && side == Side::Sell;
+
+    // calculate the position notional, fees are paid on it whichever way the position goes
+    let open_notional = margin_amount
+        .checked_mul(leverage)?
+        .checked_div(config.decimals)?;
+
+    let CalcFeeResponse {
+        spread_fee,
+        toll_fee,
+    } = query_vamm_calc_fee(&deps, vamm.to_string(), open_notional)?;
+
+    let PositionUnrealizedPnlResponse {
+        position_notional,
+        unrealized_pnl,
+    } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::SpotPrice)?;
+
+    // the leverage allowed depends on the tier of the notional after the trade
+    let notional = if is_increase {
+        position.notional.checked_add(open_notional)?
+    } else if position_notional > open_notional {
+        position_notional.checked_sub(open_notional)?
+    } else {
+        open_notional.checked_sub(position_notional)?
+    };
+    let tier_config = read_position_config(deps.storage, &vamm, notional)?;
+    require_valid_leverage(leverage, config.decimals, tier_config.initial_margin_ratio)?;
+
+    let exchanged_quote_amount: Uint128;
+    let realized_pnl: Integer;
+    let funding_payment: Integer;
+    let margin_to_vault: Integer;
+    let bad_debt: Uint128;
+
+    if is_increase {
+        let output = amm.swap_input(
+            side_to_direction(side.clone()),
+            open_notional,
+            base_asset_limit,
+        )?;
+        update_open_interest_notional(
+            &deps,
+            &mut state,
+            vamm.clone(),
+            Integer::new_positive(open_notional),
+            trader.clone(),
+        )?;
+
+        let swap_margin = open_notional
+            .checked_mul(config.decimals)?
+            .checked_div(leverage)?;
+
+        let remain_margin = calc_remain_margin_with_funding_payment(
+            deps,
+            position.clone(),
+            Integer::new_positive(swap_margin),
+        )?;
+
+        position.direction = side_to_direction(side.clone());
+        position.notional = position.notional.checked_add(open_notional)?;
+        position.size += signed_size(&side, output);
+        position.margin = remain_margin.margin;
+        position.last_updated_premium_fraction = remain_margin.latest_premium_fraction;
+        position.block_number = env.block.height;
+
+        exchanged_quote_amount = open_notional;
+        realized_pnl = Integer::zero();
+        funding_payment = remain_margin.funding_payment;
+        margin_to_vault = Integer::new_positive(swap_margin);
+        bad_debt = remain_margin.bad_debt;
+    } else if position_notional > open_notional {
+        // reduce position if old position is larger
+        let output = amm.swap_input(
+            side_to_direction(side.clone()),
+            open_notional,
+            base_asset_limit,
+        )?;
+        let signed_output = signed_size(&side, output);
+
+        // realized_pnl = unrealized_pnl * close_ratio
+        let close_pnl = unrealized_pnl.checked_mul(signed_output.abs())? / position.size.abs();
+        let unrealized_pnl_after = unrealized_pnl - close_pnl;
+
+        let remaining_notional = if position.size > Integer::zero() {
+            Integer::new_positive(position_notional)
+                - Integer::new_positive(open_notional)
+                - unrealized_pnl_after
+        } else {
+            unrealized_pnl_after + Integer::new_positive(position_notional)
+                - Integer::new_positive(open_notional)
+        };
+
+        let remain_margin =
+            calc_remain_margin_with_funding_payment(deps, position.clone(), close_pnl)?;
+
+        position.notional = remaining_notional.value;
+        position.size += signed_output;
+        position.margin = remain_margin.margin;
+        position.last_updated_premium_fraction = remain_margin.latest_premium_fraction;
+        position.block_number = env.block.height;
+
+        exchanged_quote_amount = open_notional;
+        realized_pnl = close_pnl;
+        funding_payment = remain_margin.funding_payment;
+        margin_to_vault = Integer::zero();
+        bad_debt = remain_margin.bad_debt;
+    } else {
+        // first close position swap out the entire position
+        let output = amm.swap_output(
+            position.direction.clone(),
+            position.size.value,
+            Uint128::zero(),
+        )?;
+
+        // the margin and pnl of the closed position are returned to the trader
+        let closed_margin = Integer::new_positive(position.margin) + unrealized_pnl;
+        let remaining_open_notional = if open_notional > output {
+            open_notional.checked_sub(output)?
+        } else {
+            output.checked_sub(open_notional)?
+        };
+
+        position = clear_position(env.clone(), position)?;
+
+        if remaining_open_notional.checked_div(leverage)?.is_zero() {
+            exchanged_quote_amount = output;
+            margin_to_vault = if closed_margin.is_negative() {
+                Integer::zero()
+            } else {
+                closed_margin.invert_sign()
+            };
+            bad_debt = if closed_margin.is_negative() {
+                closed_margin.value
+            } else {
+                Uint128::zero()
+            };
+        } else {
+            // now increase the position again with the remaining notional
+            let output = amm.swap_input(
+                side_to_direction(side.clone()),
+                remaining_open_notional,
+                Uint128::zero(),
+            )?;
+            update_open_interest_notional(
+                &deps,
+                &mut state,
+                vamm.clone(),
+                Integer::new_positive(remaining_open_notional),
+                trader.clone(),
+            )?;
+
+            let swap_margin = remaining_open_notional
+                .checked_mul(config.decimals)?
+                .checked_div(leverage)?;
+
+            let remain_margin = calc_remain_margin_with_funding_payment(
+                deps,
+                position.clone(),
+                Integer::new_positive(swap_margin),
+            )?;
+
+            position.direction = side_to_direction(side.clone());
+            position.notional = remaining_open_notional;
+            position.size = signed_size(&side, output);
+            position.margin = remain_margin.margin;
+            position.last_updated_premium_fraction = remain_margin.latest_premium_fraction;
+
+            exchanged_quote_amount = output.checked_add(remaining_open_notional)?;
+            margin_to_vault = Integer::new_positive(swap_margin) - closed_margin;
+            bad_debt = Uint128::zero();
+        }
+
+        realized_pnl = unrealized_pnl;
+        funding_payment = Integer::zero();
+    }
+
+    let tier_config = read_position_config(deps.storage, &vamm, position.notional)?;
+
+    // check the new position doesn't exceed any caps
+    check_base_asset_holding_cap(&deps, vamm, position.size.value, trader)?;
+
+    let margin_ratio = calc_margin_ratio(deps, &config, &amm, &position)?;
+    if !position.size.is_zero() {
+        require_additional_margin(margin_ratio, tier_config.maintenance_margin_ratio)?;
+    }
+
+    let exchanged_position_size = position.size - previous_size;
+
+    Ok(SimulationResponse {
+        entry_price: calc_entry_price(&config, exchanged_position_size, exchanged_quote_amount)?,
+        position,
+        exchanged_position_size,
+        exchanged_quote_amount,
+        spread_fee,
+        toll_fee,
+        realized_pnl,
+        funding_payment,
+        margin_to_vault,
+        margin_ratio,
+        bad_debt,
+    })
+}
+
+/// Simulates closing a position, partially if the close would be over the fluctuation limit
+pub fn query_simulate_close_position(
+    deps: Deps,
+    env: Env,
+    vamm: String,
+    trader: String,
+    quote_asset_limit: Uint128,
+) -> StdResult<SimulationResponse> {
+    let state: State = read_state(deps.storage)?;
+
+    // validate address inputs
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    let config: Config = read_market_config(deps.storage, &vamm)?;
+
+    let mut position = read_position(deps.storage, &vamm, &trader)?;
+    let previous_size = position.size;
+
+    require_not_paused(state.pause)?;
+    require_position_not_zero(position.size.value)?;
+    require_not_restriction_mode(deps.storage, &vamm, &trader, env.block.height)?;
+
+    let mut amm = SimulatedVamm::new(deps, &vamm)?;
+
+    let is_over_fluctuation_limit = query_is_over_fluctuation_limit(
+        &deps,
+        vamm.to_string(),
+        Direction::RemoveFromAmm,
+        position.size.value,
+    )?;
+
+    let exchanged_quote_amount: Uint128;
+    let realized_pnl: Integer;
+    let funding_payment: Integer;
+    let margin_to_vault: Integer;
+    let bad_debt: Uint128;
+    let fee_notional: Uint128;
+
+    if is_over_fluctuation_limit && config.partial_liquidation_ratio < config.decimals {
+        let side = position_to_side(position.size);
+
+        let partial_close_amount = position
+            .size
+            .value
+            .checked_mul(config.partial_liquidation_ratio)?
+            .checked_div(config.decimals)?;
+
+        let partial_close_notional =
+            amm.output_amount(&position.direction, partial_close_amount)?;
+
+        let PositionUnrealizedPnlResponse {
+            position_notional,
+            unrealized_pnl,
+        } = get_position_notional_unrealized_pnl(deps, &position, PnlCalcOption::SpotPrice)?;
+
+        let output = amm.swap_input(
+            side_to_direction(side.clone()),
+            partial_close_notional,
+            Uint128::zero(),
+        )?;
+        let signed_output = signed_size(&side, output);
+
+        // realized_pnl = unrealized_pnl * close_ratio
+        let close_pnl = unrealized_pnl.checked_mul(signed_output.abs())? / position.size.abs();
+        let unrealized_pnl_after = unrealized_pnl - close_pnl;
+
+        let remaining_notional = if position.size > Integer::zero() {
+            Integer::new_positive(position_notional)
+                - Integer::new_positive(partial_close_notional)
+                - unrealized_pnl_after
+        } else {
+            unrealized_pnl_after + Integer::new_positive(position_notional)
+                - Integer::new_positive(partial_close_notional)
+        };
+
+        let RemainMarginResponse {
+            funding_payment: remain_funding_payment,
+            margin,
+            bad_debt: remain_bad_debt,
+            latest_premium_fraction,
+        } = calc_remain_margin_with_funding_payment(deps, position.clone(), close_pnl)?;
+
+        position.size += signed_output;
+        position.margin = margin;
+        position.notional = remaining_notional.value;
+        position.last_updated_premium_fraction = latest_premium_fraction;
+        position.block_number = env.block.height;
+
+        exchanged_quote_amount = partial_close_notional;
+        realized_pnl = close_pnl;
+        funding_payment = remain_funding_payment;
+        margin_to_vault = Integer::zero();
+        bad_debt = remain_bad_debt;
+        fee_notional = partial_close_notional;
+    } else {
+        let output = amm.swap_output(
+            position.direction.clone(),
+            position.size.value,
+            quote_asset_limit,
+        )?;
+
+        let margin_delta: Integer = match &position.direction {
+            Direction::AddToAmm => {
+                Integer::new_positive(output) - Integer::new_positive(position.notional)
+            }
+            Direction::RemoveFromAmm => {
+                Integer::new_positive(position.notional) - Integer::new_positive(output)
+            }
+        };
+
+        let remain_margin =
+            calc_remain_margin_with_funding_payment(deps, position.clone(), margin_delta)?;
+
+        fee_notional = position.notional;
+        position = clear_position(env, position)?;
+
+        exchanged_quote_amount = output;
+        realized_pnl = margin_delta;
+        funding_payment = remain_margin.funding_payment;
+        margin_to_vault = Integer::new_negative(remain_margin.margin);
+        bad_debt = remain_margin.bad_debt;
+    }
+
+    let CalcFeeResponse {
+        spread_fee,
+        toll_fee,
+    } = query_vamm_calc_fee(&deps, vamm.to_string(), fee_notional)?;
+
+    let exchanged_position_size = position.size - previous_size;
+
+    Ok(SimulationResponse {
+        entry_price: calc_entry_price(&config, exchanged_position_size, exchanged_quote_amount)?,
+        margin_ratio: calc_margin_ratio(deps, &config, &amm, &position)?,
+        position,
+        exchanged_position_size,
+        exchanged_quote_amount,
+        spread_fee,
+        toll_fee,
+        realized_pnl,
+        funding_payment,
+        margin_to_vault,
+        bad_debt,
+    })
+}
+
+/// Simulates depositing margin into a position
+pub fn query_simulate_deposit_margin(
+    deps: Deps,
+    vamm: String,
+    trader: String,
+    amount: Uint128,
+) -> StdResult<SimulationResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    require_not_paused(state.pause)?;
+    require_non_zero_input(amount)?;
+
+    let mut position = read_position(deps.storage, &vamm, &trader)?;
+
+    if position.trader != trader {
+        return Err(ContractError::NoPosition {}.into());
+    }
+
+    position.margin = position.margin.checked_add(amount)?;
+
+    let amm = SimulatedVamm::new(deps, &vamm)?;
+
+    Ok(margin_simulation(
+        calc_margin_ratio(deps, &config, &amm, &position)?,
+        position,
+        Integer::zero(),
+        Integer::new_positive(amount),
+    ))
+}
+
+/// Simulates withdrawing margin from a position
+pub fn query_simulate_withdraw_margin(
+    deps: Deps,
+    vamm: String,
+    trader: String,
+    amount: Uint128,
+) -> StdResult<SimulationResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let state: State = read_state(deps.storage)?;
+
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let trader = deps.api.addr_validate(&trader)?;
+
+    require_vamm(deps, &config.insurance_fund, &vamm)?;
+    require_not_paused(state.pause)?;
+    require_non_zero_input(amount)?;
+
+    let mut position = read_position(deps.storage, &vamm, &trader)?;
+
+    let remain_margin = calc_remain_margin_with_funding_payment(
+        deps,
+        position.clone(),
+        Integer::new_negative(amount),
+    )?;
+    require_bad_debt(remain_margin.bad_debt)?;
+
+    position.margin = remain_margin.margin;
+    position.last_updated_premium_fraction = remain_margin.latest_premium_fraction;
+
+    // check if margin is sufficient
+    let free_collateral = query_free_collateral(deps, vamm.to_string(), trader.to_string())?;
+    if free_collateral
+        .checked_sub(Integer::new_positive(amount))?
+        .is_negative()
+    {
+        return Err(ContractError::InsufficientCollateral {
+            free_collateral,
+            amount,
+        }
+        .into());
+    }
+
+    let amm = SimulatedVamm::new(deps, &vamm)?;
+
+    Ok(margin_simulation(
+        calc_margin_ratio(deps, &config, &amm, &position)?,
+        position,
+        remain_margin.funding_payment,
+        Integer::new_negative(amount),
+    ))
+}
+
+// returns the simulation of a margin change, where nothing is exchanged with the vamm
+fn margin_simulation(
+    margin_ratio: Integer,
+    position: Position,
+    funding_payment: Integer,
+    margin_to_vault: Integer,
+) -> SimulationResponse {
+    SimulationResponse {
+        position,
+        exchanged_position_size: Integer::zero(),
+        exchanged_quote_amount: Uint128::zero(),
+        entry_price: Uint128::zero(),
+        spread_fee: Uint128::zero(),
+        toll_fee: Uint128::zero(),
+        realized_pnl: Integer::zero(),
+        funding_payment,
+        margin_to_vault,
+        margin_ratio,
+        bad_debt: Uint128::zero(),
+    }
+}
+
+// returns the margin ratio of the position at the simulated spot price
+fn calc_margin_ratio(
+    deps: Deps,
+    config: &Config,
+    amm: &SimulatedVamm,
+    position: &Position,
+) -> StdResult<Integer> {
+    let position_notional = amm.output_amount(&position.direction, position.size.value)?;
+    if position_notional.is_zero() {
+        return Ok(Integer::zero());
+    }
+
+    let unrealized_pnl = match position.direction {
+        Direction::AddToAmm => {
+            Integer::new_positive(position_notional) - Integer::new_positive(position.notional)
+        }
+        Direction::RemoveFromAmm => {
+            Integer::new_positive(position.notional) - Integer::new_positive(position_notional)
+        }
+    };
+
+    let remain_margin =
+        calc_remain_margin_with_funding_payment(deps, position.clone(), unrealized_pnl)?;
+    let margin =
+        Integer::new_positive(remain_margin.margin) - Integer::new_positive(remain_margin.bad_debt);
+
+    Ok(
+        (margin * Integer::new_positive(config.decimals))
+            / Integer::new_positive(position_notional),
+    )
+}
+
+// returns the average price of the exchanged base asset
+fn calc_entry_price(config: &Config, size: Integer, quote_amount: Uint128) -> StdResult<Uint128> {
+    if size.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    Ok(quote_amount
+        .checked_mul(config.decimals)?
+        .checked_div(size.value)?)
+}
+
+// depending on the side the exchanged size is positive or negative
+fn signed_size(side: &Side, amount: Uint128) -> Integer {
+    match side {
+        Side::Buy => Integer::new_positive(amount),
+        Side::Sell => Integer::new_negative(amount),
+    }
+}
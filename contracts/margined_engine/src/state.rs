@@ -2,27 +2,44 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
-use cosmwasm_std::{Addr, StdError, StdResult, Storage, Uint128};
-use cosmwasm_storage::{
-    bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, Singleton,
-};
+use cosmwasm_std::{Addr, StdResult, Storage, Uint128};
+use cosmwasm_storage::{bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket};
+use cw_storage_plus::{Bound, Item, Map, PrefixBound};
 
 use margined_common::{
     asset::{Asset, AssetInfo},
     integer::Integer,
 };
-use margined_perp::margined_engine::{Position, Side};
+use margined_perp::margined_engine::{
+    CollateralAsset, EngineOp, LeverageTier, Order, Position, RiskParams, Side, Trade, TraderStats,
+};
 
 use sha3::{Digest, Sha3_256};
 
+use crate::error::ContractError;
+
 pub static KEY_CONFIG: &[u8] = b"config";
 pub static KEY_POSITION: &[u8] = b"position";
 pub static KEY_STATE: &[u8] = b"state";
-pub static KEY_SENT_FUNDS: &[u8] = b"sent-funds";
-pub static KEY_TMP_SWAP: &[u8] = b"tmp-swap";
-pub static KEY_TMP_LIQUIDATOR: &[u8] = b"tmp-liquidator";
 pub static KEY_VAMM_MAP: &[u8] = b"vamm-map";
 
+pub const ORDER_COUNT: Item<u64> = Item::new("order-count");
+pub const ORDERS: Map<(&Addr, u64), Order> = Map::new("orders");
+pub const TRADER_ORDERS: Map<(&Addr, &Addr, u64), bool> = Map::new("trader-orders");
+pub const CROSS_MARGIN: Map<&Addr, bool> = Map::new("cross-margin");
+pub const POSITIONS: Map<(&Addr, &Addr), Position> = Map::new("positions");
+pub const TRADER_POSITIONS: Map<(&Addr, &Addr), bool> = Map::new("trader-positions");
+pub const TRADE_COUNT: Item<u64> = Item::new("trade-count");
+pub const TRADES: Map<(&Addr, &Addr, u64), Trade> = Map::new("trades");
+pub const TRADER_STATS: Map<(&Addr, &Addr), TraderStats> = Map::new("trader-stats");
+pub const REPLY_NONCE: Item<u64> = Item::new("reply-nonce");
+pub const REPLY_CONTEXTS: Map<u64, ReplyContext> = Map::new("reply-contexts");
+pub const BATCH_COUNT: Item<u64> = Item::new("batch-count");
+pub const BATCHES: Map<u64, Batch> = Map::new("batches");
+pub const ADL_SCANS: Map<(&Addr, &Addr), AdlScan> = Map::new("adl-scans");
+pub const COLLATERALS: Map<&str, CollateralAsset> = Map::new("collaterals");
+pub const TRADER_COLLATERAL: Map<(&Addr, &str), Asset> = Map::new("trader-collateral");
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Config {
     pub owner: Addr,
@@ -34,6 +51,7 @@ pub struct Config {
     pub maintenance_margin_ratio: Uint128,
     pub partial_liquidation_ratio: Uint128,
     pub liquidation_fee: Uint128,
+    pub keeper_fee: Uint128,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -44,6 +62,93 @@ pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
     singleton_read(storage, KEY_CONFIG).load()
 }
 
+/// Returns the config with the risk parameters in effect for the vamm
+pub fn read_market_config(storage: &dyn Storage, vamm: &Addr) -> StdResult<Config> {
+    let mut config = read_config(storage)?;
+    let risk_params = read_vamm_map(storage, vamm.clone())?.risk_params;
+
+    if let Some(initial_margin_ratio) = risk_params.initial_margin_ratio {
+        config.initial_margin_ratio = initial_margin_ratio;
+    }
+    if let Some(maintenance_margin_ratio) = risk_params.maintenance_margin_ratio {
+        config.maintenance_margin_ratio = maintenance_margin_ratio;
+    }
+    if let Some(partial_liquidation_ratio) = risk_params.partial_liquidation_ratio {
+        config.partial_liquidation_ratio = partial_liquidation_ratio;
+    }
+    if let Some(liquidation_fee) = risk_params.liquidation_fee {
+        config.liquidation_fee = liquidation_fee;
+    }
+
+    Ok(config)
+}
+
+/// Returns the market config with the margin ratios stepped up to those of the leverage tier
+/// of a position with the open notional
+pub fn read_position_config(
+    storage: &dyn Storage,
+    vamm: &Addr,
+    notional: Uint128,
+) -> StdResult<Config> {
+    let mut config = read_market_config(storage, vamm)?;
+
+    // the tiers are ordered by notional so the last one exceeded applies
+    let tier = read_vamm_map(storage, vamm.clone())?
+        .leverage_tiers
+        .into_iter()
+        .rev()
+        .find(|tier| notional > tier.notional);
+
+    if let Some(tier) = tier {
+        config.initial_margin_ratio = config.initial_margin_ratio.max(tier.initial_margin_ratio);
+        config.maintenance_margin_ratio = config
+            .maintenance_margin_ratio
+            .max(tier.maintenance_margin_ratio);
+    }
+
+    Ok(config)
+}
+
+/// Config layout used before the keeper fee was introduced
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LegacyConfig {
+    pub owner: Addr,
+    pub insurance_fund: Addr,
+    pub fee_pool: Addr,
+    pub eligible_collateral: AssetInfo,
+    pub decimals: Uint128,
+    pub initial_margin_ratio: Uint128,
+    pub maintenance_margin_ratio: Uint128,
+    pub partial_liquidation_ratio: Uint128,
+    pub liquidation_fee: Uint128,
+}
+
+/// Transforms a config stored in the legacy layout into the current one,
+/// configs already in the current layout are left untouched
+pub fn migrate_config(storage: &mut dyn Storage) -> StdResult<()> {
+    if read_config(storage).is_ok() {
+        return Ok(());
+    }
+
+    let legacy: LegacyConfig = singleton_read(storage, KEY_CONFIG).load()?;
+
+    store_config(
+        storage,
+        &Config {
+            owner: legacy.owner,
+            insurance_fund: legacy.insurance_fund,
+            fee_pool: legacy.fee_pool,
+            eligible_collateral: legacy.eligible_collateral,
+            decimals: legacy.decimals,
+            initial_margin_ratio: legacy.initial_margin_ratio,
+            maintenance_margin_ratio: legacy.maintenance_margin_ratio,
+            partial_liquidation_ratio: legacy.partial_liquidation_ratio,
+            liquidation_fee: legacy.liquidation_fee,
+            keeper_fee: Uint128::zero(),
+        },
+    )
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct State {
     pub open_interest_notional: Uint128,
@@ -67,52 +172,124 @@ fn position_bucket_read(storage: &dyn Storage) -> ReadonlyBucket<Position> {
     bucket_read(storage, KEY_POSITION)
 }
 
-pub fn store_position(storage: &mut dyn Storage, position: &Position) -> StdResult<()> {
-    // hash the vAMM and trader together to get a unique position key
+// key of a position in the legacy bucket, the hash of the vAMM and trader
+pub fn legacy_position_key(vamm: &Addr, trader: &Addr) -> Vec<u8> {
     let mut hasher = Sha3_256::new();
 
     // write input message
-    hasher.update(position.vamm.as_bytes());
-    hasher.update(position.trader.as_bytes());
+    hasher.update(vamm.as_bytes());
+    hasher.update(trader.as_bytes());
 
     // read hash digest
-    let hash = hasher.finalize();
-
-    position_bucket(storage).save(&hash, position)
+    hasher.finalize().to_vec()
 }
 
-pub fn remove_position(storage: &mut dyn Storage, position: &Position) {
-    // hash the vAMM and trader together to get a unique position key
-    let mut hasher = Sha3_256::new();
+pub fn store_position(storage: &mut dyn Storage, position: &Position) -> StdResult<()> {
+    POSITIONS.save(storage, (&position.vamm, &position.trader), position)?;
+    TRADER_POSITIONS.save(storage, (&position.trader, &position.vamm), &true)?;
 
-    // write input message
-    hasher.update(position.vamm.as_bytes());
-    hasher.update(position.trader.as_bytes());
+    // the position is now indexed, drop any copy left in the legacy bucket
+    position_bucket(storage).remove(&legacy_position_key(&position.vamm, &position.trader));
 
-    // read hash digest
-    let hash = hasher.finalize();
+    Ok(())
+}
+
+pub fn remove_position(storage: &mut dyn Storage, position: &Position) {
+    POSITIONS.remove(storage, (&position.vamm, &position.trader));
+    TRADER_POSITIONS.remove(storage, (&position.trader, &position.vamm));
 
-    // remove the position stored under the key
-    position_bucket(storage).remove(&hash)
+    position_bucket(storage).remove(&legacy_position_key(&position.vamm, &position.trader))
 }
 
 pub fn read_position(storage: &dyn Storage, vamm: &Addr, trader: &Addr) -> StdResult<Position> {
-    // hash the vAMM and trader together to get a unique position key
-    let mut hasher = Sha3_256::new();
-
-    // write input message
-    hasher.update(vamm.as_bytes());
-    hasher.update(trader.as_bytes());
+    if let Some(position) = POSITIONS.may_load(storage, (vamm, trader))? {
+        return Ok(position);
+    }
 
-    // read hash digest
-    let hash = hasher.finalize();
+    // positions that have not been migrated yet are still under the hashed key
     let result = position_bucket_read(storage)
-        .may_load(&hash)?
+        .may_load(&legacy_position_key(vamm, trader))?
         .unwrap_or_default();
 
     Ok(result)
 }
 
+/// Returns the positions in the vAMM ordered by trader, only includes positions
+/// that have been migrated out of the legacy bucket
+pub fn read_vamm_positions(
+    storage: &dyn Storage,
+    vamm: &Addr,
+    start_after: Option<Addr>,
+    limit: usize,
+) -> StdResult<Vec<Position>> {
+    POSITIONS
+        .prefix(vamm)
+        .range(
+            storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            cosmwasm_std::Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, position)| position))
+        .collect()
+}
+
+/// Returns the positions of the trader in every vAMM through the trader index, only
+/// includes positions that have been migrated out of the legacy bucket
+pub fn read_trader_positions(storage: &dyn Storage, trader: &Addr) -> StdResult<Vec<Position>> {
+    TRADER_POSITIONS
+        .prefix(trader)
+        .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|vamm| read_position(storage, &vamm?, trader))
+        .collect()
+}
+
+/// Returns the traders with a position in any vAMM, in the order of the trader index
+pub fn read_traders(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: usize,
+) -> StdResult<Vec<Addr>> {
+    let mut traders: Vec<Addr> = vec![];
+
+    // the index is ordered by trader, so positions of a trader are adjacent
+    for item in TRADER_POSITIONS.prefix_range(
+        storage,
+        start_after.as_ref().map(PrefixBound::exclusive),
+        None,
+        cosmwasm_std::Order::Ascending,
+    ) {
+        let ((trader, _), _) = item?;
+        if traders.last() == Some(&trader) {
+            continue;
+        }
+        if traders.len() == limit {
+            break;
+        }
+
+        traders.push(trader);
+    }
+
+    Ok(traders)
+}
+
+/// Moves up to `limit` positions from the legacy bucket keyed by hash into the
+/// indexed position store, returns the number of positions moved
+pub fn migrate_legacy_positions(storage: &mut dyn Storage, limit: usize) -> StdResult<usize> {
+    let positions = position_bucket_read(storage)
+        .range(None, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, position)| position))
+        .collect::<StdResult<Vec<Position>>>()?;
+
+    for position in positions.iter() {
+        store_position(storage, position)?;
+    }
+
+    Ok(positions.len())
+}
+
 /// Used to monitor that transferred native tokens are sufficient when opening a
 /// new position or relevant operations
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -123,36 +300,16 @@ pub struct SentFunds {
 
 impl SentFunds {
     /// throws an error if the required funds is less than the asset amount
-    pub fn are_sufficient(&self) -> StdResult<()> {
+    pub fn are_sufficient(&self) -> Result<(), ContractError> {
         // this should only pass if asset.amount == required
         match self.asset.amount.cmp(&self.required) {
-            Ordering::Greater => Err(StdError::generic_err("sent funds are excessive")),
-            Ordering::Less => Err(StdError::generic_err("sent funds are insufficient")),
+            Ordering::Greater => Err(ContractError::ExcessiveFunds {}),
+            Ordering::Less => Err(ContractError::InsufficientFunds {}),
             _ => Ok(()),
         }
     }
 }
 
-pub fn store_sent_funds(storage: &mut dyn Storage, funds: &SentFunds) -> StdResult<()> {
-    singleton(storage, KEY_SENT_FUNDS).save(funds)
-}
-
-pub fn remove_sent_funds(storage: &mut dyn Storage) {
-    let mut store: Singleton<SentFunds> = singleton(storage, KEY_SENT_FUNDS);
-    store.remove()
-}
-
-pub fn read_sent_funds(storage: &dyn Storage) -> StdResult<SentFunds> {
-    let res = singleton_read(storage, KEY_SENT_FUNDS).may_load();
-    match res {
-        Ok(_) => {
-            let funds = res.unwrap();
-            Ok(funds.unwrap())
-        }
-        Err(_) => Err(StdError::generic_err("no sent funds")),
-    }
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct TmpSwapInfo {
     pub vamm: Addr,
@@ -165,52 +322,128 @@ pub struct TmpSwapInfo {
     pub unrealized_pnl: Integer,    // any pnl due
     pub margin_to_vault: Integer,   // margin to be sent to vault
     pub fees_paid: bool, // true if fees have been paid, used in case of reversing position
+    pub escrowed: bool, // true if margin and fees are already held by the engine, e.g. limit orders
 }
 
-pub fn store_tmp_swap(storage: &mut dyn Storage, swap: &TmpSwapInfo) -> StdResult<()> {
-    singleton(storage, KEY_TMP_SWAP).save(swap)
+/// State of an in-flight operation that its replies need, keyed by the nonce
+/// encoded in the reply ids so that operations can be composed or nested
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ReplyContext {
+    pub swap: TmpSwapInfo,
+    pub funds: Option<SentFunds>,
+    pub liquidator: Option<Addr>,
+    pub batch: Option<u64>, // id of the batch the funds are reconciled by
 }
 
-pub fn remove_tmp_swap(storage: &mut dyn Storage) {
-    let mut store: Singleton<TmpSwapInfo> = singleton(storage, KEY_TMP_SWAP);
-    store.remove()
+/// Stores the context of a new operation and returns its nonce
+pub fn store_new_reply_context(
+    storage: &mut dyn Storage,
+    context: &ReplyContext,
+) -> StdResult<u64> {
+    let nonce = REPLY_NONCE.may_load(storage)?.unwrap_or_default() + 1;
+    REPLY_NONCE.save(storage, &nonce)?;
+
+    REPLY_CONTEXTS.save(storage, nonce, context)?;
+
+    Ok(nonce)
 }
 
-pub fn read_tmp_swap(storage: &dyn Storage) -> StdResult<TmpSwapInfo> {
-    let res = singleton_read(storage, KEY_TMP_SWAP).may_load();
-    match res {
-        Ok(_) => {
-            let swap = res.unwrap();
-            Ok(swap.unwrap())
-        }
-        Err(_) => Err(StdError::generic_err("no temporary position")),
-    }
+pub fn store_reply_context(
+    storage: &mut dyn Storage,
+    nonce: u64,
+    context: &ReplyContext,
+) -> StdResult<()> {
+    REPLY_CONTEXTS.save(storage, nonce, context)
 }
 
-pub fn store_tmp_liquidator(storage: &mut dyn Storage, liquidator: &Addr) -> StdResult<()> {
-    singleton(storage, KEY_TMP_LIQUIDATOR).save(liquidator)
+pub fn read_reply_context(
+    storage: &dyn Storage,
+    nonce: u64,
+) -> Result<ReplyContext, ContractError> {
+    REPLY_CONTEXTS
+        .may_load(storage, nonce)?
+        .ok_or(ContractError::MissingReplyContext { nonce })
 }
 
-pub fn remove_tmp_liquidator(storage: &mut dyn Storage) {
-    let mut store: Singleton<Addr> = singleton(storage, KEY_TMP_LIQUIDATOR);
-    store.remove()
+pub fn remove_reply_context(storage: &mut dyn Storage, nonce: u64) {
+    REPLY_CONTEXTS.remove(storage, nonce);
 }
 
-pub fn read_tmp_liquidator(storage: &dyn Storage) -> StdResult<Addr> {
-    let res = singleton_read(storage, KEY_TMP_LIQUIDATOR).may_load();
-    match res {
-        Ok(_) => {
-            let swap = res.unwrap();
-            Ok(swap.unwrap())
-        }
-        Err(_) => Err(StdError::generic_err("no liquidator")),
-    }
+/// Operations of a batch still to be executed and the funds the batch was sent,
+/// reconciled once every operation is executed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Batch {
+    pub trader: Addr,
+    pub ops: Vec<EngineOp>,
+    pub funds: SentFunds,
+    pub escrowed: bool, // true if the funds were received up front, e.g. cw20 sent with the batch
+}
+
+pub fn next_batch_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let batch_id = BATCH_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    BATCH_COUNT.save(storage, &batch_id)?;
+
+    Ok(batch_id)
+}
+
+pub fn store_batch(storage: &mut dyn Storage, batch_id: u64, batch: &Batch) -> StdResult<()> {
+    BATCHES.save(storage, batch_id, batch)
+}
+
+pub fn read_batch(storage: &dyn Storage, batch_id: u64) -> StdResult<Batch> {
+    BATCHES.load(storage, batch_id)
+}
+
+pub fn remove_batch(storage: &mut dyn Storage, batch_id: u64) {
+    BATCHES.remove(storage, batch_id);
+}
+
+/// Progress of the scan ranking the counterparties of a bankrupt position
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
+pub struct AdlScan {
+    pub last_trader: Option<Addr>, // last position scanned, none before the scan starts
+    pub counterparties: Vec<Addr>, // highest scored counterparties found so far
+}
+
+pub fn store_adl_scan(
+    storage: &mut dyn Storage,
+    vamm: &Addr,
+    trader: &Addr,
+    scan: &AdlScan,
+) -> StdResult<()> {
+    ADL_SCANS.save(storage, (vamm, trader), scan)
+}
+
+pub fn read_adl_scan(storage: &dyn Storage, vamm: &Addr, trader: &Addr) -> StdResult<AdlScan> {
+    Ok(ADL_SCANS
+        .may_load(storage, (vamm, trader))?
+        .unwrap_or_default())
+}
+
+pub fn remove_adl_scan(storage: &mut dyn Storage, vamm: &Addr, trader: &Addr) {
+    ADL_SCANS.remove(storage, (vamm, trader));
+}
+
+// adds to the funds a batch requires, reconciled once the batch is finished
+pub fn add_batch_required_funds(
+    storage: &mut dyn Storage,
+    batch_id: u64,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut batch = read_batch(storage, batch_id)?;
+    batch.funds.required = batch.funds.required.checked_add(amount)?;
+
+    store_batch(storage, batch_id, &batch)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
 pub struct VammMap {
     pub last_restriction_block: u64,
     pub cumulative_premium_fractions: Vec<Integer>,
+    #[serde(default)]
+    pub risk_params: RiskParams,
+    #[serde(default)]
+    pub leverage_tiers: Vec<LeverageTier>,
 }
 
 fn vamm_map_bucket(storage: &mut dyn Storage) -> Bucket<VammMap> {
@@ -233,6 +466,20 @@ pub fn read_vamm_map(storage: &dyn Storage, vamm: Addr) -> StdResult<VammMap> {
     Ok(result)
 }
 
+/// Reads the risk parameters set for every vamm
+pub fn read_vamms_risk_params(storage: &dyn Storage) -> StdResult<Vec<(Addr, RiskParams)>> {
+    vamm_map_bucket_read(storage)
+        .range(None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| {
+            let (vamm, vamm_map) = item?;
+            Ok((
+                Addr::unchecked(String::from_utf8_lossy(&vamm)),
+                vamm_map.risk_params,
+            ))
+        })
+        .collect()
+}
+
 /// Accumulates the premium fractions at each settlement payment so that eventually users take
 /// their P&L
 pub fn append_cumulative_premium_fraction(
@@ -269,3 +516,231 @@ pub fn enter_restriction_mode(
 
     store_vamm_map(storage, vamm, &vamm_map)
 }
+
+pub fn store_cross_margin(
+    storage: &mut dyn Storage,
+    trader: &Addr,
+    enabled: bool,
+) -> StdResult<()> {
+    if enabled {
+        CROSS_MARGIN.save(storage, trader, &true)
+    } else {
+        CROSS_MARGIN.remove(storage, trader);
+        Ok(())
+    }
+}
+
+/// Returns true if the trader's positions share their margin
+pub fn read_cross_margin(storage: &dyn Storage, trader: &Addr) -> StdResult<bool> {
+    Ok(CROSS_MARGIN.may_load(storage, trader)?.unwrap_or_default())
+}
+
+/// Returns the next order id and increments the counter
+pub fn next_order_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let order_id = ORDER_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    ORDER_COUNT.save(storage, &order_id)?;
+
+    Ok(order_id)
+}
+
+pub fn store_order(storage: &mut dyn Storage, order: &Order) -> StdResult<()> {
+    ORDERS.save(storage, (&order.vamm, order.order_id), order)?;
+    TRADER_ORDERS.save(storage, (&order.vamm, &order.trader, order.order_id), &true)
+}
+
+pub fn remove_order(storage: &mut dyn Storage, order: &Order) {
+    ORDERS.remove(storage, (&order.vamm, order.order_id));
+    TRADER_ORDERS.remove(storage, (&order.vamm, &order.trader, order.order_id));
+}
+
+pub fn read_order(
+    storage: &dyn Storage,
+    vamm: &Addr,
+    order_id: u64,
+) -> Result<Order, ContractError> {
+    ORDERS
+        .may_load(storage, (vamm, order_id))?
+        .ok_or(ContractError::OrderNotFound { order_id })
+}
+
+pub fn read_orders(
+    storage: &dyn Storage,
+    vamm: &Addr,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<Order>> {
+    ORDERS
+        .prefix(vamm)
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            cosmwasm_std::Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, order)| order))
+        .collect()
+}
+
+pub fn read_trader_orders(
+    storage: &dyn Storage,
+    vamm: &Addr,
+    trader: &Addr,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<Order>> {
+    TRADER_ORDERS
+        .prefix((vamm, trader))
+        .keys(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            cosmwasm_std::Order::Ascending,
+        )
+        .take(limit)
+        .map(|order_id| ORDERS.load(storage, (vamm, order_id?)))
+        .collect()
+}
+
+/// Returns the next trade id and increments the counter
+pub fn next_trade_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let trade_id = TRADE_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    TRADE_COUNT.save(storage, &trade_id)?;
+
+    Ok(trade_id)
+}
+
+/// Appends the trade to the trader's history and adds it to their running totals
+pub fn store_trade(storage: &mut dyn Storage, trade: &Trade) -> StdResult<()> {
+    TRADES.save(storage, (&trade.vamm, &trade.trader, trade.id), trade)?;
+
+    TRADER_STATS.update(
+        storage,
+        (&trade.trader, &trade.vamm),
+        |stats| -> StdResult<TraderStats> {
+            let mut stats = stats.unwrap_or_default();
+
+            stats.trade_count += 1;
+            if trade.liquidation {
+                stats.liquidation_count += 1;
+            }
+            stats.volume = stats.volume.checked_add(trade.notional)?;
+            stats.realized_pnl += trade.realized_pnl;
+            stats.spread_fees = stats.spread_fees.checked_add(trade.spread_fee)?;
+            stats.toll_fees = stats.toll_fees.checked_add(trade.toll_fee)?;
+            stats.funding_paid += trade.funding_paid;
+
+            Ok(stats)
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn read_trades(
+    storage: &dyn Storage,
+    vamm: &Addr,
+    trader: &Addr,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<Trade>> {
+    TRADES
+        .prefix((vamm, trader))
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            cosmwasm_std::Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect()
+}
+
+/// Returns the running totals of the trader in the vamm, or across every vamm if none is given
+pub fn read_trader_stats(
+    storage: &dyn Storage,
+    trader: &Addr,
+    vamm: Option<&Addr>,
+) -> StdResult<TraderStats> {
+    if let Some(vamm) = vamm {
+        return Ok(TRADER_STATS
+            .may_load(storage, (trader, vamm))?
+            .unwrap_or_default());
+    }
+
+    TRADER_STATS
+        .prefix(trader)
+        .range(storage, None, None, cosmwasm_std::Order::Ascending)
+        .try_fold(TraderStats::default(), |mut total, item| {
+            let (_, stats) = item?;
+
+            total.trade_count += stats.trade_count;
+            total.liquidation_count += stats.liquidation_count;
+            total.volume = total.volume.checked_add(stats.volume)?;
+            total.realized_pnl += stats.realized_pnl;
+            total.spread_fees = total.spread_fees.checked_add(stats.spread_fees)?;
+            total.toll_fees = total.toll_fees.checked_add(stats.toll_fees)?;
+            total.funding_paid += stats.funding_paid;
+
+            Ok(total)
+        })
+}
+
+pub fn store_collateral(storage: &mut dyn Storage, collateral: &CollateralAsset) -> StdResult<()> {
+    COLLATERALS.save(storage, &collateral.asset.to_string(), collateral)
+}
+
+pub fn remove_collateral(storage: &mut dyn Storage, asset: &AssetInfo) {
+    COLLATERALS.remove(storage, &asset.to_string());
+}
+
+/// Returns the registry entry of the collateral asset, if registered
+pub fn read_collateral(
+    storage: &dyn Storage,
+    asset: &AssetInfo,
+) -> StdResult<Option<CollateralAsset>> {
+    COLLATERALS.may_load(storage, &asset.to_string())
+}
+
+pub fn read_collaterals(storage: &dyn Storage) -> StdResult<Vec<CollateralAsset>> {
+    COLLATERALS
+        .range(storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, collateral)| collateral))
+        .collect()
+}
+
+pub fn read_trader_collateral(
+    storage: &dyn Storage,
+    trader: &Addr,
+    asset: &AssetInfo,
+) -> StdResult<Uint128> {
+    Ok(TRADER_COLLATERAL
+        .may_load(storage, (trader, &asset.to_string()))?
+        .map(|balance| balance.amount)
+        .unwrap_or_default())
+}
+
+pub fn store_trader_collateral(
+    storage: &mut dyn Storage,
+    trader: &Addr,
+    balance: &Asset,
+) -> StdResult<()> {
+    let key = balance.info.to_string();
+
+    if balance.amount.is_zero() {
+        TRADER_COLLATERAL.remove(storage, (trader, &key));
+        Ok(())
+    } else {
+        TRADER_COLLATERAL.save(storage, (trader, &key), balance)
+    }
+}
+
+/// Returns the collateral deposited by the trader, including assets no longer registered
+pub fn read_trader_collaterals(storage: &dyn Storage, trader: &Addr) -> StdResult<Vec<Asset>> {
+    TRADER_COLLATERAL
+        .prefix(trader)
+        .range(storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, balance)| balance))
+        .collect()
+}
@@ -0,0 +1,302 @@
+use cosmwasm_std::{Addr, Uint128};
+use cw_multi_test::{App, Executor};
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::{PnlCalcOption, Side};
+use margined_utils::{
+    contracts::helpers::{EngineController, VammController},
+    scenarios::{to_decimals, SimpleScenario},
+};
+
+// returns the margin ratio of the position at the spot price
+fn spot_margin_ratio(
+    router: &App,
+    engine: &EngineController,
+    vamm: &VammController,
+    trader: &Addr,
+) -> f64 {
+    let position = engine
+        .position(router, vamm.addr().to_string(), trader.to_string())
+        .unwrap();
+    let pnl = engine
+        .get_unrealized_pnl(
+            router,
+            vamm.addr().to_string(),
+            trader.to_string(),
+            PnlCalcOption::SpotPrice,
+        )
+        .unwrap();
+
+    let unrealized_pnl = if pnl.unrealized_pnl.is_negative() {
+        -(pnl.unrealized_pnl.value.u128() as f64)
+    } else {
+        pnl.unrealized_pnl.value.u128() as f64
+    };
+
+    (position.margin.u128() as f64 + unrealized_pnl) / pnl.position_notional.u128() as f64
+}
+
+// returns the quote asset that moves the vamm spot price to the target price
+fn quote_to_price(router: &App, vamm: &VammController, price: Uint128) -> f64 {
+    let state = vamm.state(router).unwrap();
+    let quote_asset_reserve = state.quote_asset_reserve.u128() as f64;
+    let invariant_k = quote_asset_reserve * state.base_asset_reserve.u128() as f64;
+
+    ((invariant_k * price.u128() as f64 / 1e9).sqrt() - quote_asset_reserve).abs()
+}
+
+#[test]
+fn test_long_position_liquidation_price() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let liquidation_price = engine
+        .liquidation_price(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert!(liquidation_price < vamm.spot_price(&router).unwrap());
+
+    // just above the liquidation price alice is still above the maintenance margin
+    let quote = quote_to_price(&router, &vamm, liquidation_price) * 0.99;
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from((quote / 5.0) as u128),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+    assert!(spot_margin_ratio(&router, &engine, &vamm, &alice) > 0.05);
+
+    // and just below it alice can be liquidated
+    let quote = quote_to_price(&router, &vamm, liquidation_price) * 1.01 + 1e9;
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from((quote / 5.0) as u128),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+    assert!(spot_margin_ratio(&router, &engine, &vamm, &alice) < 0.05);
+}
+
+#[test]
+fn test_short_position_liquidation_price() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let liquidation_price = engine
+        .liquidation_price(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert!(liquidation_price > vamm.spot_price(&router).unwrap());
+
+    let quote = quote_to_price(&router, &vamm, liquidation_price) * 0.99;
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            Uint128::from((quote / 5.0) as u128),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+    assert!(spot_margin_ratio(&router, &engine, &vamm, &alice) > 0.05);
+
+    let quote = quote_to_price(&router, &vamm, liquidation_price) * 1.01 + 1e9;
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            Uint128::from((quote / 5.0) as u128),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+    assert!(spot_margin_ratio(&router, &engine, &vamm, &alice) < 0.05);
+}
+
+#[test]
+fn test_liquidation_price_without_position() {
+    let SimpleScenario {
+        router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let err = engine
+        .liquidation_price(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Querier contract error: Generic error: No position found"
+    );
+}
+
+#[test]
+fn test_account_summary() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        owner,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = pricefeed
+        .append_price(
+            "ETH".to_string(),
+            Uint128::from(10_000_000_000u128),
+            router.block_info().time.seconds(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let summary = engine.account_summary(&router, alice.to_string()).unwrap();
+    assert!(summary.positions.is_empty());
+    assert_eq!(summary.free_collateral, Integer::zero());
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(10u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let summary = engine.account_summary(&router, alice.to_string()).unwrap();
+    assert_eq!(summary.trader, alice);
+    assert!(!summary.cross_margin);
+    assert_eq!(summary.positions.len(), 1);
+
+    let position = &summary.positions[0];
+    assert_eq!(
+        position.position,
+        engine
+            .position(&router, vamm.addr().to_string(), alice.to_string())
+            .unwrap()
+    );
+
+    let spot = engine
+        .get_unrealized_pnl(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            PnlCalcOption::SpotPrice,
+        )
+        .unwrap();
+    assert_eq!(position.position_notional, spot.position_notional);
+    assert_eq!(position.spot_unrealized_pnl, spot.unrealized_pnl);
+    assert!(position.spot_unrealized_pnl.is_positive());
+
+    let twap = engine
+        .get_unrealized_pnl(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            PnlCalcOption::Twap,
+        )
+        .unwrap();
+    assert_eq!(position.twap_unrealized_pnl, twap.unrealized_pnl);
+
+    let oracle = engine
+        .get_unrealized_pnl(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            PnlCalcOption::Oracle,
+        )
+        .unwrap();
+    assert_eq!(position.oracle_unrealized_pnl, oracle.unrealized_pnl);
+
+    assert_eq!(position.pending_funding, Integer::zero());
+    assert_eq!(
+        position.margin_ratio,
+        engine
+            .get_margin_ratio(&router, vamm.addr().to_string(), alice.to_string())
+            .unwrap()
+    );
+    assert_eq!(
+        position.free_collateral,
+        engine
+            .get_free_collateral(&router, vamm.addr().to_string(), alice.to_string())
+            .unwrap()
+    );
+    assert_eq!(summary.free_collateral, position.free_collateral);
+    assert_eq!(
+        position.liquidation_price,
+        engine
+            .liquidation_price(&router, vamm.addr().to_string(), alice.to_string())
+            .unwrap()
+    );
+}
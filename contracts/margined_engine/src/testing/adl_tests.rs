@@ -0,0 +1,411 @@
+use cosmwasm_std::{Empty, Event, Uint128};
+use cw20::Cw20ExecuteMsg;
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{OrderType, Side, TriggerPriceOption};
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// alice opens a 25 margin * 10x long at a price of 10 and david a 30 margin * 10x short
+// at 15.625, bob's sell then moves the price to about 8.84 which leaves alice bankrupt
+fn setup_bankrupt_position() -> SimpleScenario {
+    let mut scenario = SimpleScenario::new();
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        david,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(30u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(david.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(10u64),
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    scenario
+}
+
+// empties the insurance fund so it can no longer cover bad debt
+fn drain_insurance_fund(scenario: &mut SimpleScenario) {
+    let SimpleScenario {
+        router,
+        owner,
+        usdc,
+        insurance_fund,
+        ..
+    } = scenario;
+
+    let balance = usdc
+        .balance::<_, _, Empty>(&*router, insurance_fund.addr())
+        .unwrap();
+    let msg = usdc
+        .call(Cw20ExecuteMsg::Transfer {
+            recipient: owner.to_string(),
+            amount: balance,
+        })
+        .unwrap();
+    router.execute(insurance_fund.addr(), msg).unwrap();
+}
+
+#[test]
+fn test_auto_deleverage_requirements() {
+    let mut scenario = setup_bankrupt_position();
+
+    // the insurance fund still covers the bad debt
+    let msg = scenario
+        .engine
+        .auto_deleverage(
+            scenario.vamm.addr().to_string(),
+            scenario.alice.to_string(),
+            None,
+        )
+        .unwrap();
+    let err = scenario
+        .router
+        .execute(scenario.carol.clone(), msg)
+        .unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsuranceFundNotExhausted {
+            balance: to_decimals(5000u64),
+            bad_debt: Uint128::from(79_964_646_465u128),
+        }
+    );
+
+    drain_insurance_fund(&mut scenario);
+
+    let SimpleScenario {
+        mut router,
+        alice,
+        carol,
+        david,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    // the liquidation can no longer draw the same bad debt from the insurance fund
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsuranceFundExhausted {
+            balance: Uint128::zero(),
+            shortfall: Uint128::from(79_964_646_465u128),
+        }
+    );
+
+    // profitable positions are not deleveraged
+    let msg = engine
+        .auto_deleverage(vamm.addr().to_string(), david.to_string(), None)
+        .unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::NotBankrupt {}
+    );
+
+    // nor positions that do not exist
+    let msg = engine
+        .auto_deleverage(vamm.addr().to_string(), carol.to_string(), None)
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap_err();
+}
+
+#[test]
+fn test_adl_ranking() {
+    let SimpleScenario {
+        router,
+        alice,
+        david,
+        carol,
+        engine,
+        vamm,
+        ..
+    } = setup_bankrupt_position();
+
+    let ranking = engine
+        .adl_ranking(
+            &router,
+            vamm.addr().to_string(),
+            david.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(ranking.trader, david);
+    assert!(ranking.score > Integer::zero());
+    assert_eq!(ranking.ahead, 0);
+    assert_eq!(ranking.last_trader, None);
+
+    // the positions are read a page at a time
+    let ranking = engine
+        .adl_ranking(
+            &router,
+            vamm.addr().to_string(),
+            david.to_string(),
+            None,
+            Some(1),
+        )
+        .unwrap();
+    assert_eq!(ranking.last_trader, Some(alice.clone()));
+
+    // losing positions are not in the queue
+    let ranking = engine
+        .adl_ranking(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert!(ranking.score < Integer::zero());
+    assert_eq!(ranking.ahead, 0);
+
+    engine
+        .adl_ranking(
+            &router,
+            vamm.addr().to_string(),
+            carol.to_string(),
+            None,
+            None,
+        )
+        .unwrap_err();
+}
+
+#[test]
+fn test_auto_deleverage_ranks_counterparties() {
+    let mut scenario = SimpleScenario::new();
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        david,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    // bob shorts before and after david so both shorts profit from the drop
+    let trades = [
+        (alice.clone(), Side::Buy, 25u64, 10u64),
+        (bob.clone(), Side::Sell, 5u64, 1u64),
+        (david.clone(), Side::Sell, 30u64, 10u64),
+        (bob.clone(), Side::Sell, 10u64, 1u64),
+    ];
+    for (trader, side, margin, leverage) in trades.iter() {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                side.clone(),
+                to_decimals(*margin),
+                to_decimals(*leverage),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(trader.clone(), msg).unwrap();
+    }
+
+    drain_insurance_fund(&mut scenario);
+
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        carol,
+        david,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    let ranking = engine
+        .adl_ranking(
+            &router,
+            vamm.addr().to_string(),
+            bob.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert!(ranking.score > Integer::zero());
+    assert_eq!(ranking.ahead, 1);
+
+    let bob_position = engine
+        .position(&router, vamm.addr().to_string(), bob.to_string())
+        .unwrap();
+    let david_position = engine
+        .position(&router, vamm.addr().to_string(), david.to_string())
+        .unwrap();
+
+    // the first page only reaches bob, the position stays open until the scan completes
+    let msg = engine
+        .auto_deleverage(vamm.addr().to_string(), alice.to_string(), Some(2u32))
+        .unwrap();
+    let res = router.execute(carol.clone(), msg).unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("scanned", "2")));
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert_eq!(positions.len(), 1);
+
+    // david outranks bob once scanned and takes the whole position
+    let msg = engine
+        .auto_deleverage(vamm.addr().to_string(), alice.to_string(), Some(2u32))
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert!(positions.is_empty());
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), bob.to_string())
+        .unwrap();
+    assert_eq!(position, bob_position);
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), david.to_string())
+        .unwrap();
+    assert!(position.size.value < david_position.size.value);
+}
+
+#[test]
+fn test_auto_deleverage_closes_against_counterparty() {
+    let mut scenario = setup_bankrupt_position();
+    drain_insurance_fund(&mut scenario);
+
+    let SimpleScenario {
+        mut router,
+        alice,
+        carol,
+        david,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    // the stop loss was placed for the whole short
+    let msg = engine
+        .place_trigger_order(
+            vamm.addr().to_string(),
+            OrderType::StopLoss,
+            to_decimals(0u64),
+            to_decimals(20u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    router.execute(david.clone(), msg).unwrap();
+
+    let state = engine.state(&router).unwrap();
+    let vamm_state = vamm.state(&router).unwrap();
+
+    let msg = engine
+        .auto_deleverage(vamm.addr().to_string(), alice.to_string(), None)
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert!(positions.is_empty());
+
+    // david takes the 20 long at the bankruptcy price of 11.25 and realizes
+    // 237.5 - 225 of profit on the part of the short that was closed
+    let position = engine
+        .position(&router, vamm.addr().to_string(), david.to_string())
+        .unwrap();
+    assert!(position.size.is_negative());
+    assert_eq!(position.size.value, Uint128::from(5_263_157_895u128));
+    assert_eq!(position.notional, Uint128::from(62_500_000_003u128));
+    assert_eq!(position.margin, Uint128::from(42_499_999_997u128));
+
+    let orders = engine
+        .trader_orders(
+            &router,
+            vamm.addr().to_string(),
+            david.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert!(orders.is_empty());
+
+    let trades = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            david.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades[1].size, Integer::new_positive(to_decimals(20u64)));
+    assert_eq!(trades[1].price, Uint128::from(11_250_000_000u128));
+
+    let trades = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades.len(), 2);
+    assert!(trades[1].liquidation);
+    assert_eq!(
+        trades[1].realized_pnl,
+        Integer::new_negative(to_decimals(25u64))
+    );
+
+    // the vamm is untouched, only the open interest of the closed positions goes
+    assert_eq!(vamm.state(&router).unwrap(), vamm_state);
+    assert!(engine.state(&router).unwrap().open_interest_notional < state.open_interest_notional);
+}
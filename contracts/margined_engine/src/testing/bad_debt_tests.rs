@@ -1,7 +1,8 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::Uint128;
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -78,10 +79,11 @@ fn test_cannot_increase_position_when_bad_debt() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Position is undercollateralized".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_negative(291_446_915u128),
+            required: Uint128::from(50_000_000u128)
+        }
     );
 
     // pump spot price
@@ -177,10 +179,11 @@ fn test_cannot_reduce_position_when_bad_debt() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Position is undercollateralized".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_negative(2_003_967_892u128),
+            required: Uint128::from(50_000_000u128)
+        }
     );
 
     // pump spot price
@@ -273,10 +276,10 @@ fn test_cannot_close_position_when_bad_debt() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Cannot close position - bad debt".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BadDebt {
+            bad_debt: Uint128::from(46_107_954_548u128)
+        }
     );
 
     // pump spot price
@@ -372,10 +375,10 @@ fn test_cannot_partial_close_position_when_bad_debt() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Cannot close position - bad debt".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BadDebt {
+            bad_debt: Uint128::from(4_026_988_631u128)
+        }
     );
 }
 
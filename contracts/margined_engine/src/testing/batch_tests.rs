@@ -0,0 +1,299 @@
+use cosmwasm_std::{Coin, Empty, Uint128};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{EngineOp, ExecuteMsg, Side};
+use margined_utils::scenarios::{to_decimals, NativeTokenScenario, SimpleScenario};
+
+#[test]
+fn test_batch_across_vamms() {
+    let mut scenario = SimpleScenario::new();
+    let other_vamm = scenario.add_vamm();
+
+    let SimpleScenario {
+        router,
+        alice,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    let alice_balance = usdc.balance::<_, _, Empty>(router, alice.clone()).unwrap();
+
+    let msg = engine
+        .batch(
+            vec![
+                EngineOp::OpenPosition {
+                    vamm: vamm.addr().to_string(),
+                    side: Side::Buy,
+                    margin_amount: to_decimals(60u64),
+                    leverage: to_decimals(10u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                EngineOp::OpenPosition {
+                    vamm: other_vamm.addr().to_string(),
+                    side: Side::Sell,
+                    margin_amount: to_decimals(20u64),
+                    leverage: to_decimals(5u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                // the position opened by the first operation is already stored
+                EngineOp::DepositMargin {
+                    vamm: vamm.addr().to_string(),
+                    amount: to_decimals(10u64),
+                },
+                EngineOp::WithdrawMargin {
+                    vamm: other_vamm.addr().to_string(),
+                    amount: to_decimals(5u64),
+                },
+            ],
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let position = engine
+        .position(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(70u64));
+    assert_eq!(position.notional, to_decimals(600u64));
+    assert!(position.size > Integer::zero());
+
+    let position = engine
+        .position(router, other_vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(15u64));
+    assert_eq!(position.notional, to_decimals(100u64));
+    assert!(position.size < Integer::zero());
+
+    let balance = usdc.balance::<_, _, Empty>(router, alice.clone()).unwrap();
+    assert_eq!(alice_balance - balance, to_decimals(85u64));
+}
+
+#[test]
+fn test_batch_open_and_close() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+
+    let msg = engine
+        .batch(
+            vec![
+                EngineOp::OpenPosition {
+                    vamm: vamm.addr().to_string(),
+                    side: Side::Buy,
+                    margin_amount: to_decimals(60u64),
+                    leverage: to_decimals(10u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                EngineOp::ClosePosition {
+                    vamm: vamm.addr().to_string(),
+                    quote_asset_limit: to_decimals(0u64),
+                },
+            ],
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert!(positions.is_empty());
+
+    let trades = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades.len(), 2);
+
+    // without fees or other trades the margin is returned in full
+    let balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(balance, alice_balance);
+}
+
+#[test]
+fn test_batch_fails_if_any_operation_fails() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+
+    // withdrawing more than the free collateral fails the whole batch
+    let msg = engine
+        .batch(
+            vec![
+                EngineOp::OpenPosition {
+                    vamm: vamm.addr().to_string(),
+                    side: Side::Buy,
+                    margin_amount: to_decimals(60u64),
+                    leverage: to_decimals(10u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                EngineOp::WithdrawMargin {
+                    vamm: vamm.addr().to_string(),
+                    amount: to_decimals(50u64),
+                },
+            ],
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(to_decimals(30u64)),
+            amount: to_decimals(50u64),
+        }
+    );
+
+    // so does breaching the slippage limit of any operation
+    let msg = engine
+        .batch(
+            vec![
+                EngineOp::OpenPosition {
+                    vamm: vamm.addr().to_string(),
+                    side: Side::Buy,
+                    margin_amount: to_decimals(60u64),
+                    leverage: to_decimals(10u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                EngineOp::OpenPosition {
+                    vamm: vamm.addr().to_string(),
+                    side: Side::Buy,
+                    margin_amount: to_decimals(10u64),
+                    leverage: to_decimals(10u64),
+                    base_asset_limit: to_decimals(100u64),
+                },
+            ],
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap_err();
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert!(positions.is_empty());
+
+    let balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(balance, alice_balance);
+}
+
+#[test]
+fn test_batch_validation() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        engine,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine.batch(vec![], vec![]).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::EmptyBatch {}
+    );
+
+    // only the engine can execute the steps of a batch
+    let err = router
+        .execute_contract(
+            alice.clone(),
+            engine.addr(),
+            &ExecuteMsg::BatchStep { batch_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
+    );
+}
+
+#[test]
+fn test_batch_reconciles_native_funds_once() {
+    let NativeTokenScenario {
+        mut router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = NativeTokenScenario::new();
+
+    let ops = vec![
+        EngineOp::OpenPosition {
+            vamm: vamm.addr().to_string(),
+            side: Side::Buy,
+            margin_amount: Uint128::from(60_000_000u64),
+            leverage: Uint128::from(10_000_000u64),
+            base_asset_limit: Uint128::zero(),
+        },
+        EngineOp::OpenPosition {
+            vamm: vamm.addr().to_string(),
+            side: Side::Buy,
+            margin_amount: Uint128::from(20_000_000u64),
+            leverage: Uint128::from(5_000_000u64),
+            base_asset_limit: Uint128::zero(),
+        },
+        EngineOp::DepositMargin {
+            vamm: vamm.addr().to_string(),
+            amount: Uint128::from(10_000_000u64),
+        },
+    ];
+
+    // the funds sent must cover every operation
+    let msg = engine
+        .batch(ops.clone(), vec![Coin::new(80_000_000u128, "uwasm")])
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientFunds {}
+    );
+
+    let msg = engine
+        .batch(ops.clone(), vec![Coin::new(100_000_000u128, "uwasm")])
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ExcessiveFunds {}
+    );
+
+    let msg = engine
+        .batch(ops, vec![Coin::new(90_000_000u128, "uwasm")])
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let engine_balance = router
+        .wrap()
+        .query_balance(engine.addr(), "uwasm")
+        .unwrap()
+        .amount;
+    assert_eq!(engine_balance, Uint128::from(90_000_000u64));
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.margin, Uint128::from(90_000_000u64));
+}
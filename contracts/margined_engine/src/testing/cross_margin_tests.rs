@@ -0,0 +1,236 @@
+use cosmwasm_std::{Empty, StdError, Uint128};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::Side;
+use margined_utils::{
+    contracts::helpers::margined_vamm::VammController,
+    scenarios::{to_decimals, SimpleScenario},
+};
+
+// alice goes long in the first vamm and short in the second one using
+// cross margin, bob then pushes the price up in both vamms
+fn setup_cross_margin(bob_quote_amount: u64) -> (SimpleScenario, VammController) {
+    let mut scenario = SimpleScenario::new();
+    let vamm2 = scenario.add_vamm();
+
+    let SimpleScenario {
+        ref mut router,
+        ref owner,
+        ref alice,
+        ref bob,
+        ref engine,
+        ref vamm,
+        ..
+    } = scenario;
+
+    let msg = engine
+        .set_margin_ratios(Uint128::from(250_000_000u128)) // 0.25
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine.set_cross_margin(true).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    for (vamm, side) in [(vamm, Side::Buy), (&vamm2, Side::Sell)] {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                side,
+                to_decimals(60u64),
+                to_decimals(2u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(alice.clone(), msg).unwrap();
+    }
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    for (vamm, quote_amount) in [(vamm, 100u64), (&vamm2, bob_quote_amount)] {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                Side::Buy,
+                to_decimals(quote_amount),
+                to_decimals(1u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(bob.clone(), msg).unwrap();
+    }
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    (scenario, vamm2)
+}
+
+#[test]
+fn test_set_cross_margin() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let is_cross_margin = engine.is_cross_margin(&router, alice.to_string()).unwrap();
+    assert!(!is_cross_margin);
+
+    let msg = engine.set_cross_margin(true).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let is_cross_margin = engine.is_cross_margin(&router, alice.to_string()).unwrap();
+    assert!(is_cross_margin);
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(2u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // margin mode cannot be changed with open positions
+    let msg = engine.set_cross_margin(false).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OpenPositionsExist {}
+    );
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine.set_cross_margin(false).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let is_cross_margin = engine.is_cross_margin(&router, alice.to_string()).unwrap();
+    assert!(!is_cross_margin);
+}
+
+#[test]
+fn test_cross_margin_profit_supports_losing_position() {
+    let (scenario, vamm2) = setup_cross_margin(100u64);
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    // the margin ratio is computed over the whole account
+    let margin_ratio = engine
+        .get_margin_ratio(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    let margin_ratio_vamm2 = engine
+        .get_margin_ratio(&router, vamm2.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(margin_ratio, margin_ratio_vamm2);
+    assert_eq!(margin_ratio, Integer::new_positive(376_012_944u128));
+
+    let free_collateral = engine
+        .get_free_collateral(&router, vamm2.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_positive(21_044_071_347u128));
+
+    // the short position alone is below the maintenance margin but the long position supports it
+    let msg = engine
+        .liquidate(
+            vamm2.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(376_012_944u128),
+            maintenance: Uint128::from(250_000_000u128),
+        }
+    );
+}
+
+#[test]
+fn test_cross_margin_liquidation_absorbs_bad_debt() {
+    let (scenario, vamm2) = setup_cross_margin(300u64);
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        usdc,
+        engine,
+        vamm,
+        pricefeed,
+        insurance_fund,
+        ..
+    } = scenario;
+
+    // move the oracle price close to the second vamm's spot price
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(14u64), 1_000_000_000u64)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let margin_ratio = engine
+        .get_margin_ratio(&router, vamm2.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(margin_ratio, Integer::new_positive(94_610_049u128));
+
+    let msg = engine
+        .liquidate(
+            vamm2.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let err = engine
+        .position(&router, vamm2.addr().to_string(), alice.to_string())
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Querier contract error: Generic error: No position found".to_string()
+        },
+        err
+    );
+
+    // the bad debt of the short position is covered by the margin of the long position
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.margin, Uint128::from(8_059_046_578u128));
+
+    let state = engine.state(&router).unwrap();
+    assert_eq!(state.bad_debt, Uint128::zero());
+
+    let insurance_balance = usdc
+        .balance::<_, _, Empty>(&router, insurance_fund.addr())
+        .unwrap();
+    assert_eq!(insurance_balance, to_decimals(5_000u64));
+}
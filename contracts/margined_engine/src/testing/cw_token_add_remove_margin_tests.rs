@@ -1,6 +1,7 @@
 use cosmwasm_std::{Empty, Uint128};
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -89,8 +90,11 @@ fn test_add_margin_insufficent_balance() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: transfer failure - reply (id 9)"
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "transfer".to_string(),
+            id: 9,
+        }
     );
 }
 
@@ -110,8 +114,8 @@ fn test_add_margin_no_open_position() {
     let err = router.execute(alice.clone(), msg).unwrap_err();
 
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: No position found"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::NoPosition {}
     );
 }
 
@@ -273,8 +277,10 @@ fn test_remove_margin_insufficient_margin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient margin"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientMargin {
+            bad_debt: Uint128::from(1_000_000_000u128),
+        }
     );
 }
 
@@ -311,8 +317,11 @@ fn test_remove_margin_incorrect_ratio_four_percent() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(30_000_000_000u128),
+            amount: Uint128::from(36_000_000_000u128),
+        }
     );
 }
 
@@ -359,8 +368,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_profit_using_spot_price(
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(45_000_000_000u128),
+            amount: Uint128::from(45_010_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -417,8 +429,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_loss_using_spot_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(24_850_746_257u128),
+            amount: Uint128::from(24_900_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -481,8 +496,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_profit_using_spot_price
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(16_097_560_976u128),
+            amount: Uint128::from(16_500_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -538,8 +556,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_loss_using_spot_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(2_282_608_687u128),
+            amount: Uint128::from(2_500_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -606,8 +627,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_profit_using_twap_price(
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(45_000_000_000u128),
+            amount: Uint128::from(45_010_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -674,8 +698,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_loss_using_twap_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(34_925_373_122u128),
+            amount: Uint128::from(34_930_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -749,8 +776,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_profit_using_twap_price
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(15_548_780_488u128),
+            amount: Uint128::from(15_600_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -823,8 +853,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_loss_using_twap_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(8_641_304_340u128),
+            amount: Uint128::from(8_700_000_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -1,3 +1,4 @@
+use margined_engine::error::ContractError;
 // use crate::testing::setup::{self, to_decimals};
 use cosmwasm_std::Uint128;
 use cw20::Cw20ExecuteMsg;
@@ -372,8 +373,8 @@ fn test_can_open_position_short_and_liquidate_but_cannot_do_anything_more_in_sam
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
 
@@ -552,8 +553,8 @@ fn test_can_open_position_long_and_liquidate_but_cannot_do_anything_more_in_same
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
 
@@ -739,8 +740,8 @@ fn test_can_open_position_and_liquidate_but_cannot_do_anything_more_in_same_bloc
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
 
@@ -919,7 +920,7 @@ fn test_can_open_position_same_side_and_liquidate_but_cannot_do_anything_more_in
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
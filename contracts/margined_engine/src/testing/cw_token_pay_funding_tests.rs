@@ -1,6 +1,7 @@
 use cosmwasm_std::{Empty, Uint128};
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -744,8 +745,10 @@ fn test_have_huge_funding_payment_loss_margin_zero_cannot_remove_margin() {
         .unwrap();
     let err = router.execute(bob.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient margin".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientMargin {
+            bad_debt: Uint128::from(2_550_000_000_001u128),
+        }
     );
 }
 
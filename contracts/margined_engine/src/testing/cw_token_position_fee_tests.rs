@@ -3,6 +3,7 @@ use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
 use margined_perp::margined_engine::{PnlCalcOption, Position, Side};
+use margined_perp::margined_fee_pool::SenderInflow;
 use margined_utils::scenarios::SimpleScenario;
 
 // Note: these tests also verify the 10% fees for the amm are functioning
@@ -2322,3 +2323,53 @@ fn test_ten_percent_fee_open_long_price_down_liquidation() {
 //     assert_eq!(position.notional, Uint128::from(34_599_222_000u64));
 //     assert_eq!(position.margin, Uint128::from(4_599_222_000u64));
 // }
+
+#[test]
+fn test_fees_recorded_against_engine() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        usdc,
+        fee_pool,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    // 10% fee
+    let msg = vamm.set_toll_ratio(Uint128::from(100_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = vamm.set_spread_ratio(Uint128::zero()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            Uint128::from(60_000_000_000u64),
+            Uint128::from(10_000_000_000u64),
+            Uint128::from(37_500_000_000u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // the toll fee is deposited in the fee pool on behalf of the engine
+    let stats = fee_pool
+        .token_stats(usdc.addr().to_string(), &router)
+        .unwrap();
+    assert_eq!(stats.inflow, Uint128::from(60_000_000_000u64));
+
+    let res = fee_pool
+        .token_inflows(usdc.addr().to_string(), None, None, &router)
+        .unwrap();
+    assert_eq!(
+        res.inflows,
+        vec![SenderInflow {
+            sender: engine.addr(),
+            amount: Uint128::from(60_000_000_000u64),
+        }]
+    );
+}
@@ -0,0 +1,297 @@
+use cosmwasm_std::{to_binary, Empty, Uint128};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{Cw20HookMsg, EngineOp, ExecuteMsg, Side};
+use margined_perp::margined_vamm::Direction;
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// carol has no allowance for the engine, so can only pay by sending tokens
+fn setup_fees_and_fund_carol(scenario: &mut SimpleScenario) {
+    let SimpleScenario {
+        router,
+        owner,
+        carol,
+        usdc,
+        vamm,
+        ..
+    } = scenario;
+
+    let msg = vamm.set_toll_ratio(Uint128::from(10_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm
+        .set_spread_ratio(Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = usdc
+        .call(Cw20ExecuteMsg::Mint {
+            recipient: carol.to_string(),
+            amount: to_decimals(1000u64),
+        })
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_open_position_and_deposit_margin_with_cw20() {
+    let mut scenario = SimpleScenario::new();
+    setup_fees_and_fund_carol(&mut scenario);
+
+    let SimpleScenario {
+        router,
+        carol,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    // the margin of 60 and fees of 1% for both toll and spread on the notional of 600
+    let msg = engine
+        .open_position_cw20(
+            usdc.addr().to_string(),
+            to_decimals(72u64),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let position = engine
+        .position(router, vamm.addr().to_string(), carol.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(60u64));
+    assert_eq!(position.notional, to_decimals(600u64));
+
+    let trades = engine
+        .trade_history(
+            router,
+            vamm.addr().to_string(),
+            carol.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades[0].spread_fee, to_decimals(6u64));
+    assert_eq!(trades[0].toll_fee, to_decimals(6u64));
+
+    // the fees are paid out of the tokens received
+    let engine_balance = usdc.balance::<_, _, Empty>(router, engine.addr()).unwrap();
+    assert_eq!(engine_balance, to_decimals(60u64));
+
+    let msg = engine
+        .deposit_margin_cw20(
+            usdc.addr().to_string(),
+            to_decimals(20u64),
+            vamm.addr().to_string(),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let position = engine
+        .position(router, vamm.addr().to_string(), carol.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(80u64));
+
+    let carol_balance = usdc.balance::<_, _, Empty>(router, carol.clone()).unwrap();
+    assert_eq!(carol_balance, to_decimals(908u64));
+}
+
+#[test]
+fn test_cw20_amount_must_cover_margin_and_fees_exactly() {
+    let mut scenario = SimpleScenario::new();
+    setup_fees_and_fund_carol(&mut scenario);
+
+    let SimpleScenario {
+        router,
+        carol,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    let msg = engine
+        .open_position_cw20(
+            usdc.addr().to_string(),
+            to_decimals(70u64),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientFunds {}
+    );
+
+    let msg = engine
+        .open_position_cw20(
+            usdc.addr().to_string(),
+            to_decimals(80u64),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ExcessiveFunds {}
+    );
+
+    let carol_balance = usdc.balance::<_, _, Empty>(router, carol.clone()).unwrap();
+    assert_eq!(carol_balance, to_decimals(1000u64));
+}
+
+#[test]
+fn test_reverse_position_with_cw20() {
+    let mut scenario = SimpleScenario::new();
+    setup_fees_and_fund_carol(&mut scenario);
+
+    let SimpleScenario {
+        router,
+        carol,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    let msg = engine
+        .open_position_cw20(
+            usdc.addr().to_string(),
+            to_decimals(72u64),
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let carol_balance = usdc.balance::<_, _, Empty>(router, carol.clone()).unwrap();
+
+    // the fees on the notional of 800 are paid from the tokens received and the
+    // margin in excess of the new position is returned
+    let msg = engine
+        .open_position_cw20(
+            usdc.addr().to_string(),
+            to_decimals(16u64),
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(80u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let position = engine
+        .position(router, vamm.addr().to_string(), carol.to_string())
+        .unwrap();
+    assert_eq!(position.direction, Direction::RemoveFromAmm);
+    assert!(position.size < Integer::zero());
+    assert_eq!(position.margin, to_decimals(20u64));
+
+    let balance = usdc.balance::<_, _, Empty>(router, carol.clone()).unwrap();
+    assert!(balance > carol_balance);
+}
+
+#[test]
+fn test_batch_with_cw20() {
+    let mut scenario = SimpleScenario::new();
+    setup_fees_and_fund_carol(&mut scenario);
+    let other_vamm = scenario.add_vamm();
+
+    let SimpleScenario {
+        router,
+        carol,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    // 72 for the first position, 12 for the second without fees and 8 of margin
+    let msg = engine
+        .batch_cw20(
+            usdc.addr().to_string(),
+            to_decimals(92u64),
+            vec![
+                EngineOp::OpenPosition {
+                    vamm: vamm.addr().to_string(),
+                    side: Side::Buy,
+                    margin_amount: to_decimals(60u64),
+                    leverage: to_decimals(10u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                EngineOp::OpenPosition {
+                    vamm: other_vamm.addr().to_string(),
+                    side: Side::Sell,
+                    margin_amount: to_decimals(12u64),
+                    leverage: to_decimals(5u64),
+                    base_asset_limit: to_decimals(0u64),
+                },
+                EngineOp::DepositMargin {
+                    vamm: vamm.addr().to_string(),
+                    amount: to_decimals(8u64),
+                },
+            ],
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let position = engine
+        .position(router, vamm.addr().to_string(), carol.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(68u64));
+
+    let position = engine
+        .position(router, other_vamm.addr().to_string(), carol.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(12u64));
+
+    let carol_balance = usdc.balance::<_, _, Empty>(router, carol.clone()).unwrap();
+    assert_eq!(carol_balance, to_decimals(908u64));
+}
+
+#[test]
+fn test_receive_only_accepts_eligible_collateral() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: alice.to_string(),
+        amount: to_decimals(10u64),
+        msg: to_binary(&Cw20HookMsg::DepositMargin {
+            vamm: vamm.addr().to_string(),
+        })
+        .unwrap(),
+    });
+    let err = router
+        .execute_contract(alice.clone(), engine.addr(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::IneligibleCollateral {
+            token: alice.to_string()
+        }
+    );
+}
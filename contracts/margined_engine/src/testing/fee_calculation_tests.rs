@@ -1,6 +1,7 @@
-use cosmwasm_std::{Empty, StdError, Uint128};
+use cosmwasm_std::{Empty, Uint128};
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::{PnlCalcOption, Side};
 use margined_utils::scenarios::SimpleScenario;
 
@@ -581,10 +582,11 @@ fn test_force_error_insufficient_balance_open_position_total_fee_ten_percent() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "transfer failure - reply (id 9)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "transfer".to_string(),
+            id: 9
+        }
     );
 }
 
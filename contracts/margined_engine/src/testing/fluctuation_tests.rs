@@ -1,6 +1,7 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::Uint128;
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -49,10 +50,11 @@ fn test_force_error_open_position_exceeds_fluctuation_limit() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "increase position failure - reply (id 1)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "increase position".to_string(),
+            id: 1
+        }
     );
 }
 
@@ -115,10 +117,11 @@ fn test_force_error_reduce_position_exceeds_fluctuation_limit() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "decrease position failure - reply (id 2)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "decrease position".to_string(),
+            id: 2
+        }
     );
 }
 
@@ -225,10 +228,11 @@ fn test_close_position_limit_force_error_exceeding_fluctuation_limit_twice_in_sa
         .unwrap();
     let err = router.execute(bob.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "close position failure - reply (id 4)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "close position".to_string(),
+            id: 4
+        }
     );
 }
 
@@ -506,10 +510,11 @@ fn test_force_error_close_position_slippage_limit_originally_long() {
         .unwrap();
     let err = router.execute(bob.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "close position failure - reply (id 4)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "close position".to_string(),
+            id: 4
+        }
     );
 }
 
@@ -595,9 +600,10 @@ fn test_force_error_close_position_slippage_limit_originally_short() {
         .unwrap();
     let err = router.execute(bob.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "close position failure - reply (id 4)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "close position".to_string(),
+            id: 4
+        }
     );
 }
@@ -0,0 +1,383 @@
+use cosmwasm_std::{StdError, Uint128};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{LeverageTier, Side};
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// positions above 500 notional need a margin ratio of 0.2 to open and 0.15 to stay open
+fn whale_tier() -> LeverageTier {
+    LeverageTier {
+        notional: to_decimals(500u64),
+        initial_margin_ratio: Uint128::from(200_000_000u128),
+        maintenance_margin_ratio: Uint128::from(150_000_000u128),
+    }
+}
+
+#[test]
+fn test_set_and_query_leverage_tiers() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let response = engine
+        .leverage_tiers(&router, vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(response.vamm, vamm.addr());
+    assert!(response.tiers.is_empty());
+
+    let tiers = vec![
+        LeverageTier {
+            notional: to_decimals(100u64),
+            initial_margin_ratio: Uint128::from(100_000_000u128),
+            maintenance_margin_ratio: Uint128::from(75_000_000u128),
+        },
+        whale_tier(),
+    ];
+    let msg = engine
+        .set_leverage_tiers(vamm.addr().to_string(), tiers.clone())
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let response = engine
+        .leverage_tiers(&router, vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(response.tiers, tiers);
+
+    // only the owner can set the tiers
+    let msg = engine
+        .set_leverage_tiers(vamm.addr().to_string(), vec![])
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
+    );
+
+    // an empty list removes the tiers
+    let msg = engine
+        .set_leverage_tiers(vamm.addr().to_string(), vec![])
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let response = engine
+        .leverage_tiers(&router, vamm.addr().to_string())
+        .unwrap();
+    assert!(response.tiers.is_empty());
+}
+
+#[test]
+fn test_leverage_tiers_validation() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    // the tiers must be ordered by notional
+    let msg = engine
+        .set_leverage_tiers(
+            vamm.addr().to_string(),
+            vec![
+                whale_tier(),
+                LeverageTier {
+                    notional: to_decimals(100u64),
+                    initial_margin_ratio: Uint128::from(250_000_000u128),
+                    maintenance_margin_ratio: Uint128::from(200_000_000u128),
+                },
+            ],
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InvalidLeverageTiers {}
+    );
+
+    // the margin ratios cannot step down
+    let msg = engine
+        .set_leverage_tiers(
+            vamm.addr().to_string(),
+            vec![
+                whale_tier(),
+                LeverageTier {
+                    notional: to_decimals(1_000u64),
+                    initial_margin_ratio: Uint128::from(250_000_000u128),
+                    maintenance_margin_ratio: Uint128::from(100_000_000u128),
+                },
+            ],
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InvalidLeverageTiers {}
+    );
+
+    let msg = engine
+        .set_leverage_tiers(
+            vamm.addr().to_string(),
+            vec![LeverageTier {
+                notional: to_decimals(100u64),
+                initial_margin_ratio: Uint128::from(100_000_000u128),
+                maintenance_margin_ratio: Uint128::from(200_000_000u128),
+            }],
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<StdError>().unwrap(),
+        &StdError::generic_err("Incorrect initialisation of margin ratios, initial >= maintenance")
+    );
+}
+
+#[test]
+fn test_leverage_tier_limits_open_position() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .set_leverage_tiers(vamm.addr().to_string(), vec![whale_tier()])
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // a notional of 600 is in the tier so 10x is too much
+    let err = engine
+        .simulate_open_position(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains(
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_positive(100_000_000u128),
+            required: Uint128::from(200_000_000u128),
+        }
+        .to_string()
+    ));
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_positive(100_000_000u128),
+            required: Uint128::from(200_000_000u128),
+        }
+    );
+
+    // positions can use 10x until they grow into the tier
+    for margin in [40u64, 10u64].iter() {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                Side::Buy,
+                to_decimals(*margin),
+                to_decimals(10u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(alice.clone(), msg).unwrap();
+    }
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(1u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_positive(100_000_000u128),
+            required: Uint128::from(200_000_000u128),
+        }
+    );
+
+    // 5x is within the tier as long as the margin ratio stays above its maintenance
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(110u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.notional, to_decimals(1_050u64));
+}
+
+#[test]
+fn test_leverage_tier_limits_free_collateral() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // margin of 60 less a requirement of 600 * 0.05
+    let free_collateral = engine
+        .get_free_collateral(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_positive(to_decimals(30u64)));
+
+    let msg = engine
+        .set_leverage_tiers(vamm.addr().to_string(), vec![whale_tier()])
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // the tier raises the requirement to 600 * 0.2
+    let free_collateral = engine
+        .get_free_collateral(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_negative(to_decimals(60u64)));
+
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), to_decimals(1u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_negative(to_decimals(60u64)),
+            amount: to_decimals(1u64),
+        }
+    );
+}
+
+#[test]
+fn test_leverage_tier_sets_liquidation() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        carol,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = SimpleScenario::new();
+
+    // the oracle agrees with the vamm price
+    let timestamp: u64 = router.block_info().time.seconds();
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(10u64), timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let liquidation_price = engine
+        .liquidation_price(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+
+    // the position is safe under the maintenance margin ratio of the engine
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap_err();
+
+    let msg = engine
+        .set_leverage_tiers(vamm.addr().to_string(), vec![whale_tier()])
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // the higher maintenance margin ratio raises the liquidation price of the long
+    let tier_liquidation_price = engine
+        .liquidation_price(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert!(tier_liquidation_price > liquidation_price);
+
+    let liquidatable = engine
+        .liquidatable_positions(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(liquidatable.positions.len(), 1);
+    assert_eq!(liquidatable.positions[0].trader, alice);
+
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert!(positions.is_empty());
+}
@@ -0,0 +1,209 @@
+use cosmwasm_std::{Addr, Uint128};
+use cw20::Cw20ExecuteMsg;
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::{LiquidationType, Side};
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// alice opens a 25 margin * 10x long and bob then sells `bob_margin` at 1x
+fn setup_positions(bob_margin: Uint128) -> SimpleScenario {
+    let mut scenario = SimpleScenario::new();
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        owner,
+        usdc,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = &mut scenario;
+
+    // set the latest price
+    let price: Uint128 = Uint128::from(10_000_000_000u128);
+    let timestamp: u64 = router.block_info().time.seconds();
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), price, timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .set_margin_ratios(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_partial_liquidation_ratio(Uint128::from(250_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_liquidation_fee(Uint128::from(25_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // reduce the allowances
+    for trader in [alice.clone(), bob.clone()] {
+        router
+            .execute_contract(
+                trader.clone(),
+                usdc.addr().clone(),
+                &Cw20ExecuteMsg::DecreaseAllowance {
+                    spender: engine.addr().to_string(),
+                    amount: to_decimals(1900),
+                    expires: None,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            bob_margin,
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    scenario
+}
+
+#[test]
+fn test_partially_liquidatable_position() {
+    let scenario = setup_positions(Uint128::from(45_180_722_890u128));
+    let SimpleScenario {
+        mut router,
+        alice,
+        carol,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    let res = engine
+        .liquidatable_positions(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(res.positions.len(), 1);
+    assert_eq!(res.positions[0].trader, alice);
+    assert_eq!(res.positions[0].liquidation_type, LiquidationType::Partial);
+    assert!(res.positions[0].margin_ratio.value < Uint128::from(100_000_000u128));
+    assert!(!res.positions[0].shortfall.is_zero());
+
+    // a partial liquidation reduces the shortfall, but alice remains liquidatable
+    let shortfall = res.positions[0].shortfall;
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol, msg).unwrap();
+
+    let res = engine
+        .liquidatable_positions(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(res.positions.len(), 1);
+    assert_eq!(
+        res.positions[0].margin_ratio,
+        Integer::new_positive(43_713_253u128)
+    );
+    assert!(res.positions[0].shortfall < shortfall);
+}
+
+#[test]
+fn test_fully_liquidatable_position() {
+    let scenario = setup_positions(Uint128::from(73_529_411_760u128));
+    let SimpleScenario {
+        router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    let res = engine
+        .liquidatable_positions(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(res.positions.len(), 1);
+    assert_eq!(res.positions[0].trader, alice);
+    assert_eq!(res.positions[0].liquidation_type, LiquidationType::Full);
+}
+
+#[test]
+fn test_liquidatable_positions_pagination() {
+    let scenario = setup_positions(Uint128::from(45_180_722_890u128));
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = scenario;
+
+    // the scan follows the position index, so every page reports where it stopped
+    let mut scanned: Vec<Addr> = vec![];
+    let mut liquidatable: Vec<Addr> = vec![];
+    let mut start_after: Option<String> = None;
+    loop {
+        let res = engine
+            .liquidatable_positions(&router, vamm.addr().to_string(), start_after, Some(1))
+            .unwrap();
+        match res.last_trader {
+            Some(trader) => {
+                liquidatable.extend(res.positions.into_iter().map(|position| position.trader));
+                start_after = Some(trader.to_string());
+                scanned.push(trader);
+            }
+            None => {
+                assert!(res.positions.is_empty());
+                break;
+            }
+        }
+    }
+
+    scanned.sort();
+    assert_eq!(scanned, vec![alice.clone(), bob]);
+    assert_eq!(liquidatable, vec![alice]);
+}
+
+#[test]
+fn test_liquidatable_positions_unknown_vamm() {
+    let scenario = setup_positions(Uint128::from(45_180_722_890u128));
+    let SimpleScenario { router, engine, .. } = scenario;
+
+    let err = engine
+        .liquidatable_positions(&router, "unknown".to_string(), None, None)
+        .unwrap_err();
+    assert!(err.to_string().contains("vAMM"));
+}
@@ -0,0 +1,249 @@
+use crate::error::ContractError;
+use crate::{
+    contract::{execute, instantiate, migrate, query},
+    state::{
+        legacy_position_key, read_position, read_state, store_position, LegacyConfig, KEY_CONFIG,
+        KEY_POSITION,
+    },
+};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Addr, StdError, Uint128};
+use cosmwasm_storage::{bucket, singleton};
+use cw2::{get_contract_version, set_contract_version};
+use margined_common::{asset::AssetInfo, integer::Integer};
+use margined_perp::{
+    margined_engine::{
+        ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, Position, PositionsResponse,
+        QueryMsg, TradersResponse,
+    },
+    margined_vamm::Direction,
+};
+
+const TOKEN: &str = "uwasm";
+const OWNER: &str = "owner";
+const INSURANCE_FUND: &str = "insurance_fund";
+const FEE_POOL: &str = "fee_pool";
+
+fn instantiate_engine(deps: cosmwasm_std::DepsMut) {
+    let msg = InstantiateMsg {
+        pauser: OWNER.to_string(),
+        insurance_fund: INSURANCE_FUND.to_string(),
+        fee_pool: FEE_POOL.to_string(),
+        eligible_collateral: TOKEN.to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128), // 0.05
+        maintenance_margin_ratio: Uint128::from(50_000u128), // 0.05
+        liquidation_fee: Uint128::from(100u128),
+    };
+    let info = mock_info(OWNER, &[]);
+    instantiate(deps, mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn test_migrate_legacy_state() {
+    let mut deps = mock_dependencies();
+    instantiate_engine(deps.as_mut());
+
+    // overwrite the config with the layout of the previous release
+    let legacy = LegacyConfig {
+        owner: Addr::unchecked(OWNER),
+        insurance_fund: Addr::unchecked(INSURANCE_FUND),
+        fee_pool: Addr::unchecked(FEE_POOL),
+        eligible_collateral: AssetInfo::NativeToken {
+            denom: TOKEN.to_string(),
+        },
+        decimals: Uint128::from(10u128.pow(6u32)),
+        initial_margin_ratio: Uint128::from(100_000u128),
+        maintenance_margin_ratio: Uint128::from(50_000u128),
+        partial_liquidation_ratio: Uint128::from(250_000u128),
+        liquidation_fee: Uint128::from(100u128),
+    };
+    singleton(deps.as_mut().storage, KEY_CONFIG)
+        .save(&legacy)
+        .unwrap();
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "0.0.1").unwrap();
+
+    let position = Position {
+        vamm: Addr::unchecked("vamm"),
+        trader: Addr::unchecked("alice"),
+        direction: Direction::AddToAmm,
+        size: Integer::new_positive(1_000u128),
+        margin: Uint128::from(500u128),
+        notional: Uint128::from(5_000u128),
+        last_updated_premium_fraction: Integer::zero(),
+        block_number: 10u64,
+    };
+    store_position(deps.as_mut().storage, &position).unwrap();
+    let state = read_state(deps.as_ref().storage).unwrap();
+
+    // the config cannot be read before the migration
+    let err = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    assert!(matches!(err, StdError::ParseErr { .. }));
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        config,
+        ConfigResponse {
+            owner: legacy.owner,
+            insurance_fund: legacy.insurance_fund,
+            fee_pool: legacy.fee_pool,
+            eligible_collateral: legacy.eligible_collateral,
+            decimals: legacy.decimals,
+            initial_margin_ratio: legacy.initial_margin_ratio,
+            maintenance_margin_ratio: legacy.maintenance_margin_ratio,
+            partial_liquidation_ratio: legacy.partial_liquidation_ratio,
+            liquidation_fee: legacy.liquidation_fee,
+            keeper_fee: Uint128::zero(),
+        }
+    );
+
+    // state and positions survive the upgrade
+    assert_eq!(read_state(deps.as_ref().storage).unwrap(), state);
+    assert_eq!(
+        read_position(
+            deps.as_ref().storage,
+            &Addr::unchecked("vamm"),
+            &Addr::unchecked("alice")
+        )
+        .unwrap(),
+        position
+    );
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+}
+
+#[test]
+fn test_migrate_current_state() {
+    let mut deps = mock_dependencies();
+    instantiate_engine(deps.as_mut());
+
+    let before = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let after = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn test_migrate_version_checks() {
+    let mut deps = mock_dependencies();
+    instantiate_engine(deps.as_mut());
+
+    // cannot migrate from a different contract
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-vamm", "0.0.1").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a different contract".to_string()
+    );
+
+    // cannot downgrade the contract
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a newer contract version".to_string()
+    );
+}
+
+#[test]
+fn test_migrate_legacy_positions() {
+    let mut deps = mock_dependencies();
+    instantiate_engine(deps.as_mut());
+
+    // store positions under the hashed keys of the previous release
+    let positions: Vec<Position> = [("vamm1", "bob"), ("vamm1", "alice"), ("vamm2", "alice")]
+        .iter()
+        .map(|(vamm, trader)| Position {
+            vamm: Addr::unchecked(*vamm),
+            trader: Addr::unchecked(*trader),
+            direction: Direction::AddToAmm,
+            size: Integer::new_positive(1_000u128),
+            margin: Uint128::from(500u128),
+            notional: Uint128::from(5_000u128),
+            last_updated_premium_fraction: Integer::zero(),
+            block_number: 10u64,
+        })
+        .collect();
+    for position in positions.iter() {
+        bucket(deps.as_mut().storage, KEY_POSITION)
+            .save(
+                &legacy_position_key(&position.vamm, &position.trader),
+                position,
+            )
+            .unwrap();
+    }
+
+    // legacy positions can be read but are not indexed yet
+    assert_eq!(
+        read_position(
+            deps.as_ref().storage,
+            &Addr::unchecked("vamm1"),
+            &Addr::unchecked("bob")
+        )
+        .unwrap(),
+        positions[0]
+    );
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AllTraders {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: TradersResponse = from_binary(&res).unwrap();
+    assert!(res.traders.is_empty());
+
+    // only the owner can migrate the positions
+    let msg = ExecuteMsg::MigratePositions { limit: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // migrate in batches of two
+    let mut migrated: Vec<String> = vec![];
+    for _ in 0..3 {
+        let msg = ExecuteMsg::MigratePositions { limit: Some(2u32) };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+        let attr = res.attributes.iter().find(|a| a.key == "migrated").unwrap();
+        migrated.push(attr.value.clone());
+    }
+    assert_eq!(migrated, vec!["2", "1", "0"]);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AllPositionsForVamm {
+            vamm: "vamm1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: PositionsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        res.positions,
+        vec![positions[1].clone(), positions[0].clone()]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AllTraders {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let mut res: TradersResponse = from_binary(&res).unwrap();
+    res.traders.sort();
+    assert_eq!(
+        res.traders,
+        vec![Addr::unchecked("alice"), Addr::unchecked("bob")]
+    );
+}
@@ -1,24 +1,39 @@
 #[cfg(test)]
+mod account_summary_tests;
+mod adl_tests;
 mod bad_debt_tests;
+mod batch_tests;
+mod cross_margin_tests;
 mod cw_token_add_remove_margin_tests;
 mod cw_token_liquidation_frontrun_hack_tests;
 mod cw_token_liquidation_tests;
 mod cw_token_pay_funding_tests;
 mod cw_token_position_fee_tests;
+mod cw_token_receive_tests;
 mod fee_calculation_tests;
 mod fluctuation_tests;
+mod leverage_tier_tests;
+mod liquidatable_positions_tests;
 mod margin_engine_tests;
 mod margin_ratio_tests;
+mod migrate_tests;
+mod multi_collateral_tests;
 mod native_token_add_remove_margin_tests;
 mod native_token_liquidation_frontrun_hack_tests;
 mod native_token_liquidation_tests;
 mod native_token_pay_funding_tests;
 mod native_token_position_fee_tests;
 mod open_interest_notional_tests;
+mod order_tests;
 mod pausable_tests;
 mod personal_position_tests;
+mod position_index_tests;
 mod position_liquidation_tests;
 mod position_tests;
 mod position_upper_bound_tests;
+mod reply_context_tests;
+mod risk_params_tests;
+mod simulation_tests;
 mod tests;
+mod trade_history_tests;
 mod whitelist_tests;
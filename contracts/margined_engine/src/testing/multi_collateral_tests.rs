@@ -0,0 +1,648 @@
+use cosmwasm_std::{Coin, Empty, StdError, Uint128};
+use cw20::Cw20Contract;
+use cw_multi_test::Executor;
+use margined_common::{asset::AssetInfo, integer::Integer};
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{CollateralBalance, Side};
+use margined_utils::contracts::helpers::margined_pricefeed::PricefeedController;
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// atom uses 6 decimals while the engine uses 9
+fn atom(amount: u64) -> Uint128 {
+    Uint128::from(amount * 1_000_000u64)
+}
+
+// registers an atom token priced at 10 usdc with a weight of 0.8
+fn setup_atom(scenario: &mut SimpleScenario) -> (Cw20Contract, PricefeedController) {
+    let token = scenario.add_token("ATOM", 6u8);
+    let pricefeed = scenario.add_pricefeed("ATOM", to_decimals(10u64));
+
+    let msg = scenario
+        .engine
+        .register_collateral(
+            token_info(&token),
+            Uint128::from(800_000_000u128),
+            pricefeed.addr().to_string(),
+            "ATOM".to_string(),
+            9u8,
+        )
+        .unwrap();
+    scenario
+        .router
+        .execute(scenario.owner.clone(), msg)
+        .unwrap();
+
+    (token, pricefeed)
+}
+
+fn token_info(token: &Cw20Contract) -> AssetInfo {
+    AssetInfo::Token {
+        contract_addr: token.addr(),
+    }
+}
+
+#[test]
+fn test_register_and_remove_collateral() {
+    let mut scenario = SimpleScenario::new();
+    let (atom_token, pricefeed) = setup_atom(&mut scenario);
+
+    let SimpleScenario {
+        router,
+        owner,
+        alice,
+        usdc,
+        engine,
+        ..
+    } = &mut scenario;
+
+    let collaterals = engine.collaterals(router).unwrap().collaterals;
+    assert_eq!(collaterals.len(), 1);
+    assert_eq!(collaterals[0].asset, token_info(&atom_token));
+    assert_eq!(collaterals[0].decimals, Uint128::from(1_000_000u128));
+    assert_eq!(collaterals[0].weight, Uint128::from(800_000_000u128));
+    assert_eq!(collaterals[0].pricefeed, pricefeed.addr());
+    assert_eq!(collaterals[0].price_key, "ATOM".to_string());
+    assert_eq!(
+        collaterals[0].price_decimals,
+        Uint128::from(1_000_000_000u128)
+    );
+
+    // only the owner can change the registry
+    let msg = engine
+        .register_collateral(
+            AssetInfo::NativeToken {
+                denom: "uatom".to_string(),
+            },
+            Uint128::from(800_000_000u128),
+            pricefeed.addr().to_string(),
+            "ATOM".to_string(),
+            9u8,
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
+    );
+
+    let msg = engine.remove_collateral(token_info(&atom_token)).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
+    );
+
+    // the eligible collateral is already accepted as margin
+    let msg = engine
+        .register_collateral(
+            token_info(usdc),
+            Uint128::from(1_000_000_000u128),
+            pricefeed.addr().to_string(),
+            "USDC".to_string(),
+            9u8,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::CollateralIsEligible {}
+    );
+
+    // the weight cannot be above one
+    let msg = engine
+        .register_collateral(
+            token_info(&atom_token),
+            Uint128::from(1_000_000_001u128),
+            pricefeed.addr().to_string(),
+            "ATOM".to_string(),
+            9u8,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<StdError>().unwrap(),
+        &StdError::generic_err("Invalid ratio")
+    );
+
+    // prices need at least six decimals
+    let msg = engine
+        .register_collateral(
+            token_info(&atom_token),
+            Uint128::from(800_000_000u128),
+            pricefeed.addr().to_string(),
+            "ATOM".to_string(),
+            5u8,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<StdError>().unwrap(),
+        &StdError::generic_err("Decimal places cannot be less than six")
+    );
+
+    // registering again updates the entry
+    let msg = engine
+        .register_collateral(
+            token_info(&atom_token),
+            Uint128::from(500_000_000u128),
+            pricefeed.addr().to_string(),
+            "ATOM".to_string(),
+            9u8,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let collaterals = engine.collaterals(router).unwrap().collaterals;
+    assert_eq!(collaterals.len(), 1);
+    assert_eq!(collaterals[0].weight, Uint128::from(500_000_000u128));
+
+    let msg = engine.remove_collateral(token_info(&atom_token)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let collaterals = engine.collaterals(router).unwrap().collaterals;
+    assert!(collaterals.is_empty());
+
+    let msg = engine.remove_collateral(token_info(&atom_token)).unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::CollateralNotRegistered {
+            asset: atom_token.addr().to_string()
+        }
+    );
+}
+
+#[test]
+fn test_deposit_and_withdraw_collateral() {
+    let mut scenario = SimpleScenario::new();
+    let (atom_token, _) = setup_atom(&mut scenario);
+    let other_token = scenario.add_token("OSMO", 6u8);
+
+    let SimpleScenario {
+        router,
+        owner,
+        alice,
+        usdc,
+        engine,
+        ..
+    } = &mut scenario;
+
+    let msg = engine
+        .deposit_collateral_cw20(atom_token.addr().to_string(), atom(100u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // 100 atom at 10 usdc, weighted at 0.8
+    let collateral = engine.trader_collateral(router, alice.to_string()).unwrap();
+    assert_eq!(
+        collateral.balances,
+        vec![CollateralBalance {
+            asset: token_info(&atom_token),
+            amount: atom(100u64),
+            value: to_decimals(800u64),
+        }]
+    );
+    assert_eq!(collateral.total_value, to_decimals(800u64));
+
+    let engine_balance = atom_token
+        .balance::<_, _, Empty>(router, engine.addr())
+        .unwrap();
+    assert_eq!(engine_balance, atom(100u64));
+
+    // neither unregistered tokens nor the eligible collateral can be deposited
+    let msg = engine
+        .deposit_collateral_cw20(other_token.addr().to_string(), atom(100u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::CollateralNotRegistered {
+            asset: other_token.addr().to_string()
+        }
+    );
+
+    let msg = engine
+        .deposit_collateral_cw20(usdc.addr().to_string(), to_decimals(100u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::CollateralNotRegistered {
+            asset: usdc.addr().to_string()
+        }
+    );
+
+    let msg = engine
+        .withdraw_collateral(token_info(&atom_token), atom(40u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = atom_token
+        .balance::<_, _, Empty>(router, alice.clone())
+        .unwrap();
+    assert_eq!(balance, atom(940u64));
+
+    let msg = engine
+        .withdraw_collateral(token_info(&atom_token), atom(100u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateralBalance {
+            balance: atom(60u64),
+            amount: atom(100u64),
+        }
+    );
+
+    // deposits of removed assets have no value but can still be withdrawn
+    let msg = engine.remove_collateral(token_info(&atom_token)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let collateral = engine.trader_collateral(router, alice.to_string()).unwrap();
+    assert_eq!(collateral.balances[0].amount, atom(60u64));
+    assert_eq!(collateral.total_value, Uint128::zero());
+
+    let msg = engine
+        .withdraw_collateral(token_info(&atom_token), atom(60u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let collateral = engine.trader_collateral(router, alice.to_string()).unwrap();
+    assert!(collateral.balances.is_empty());
+}
+
+#[test]
+fn test_deposit_native_collateral() {
+    let mut scenario = SimpleScenario::new();
+    // the feed quotes atom at 10 usdc with 6 decimals
+    let pricefeed = scenario.add_pricefeed("ATOM", Uint128::from(10_000_000u128));
+
+    let SimpleScenario {
+        router,
+        owner,
+        alice,
+        engine,
+        ..
+    } = &mut scenario;
+
+    router
+        .init_modules(|router, _, storage| {
+            router.bank.init_balance(
+                storage,
+                alice,
+                vec![
+                    Coin::new(100_000_000u128, "uatom"),
+                    Coin::new(100u128, "uosmo"),
+                ],
+            )
+        })
+        .unwrap();
+
+    let uatom = AssetInfo::NativeToken {
+        denom: "uatom".to_string(),
+    };
+
+    let msg = engine
+        .register_collateral(
+            uatom.clone(),
+            Uint128::from(500_000_000u128),
+            pricefeed.addr().to_string(),
+            "ATOM".to_string(),
+            6u8,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // only a single coin can be deposited at once
+    let msg = engine
+        .deposit_collateral(vec![
+            Coin::new(10_000_000u128, "uatom"),
+            Coin::new(100u128, "uosmo"),
+        ])
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InvalidCollateralFunds {}
+    );
+
+    let msg = engine
+        .deposit_collateral(vec![Coin::new(100u128, "uosmo")])
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::CollateralNotRegistered {
+            asset: "uosmo".to_string()
+        }
+    );
+
+    let msg = engine
+        .deposit_collateral(vec![Coin::new(10_000_000u128, "uatom")])
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let collateral = engine.trader_collateral(router, alice.to_string()).unwrap();
+    // 10 atom at 10 usdc weighted at 0.5, in the engine decimals
+    assert_eq!(collateral.balances[0].asset, uatom);
+    assert_eq!(collateral.total_value, to_decimals(50u64));
+
+    let msg = engine
+        .withdraw_collateral(uatom, Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let balance = router.wrap().query_balance(alice.clone(), "uatom").unwrap();
+    assert_eq!(balance.amount, Uint128::from(100_000_000u128));
+}
+
+#[test]
+fn test_collateral_backs_cross_margined_accounts() {
+    let mut scenario = SimpleScenario::new();
+    let (atom_token, _) = setup_atom(&mut scenario);
+
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    let msg = engine.set_cross_margin(true).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .deposit_collateral_cw20(atom_token.addr().to_string(), atom(10u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // the 80 of weighted collateral backs alice's account, (60 + 80) / 600
+    let margin_ratio = engine
+        .get_margin_ratio(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(
+        margin_ratio,
+        Integer::new_positive(Uint128::from(233_333_333u128))
+    );
+
+    let free_collateral = engine
+        .get_free_collateral(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_positive(to_decimals(110u64)));
+
+    let summary = engine.account_summary(router, alice.to_string()).unwrap();
+    assert_eq!(summary.free_collateral, free_collateral);
+
+    // while the collateral of bob's isolated position is not counted
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let margin_ratio = engine
+        .get_margin_ratio(router, vamm.addr().to_string(), bob.to_string())
+        .unwrap();
+    let free_collateral = engine
+        .get_free_collateral(router, vamm.addr().to_string(), bob.to_string())
+        .unwrap();
+
+    let msg = engine
+        .deposit_collateral_cw20(atom_token.addr().to_string(), atom(10u64))
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    assert_eq!(
+        engine
+            .get_margin_ratio(router, vamm.addr().to_string(), bob.to_string())
+            .unwrap(),
+        margin_ratio
+    );
+    assert_eq!(
+        engine
+            .get_free_collateral(router, vamm.addr().to_string(), bob.to_string())
+            .unwrap(),
+        free_collateral
+    );
+
+    // alice can withdraw margin against her collateral
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), to_decimals(50u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let free_collateral = engine
+        .get_free_collateral(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_positive(to_decimals(60u64)));
+
+    // but no longer all of the collateral
+    let msg = engine
+        .withdraw_collateral(token_info(&atom_token), atom(10u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(to_decimals(60u64)),
+            amount: to_decimals(80u64),
+        }
+    );
+
+    let msg = engine
+        .withdraw_collateral(token_info(&atom_token), atom(5u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // bob's collateral does not back his position so can be withdrawn in full
+    let msg = engine
+        .withdraw_collateral(token_info(&atom_token), atom(10u64))
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_liquidate_collateral() {
+    let mut scenario = SimpleScenario::new();
+    let (atom_token, _) = setup_atom(&mut scenario);
+
+    let SimpleScenario {
+        router,
+        owner,
+        alice,
+        bob,
+        david,
+        usdc,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = &mut scenario;
+
+    let price: Uint128 = Uint128::from(10_000_000_000u128);
+    let timestamp: u64 = router.block_info().time.seconds();
+    let msg = pricefeed
+        .append_price("ETH".to_string(), price, timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .set_margin_ratios(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_liquidation_fee(Uint128::from(25_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // bob's collateral does not back an isolated position
+    let msg = engine
+        .deposit_collateral_cw20(atom_token.addr().to_string(), atom(1u64))
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = engine.set_cross_margin(true).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .deposit_collateral_cw20(atom_token.addr().to_string(), atom(1u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // the account is above the maintenance margin ratio
+    let msg = engine
+        .liquidate_collateral(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            token_info(&atom_token),
+            atom(1u64),
+            vec![],
+        )
+        .unwrap();
+    // (25 + 8) / 250
+    let err = router.execute(david.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(Uint128::from(132_000_000u128)),
+            maintenance: Uint128::from(100_000_000u128),
+        }
+    );
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from(73_529_411_760u128),
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let margin_ratio = engine
+        .get_margin_ratio(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert!(margin_ratio < Integer::new_positive(Uint128::from(100_000_000u128)));
+
+    let msg = engine
+        .liquidate_collateral(
+            vamm.addr().to_string(),
+            bob.to_string(),
+            token_info(&atom_token),
+            atom(1u64),
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(david.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::NotCrossMargined {}
+    );
+
+    let david_balance = usdc.balance::<_, _, Empty>(router, david.clone()).unwrap();
+
+    let msg = engine
+        .liquidate_collateral(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            token_info(&atom_token),
+            atom(1u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(david.clone(), msg).unwrap();
+
+    // david buys the atom worth 10 at the liquidation fee discount of 2.5%
+    let payment = Uint128::from(9_750_000_000u128);
+    let balance = usdc.balance::<_, _, Empty>(router, david.clone()).unwrap();
+    assert_eq!(david_balance - balance, payment);
+
+    let balance = atom_token
+        .balance::<_, _, Empty>(router, david.clone())
+        .unwrap();
+    assert_eq!(balance, atom(1u64));
+
+    // and the payment is added to alice's margin
+    let position = engine
+        .position(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(25u64) + payment);
+
+    let collateral = engine.trader_collateral(router, alice.to_string()).unwrap();
+    assert!(collateral.balances.is_empty());
+
+    let new_margin_ratio = engine
+        .get_margin_ratio(router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert!(new_margin_ratio > margin_ratio);
+
+    let collateral = engine
+        .trader_collateral(router, "nobody".to_string())
+        .unwrap();
+    assert_eq!(collateral.total_value, Uint128::zero());
+}
@@ -4,6 +4,7 @@ use margined_common::{
     asset::{Asset, AssetInfo},
     integer::Integer,
 };
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::NativeTokenScenario;
 
@@ -100,10 +101,8 @@ fn test_force_error_add_incorrect_margin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Must send reserve token 'uwasm'".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Std(StdError::generic_err("Must send reserve token 'uwasm'"))
     );
 }
 
@@ -127,8 +126,8 @@ fn test_add_margin_no_open_position() {
     let err = router.execute(alice.clone(), msg).unwrap_err();
 
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: No position found"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::NoPosition {}
     );
 }
 
@@ -292,8 +291,10 @@ fn test_remove_margin_insufficient_margin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient margin"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientMargin {
+            bad_debt: Uint128::from(1_000_000u128),
+        }
     );
 }
 
@@ -331,8 +332,11 @@ fn test_remove_margin_incorrect_ratio_four_percent() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(30_000_000u128),
+            amount: Uint128::from(36_000_000u128),
+        }
     );
 }
 
@@ -379,8 +383,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_profit_using_spot_price(
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(45_000_000u128),
+            amount: Uint128::from(45_010_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -437,8 +444,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_loss_using_spot_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(24_850_742u128),
+            amount: Uint128::from(24_900_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -501,8 +511,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_profit_using_spot_price
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(16_097_561u128),
+            amount: Uint128::from(16_500_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -558,8 +571,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_loss_using_spot_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(2_282_600u128),
+            amount: Uint128::from(2_500_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -626,8 +642,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_profit_using_twap_price(
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(45_000_000u128),
+            amount: Uint128::from(45_010_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -694,8 +713,11 @@ fn test_remove_margin_unrealized_pnl_long_position_with_loss_using_twap_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(34_925_370u128),
+            amount: Uint128::from(34_930_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -768,8 +790,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_profit_using_twap_price
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(15_548_781u128),
+            amount: Uint128::from(15_600_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -842,8 +867,11 @@ fn test_remove_margin_unrealized_pnl_short_position_with_loss_using_twap_price()
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Insufficient collateral"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(8_641_296u128),
+            amount: Uint128::from(8_700_000u128),
+        }
     );
 
     let free_collateral = engine
@@ -1,5 +1,6 @@
 use cosmwasm_std::{BankMsg, Coin, CosmosMsg, Uint128};
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::NativeTokenScenario;
 
@@ -272,8 +273,8 @@ fn test_can_open_position_short_and_liquidate_but_cannot_do_anything_more_in_sam
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
 
@@ -404,8 +405,8 @@ fn test_can_open_position_long_and_liquidate_but_cannot_do_anything_more_in_same
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
 
@@ -543,8 +544,8 @@ fn test_can_open_position_and_liquidate_but_cannot_do_anything_more_in_same_bloc
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
 
@@ -675,7 +676,7 @@ fn test_can_open_position_same_side_and_liquidate_but_cannot_do_anything_more_in
         .unwrap();
     let err = env.router.execute(env.carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Only one action allowed".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OnlyOneAction {}
     );
 }
@@ -1,6 +1,7 @@
 use cosmwasm_std::{BankMsg, Coin, CosmosMsg, StdError, Uint128};
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::{PnlCalcOption, Side};
 use margined_utils::scenarios::NativeTokenScenario;
 
@@ -196,10 +197,11 @@ fn test_partially_liquidate_long_position_with_quote_asset_limit() {
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "partial liquidation failure - reply (id 7)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "partial liquidation".to_string(),
+            id: 7
+        }
     );
 
     // if quoteAssetAmountLimit == 273.8 < 68.455 * 4 = 273.82, quote asset gets is more than expected
@@ -405,10 +407,11 @@ fn test_partially_liquidate_short_position_with_quote_asset_limit() {
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "partial liquidation failure - reply (id 7)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "partial liquidation".to_string(),
+            id: 7
+        }
     );
 
     // if quoteAssetAmountLimit == 177.1 < 44.258 * 4 = 177.032, quote asset pays is less than expected
@@ -611,10 +614,11 @@ fn test_long_position_complete_liquidation_with_slippage_limit() {
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "liquidation failure - reply (id 6)".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "liquidation".to_string(),
+            id: 6
+        }
     );
 
     let msg = engine
@@ -870,10 +874,11 @@ fn test_force_error_position_not_liquidation_twap_over_maintenance_margin() {
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Position is overcollateralized".to_string(),
-        },
-        err.downcast().unwrap()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(117_133u128),
+            maintenance: Uint128::from(100_000u128)
+        }
     );
 }
 
@@ -979,10 +984,11 @@ fn test_force_error_position_not_liquidation_spot_over_maintenance_margin() {
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Position is overcollateralized".to_string(),
-        },
-        err.downcast().unwrap()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(199_999u128),
+            maintenance: Uint128::from(100_000u128)
+        }
     );
 }
 
@@ -1019,8 +1025,8 @@ fn test_force_error_empty_position() {
     let err = router.execute(carol.clone(), msg).unwrap_err();
 
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is zero"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::UserPositionZero
     );
 }
 
@@ -1755,10 +1761,11 @@ fn test_partially_liquidate_one_position_exceeding_fluctuation_limit() {
         .unwrap();
     let err = env.router.execute(env.alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "decrease position failure - reply (id 2)".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "decrease position".to_string(),
+            id: 2
+        }
     );
 
     let msg = env
@@ -1927,9 +1934,10 @@ fn test_force_error_partially_liquidate_two_positions_exceeding_fluctuation_limi
         .unwrap();
     let err = env.router.execute(env.alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "partial liquidation failure - reply (id 7)".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "partial liquidation".to_string(),
+            id: 7
+        }
     );
 }
@@ -1,6 +1,7 @@
 use cosmwasm_std::{BankMsg, Coin, CosmosMsg, Uint128};
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::{PnlCalcOption, Position, Side};
 use margined_utils::scenarios::NativeTokenScenario;
 use margined_utils::tools::fund_calculator::calculate_funds_needed;
@@ -64,8 +65,8 @@ fn test_force_error_open_position_no_token_sent() {
     let err = router.execute(alice.clone(), msg).unwrap_err();
 
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: sent funds are insufficient".to_string()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientFunds {}
     );
 }
 
@@ -167,8 +168,8 @@ fn test_force_error_insufficient_token_long_position() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: sent funds are insufficient".to_string()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientFunds {}
     );
 }
 
@@ -272,8 +273,8 @@ fn test_force_error_insufficient_token_short_position() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: sent funds are insufficient".to_string()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientFunds {}
     );
 }
 
@@ -1,6 +1,7 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::Uint128;
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -607,10 +608,11 @@ fn test_stop_trading_if_over_open_interest_notional_cap() {
         .unwrap();
     let err = router.execute(bob.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "open interest exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OpenInterestCapExceeded {
+            open_interest: Uint128::from(601_000_000_000u128),
+            cap: Uint128::from(600_000_000_000u128)
+        }
     );
 }
 
@@ -0,0 +1,691 @@
+use cosmwasm_std::{Coin, Empty, StdError, Uint128};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{OrderType, Side, TriggerPriceOption};
+use margined_utils::scenarios::{to_decimals, NativeTokenScenario, SimpleScenario};
+
+#[test]
+fn test_place_and_execute_limit_order() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        usdc,
+        fee_pool,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    // 10% fee
+    let msg = vamm.set_toll_ratio(Uint128::from(100_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine.set_keeper_fee(to_decimals(1u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // alice places a limit buy of 60 margin, 10x leverage, when the price is 5 or less
+    let msg = engine
+        .place_limit_order(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            to_decimals(5u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // escrow = margin + fee + keeper fee = 60 + (60 * 10 * 10%) + 1 = 121
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(4_879u64));
+    let engine_balance = usdc
+        .balance::<_, _, Empty>(&router, engine.addr().clone())
+        .unwrap();
+    assert_eq!(engine_balance, to_decimals(121u64));
+
+    let order = engine
+        .order(&router, vamm.addr().to_string(), 1u64)
+        .unwrap();
+    assert_eq!(order.trader, alice);
+    assert_eq!(order.order_type, OrderType::Limit);
+    assert_eq!(order.escrow, to_decimals(120u64));
+    assert_eq!(order.keeper_fee, to_decimals(1u64));
+
+    // price is 10 so the order cannot be executed
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OrderNotTriggered {}
+    );
+
+    // bob shorts with 60 margin, 10x leverage, price drops to 1.6
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let bob_balance = usdc.balance::<_, _, Empty>(&router, bob.clone()).unwrap();
+    assert_eq!(bob_balance, to_decimals(4_880u64));
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    // (400 + 600) * (250 - baseAssetDelta) = 100k, baseAssetDelta = 150
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.size, Integer::new_positive(to_decimals(150u64)));
+    assert_eq!(position.margin, to_decimals(60u64));
+    assert_eq!(position.notional, to_decimals(600u64));
+
+    // bob received the keeper fee, alice paid nothing more
+    let bob_balance = usdc.balance::<_, _, Empty>(&router, bob.clone()).unwrap();
+    assert_eq!(bob_balance, to_decimals(4_881u64));
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(4_879u64));
+
+    let fee_pool_balance = usdc
+        .balance::<_, _, Empty>(&router, fee_pool.addr().clone())
+        .unwrap();
+    assert_eq!(fee_pool_balance, to_decimals(120u64));
+    let engine_balance = usdc
+        .balance::<_, _, Empty>(&router, engine.addr().clone())
+        .unwrap();
+    assert_eq!(engine_balance, to_decimals(120u64));
+
+    // the order is removed once executed
+    let orders = engine
+        .orders(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert!(orders.is_empty());
+}
+
+#[test]
+fn test_cancel_order_refunds_escrow() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine.set_keeper_fee(to_decimals(1u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .place_limit_order(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(60u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            to_decimals(15u64),
+            TriggerPriceOption::Twap,
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(4_939u64));
+
+    // only the trader can cancel the order
+    let msg = engine.cancel_order(vamm.addr().to_string(), 1u64).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
+    );
+
+    let msg = engine.cancel_order(vamm.addr().to_string(), 1u64).unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(5_000u64));
+    let engine_balance = usdc
+        .balance::<_, _, Empty>(&router, engine.addr().clone())
+        .unwrap();
+    assert_eq!(engine_balance, Uint128::zero());
+
+    let err = engine
+        .order(&router, vamm.addr().to_string(), 1u64)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "Querier contract error: {}",
+            StdError::from(ContractError::OrderNotFound { order_id: 1u64 })
+        ))
+    );
+}
+
+#[test]
+fn test_limit_order_cannot_reverse_position() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // price is 25.6 so the sell is immediately executable
+    let msg = engine
+        .place_limit_order(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(10u64),
+            to_decimals(2u64),
+            to_decimals(0u64),
+            to_decimals(20u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OrderReversesPosition {}
+    );
+}
+
+#[test]
+fn test_stop_loss_closes_position() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine.set_keeper_fee(to_decimals(1u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // stop losses require an existing position
+    let msg = engine
+        .place_trigger_order(
+            vamm.addr().to_string(),
+            OrderType::StopLoss,
+            to_decimals(0u64),
+            to_decimals(20u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::UserPositionZero
+    );
+
+    // alice longs with 60 margin, 2x leverage, price rises to ~12.54
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(2u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .place_trigger_order(
+            vamm.addr().to_string(),
+            OrderType::StopLoss,
+            to_decimals(0u64),
+            to_decimals(12u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let order = engine
+        .order(&router, vamm.addr().to_string(), 1u64)
+        .unwrap();
+    assert_eq!(order.side, Side::Buy);
+    assert_eq!(order.escrow, Uint128::zero());
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OrderNotTriggered {}
+    );
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // bob shorts with 60 margin, 1x leverage, price drops to ~11.24
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(60u64),
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let err = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Querier contract error: Generic error: No position found".to_string()
+        },
+        err
+    );
+
+    let owner_balance = usdc.balance::<_, _, Empty>(&router, owner.clone()).unwrap();
+    assert_eq!(owner_balance, to_decimals(1u64));
+}
+
+#[test]
+fn test_take_profit_closes_short_position() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    // limit orders cannot be placed as trigger orders
+    let msg = engine
+        .place_trigger_order(
+            vamm.addr().to_string(),
+            OrderType::Limit,
+            to_decimals(0u64),
+            to_decimals(5u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InvalidTriggerOrder {}
+    );
+
+    // alice shorts with 20 margin, 10x leverage, price drops to 6.4
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(20u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .place_trigger_order(
+            vamm.addr().to_string(),
+            OrderType::TakeProfit,
+            to_decimals(0u64),
+            to_decimals(5u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // bob shorts further, price drops below 5
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(20u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let err = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap_err();
+    assert_eq!(
+        StdError::GenericErr {
+            msg: "Querier contract error: Generic error: No position found".to_string()
+        },
+        err
+    );
+}
+
+#[test]
+fn test_closing_position_cancels_trigger_orders() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine.set_keeper_fee(to_decimals(1u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // orders can only be placed against registered vamms
+    let msg = engine
+        .place_trigger_order(
+            alice.to_string(),
+            OrderType::StopLoss,
+            to_decimals(0u64),
+            to_decimals(8u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::VammNotRegistered {
+            vamm: alice.to_string()
+        }
+    );
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(2u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    for (order_type, trigger_price) in [
+        (OrderType::StopLoss, to_decimals(8u64)),
+        (OrderType::TakeProfit, to_decimals(20u64)),
+    ]
+    .iter()
+    {
+        let msg = engine
+            .place_trigger_order(
+                vamm.addr().to_string(),
+                order_type.clone(),
+                to_decimals(0u64),
+                *trigger_price,
+                TriggerPriceOption::SpotPrice,
+                vec![],
+            )
+            .unwrap();
+        router.execute(alice.clone(), msg).unwrap();
+    }
+
+    // limit orders do not depend on the position
+    let msg = engine
+        .place_limit_order(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            to_decimals(5u64),
+            TriggerPriceOption::SpotPrice,
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(alice_balance, to_decimals(4_877u64));
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // the keeper fees of the trigger orders are refunded with the margin, less rounding
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(alice_balance, Uint128::from(4_938_999_999_996u128));
+
+    let orders = engine
+        .trader_orders(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, 3u64);
+
+    // a new position in the same direction is not closed by the old stop loss
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(2u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    let err = router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::OrderNotFound { order_id: 1u64 }
+    );
+}
+
+#[test]
+fn test_query_orders_paginated() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    for trader in [&alice, &bob, &alice] {
+        let msg = engine
+            .place_limit_order(
+                vamm.addr().to_string(),
+                Side::Buy,
+                to_decimals(10u64),
+                to_decimals(2u64),
+                to_decimals(0u64),
+                to_decimals(5u64),
+                TriggerPriceOption::SpotPrice,
+                vec![],
+            )
+            .unwrap();
+        router.execute(trader.clone(), msg).unwrap();
+    }
+
+    let orders = engine
+        .orders(&router, vamm.addr().to_string(), None, Some(2u32))
+        .unwrap();
+    assert_eq!(
+        orders.iter().map(|o| o.order_id).collect::<Vec<u64>>(),
+        vec![1u64, 2u64]
+    );
+
+    let orders = engine
+        .orders(&router, vamm.addr().to_string(), Some(2u64), None)
+        .unwrap();
+    assert_eq!(
+        orders.iter().map(|o| o.order_id).collect::<Vec<u64>>(),
+        vec![3u64]
+    );
+
+    let orders = engine
+        .trader_orders(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        orders.iter().map(|o| o.order_id).collect::<Vec<u64>>(),
+        vec![1u64, 3u64]
+    );
+}
+
+#[test]
+fn test_native_token_limit_order() {
+    let NativeTokenScenario {
+        mut router,
+        owner,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = NativeTokenScenario::new();
+
+    let msg = engine.set_keeper_fee(Uint128::from(1_000_000u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // sending less than the escrow and keeper fee fails
+    let msg = engine
+        .place_limit_order(
+            vamm.addr().to_string(),
+            Side::Buy,
+            Uint128::from(60_000_000u64),
+            Uint128::from(10_000_000u64),
+            Uint128::zero(),
+            Uint128::from(5_000_000u64),
+            TriggerPriceOption::SpotPrice,
+            vec![Coin::new(60_000_000u128, "uwasm")],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<StdError>().unwrap(),
+        &StdError::generic_err(
+            "Native token balance mismatch between the argument and the transferred"
+        )
+    );
+
+    let msg = engine
+        .place_limit_order(
+            vamm.addr().to_string(),
+            Side::Buy,
+            Uint128::from(60_000_000u64),
+            Uint128::from(10_000_000u64),
+            Uint128::zero(),
+            Uint128::from(5_000_000u64),
+            TriggerPriceOption::SpotPrice,
+            vec![Coin::new(61_000_000u128, "uwasm")],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // bob shorts with 60 margin, 10x leverage, price drops to 1.6
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from(60_000_000u64),
+            Uint128::from(10_000_000u64),
+            Uint128::zero(),
+            vec![Coin::new(60_000_000u128, "uwasm")],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = engine.execute_order(vamm.addr().to_string(), 1u64).unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(position.size, Integer::new_positive(150_000_000u128));
+    assert_eq!(position.margin, Uint128::from(60_000_000u64));
+
+    let bob_balance = router.wrap().query_balance(&bob, "uwasm").unwrap().amount;
+    assert_eq!(bob_balance, Uint128::new(4_941_000_000));
+    let engine_balance = router
+        .wrap()
+        .query_balance(engine.addr(), "uwasm")
+        .unwrap()
+        .amount;
+    assert_eq!(engine_balance, Uint128::new(120_000_000));
+}
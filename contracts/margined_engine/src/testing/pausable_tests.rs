@@ -1,4 +1,5 @@
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -28,8 +29,8 @@ fn test_paused_by_admin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Margin engine is paused".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Paused {}
     );
 
     let msg = engine
@@ -37,8 +38,8 @@ fn test_paused_by_admin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Margin engine is paused".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Paused {}
     );
 
     let msg = engine
@@ -46,8 +47,8 @@ fn test_paused_by_admin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Margin engine is paused".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Paused {}
     );
 
     let msg = engine
@@ -55,8 +56,8 @@ fn test_paused_by_admin() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Margin engine is paused".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Paused {}
     );
 }
 
@@ -72,8 +73,8 @@ fn test_cant_be_paused_by_non_admin() {
     let msg = engine.set_pause(true).unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: unauthorized".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
     );
 }
 
@@ -134,8 +135,8 @@ fn test_cant_unpause_when_already_unpaused_and_vice_versa() {
     let msg = engine.set_pause(false).unwrap();
     let err = router.execute(owner.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: unauthorized".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
     );
 
     let msg = engine.set_pause(true).unwrap();
@@ -144,7 +145,7 @@ fn test_cant_unpause_when_already_unpaused_and_vice_versa() {
     let msg = engine.set_pause(true).unwrap();
     let err = router.execute(owner.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: unauthorized".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
     );
 }
@@ -0,0 +1,88 @@
+use cw_multi_test::Executor;
+use margined_perp::margined_engine::Side;
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+#[test]
+fn test_positions_are_indexed_by_vamm_and_trader() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        david,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    for trader in [&david, &alice, &bob] {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                Side::Buy,
+                to_decimals(10u64),
+                to_decimals(2u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(trader.clone(), msg).unwrap();
+    }
+
+    let positions = engine
+        .all_positions_for_vamm(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(
+        positions
+            .iter()
+            .map(|position| position.trader.clone())
+            .collect::<Vec<_>>(),
+        vec![alice.clone(), bob.clone(), david.clone()]
+    );
+    assert!(positions
+        .iter()
+        .all(|position| position.vamm == vamm.addr() && !position.size.is_zero()));
+
+    // paginate through the positions of the vamm
+    let positions = engine
+        .all_positions_for_vamm(
+            &router,
+            vamm.addr().to_string(),
+            Some(alice.to_string()),
+            Some(1u32),
+        )
+        .unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].trader, bob);
+
+    // paginate through the traders
+    let first = engine.all_traders(&router, None, Some(2u32)).unwrap();
+    let rest = engine
+        .all_traders(&router, Some(first[1].to_string()), None)
+        .unwrap();
+    assert_eq!(first.len(), 2);
+    assert_eq!(rest.len(), 1);
+
+    let mut traders = [first, rest].concat();
+    traders.sort();
+    assert_eq!(traders, vec![alice.clone(), bob.clone(), david.clone()]);
+
+    // closed positions are removed from the indexes
+    let msg = engine
+        .close_position(vamm.addr().to_string(), to_decimals(0u64))
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let positions = engine
+        .all_positions_for_vamm(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(
+        positions
+            .iter()
+            .map(|position| position.trader.clone())
+            .collect::<Vec<_>>(),
+        vec![alice.clone(), david.clone()]
+    );
+    let mut traders = engine.all_traders(&router, None, None).unwrap();
+    traders.sort();
+    assert_eq!(traders, vec![alice, david]);
+}
@@ -2,6 +2,7 @@ use cosmwasm_std::{Empty, StdError, Uint128};
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::{PnlCalcOption, Side};
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -407,8 +408,11 @@ fn test_alice_has_enough_margin_cant_get_liquidated() {
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is overcollateralized".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(200_000_000u128),
+            maintenance: Uint128::from(50_000_000u128),
+        }
     );
 }
 
@@ -585,8 +589,11 @@ fn test_alice_long_position_underwater_oracle_price_activated_doesnt_get_liquida
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is overcollateralized"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(531_250_000u128),
+            maintenance: Uint128::from(50_000_000u128),
+        }
     );
 }
 
@@ -682,8 +689,11 @@ fn test_alice_short_position_underwater_oracle_price_activated_doesnt_get_liquid
         .unwrap();
     let err = router.execute(carol.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is overcollateralized"
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio: Integer::new_positive(481_632_653u128),
+            maintenance: Uint128::from(50_000_000u128),
+        }
     );
 }
 
@@ -887,8 +897,11 @@ fn test_cannot_open_position_even_thought_long_is_underwater_if_still_under_afte
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is undercollateralized"
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_negative(341_948_310u128),
+            required: Uint128::from(50_000_000u128),
+        }
     );
 
     let msg = engine
@@ -903,8 +916,11 @@ fn test_cannot_open_position_even_thought_long_is_underwater_if_still_under_afte
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is undercollateralized"
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_negative(352_112_676u128),
+            required: Uint128::from(50_000_000u128),
+        }
     );
 }
 
@@ -955,8 +971,11 @@ fn test_cannot_open_position_even_thought_short_is_underwater_if_still_under_aft
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Position is undercollateralized"
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_negative(407_571_577u128),
+            required: Uint128::from(50_000_000u128),
+        }
     );
 }
 
@@ -2,6 +2,7 @@ use cosmwasm_std::{Empty, StdError, Uint128};
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
 use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::{PnlCalcOption, Position, Side};
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -52,8 +53,8 @@ fn test_force_error_open_position_zero() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Input must be non-zero".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ZeroInput {}
     );
 }
 
@@ -79,8 +80,8 @@ fn test_force_error_open_position_zero_leverage_or_fractional_leverage() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Input must be non-zero".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ZeroInput {}
     );
 
     let msg = engine
@@ -95,8 +96,10 @@ fn test_force_error_open_position_zero_leverage_or_fractional_leverage() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        err.source().unwrap().to_string(),
-        "Generic error: Leverage must be greater than 1".to_string()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InvalidLeverage {
+            leverage: Uint128::from(1u128),
+        }
     );
 }
 
@@ -143,6 +146,40 @@ fn test_get_all_positions_open_position_long() {
     assert_eq!(engine_balance, to_decimals(60));
 }
 
+#[test]
+fn test_balance_with_funding_payment_across_vamms() {
+    let mut scenario = SimpleScenario::new();
+    let other_vamm = scenario.add_vamm();
+
+    let SimpleScenario {
+        router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    for (vamm, margin) in [(vamm.addr(), 60u64), (other_vamm.addr(), 40u64)].iter() {
+        let msg = engine
+            .open_position(
+                vamm.to_string(),
+                Side::Buy,
+                to_decimals(*margin),
+                to_decimals(5u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(alice.clone(), msg).unwrap();
+    }
+
+    // the margin of every position of the trader is included
+    let margin = engine
+        .get_balance_with_funding_payment(&*router, alice.to_string())
+        .unwrap();
+    assert_eq!(margin, to_decimals(100));
+}
+
 #[test]
 fn test_open_position_long() {
     let SimpleScenario {
@@ -823,10 +860,10 @@ fn test_cannot_close_position_with_bad_debt() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Cannot close position - bad debt".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BadDebt {
+            bad_debt: Uint128::from(58_333_333_334u128)
+        }
     );
 }
 
@@ -845,10 +882,8 @@ fn test_close_zero_position() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Position is zero".to_string()
-        },
-        err.downcast().unwrap()
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::UserPositionZero
     );
 }
 
@@ -1119,10 +1154,11 @@ fn test_error_open_position_insufficient_balance() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "transfer failure - reply (id 9)".to_string()
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::ReplyFailure {
+            action: "transfer".to_string(),
+            id: 9
+        }
     );
 }
 
@@ -1148,10 +1184,11 @@ fn test_error_open_position_exceed_margin_ratio() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Position is undercollateralized".to_string()
-        },
-        err.downcast().unwrap(),
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_positive(47_619_047u128),
+            required: Uint128::from(50_000_000u128)
+        }
     );
 }
 
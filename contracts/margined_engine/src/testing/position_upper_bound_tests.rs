@@ -1,5 +1,6 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::Uint128;
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -245,10 +246,11 @@ fn test_force_error_open_long_position_over_cap() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(10_714_285_714u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -292,10 +294,11 @@ fn test_force_error_open_two_long_positions_over_cap() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(10_714_285_713u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -327,10 +330,11 @@ fn test_force_error_open_short_position_over_cap() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(10_497_237_570u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -374,10 +378,11 @@ fn test_force_error_open_two_short_positions_over_cap() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(10_497_237_570u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -421,10 +426,11 @@ fn test_force_error_open_long_and_reverse_short_over_cap() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(23_456_790_125u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -468,9 +474,10 @@ fn test_force_error_open_short_and_reverse_long_over_cap() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(16_736_053_288u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
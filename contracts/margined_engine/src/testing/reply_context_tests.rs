@@ -0,0 +1,426 @@
+use crate::{
+    contract::{
+        instantiate, reply, INCREASE_POSITION_REPLY_ID, LIQUIDATION_REPLY_ID,
+        TRANSFER_FAILURE_REPLY_ID,
+    },
+    error::ContractError,
+    state::{store_reply_context, ReplyContext, TmpSwapInfo, REPLY_CONTEXTS, REPLY_NONCE},
+    utils::{decode_reply_id, encode_reply_id},
+};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{
+    to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Empty, Env, Event, MessageInfo, Reply,
+    Response, StdResult, SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::{InstantiateMsg, Side};
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// forwards every message it is sent in a single response, so that several engine
+// operations are executed within one transaction
+fn contract_proxy() -> Box<dyn Contract<Empty>> {
+    fn execute(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msgs: Vec<CosmosMsg>,
+    ) -> StdResult<Response> {
+        Ok(Response::new().add_messages(msgs))
+    }
+
+    fn instantiate(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        to_binary(&Empty {})
+    }
+
+    Box::new(ContractWrapper::new_with_empty(execute, instantiate, query))
+}
+
+// instantiates a proxy holding the amount of usdc and allowing the engine to spend it
+fn setup_proxy(scenario: &mut SimpleScenario, amount: Uint128) -> Addr {
+    let code_id = scenario.router.store_code(contract_proxy());
+    let proxy = scenario
+        .router
+        .instantiate_contract(
+            code_id,
+            scenario.owner.clone(),
+            &Empty {},
+            &[],
+            "proxy",
+            None,
+        )
+        .unwrap();
+
+    let msg = scenario
+        .usdc
+        .call(Cw20ExecuteMsg::Mint {
+            recipient: proxy.to_string(),
+            amount,
+        })
+        .unwrap();
+    scenario
+        .router
+        .execute(scenario.owner.clone(), msg)
+        .unwrap();
+
+    let msg = scenario
+        .usdc
+        .call(Cw20ExecuteMsg::IncreaseAllowance {
+            spender: scenario.engine.addr().to_string(),
+            amount,
+            expires: None,
+        })
+        .unwrap();
+    execute_in_one_response(&mut scenario.router, &proxy, vec![msg]);
+
+    proxy
+}
+
+fn execute_in_one_response(router: &mut App, proxy: &Addr, msgs: Vec<CosmosMsg>) {
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: proxy.to_string(),
+        msg: to_binary(&msgs).unwrap(),
+        funds: vec![],
+    });
+    router.execute(Addr::unchecked("keeper"), msg).unwrap();
+}
+
+// every operation must clean up the context it stored
+fn assert_no_reply_contexts(router: &App, engine: Addr) -> u64 {
+    let nonce = REPLY_NONCE.query(&router.wrap(), engine.clone()).unwrap();
+    for n in 1..=nonce {
+        let context = REPLY_CONTEXTS
+            .query(&router.wrap(), engine.clone(), n)
+            .unwrap();
+        assert_eq!(context, None);
+    }
+
+    nonce
+}
+
+#[test]
+fn test_reply_id_encoding() {
+    // replies without a context keep their plain ids
+    assert_eq!(
+        encode_reply_id(0, TRANSFER_FAILURE_REPLY_ID),
+        TRANSFER_FAILURE_REPLY_ID
+    );
+    assert_eq!(
+        decode_reply_id(TRANSFER_FAILURE_REPLY_ID),
+        (0, TRANSFER_FAILURE_REPLY_ID)
+    );
+
+    let id = encode_reply_id(42, INCREASE_POSITION_REPLY_ID);
+    assert_eq!(decode_reply_id(id), (42, INCREASE_POSITION_REPLY_ID));
+
+    let id = encode_reply_id(u32::MAX as u64, LIQUIDATION_REPLY_ID);
+    assert_eq!(decode_reply_id(id), (u32::MAX as u64, LIQUIDATION_REPLY_ID));
+}
+
+// reply of a swap input on the vamm
+fn swap_reply(nonce: u64, reply_id: u64) -> Reply {
+    Reply {
+        id: encode_reply_id(nonce, reply_id),
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![Event::new("wasm").add_attributes(vec![
+                ("type", "input"),
+                ("quote_asset_amount", "100"),
+                ("base_asset_amount", "10"),
+            ])],
+            data: None,
+        }),
+    }
+}
+
+#[test]
+fn test_reply_without_context() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        pauser: "owner".to_string(),
+        insurance_fund: "insurance_fund".to_string(),
+        fee_pool: "fee_pool".to_string(),
+        eligible_collateral: "uwasm".to_string(),
+        initial_margin_ratio: Uint128::from(50_000u128),
+        maintenance_margin_ratio: Uint128::from(50_000u128),
+        liquidation_fee: Uint128::from(100u128),
+    };
+    let info = mock_info("owner", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        swap_reply(1u64, INCREASE_POSITION_REPLY_ID),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MissingReplyContext { nonce: 1u64 });
+
+    // a context without the funds or liquidator the reply needs
+    let context = ReplyContext {
+        swap: TmpSwapInfo {
+            vamm: Addr::unchecked("vamm"),
+            trader: Addr::unchecked("trader"),
+            side: Side::Buy,
+            margin_amount: Uint128::from(10u128),
+            leverage: Uint128::from(10_000_000u128),
+            open_notional: Uint128::from(100u128),
+            position_notional: Uint128::zero(),
+            unrealized_pnl: Integer::zero(),
+            margin_to_vault: Integer::zero(),
+            fees_paid: false,
+            escrowed: false,
+        },
+        funds: None,
+        liquidator: None,
+        batch: None,
+    };
+    store_reply_context(deps.as_mut().storage, 1u64, &context).unwrap();
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        swap_reply(1u64, INCREASE_POSITION_REPLY_ID),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MissingSentFunds {});
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        swap_reply(1u64, LIQUIDATION_REPLY_ID),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MissingLiquidator {});
+}
+
+#[test]
+fn test_several_positions_updated_in_one_response() {
+    // the same trades as separate transactions
+    let SimpleScenario {
+        mut router,
+        alice,
+        usdc,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+
+    for (side, margin) in [(Side::Buy, 60u64), (Side::Buy, 10u64), (Side::Sell, 100u64)] {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                side,
+                to_decimals(margin),
+                to_decimals(10u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(alice.clone(), msg).unwrap();
+    }
+
+    let expected = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    let expected_spent =
+        alice_balance - usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+
+    // open, increase and reverse a position within one transaction
+    let mut scenario = SimpleScenario::new();
+    let proxy = setup_proxy(&mut scenario, to_decimals(2000u64));
+
+    let msgs = vec![(Side::Buy, 60u64), (Side::Buy, 10u64), (Side::Sell, 100u64)]
+        .into_iter()
+        .map(|(side, margin)| {
+            scenario
+                .engine
+                .open_position(
+                    scenario.vamm.addr().to_string(),
+                    side,
+                    to_decimals(margin),
+                    to_decimals(10u64),
+                    to_decimals(0u64),
+                    vec![],
+                )
+                .unwrap()
+        })
+        .collect();
+    execute_in_one_response(&mut scenario.router, &proxy, msgs);
+
+    let position = scenario
+        .engine
+        .position(
+            &scenario.router,
+            scenario.vamm.addr().to_string(),
+            proxy.to_string(),
+        )
+        .unwrap();
+    assert_eq!(position.size, expected.size);
+    assert_eq!(position.margin, expected.margin);
+    assert_eq!(position.notional, expected.notional);
+    assert_eq!(position.direction, expected.direction);
+
+    let spent = to_decimals(2000u64)
+        - scenario
+            .usdc
+            .balance::<_, _, Empty>(&scenario.router, proxy.clone())
+            .unwrap();
+    assert_eq!(spent, expected_spent);
+
+    // the reversal replies twice to the context of the third trade
+    let nonce = assert_no_reply_contexts(&scenario.router, scenario.engine.addr());
+    assert_eq!(nonce, 3);
+}
+
+#[test]
+fn test_liquidation_and_trades_in_one_response() {
+    let mut scenario = SimpleScenario::new();
+    let proxy = setup_proxy(&mut scenario, to_decimals(1000u64));
+
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        owner,
+        engine,
+        vamm,
+        pricefeed,
+        usdc,
+        ..
+    } = &mut scenario;
+
+    let price: Uint128 = Uint128::from(10_000_000_000u128);
+    let timestamp: u64 = router.block_info().time.seconds();
+    let msg = pricefeed
+        .append_price("ETH".to_string(), price, timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .set_margin_ratios(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_liquidation_fee(Uint128::from(25_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from(73_529_411_760u128),
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let proxy_balance = usdc.balance::<_, _, Empty>(router, proxy.clone()).unwrap();
+
+    // the proxy opens and increases a position and liquidates alice at once
+    let msgs = vec![
+        engine
+            .open_position(
+                vamm.addr().to_string(),
+                Side::Sell,
+                to_decimals(10u64),
+                to_decimals(2u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap(),
+        engine
+            .open_position(
+                vamm.addr().to_string(),
+                Side::Sell,
+                to_decimals(10u64),
+                to_decimals(2u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap(),
+        engine
+            .liquidate(
+                vamm.addr().to_string(),
+                alice.to_string(),
+                to_decimals(0u64),
+            )
+            .unwrap(),
+    ];
+    execute_in_one_response(router, &proxy, msgs);
+
+    // alice was liquidated and the liquidation fee paid to the proxy
+    let trades = engine
+        .trade_history(
+            router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades.len(), 2);
+    assert!(trades[1].liquidation);
+
+    let proxy_trades = engine
+        .trade_history(
+            router,
+            vamm.addr().to_string(),
+            proxy.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(proxy_trades.len(), 2);
+    assert!(proxy_trades.iter().all(|trade| !trade.liquidation));
+
+    let position = engine
+        .position(router, vamm.addr().to_string(), proxy.to_string())
+        .unwrap();
+    assert_eq!(position.margin, to_decimals(20u64));
+    assert!(position.size < Integer::zero());
+
+    // the proxy paid the margin of both trades and received the liquidation fee
+    let balance = usdc.balance::<_, _, Empty>(router, proxy.clone()).unwrap();
+    assert!(balance > proxy_balance - to_decimals(20u64));
+
+    let nonce = assert_no_reply_contexts(router, engine.addr());
+    assert_eq!(nonce, 5);
+}
@@ -0,0 +1,494 @@
+use cosmwasm_std::{StdError, Uint128};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::{RiskParams, Side};
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+// alice opens a 25 margin * 10x long and bob then sells enough to bring its margin ratio
+// to about 0.038, below the maintenance margin ratio of 0.1
+fn setup_liquidatable_position() -> SimpleScenario {
+    let mut scenario = SimpleScenario::new();
+    let SimpleScenario {
+        router,
+        alice,
+        bob,
+        owner,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = &mut scenario;
+
+    let timestamp: u64 = router.block_info().time.seconds();
+    let msg = pricefeed
+        .append_price("ETH".to_string(), to_decimals(10u64), timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .set_margin_ratios(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_liquidation_fee(Uint128::from(25_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from(45_180_722_890u128),
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    scenario
+}
+
+#[test]
+fn test_set_and_query_risk_params() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    // without overrides the engine config is in effect
+    let config = engine.config(&router).unwrap();
+    let response = engine
+        .risk_params(&router, vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(response.vamm, vamm.addr());
+    assert_eq!(response.initial_margin_ratio, config.initial_margin_ratio);
+    assert_eq!(
+        response.maintenance_margin_ratio,
+        config.maintenance_margin_ratio
+    );
+    assert_eq!(
+        response.partial_liquidation_ratio,
+        config.partial_liquidation_ratio
+    );
+    assert_eq!(response.liquidation_fee, config.liquidation_fee);
+    assert_eq!(response.overrides, RiskParams::default());
+
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            Some(Uint128::from(100_000_000u128)),
+            None,
+            Some(Uint128::from(500_000_000u128)),
+            None,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let response = engine
+        .risk_params(&router, vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(
+        response.initial_margin_ratio,
+        Uint128::from(100_000_000u128)
+    );
+    assert_eq!(
+        response.maintenance_margin_ratio,
+        config.maintenance_margin_ratio
+    );
+    assert_eq!(
+        response.partial_liquidation_ratio,
+        Uint128::from(500_000_000u128)
+    );
+    assert_eq!(response.liquidation_fee, config.liquidation_fee);
+    assert_eq!(
+        response.overrides,
+        RiskParams {
+            initial_margin_ratio: Some(Uint128::from(100_000_000u128)),
+            maintenance_margin_ratio: None,
+            partial_liquidation_ratio: Some(Uint128::from(500_000_000u128)),
+            liquidation_fee: None,
+        }
+    );
+
+    // the engine config is unchanged
+    assert_eq!(engine.config(&router).unwrap(), config);
+
+    // setting the parameters again replaces the overrides
+    let msg = engine
+        .set_risk_params(vamm.addr().to_string(), None, None, None, None)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let response = engine
+        .risk_params(&router, vamm.addr().to_string())
+        .unwrap();
+    assert_eq!(response.initial_margin_ratio, config.initial_margin_ratio);
+    assert_eq!(response.overrides, RiskParams::default());
+
+    // only the owner can set the parameters
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            Some(Uint128::from(100_000_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Unauthorized {}
+    );
+}
+
+#[test]
+fn test_risk_params_validation() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            None,
+            None,
+            None,
+            Some(to_decimals(2u64)),
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<StdError>().unwrap(),
+        &StdError::generic_err("Invalid ratio")
+    );
+
+    // the maintenance margin ratio cannot exceed the effective initial margin ratio
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            None,
+            Some(Uint128::from(100_000_000u128)),
+            None,
+            None,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<StdError>().unwrap(),
+        &StdError::generic_err("Incorrect initialisation of margin ratios, initial >= maintenance")
+    );
+
+    // only registered vamms have risk parameters
+    let msg = engine
+        .set_risk_params(
+            alice.to_string(),
+            Some(Uint128::from(100_000_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::VammNotRegistered {
+            vamm: alice.to_string()
+        }
+    );
+    engine.risk_params(&router, alice.to_string()).unwrap_err();
+}
+
+#[test]
+fn test_update_config_keeps_risk_params_consistent() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    // the vamm only overrides the initial margin ratio
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            Some(Uint128::from(60_000_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // raising the global maintenance margin ratio above it is rejected
+    let msg = engine
+        .set_margin_ratios(Uint128::from(100_000_000u128))
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InconsistentMarginRatios {
+            vamm: vamm.addr().to_string()
+        }
+    );
+
+    // changes that keep the effective ratios consistent are accepted
+    let msg = engine
+        .set_initial_margin_ratio(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let config = engine.config(&router).unwrap();
+    assert_eq!(config.initial_margin_ratio, Uint128::from(100_000_000u128));
+    assert_eq!(
+        config.maintenance_margin_ratio,
+        Uint128::from(50_000_000u128)
+    );
+}
+
+#[test]
+fn test_vamm_initial_margin_ratio_limits_leverage() {
+    let mut scenario = SimpleScenario::new();
+    let other_vamm = scenario.add_vamm();
+
+    let SimpleScenario {
+        router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = &mut scenario;
+
+    // a maximum leverage of 5x
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            Some(Uint128::from(200_000_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Undercollateralized {
+            margin_ratio: Integer::new_positive(100_000_000u128),
+            required: Uint128::from(200_000_000u128),
+        }
+    );
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // other vamms still use the engine config
+    let msg = engine
+        .open_position(
+            other_vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_vamm_initial_margin_ratio_sets_free_collateral() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // 60 margin less 600 notional * 0.05
+    let free_collateral = engine
+        .get_free_collateral(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_positive(to_decimals(30u64)));
+
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            Some(Uint128::from(80_000_000u128)),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // 60 margin less 600 notional * 0.08
+    let free_collateral = engine
+        .get_free_collateral(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(free_collateral, Integer::new_positive(to_decimals(12u64)));
+
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), to_decimals(20u64))
+        .unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::InsufficientCollateral {
+            free_collateral: Integer::new_positive(to_decimals(12u64)),
+            amount: to_decimals(20u64),
+        }
+    );
+}
+
+#[test]
+fn test_vamm_maintenance_margin_ratio_sets_liquidation() {
+    let SimpleScenario {
+        mut router,
+        owner,
+        alice,
+        carol,
+        engine,
+        vamm,
+        ..
+    } = setup_liquidatable_position();
+
+    let margin_ratio = engine
+        .get_margin_ratio(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert!(margin_ratio < Integer::new_positive(100_000_000u128));
+
+    // with a lower maintenance margin ratio on the vamm the position is safe
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            None,
+            Some(Uint128::from(30_000_000u128)),
+            None,
+            None,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    let err = router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.root_cause().downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Overcollateralized {
+            margin_ratio,
+            maintenance: Uint128::from(30_000_000u128),
+        }
+    );
+
+    let liquidatable = engine
+        .liquidatable_positions(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert!(liquidatable.positions.is_empty());
+
+    // a higher one makes it liquidatable again
+    let msg = engine
+        .set_risk_params(
+            vamm.addr().to_string(),
+            Some(Uint128::from(200_000_000u128)),
+            Some(Uint128::from(150_000_000u128)),
+            None,
+            None,
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let liquidatable = engine
+        .liquidatable_positions(&router, vamm.addr().to_string(), None, None)
+        .unwrap();
+    assert_eq!(liquidatable.positions.len(), 1);
+
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let positions = engine
+        .get_all_positions(&router, alice.to_string())
+        .unwrap();
+    assert!(positions.is_empty());
+}
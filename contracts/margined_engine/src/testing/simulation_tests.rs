@@ -0,0 +1,402 @@
+use cosmwasm_std::{Empty, StdError, Uint128};
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_engine::error::ContractError;
+use margined_perp::margined_engine::Side;
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+#[test]
+fn test_simulate_open_position_matches_execution() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        owner,
+        engine,
+        usdc,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = vamm.set_toll_ratio(Uint128::from(10_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm
+        .set_spread_ratio(Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let simulation = engine
+        .simulate_open_position(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    assert_eq!(simulation.exchanged_quote_amount, to_decimals(600u64));
+    assert_eq!(simulation.spread_fee, to_decimals(6u64));
+    assert_eq!(simulation.toll_fee, to_decimals(6u64));
+    assert_eq!(
+        simulation.margin_to_vault,
+        Integer::new_positive(60_000_000_000u128)
+    );
+    assert_eq!(simulation.realized_pnl, Integer::zero());
+    assert_eq!(simulation.bad_debt, Uint128::zero());
+
+    // a base asset limit the swap can't reach is rejected as in execution
+    let err = engine
+        .simulate_open_position(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(100u64),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "Querier contract error: {}",
+            StdError::from(ContractError::BaseAssetBelowLimit {
+                amount: simulation.exchanged_position_size.value,
+                limit: to_decimals(100u64),
+            })
+        ))
+    );
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(simulation.position, position);
+    assert_eq!(simulation.exchanged_position_size, position.size);
+    assert_eq!(
+        simulation.entry_price,
+        to_decimals(600u64)
+            .checked_mul(to_decimals(1u64))
+            .unwrap()
+            .checked_div(position.size.value)
+            .unwrap()
+    );
+
+    let margin_ratio = engine
+        .get_margin_ratio(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(simulation.margin_ratio, margin_ratio);
+
+    // the trader pays the margin and both fees
+    let alice_spent = alice_balance - usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+    assert_eq!(
+        alice_spent,
+        simulation.margin_to_vault.value + simulation.spread_fee + simulation.toll_fee
+    );
+}
+
+#[test]
+fn test_simulate_reduce_and_reverse_position_matches_execution() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // move the price so that alice has some pnl to realize
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(10u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // reduce the position
+    let simulation = engine
+        .simulate_open_position(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            Side::Sell,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    assert!(simulation.realized_pnl.is_positive());
+    assert!(simulation.exchanged_position_size.is_negative());
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(simulation.position, position);
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // reverse the position into a short
+    let simulation = engine
+        .simulate_open_position(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            Side::Sell,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    assert!(simulation.position.size.is_negative());
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let reversed_position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(simulation.position, reversed_position);
+    assert_eq!(
+        simulation.exchanged_position_size,
+        reversed_position.size - position.size
+    );
+}
+
+#[test]
+fn test_simulate_close_position_matches_execution() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        engine,
+        usdc,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(10u64),
+            to_decimals(5u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let simulation = engine
+        .simulate_close_position(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    assert!(simulation.realized_pnl.is_negative());
+    assert!(simulation.position.size.is_zero());
+    assert_eq!(simulation.margin_ratio, Integer::zero());
+    assert_eq!(simulation.bad_debt, Uint128::zero());
+
+    let alice_balance = usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap();
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    // the remaining margin is returned to the trader
+    let alice_received =
+        usdc.balance::<_, _, Empty>(&router, alice.clone()).unwrap() - alice_balance;
+    assert_eq!(
+        Integer::new_negative(alice_received),
+        simulation.margin_to_vault
+    );
+
+    // a limit the close can't reach is rejected as in execution
+    let simulation = engine
+        .simulate_close_position(
+            &router,
+            vamm.addr().to_string(),
+            bob.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    let err = engine
+        .simulate_close_position(
+            &router,
+            vamm.addr().to_string(),
+            bob.to_string(),
+            to_decimals(10u64),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "Querier contract error: {}",
+            StdError::from(ContractError::QuoteAssetAboveLimit {
+                amount: simulation.exchanged_quote_amount,
+                limit: to_decimals(10u64),
+            })
+        ))
+    );
+}
+
+#[test]
+fn test_simulate_deposit_and_withdraw_margin() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let simulation = engine
+        .simulate_deposit_margin(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(20u64),
+        )
+        .unwrap();
+    assert_eq!(simulation.position.margin, to_decimals(80u64));
+
+    let msg = engine
+        .deposit_margin(vamm.addr().to_string(), to_decimals(20u64), vec![])
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let margin_ratio = engine
+        .get_margin_ratio(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(simulation.margin_ratio, margin_ratio);
+
+    let simulation = engine
+        .simulate_withdraw_margin(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(10u64),
+        )
+        .unwrap();
+    assert_eq!(simulation.position.margin, to_decimals(70u64));
+    assert_eq!(
+        simulation.margin_to_vault,
+        Integer::new_negative(to_decimals(10u64))
+    );
+
+    let msg = engine
+        .withdraw_margin(vamm.addr().to_string(), to_decimals(10u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let position = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+    assert_eq!(simulation.position, position);
+
+    let err = engine
+        .simulate_withdraw_margin(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(70u64),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Querier contract error: Generic error: Insufficient collateral, 40000000000 free but 70000000000 requested"
+    );
+}
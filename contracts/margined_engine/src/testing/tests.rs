@@ -43,6 +43,7 @@ fn test_instantiation() {
             maintenance_margin_ratio: Uint128::from(50_000u128),
             partial_liquidation_ratio: Uint128::zero(),
             liquidation_fee: Uint128::from(100u128),
+            keeper_fee: Uint128::zero(),
         }
     );
 }
@@ -71,6 +72,7 @@ fn test_update_config() {
         maintenance_margin_ratio: None,
         partial_liquidation_ratio: None,
         liquidation_fee: None,
+        keeper_fee: None,
     };
 
     let info = mock_info(OWNER, &[]);
@@ -92,6 +94,7 @@ fn test_update_config() {
             maintenance_margin_ratio: Uint128::from(50_000u128),
             partial_liquidation_ratio: Uint128::zero(),
             liquidation_fee: Uint128::from(100u128),
+            keeper_fee: Uint128::zero(),
         }
     );
 
@@ -104,6 +107,7 @@ fn test_update_config() {
         maintenance_margin_ratio: None,
         partial_liquidation_ratio: None,
         liquidation_fee: None,
+        keeper_fee: None,
     };
 
     let info = mock_info(OWNER, &[]);
@@ -119,6 +123,7 @@ fn test_update_config() {
         maintenance_margin_ratio: None,
         partial_liquidation_ratio: None,
         liquidation_fee: None,
+        keeper_fee: None,
     };
 
     let info = mock_info(OWNER, &[]);
@@ -0,0 +1,317 @@
+use cosmwasm_std::Uint128;
+use cw_multi_test::Executor;
+use margined_common::integer::Integer;
+use margined_perp::margined_engine::Side;
+use margined_utils::scenarios::{to_decimals, SimpleScenario};
+
+#[test]
+fn test_trade_history_records_open_reduce_and_close() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        owner,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    let msg = vamm.set_toll_ratio(Uint128::from(10_000_000u128)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    let msg = vamm
+        .set_spread_ratio(Uint128::from(10_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(60u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+    let opened = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    // bob pushes the price up before alice reduces her position
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(10u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            to_decimals(10u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+    let reduced = engine
+        .position(&router, vamm.addr().to_string(), alice.to_string())
+        .unwrap();
+
+    let msg = engine
+        .close_position(vamm.addr().to_string(), to_decimals(0u64))
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    let trades = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades.len(), 3);
+
+    // the first trade opens the position and pays both fees
+    assert_eq!(trades[0].trader, alice);
+    assert_eq!(trades[0].vamm, vamm.addr());
+    assert_eq!(trades[0].size, opened.size);
+    assert_eq!(trades[0].notional, to_decimals(600u64));
+    assert_eq!(trades[0].spread_fee, to_decimals(6u64));
+    assert_eq!(trades[0].toll_fee, to_decimals(6u64));
+    assert_eq!(trades[0].realized_pnl, Integer::zero());
+    assert!(!trades[0].liquidation);
+    assert_eq!(
+        trades[0].price,
+        to_decimals(600u64)
+            .checked_mul(to_decimals(1u64))
+            .unwrap()
+            .checked_div(opened.size.value)
+            .unwrap()
+    );
+
+    // the reduction sells part of the position at a profit
+    assert!(trades[1].id > trades[0].id);
+    assert_eq!(trades[1].size, reduced.size - opened.size);
+    assert_eq!(trades[1].notional, to_decimals(100u64));
+    assert!(trades[1].realized_pnl > Integer::zero());
+
+    // the close sells the remainder
+    assert!(trades[2].id > trades[1].id);
+    assert_eq!(trades[2].size, reduced.size.invert_sign());
+    assert_eq!(
+        trades[0].size + trades[1].size + trades[2].size,
+        Integer::zero()
+    );
+
+    let stats = engine
+        .trader_stats(&router, alice.to_string(), Some(vamm.addr().to_string()))
+        .unwrap();
+    assert_eq!(stats.trade_count, 3);
+    assert_eq!(stats.liquidation_count, 0);
+    assert_eq!(
+        stats.volume,
+        trades[0].notional + trades[1].notional + trades[2].notional
+    );
+    assert_eq!(
+        stats.spread_fees,
+        trades[0].spread_fee + trades[1].spread_fee + trades[2].spread_fee
+    );
+    assert_eq!(
+        stats.toll_fees,
+        trades[0].toll_fee + trades[1].toll_fee + trades[2].toll_fee
+    );
+    assert_eq!(
+        stats.realized_pnl,
+        trades[0].realized_pnl + trades[1].realized_pnl + trades[2].realized_pnl
+    );
+
+    // without a vamm the stats are aggregated over every market
+    let all_stats = engine
+        .trader_stats(&router, alice.to_string(), None)
+        .unwrap();
+    assert_eq!(all_stats, stats);
+}
+
+#[test]
+fn test_trade_history_pagination() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        engine,
+        vamm,
+        ..
+    } = SimpleScenario::new();
+
+    for _ in 0..3 {
+        let msg = engine
+            .open_position(
+                vamm.addr().to_string(),
+                Side::Buy,
+                to_decimals(10u64),
+                to_decimals(2u64),
+                to_decimals(0u64),
+                vec![],
+            )
+            .unwrap();
+        router.execute(alice.clone(), msg).unwrap();
+    }
+
+    let first_page = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            Some(2),
+        )
+        .unwrap();
+    assert_eq!(first_page.len(), 2);
+
+    let second_page = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            Some(first_page[1].id),
+            Some(2),
+        )
+        .unwrap();
+    assert_eq!(second_page.len(), 1);
+    assert!(second_page[0].id > first_page[1].id);
+
+    // a trader without trades has an empty history and zeroed stats
+    let trades = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            "nobody".to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert!(trades.is_empty());
+
+    let stats = engine
+        .trader_stats(&router, "nobody".to_string(), None)
+        .unwrap();
+    assert_eq!(stats.trade_count, 0);
+    assert_eq!(stats.volume, Uint128::zero());
+}
+
+#[test]
+fn test_trade_history_flags_liquidations() {
+    let SimpleScenario {
+        mut router,
+        alice,
+        bob,
+        carol,
+        owner,
+        engine,
+        vamm,
+        pricefeed,
+        ..
+    } = SimpleScenario::new();
+
+    let price: Uint128 = Uint128::from(10_000_000_000u128);
+    let timestamp: u64 = router.block_info().time.seconds();
+    let msg = pricefeed
+        .append_price("ETH".to_string(), price, timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(900);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .set_margin_ratios(Uint128::from(100_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .set_liquidation_fee(Uint128::from(25_000_000u128))
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Buy,
+            to_decimals(25u64),
+            to_decimals(10u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(alice.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(15);
+        block.height += 1;
+    });
+
+    let msg = engine
+        .open_position(
+            vamm.addr().to_string(),
+            Side::Sell,
+            Uint128::from(73_529_411_760u128),
+            to_decimals(1u64),
+            to_decimals(0u64),
+            vec![],
+        )
+        .unwrap();
+    router.execute(bob.clone(), msg).unwrap();
+
+    let msg = engine
+        .liquidate(
+            vamm.addr().to_string(),
+            alice.to_string(),
+            to_decimals(0u64),
+        )
+        .unwrap();
+    router.execute(carol.clone(), msg).unwrap();
+
+    let trades = engine
+        .trade_history(
+            &router,
+            vamm.addr().to_string(),
+            alice.to_string(),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(trades.len(), 2);
+    assert!(!trades[0].liquidation);
+    assert!(trades[1].liquidation);
+    assert_eq!(trades[1].size, trades[0].size.invert_sign());
+    assert!(trades[1].realized_pnl < Integer::zero());
+
+    let stats = engine
+        .trader_stats(&router, alice.to_string(), None)
+        .unwrap();
+    assert_eq!(stats.trade_count, 2);
+    assert_eq!(stats.liquidation_count, 1);
+
+    // the liquidator does not trade so has no history
+    let stats = engine
+        .trader_stats(&router, carol.to_string(), None)
+        .unwrap();
+    assert_eq!(stats.trade_count, 0);
+}
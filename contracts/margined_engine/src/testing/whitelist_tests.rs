@@ -1,5 +1,7 @@
-use cosmwasm_std::{Addr, StdError, Uint128};
+use cosmwasm_std::{Addr, Uint128};
+use cw_controllers::{AdminError, HookError};
 use cw_multi_test::Executor;
+use margined_engine::error::ContractError;
 use margined_perp::margined_engine::Side;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
 
@@ -26,10 +28,8 @@ fn test_add_remove_whitelist() {
     let err = router.execute(owner.clone(), msg).unwrap_err();
 
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Given address already registered as a hook".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Hook(HookError::HookAlreadyRegistered {})
     );
 
     // remove alice from whitelist
@@ -46,10 +46,8 @@ fn test_add_remove_whitelist() {
     let err = router.execute(owner.clone(), msg).unwrap_err();
 
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Given address not registered as a hook".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Hook(HookError::HookNotRegistered {})
     );
 }
 
@@ -105,10 +103,8 @@ fn test_not_admin() {
     let err = router.execute(not_owner.clone(), msg).unwrap_err();
 
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Caller is not admin".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Hook(HookError::Admin(AdminError::NotAdmin {}))
     );
 
     // test remove as non-admin
@@ -116,10 +112,8 @@ fn test_not_admin() {
     let err = router.execute(not_owner.clone(), msg).unwrap_err();
 
     assert_eq!(
-        StdError::GenericErr {
-            msg: "Caller is not admin".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::Hook(HookError::Admin(AdminError::NotAdmin {}))
     );
 }
 
@@ -370,10 +364,11 @@ fn test_whitelist_works_blocks_short_into_reverse_long() {
     let err = router.execute(alice.clone(), msg).unwrap_err();
 
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(16_736_053_288u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -419,10 +414,11 @@ fn test_whitelist_blocks_open_long_into_reverse_short() {
     let err = router.execute(alice.clone(), msg).unwrap_err();
 
     assert_eq!(
-        StdError::GenericErr {
-            msg: "base asset holding exceeds cap".to_string(),
-        },
-        err.downcast().unwrap()
+        err.downcast_ref::<ContractError>().unwrap(),
+        &ContractError::BaseAssetHoldingCapExceeded {
+            size: Uint128::from(10_497_237_571u128),
+            cap: Uint128::from(10_000_000_000u128)
+        }
     );
 }
 
@@ -1,28 +1,36 @@
 use cosmwasm_std::{
-    Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult, Storage, SubMsg,
-    SubMsgResponse, Uint128,
+    Addr, Deps, DepsMut, Env, Event, Isqrt, MessageInfo, Response, StdResult, Storage, SubMsg,
+    SubMsgResponse, Uint128, Uint256,
 };
 
-use std::str::FromStr;
+use std::{convert::TryFrom, str::FromStr};
 
 use margined_common::{
     asset::{Asset, AssetInfo},
     integer::Integer,
 };
 use margined_perp::margined_engine::{
-    PnlCalcOption, Position, PositionUnrealizedPnlResponse, RemainMarginResponse, Side,
+    CollateralAsset, Order, OrderType, PnlCalcOption, Position, PositionUnrealizedPnlResponse,
+    RemainMarginResponse, Side, Trade, TriggerPriceOption,
 };
 use margined_perp::margined_vamm::Direction;
 
 use crate::{
-    contract::{PAUSER, WHITELIST},
-    messages::execute_insurance_fund_withdrawal,
+    contract::{PAUSER, REPLY_KIND_BITS, WHITELIST},
+    error::ContractError,
+    messages::{execute_insurance_fund_cover, execute_transfer},
     querier::{
-        query_insurance_is_vamm, query_vamm_config, query_vamm_output_amount,
-        query_vamm_output_twap, query_vamm_state, query_vamm_underlying_price,
+        query_insurance_is_vamm, query_pricefeed_price, query_vamm_config,
+        query_vamm_output_amount, query_vamm_output_twap, query_vamm_over_spread_limit,
+        query_vamm_spot_price, query_vamm_state, query_vamm_twap_price,
+        query_vamm_underlying_price,
+    },
+    query::{query_all_positions, query_cumulative_premium_fraction, query_margin_ratio},
+    state::{
+        next_trade_id, read_collateral, read_config, read_cross_margin, read_position,
+        read_position_config, read_state, read_trader_collaterals, read_trader_orders,
+        read_vamm_map, remove_order, store_position, store_state, Config, State,
     },
-    query::query_cumulative_premium_fraction,
-    state::{read_config, read_position, read_state, read_vamm_map, store_state, State},
 };
 
 // reads position from storage but also handles the case where there is no
@@ -49,6 +57,42 @@ pub fn get_position(
     position
 }
 
+// creates the record of a trade against the vamm, fees and pnl are set by the caller
+pub fn new_trade(
+    storage: &mut dyn Storage,
+    env: &Env,
+    vamm: &Addr,
+    trader: &Addr,
+    size: Integer,
+    notional: Uint128,
+) -> StdResult<Trade> {
+    let config = read_config(storage)?;
+
+    let price = if size.is_zero() {
+        Uint128::zero()
+    } else {
+        notional
+            .checked_mul(config.decimals)?
+            .checked_div(size.value)?
+    };
+
+    Ok(Trade {
+        id: next_trade_id(storage)?,
+        vamm: vamm.clone(),
+        trader: trader.clone(),
+        size,
+        price,
+        notional,
+        spread_fee: Uint128::zero(),
+        toll_fee: Uint128::zero(),
+        realized_pnl: Integer::zero(),
+        funding_paid: Integer::zero(),
+        liquidation: false,
+        block_height: env.block.height,
+        timestamp: env.block.time.seconds(),
+    })
+}
+
 // Creates an asset from the eligible collateral and msg sent
 pub fn get_asset(info: MessageInfo, eligible_collateral: AssetInfo) -> Asset {
     match eligible_collateral.clone() {
@@ -74,22 +118,22 @@ pub fn realize_bad_debt(
     bad_debt: Uint128,
     messages: &mut Vec<SubMsg>,
     state: &mut State,
-) -> Uint128 {
+) -> Result<Uint128, ContractError> {
     if state.prepaid_bad_debt > bad_debt {
         // no need to move extra tokens because vault already prepay bad debt, only need to update the numbers
-        state.prepaid_bad_debt = state.prepaid_bad_debt.checked_sub(bad_debt).unwrap();
+        state.prepaid_bad_debt = state.prepaid_bad_debt.checked_sub(bad_debt)?;
     } else {
         // in order to realize all the bad debt vault need extra tokens from insuranceFund
-        let bad_debt_delta = bad_debt.checked_sub(state.prepaid_bad_debt).unwrap();
+        let bad_debt_delta = bad_debt.checked_sub(state.prepaid_bad_debt)?;
 
-        messages.push(execute_insurance_fund_withdrawal(deps, bad_debt_delta).unwrap());
+        messages.push(execute_insurance_fund_cover(deps, bad_debt_delta)?);
 
         state.prepaid_bad_debt = Uint128::zero();
 
-        return bad_debt_delta;
+        return Ok(bad_debt_delta);
     };
 
-    Uint128::zero()
+    Ok(Uint128::zero())
 }
 
 // this blocks trades if open interest is too high, required during the bootstrapping of the project
@@ -99,7 +143,7 @@ pub fn update_open_interest_notional(
     vamm: Addr,
     amount: Integer,
     trader: Addr,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let cap = query_vamm_config(deps, vamm.to_string())?.open_interest_notional_cap;
 
     let mut updated_open_interest =
@@ -115,7 +159,10 @@ pub fn update_open_interest_notional(
         && updated_open_interest > Integer::new_positive(cap))
         && !WHITELIST.query_hook(deps.to_owned(), trader.to_string())?
     {
-        return Err(StdError::generic_err("open interest exceeds cap"));
+        return Err(ContractError::OpenInterestCapExceeded {
+            open_interest: updated_open_interest.value,
+            cap,
+        });
     }
 
     state.open_interest_notional = updated_open_interest.value;
@@ -129,19 +176,117 @@ pub fn check_base_asset_holding_cap(
     vamm: Addr,
     size: Uint128,
     trader: Addr,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let cap = query_vamm_config(deps, vamm.to_string())?.base_asset_holding_cap;
 
     // check if the cap has been exceeded - if trader address is in whitelist this bypasses
     if (!cap.is_zero() && size > cap)
         && !WHITELIST.query_hook(deps.to_owned(), trader.to_string())?
     {
-        return Err(StdError::generic_err("base asset holding exceeds cap"));
+        return Err(ContractError::BaseAssetHoldingCapExceeded { size, cap });
     }
 
     Ok(Response::new())
 }
 
+/// Returns the margin ratio used for liquidations, when the vamm is over its spread
+/// limit the oracle margin ratio is used if it is higher than the spot margin ratio
+pub fn get_liquidation_margin_ratio(
+    deps: Deps,
+    vamm: String,
+    trader: String,
+) -> StdResult<Integer> {
+    // retrieve the existing margin ratio of the position
+    let mut margin_ratio = query_margin_ratio(deps, vamm.clone(), trader.clone())?;
+
+    if query_vamm_over_spread_limit(&deps, vamm.clone())? {
+        let oracle_margin_ratio =
+            get_margin_ratio_calc_option(deps, vamm, trader, PnlCalcOption::Oracle)?;
+
+        if oracle_margin_ratio.checked_sub(margin_ratio)? > Integer::zero() {
+            margin_ratio = oracle_margin_ratio
+        }
+    }
+
+    Ok(margin_ratio)
+}
+
+/// Returns the vamm spot price at which the margin ratio of the position, taken in
+/// isolation, falls to the maintenance margin ratio. Zero if it cannot be liquidated
+pub fn calc_liquidation_price(deps: Deps, position: &Position) -> StdResult<Uint128> {
+    let config = read_position_config(deps.storage, &position.vamm, position.notional)?;
+
+    if position.size.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    // the margin after the pending funding payment
+    let RemainMarginResponse { margin, .. } =
+        calc_remain_margin_with_funding_payment(deps, position.clone(), Integer::zero())?;
+
+    let decimals = Uint256::from(config.decimals);
+    let maintenance_margin_ratio = Uint256::from(config.maintenance_margin_ratio);
+    let notional = Uint256::from(position.notional);
+    let margin = Uint256::from(margin);
+    let size = Uint256::from(position.size.value);
+
+    // the notional received when closing at the liquidation price, solved from
+    // (margin + pnl) / close_notional = maintenance margin ratio
+    let close_notional = match position.direction {
+        Direction::AddToAmm => {
+            if notional <= margin {
+                return Ok(Uint128::zero());
+            }
+
+            (notional - margin).multiply_ratio(decimals, decimals - maintenance_margin_ratio)
+        }
+        Direction::RemoveFromAmm => {
+            (notional + margin).multiply_ratio(decimals, decimals + maintenance_margin_ratio)
+        }
+    };
+
+    if close_notional.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let state = query_vamm_state(&deps, position.vamm.to_string())?;
+    let invariant_k = Uint256::from(state.quote_asset_reserve)
+        .checked_mul(Uint256::from(state.base_asset_reserve))?
+        .checked_div(decimals)?;
+
+    // closing the position exchanges `size` against the base reserve y, so that
+    // close_notional = k * size / (y * (y +/- size)), solved for y
+    let discriminant = size.checked_mul(size)?.checked_add(
+        Uint256::from(4u8).checked_mul(
+            invariant_k
+                .checked_mul(size)?
+                .checked_mul(decimals)?
+                .checked_div(close_notional)?,
+        )?,
+    )?;
+    let base_asset_reserve = match position.direction {
+        Direction::AddToAmm => (discriminant.isqrt() - size) / Uint256::from(2u8),
+        Direction::RemoveFromAmm => (discriminant.isqrt() + size) / Uint256::from(2u8),
+    };
+
+    if base_asset_reserve.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    // spot price = quote reserve / base reserve = k / y^2
+    let price = invariant_k
+        .checked_mul(decimals)?
+        .checked_mul(decimals)?
+        .checked_div(base_asset_reserve.checked_mul(base_asset_reserve)?)?;
+
+    Ok(Uint128::try_from(price)?)
+}
+
+/// Returns true if a position liquidated at the margin ratio is only partially closed
+pub fn is_partial_liquidation(config: &Config, margin_ratio: Integer) -> bool {
+    margin_ratio.value > config.liquidation_fee && !config.partial_liquidation_ratio.is_zero()
+}
+
 pub fn get_margin_ratio_calc_option(
     deps: Deps,
     vamm: String,
@@ -150,33 +295,159 @@ pub fn get_margin_ratio_calc_option(
 ) -> StdResult<Integer> {
     let config = read_config(deps.storage)?;
 
-    // retrieve the latest position
-    let position = read_position(
-        deps.storage,
-        &deps.api.addr_validate(&vamm)?,
-        &deps.api.addr_validate(&trader)?,
-    )
-    .unwrap();
+    // retrieve the positions that share margin with this one
+    let trader = deps.api.addr_validate(&trader)?;
+    let positions = get_margin_account_positions(deps, &deps.api.addr_validate(&vamm)?, &trader)?;
 
-    if position.size.is_zero() {
-        return Ok(Integer::zero());
+    let mut account_margin = Integer::zero();
+    let mut account_notional = Uint128::zero();
+    for position in positions {
+        let PositionUnrealizedPnlResponse {
+            position_notional,
+            unrealized_pnl,
+        } = get_position_notional_unrealized_pnl(deps, &position, calc_option.clone())?;
+
+        let remain_margin =
+            calc_remain_margin_with_funding_payment(deps, position, unrealized_pnl)?;
+
+        account_margin += Integer::new_positive(remain_margin.margin)
+            - Integer::new_positive(remain_margin.bad_debt);
+        account_notional = account_notional.checked_add(position_notional)?;
     }
 
-    let PositionUnrealizedPnlResponse {
-        position_notional,
-        unrealized_pnl,
-    } = get_position_notional_unrealized_pnl(deps, &position, calc_option)?;
+    if account_notional.is_zero() {
+        return Ok(Integer::zero());
+    }
 
-    let remain_margin = calc_remain_margin_with_funding_payment(deps, position, unrealized_pnl)?;
+    // collateral deposited by cross margined traders backs the whole account
+    account_margin += Integer::new_positive(get_account_collateral_value(deps, &trader)?);
 
-    let margin_ratio = ((Integer::new_positive(remain_margin.margin)
-        - Integer::new_positive(remain_margin.bad_debt))
-        * Integer::new_positive(config.decimals))
-        / Integer::new_positive(position_notional);
+    let margin_ratio = (account_margin * Integer::new_positive(config.decimals))
+        / Integer::new_positive(account_notional);
 
     Ok(margin_ratio)
 }
 
+// returns the open positions that share margin with the position of the trader in the vamm,
+// i.e. all of the trader's positions if cross margined, otherwise only the position itself
+pub fn get_margin_account_positions(
+    deps: Deps,
+    vamm: &Addr,
+    trader: &Addr,
+) -> StdResult<Vec<Position>> {
+    let positions = if read_cross_margin(deps.storage, trader)? {
+        query_all_positions(deps, trader.to_string())?
+    } else {
+        vec![read_position(deps.storage, vamm, trader)?]
+    };
+
+    Ok(positions
+        .into_iter()
+        .filter(|position| !position.size.is_zero())
+        .collect())
+}
+
+// returns the value of an amount of a registered collateral asset in the eligible
+// collateral with the engine decimals, at the price of the pricefeed and before the
+// weight is applied
+pub fn calc_collateral_value(
+    deps: Deps,
+    config: &Config,
+    collateral: &CollateralAsset,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let price = query_pricefeed_price(
+        &deps,
+        collateral.pricefeed.to_string(),
+        collateral.price_key.clone(),
+    )?;
+
+    Ok(amount.multiply_ratio(
+        price.checked_mul(config.decimals)?,
+        collateral.decimals.checked_mul(collateral.price_decimals)?,
+    ))
+}
+
+// returns the value of an amount of a registered collateral asset discounted by its weight
+pub fn calc_weighted_collateral_value(
+    deps: Deps,
+    config: &Config,
+    collateral: &CollateralAsset,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    Ok(calc_collateral_value(deps, config, collateral, amount)?
+        .multiply_ratio(collateral.weight, config.decimals))
+}
+
+// returns the weighted value of the collateral deposited by the trader, which only backs
+// cross margined accounts. Assets removed from the registry are not valued
+pub fn get_account_collateral_value(deps: Deps, trader: &Addr) -> StdResult<Uint128> {
+    if !read_cross_margin(deps.storage, trader)? {
+        return Ok(Uint128::zero());
+    }
+
+    let config = read_config(deps.storage)?;
+
+    let mut value = Uint128::zero();
+    for balance in read_trader_collaterals(deps.storage, trader)? {
+        if let Some(collateral) = read_collateral(deps.storage, &balance.info)? {
+            value = value.checked_add(calc_weighted_collateral_value(
+                deps,
+                &config,
+                &collateral,
+                balance.amount,
+            )?)?;
+        }
+    }
+
+    Ok(value)
+}
+
+// covers the bad debt of a cross margined position using the margin of the trader's other
+// positions, returns the bad debt that could not be covered
+pub fn absorb_account_bad_debt(
+    deps: DepsMut,
+    position: &Position,
+    bad_debt: Uint128,
+) -> StdResult<Uint128> {
+    if bad_debt.is_zero() || !read_cross_margin(deps.storage, &position.trader)? {
+        return Ok(bad_debt);
+    }
+
+    let mut remaining = bad_debt;
+    for mut sibling in
+        get_margin_account_positions(deps.as_ref(), &position.vamm, &position.trader)?
+    {
+        if sibling.vamm == position.vamm || remaining.is_zero() {
+            continue;
+        }
+
+        let absorbed = sibling.margin.min(remaining);
+        sibling.margin = sibling.margin.checked_sub(absorbed)?;
+        remaining = remaining.checked_sub(absorbed)?;
+
+        store_position(deps.storage, &sibling)?;
+    }
+
+    Ok(remaining)
+}
+
+/// Returns the auto-deleveraging score of a position, its unrealized PnL at the spot price
+/// per unit of margin
+pub fn calc_adl_score(deps: Deps, position: &Position) -> StdResult<Integer> {
+    let config = read_config(deps.storage)?;
+
+    if position.size.is_zero() || position.margin.is_zero() {
+        return Ok(Integer::zero());
+    }
+
+    let PositionUnrealizedPnlResponse { unrealized_pnl, .. } =
+        get_position_notional_unrealized_pnl(deps, position, PnlCalcOption::SpotPrice)?;
+
+    Ok(unrealized_pnl * Integer::new_positive(config.decimals)
+        / Integer::new_positive(position.margin))
+}
+
 pub fn get_position_notional_unrealized_pnl(
     deps: Deps,
     position: &Position,
@@ -288,42 +559,53 @@ pub fn clear_position(env: Env, mut position: Position) -> StdResult<Position> {
     Ok(position)
 }
 
-pub fn update_pauser(deps: DepsMut, info: MessageInfo, pauser: String) -> StdResult<Response> {
+pub fn update_pauser(
+    deps: DepsMut,
+    info: MessageInfo,
+    pauser: String,
+) -> Result<Response, ContractError> {
     // validate the address
     let valid_pauser = deps.api.addr_validate(&pauser)?;
 
-    PAUSER
-        .execute_update_admin(deps, info, Some(valid_pauser))
-        .map_err(|error| StdError::generic_err(format!("{}", error)))
+    Ok(PAUSER.execute_update_admin(deps, info, Some(valid_pauser))?)
 }
 
 // Adds an address to the whitelist for base asset holding cap
-pub fn add_whitelist(deps: DepsMut, info: MessageInfo, address: String) -> StdResult<Response> {
+pub fn add_whitelist(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
     // validate the address
     let valid_addr = deps.api.addr_validate(&address)?;
 
-    WHITELIST
-        .execute_add_hook(&PAUSER, deps, info, valid_addr)
-        .map_err(|error| StdError::generic_err(format!("{}", error)))
+    Ok(WHITELIST.execute_add_hook(&PAUSER, deps, info, valid_addr)?)
 }
 
 // Removes an address to the whitelist for base asset holding cap
-pub fn remove_whitelist(deps: DepsMut, info: MessageInfo, address: String) -> StdResult<Response> {
+pub fn remove_whitelist(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
     // validate the address
     let valid_addr = deps.api.addr_validate(&address)?;
 
-    WHITELIST
-        .execute_remove_hook(&PAUSER, deps, info, valid_addr)
-        .map_err(|error| StdError::generic_err(format!("{}", error)))
+    Ok(WHITELIST.execute_remove_hook(&PAUSER, deps, info, valid_addr)?)
 }
 
-pub fn set_pause(deps: DepsMut, _env: Env, info: MessageInfo, pause: bool) -> StdResult<Response> {
+pub fn set_pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pause: bool,
+) -> Result<Response, ContractError> {
     let mut state: State = read_state(deps.storage)?;
 
     // check permission and if state matches
     // note: we could use `assert_admin` instead of `is_admin` except this would throw an `AdminError` and we would have to change the function sig
     if !PAUSER.is_admin(deps.as_ref(), &info.sender)? || state.pause == pause {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
 
     state.pause = pause;
@@ -333,33 +615,37 @@ pub fn set_pause(deps: DepsMut, _env: Env, info: MessageInfo, pause: bool) -> St
     Ok(Response::default().add_attribute("action", "set_pause"))
 }
 
-pub fn require_vamm(deps: Deps, insurance: &Addr, vamm: &Addr) -> StdResult<Response> {
+pub fn require_vamm(deps: Deps, insurance: &Addr, vamm: &Addr) -> Result<Response, ContractError> {
     // check that it is a registered vamm
     if !query_insurance_is_vamm(&deps, insurance.to_string(), vamm.to_string())?.is_vamm {
-        return Err(StdError::generic_err("vAMM is not registered"));
+        return Err(ContractError::VammNotRegistered {
+            vamm: vamm.to_string(),
+        });
     }
 
     // check that vamm is open
     if !query_vamm_state(&deps, vamm.to_string())?.open {
-        return Err(StdError::generic_err("vAMM is not open"));
+        return Err(ContractError::VammNotOpen {
+            vamm: vamm.to_string(),
+        });
     }
 
     Ok(Response::new())
 }
 
 // Check no bad debt
-pub fn require_bad_debt(bad_debt: Uint128) -> StdResult<Response> {
+pub fn require_bad_debt(bad_debt: Uint128) -> Result<Response, ContractError> {
     if !bad_debt.is_zero() {
-        return Err(StdError::generic_err("Insufficient margin"));
+        return Err(ContractError::InsufficientMargin { bad_debt });
     }
 
     Ok(Response::new())
 }
 
 // Checks that position isn't zero
-pub fn require_position_not_zero(size: Uint128) -> StdResult<Response> {
+pub fn require_position_not_zero(size: Uint128) -> Result<Response, ContractError> {
     if size.is_zero() {
-        return Err(StdError::generic_err("Position is zero"));
+        return Err(ContractError::UserPositionZero);
     }
 
     Ok(Response::new())
@@ -369,9 +655,12 @@ pub fn require_position_not_zero(size: Uint128) -> StdResult<Response> {
 pub fn require_additional_margin(
     margin_ratio: Integer,
     base_margin: Uint128,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     if margin_ratio < Integer::new_positive(base_margin) {
-        return Err(StdError::generic_err("Position is undercollateralized"));
+        return Err(ContractError::Undercollateralized {
+            margin_ratio,
+            required: base_margin,
+        });
     }
 
     Ok(Response::new())
@@ -380,9 +669,12 @@ pub fn require_additional_margin(
 pub fn require_insufficient_margin(
     margin_ratio: Integer,
     base_margin: Uint128,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     if margin_ratio > Integer::new_positive(base_margin) {
-        return Err(StdError::generic_err("Position is overcollateralized"));
+        return Err(ContractError::Overcollateralized {
+            margin_ratio,
+            maintenance: base_margin,
+        });
     }
 
     Ok(Response::new())
@@ -393,36 +685,131 @@ pub fn require_not_restriction_mode(
     vamm: &Addr,
     trader: &Addr,
     block_height: u64,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let vamm_map = read_vamm_map(storage, vamm.clone())?;
     let position = read_position(storage, vamm, trader).unwrap();
 
     if vamm_map.last_restriction_block == block_height && position.block_number == block_height {
-        return Err(StdError::generic_err("Only one action allowed"));
+        return Err(ContractError::OnlyOneAction {});
     }
 
     Ok(Response::new())
 }
 
 // check margin engine is not paused
-pub fn require_not_paused(paused: bool) -> StdResult<Response> {
+pub fn require_not_paused(paused: bool) -> Result<Response, ContractError> {
     if paused {
-        return Err(StdError::generic_err("Margin engine is paused"));
+        return Err(ContractError::Paused {});
     }
 
     Ok(Response::new())
 }
 
+// Checks that the leverage is at least 1x and within the initial margin ratio
+pub fn require_valid_leverage(
+    leverage: Uint128,
+    decimals: Uint128,
+    initial_margin_ratio: Uint128,
+) -> Result<Response, ContractError> {
+    if leverage < decimals {
+        return Err(ContractError::InvalidLeverage { leverage });
+    }
+
+    // calculate the margin ratio of new position wrt to leverage
+    let margin_ratio = decimals.checked_mul(decimals)?.checked_div(leverage)?;
+    require_additional_margin(Integer::from(margin_ratio), initial_margin_ratio)
+}
+
+// Returns true if the vamm price has crossed the trigger price of the order
+pub fn is_order_triggered(deps: Deps, order: &Order) -> StdResult<bool> {
+    let price = match order.trigger_option {
+        TriggerPriceOption::SpotPrice => query_vamm_spot_price(&deps, order.vamm.to_string())?,
+        TriggerPriceOption::Twap => {
+            let interval =
+                query_vamm_config(&deps, order.vamm.to_string())?.spot_price_twap_interval;
+            query_vamm_twap_price(&deps, order.vamm.to_string(), interval)?
+        }
+    };
+
+    // buy limits and long stop losses trigger when the price falls to the trigger,
+    // take profits for shorts mirror this, everything else triggers on the way up
+    let triggered = match (&order.order_type, &order.side) {
+        (OrderType::Limit, Side::Buy)
+        | (OrderType::StopLoss, Side::Buy)
+        | (OrderType::TakeProfit, Side::Sell) => price <= order.trigger_price,
+        _ => price >= order.trigger_price,
+    };
+
+    Ok(triggered)
+}
+
+// removes the stop loss and take profit orders of a position that was closed, so they
+// cannot close a later position, and refunds their keeper fees to the trader
+pub fn cancel_trigger_orders(
+    storage: &mut dyn Storage,
+    vamm: &Addr,
+    trader: &Addr,
+) -> StdResult<Vec<SubMsg>> {
+    cancel_orders(storage, vamm, trader, |order| {
+        order.order_type != OrderType::Limit
+    })
+}
+
+// removes every order of a trader whose position was auto-deleveraged, as they were
+// placed for a position the trader no longer holds, and refunds their escrow and fees
+pub fn cancel_all_orders(
+    storage: &mut dyn Storage,
+    vamm: &Addr,
+    trader: &Addr,
+) -> StdResult<Vec<SubMsg>> {
+    cancel_orders(storage, vamm, trader, |_| true)
+}
+
+fn cancel_orders(
+    storage: &mut dyn Storage,
+    vamm: &Addr,
+    trader: &Addr,
+    filter: impl Fn(&Order) -> bool,
+) -> StdResult<Vec<SubMsg>> {
+    let mut refund = Uint128::zero();
+    for order in read_trader_orders(storage, vamm, trader, None, usize::MAX)? {
+        if !filter(&order) {
+            continue;
+        }
+
+        refund = refund
+            .checked_add(order.escrow)?
+            .checked_add(order.keeper_fee)?;
+        remove_order(storage, &order);
+    }
+
+    if refund.is_zero() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![execute_transfer(storage, trader, refund)?])
+}
+
 // check an input is non-zero
-pub fn require_non_zero_input(input: Uint128) -> StdResult<Response> {
+pub fn require_non_zero_input(input: Uint128) -> Result<Response, ContractError> {
     if input.is_zero() {
-        return Err(StdError::generic_err("Input must be non-zero"));
+        return Err(ContractError::ZeroInput {});
     }
 
     Ok(Response::new())
 }
 
-pub fn parse_swap(response: SubMsgResponse) -> StdResult<(Uint128, Uint128)> {
+// combines the nonce of a reply context with the kind of reply into a reply id
+pub fn encode_reply_id(nonce: u64, kind: u64) -> u64 {
+    nonce << REPLY_KIND_BITS | kind
+}
+
+// splits a reply id into the nonce of its reply context and the kind of reply
+pub fn decode_reply_id(id: u64) -> (u64, u64) {
+    (id >> REPLY_KIND_BITS, id & ((1 << REPLY_KIND_BITS) - 1))
+}
+
+pub fn parse_swap(response: SubMsgResponse) -> Result<(Uint128, Uint128), ContractError> {
     // Find swap inputs and output events
     let wasm = response.events.iter().find(|&e| e.ty == "wasm");
 
@@ -448,14 +835,14 @@ pub fn parse_swap(response: SubMsgResponse) -> StdResult<(Uint128, Uint128)> {
             output = Uint128::from_str(&output_str).unwrap();
         }
         _ => {
-            return Err(StdError::generic_err("Cannot parse swap"));
+            return Err(ContractError::CannotParseSwap {});
         }
     }
 
     Ok((input, output))
 }
 
-pub fn parse_pay_funding(response: SubMsgResponse) -> StdResult<(Integer, String)> {
+pub fn parse_pay_funding(response: SubMsgResponse) -> Result<(Integer, String), ContractError> {
     // Find swap inputs and output events
     let wasm = response.events.iter().find(|&e| e.ty == "wasm");
     let wasm = wasm.unwrap();
@@ -488,11 +875,11 @@ fn read_contract_address(event: &Event) -> StdResult<String> {
     Ok(value.to_string())
 }
 
-fn read_event(key: String, event: &Event) -> StdResult<String> {
+fn read_event(key: String, event: &Event) -> Result<String, ContractError> {
     let result = event.attributes.iter().find(|&attr| attr.key == key);
 
     if result.is_none() {
-        return Err(StdError::generic_err(format!("No event found: {}", key)));
+        return Err(ContractError::EventNotFound { key });
     }
 
     let value = &result.unwrap().value;
@@ -0,0 +1,131 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
+};
+use cw2::set_contract_version;
+use cw_controllers::Admin;
+use margined_common::validate::validate_migration;
+use margined_perp::margined_factory::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+
+use crate::{
+    error::ContractError,
+    handle::{
+        create_market, set_market_status, update_config, update_market_owner, update_owner,
+        validate_template,
+    },
+    query::{query_config, query_market, query_markets, query_owner},
+    reply::instantiate_vamm_reply,
+    state::{store_config, Config},
+};
+
+/// Contract name that is used for migration.
+const CONTRACT_NAME: &str = "crates.io:margined-factory";
+/// Contract version that is used for migration.
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Owner admin
+pub const OWNER: Admin = Admin::new("owner");
+
+pub const INSTANTIATE_VAMM_REPLY_ID: u64 = 1;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    validate_template(&msg.template)?;
+
+    let config = Config {
+        vamm_code_id: msg.vamm_code_id,
+        engine: deps.api.addr_validate(&msg.engine)?,
+        insurance_fund: deps.api.addr_validate(&msg.insurance_fund)?,
+        pricefeed: deps.api.addr_validate(&msg.pricefeed)?,
+        template: msg.template,
+    };
+
+    store_config(deps.storage, &config)?;
+
+    OWNER.set(deps, Some(info.sender))?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::UpdateConfig {
+            vamm_code_id,
+            engine,
+            insurance_fund,
+            pricefeed,
+            template,
+        } => update_config(
+            deps,
+            info,
+            vamm_code_id,
+            engine,
+            insurance_fund,
+            pricefeed,
+            template,
+        ),
+        ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
+        ExecuteMsg::CreateMarket {
+            base_asset,
+            quote_asset_reserve,
+            base_asset_reserve,
+            open,
+        } => create_market(
+            deps,
+            info,
+            base_asset,
+            quote_asset_reserve,
+            base_asset_reserve,
+            open,
+        ),
+        ExecuteMsg::SetMarketStatus { vamm, status } => set_market_status(deps, info, vamm, status),
+        ExecuteMsg::UpdateMarketOwner { vamm, owner } => {
+            update_market_owner(deps, info, vamm, owner)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
+        QueryMsg::GetMarket { vamm } => to_binary(&query_market(deps, vamm)?),
+        QueryMsg::GetMarkets { start_after, limit } => {
+            to_binary(&query_markets(deps, start_after, limit)?)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.id {
+        INSTANTIATE_VAMM_REPLY_ID => instantiate_vamm_reply(deps, env, msg),
+        _ => Err(StdError::generic_err(format!(
+            "reply (id {:?}) invalid",
+            msg.id
+        ))),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
@@ -0,0 +1,21 @@
+use cosmwasm_std::StdError;
+use cw_controllers::AdminError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Market already exists for {0}:{1}")]
+    MarketExists(String, String),
+
+    #[error("Market is closed")]
+    MarketClosed {},
+}
@@ -0,0 +1,229 @@
+use cosmwasm_std::{
+    to_binary, DepsMut, MessageInfo, ReplyOn, Response, StdError, SubMsg, Uint128, WasmMsg,
+};
+use margined_common::validate::{validate_assets, validate_decimal_places, validate_ratio};
+use margined_perp::{
+    margined_factory::{MarketStatus, VammTemplate},
+    margined_vamm::InstantiateMsg as VammInstantiateMsg,
+};
+
+use crate::{
+    contract::{INSTANTIATE_VAMM_REPLY_ID, OWNER},
+    error::ContractError,
+    messages::{
+        execute_insurance_fund_remove_vamm, execute_vamm_set_open, execute_vamm_update_owner,
+    },
+    querier::query_vamm_open,
+    state::{
+        has_market_pair, read_config, read_market, remove_market_pair, store_config, store_market,
+        store_tmp_market, Config, TmpMarket,
+    },
+};
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm_code_id: Option<u64>,
+    engine: Option<String>,
+    insurance_fund: Option<String>,
+    pricefeed: Option<String>,
+    template: Option<VammTemplate>,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut config: Config = read_config(deps.storage)?;
+
+    // change vamm code id
+    if let Some(vamm_code_id) = vamm_code_id {
+        config.vamm_code_id = vamm_code_id;
+    }
+
+    // change margin engine
+    if let Some(engine) = engine {
+        config.engine = deps.api.addr_validate(&engine)?;
+    }
+
+    // change insurance fund
+    if let Some(insurance_fund) = insurance_fund {
+        config.insurance_fund = deps.api.addr_validate(&insurance_fund)?;
+    }
+
+    // change pricefeed
+    if let Some(pricefeed) = pricefeed {
+        config.pricefeed = deps.api.addr_validate(&pricefeed)?;
+    }
+
+    // change vamm template
+    if let Some(template) = template {
+        validate_template(&template)?;
+        config.template = template;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
+pub fn update_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: String,
+) -> Result<Response, ContractError> {
+    // validate the address
+    let valid_owner = deps.api.addr_validate(&owner)?;
+
+    Ok(OWNER.execute_update_admin(deps, info, Some(valid_owner))?)
+}
+
+/// Instantiates a vAMM from the template, the market is registered once the
+/// instantiation succeeds
+pub fn create_market(
+    deps: DepsMut,
+    info: MessageInfo,
+    base_asset: String,
+    quote_asset_reserve: Uint128,
+    base_asset_reserve: Uint128,
+    open: bool,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let config: Config = read_config(deps.storage)?;
+    let quote_asset = config.template.quote_asset.clone();
+
+    validate_assets(base_asset.clone())?;
+
+    if has_market_pair(deps.storage, &base_asset, &quote_asset) {
+        return Err(ContractError::MarketExists(base_asset, quote_asset));
+    }
+
+    store_tmp_market(
+        deps.storage,
+        &TmpMarket {
+            base_asset: base_asset.clone(),
+            quote_asset: quote_asset.clone(),
+            open,
+        },
+    )?;
+
+    let msg = WasmMsg::Instantiate {
+        admin: Some(info.sender.to_string()),
+        code_id: config.vamm_code_id,
+        msg: to_binary(&VammInstantiateMsg {
+            decimals: config.template.decimals,
+            pricefeed: config.pricefeed.to_string(),
+            margin_engine: Some(config.engine.to_string()),
+            insurance_fund: Some(config.insurance_fund.to_string()),
+            quote_asset: quote_asset.clone(),
+            base_asset: base_asset.clone(),
+            quote_asset_reserve,
+            base_asset_reserve,
+            funding_period: config.template.funding_period,
+            toll_ratio: config.template.toll_ratio,
+            spread_ratio: config.template.spread_ratio,
+            fluctuation_limit_ratio: config.template.fluctuation_limit_ratio,
+        })?,
+        funds: vec![],
+        label: format!("margined vamm {}:{}", base_asset, quote_asset),
+    };
+
+    let instantiate_msg = SubMsg {
+        msg: msg.into(),
+        gas_limit: None,
+        id: INSTANTIATE_VAMM_REPLY_ID,
+        reply_on: ReplyOn::Success,
+    };
+
+    Ok(Response::default().add_submessage(instantiate_msg))
+}
+
+/// Opens, pauses or closes a market, closed markets are removed from the
+/// insurance fund and cannot be reopened
+pub fn set_market_status(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm: String,
+    status: MarketStatus,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let config: Config = read_config(deps.storage)?;
+
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let mut market = read_market(deps.storage, &vamm)?;
+
+    if market.status == MarketStatus::Closed {
+        return Err(ContractError::MarketClosed {});
+    }
+    if market.status == status {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Market status is unchanged",
+        )));
+    }
+
+    let mut msgs = vec![];
+
+    // the vamm may have been shut down by the insurance fund in the meantime
+    let open = status == MarketStatus::Open;
+    if query_vamm_open(&deps.as_ref(), vamm.to_string())? != open {
+        msgs.push(execute_vamm_set_open(&vamm, open)?);
+    }
+
+    if status == MarketStatus::Closed {
+        msgs.push(execute_insurance_fund_remove_vamm(
+            &config.insurance_fund,
+            &vamm,
+        )?);
+        remove_market_pair(deps.storage, &market.base_asset, &market.quote_asset);
+    }
+
+    market.status = status;
+    store_market(deps.storage, &market)?;
+
+    Ok(Response::default().add_messages(msgs).add_attributes(vec![
+        ("action", "set_market_status"),
+        ("vamm", vamm.as_str()),
+        ("status", &format!("{:?}", status).to_lowercase()),
+    ]))
+}
+
+/// Hands the ownership of a market's vAMM over, the factory can no longer
+/// change the status of the market afterwards
+pub fn update_market_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    vamm: String,
+    owner: String,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let vamm = deps.api.addr_validate(&vamm)?;
+    let owner = deps.api.addr_validate(&owner)?;
+
+    // ensure the vamm was deployed by the factory
+    read_market(deps.storage, &vamm)?;
+
+    Ok(Response::default()
+        .add_message(execute_vamm_update_owner(&vamm, &owner)?)
+        .add_attributes(vec![
+            ("action", "update_market_owner"),
+            ("vamm", vamm.as_str()),
+            ("owner", owner.as_str()),
+        ]))
+}
+
+pub fn validate_template(template: &VammTemplate) -> Result<(), ContractError> {
+    let decimals = validate_decimal_places(template.decimals)?;
+
+    validate_ratio(template.toll_ratio, decimals)?;
+    validate_ratio(template.spread_ratio, decimals)?;
+    validate_ratio(template.fluctuation_limit_ratio, decimals)?;
+
+    validate_assets(template.quote_asset.clone())?;
+
+    Ok(())
+}
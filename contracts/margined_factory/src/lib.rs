@@ -0,0 +1,11 @@
+pub mod contract;
+mod error;
+mod handle;
+mod messages;
+mod querier;
+mod query;
+mod reply;
+mod state;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,45 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, StdResult, WasmMsg};
+
+use margined_perp::margined_insurance_fund::ExecuteMsg as InsuranceFundExecuteMessage;
+use margined_perp::margined_vamm::ExecuteMsg as VammExecuteMessage;
+
+pub fn execute_insurance_fund_add_vamm(insurance_fund: &Addr, vamm: &Addr) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: insurance_fund.to_string(),
+        funds: vec![],
+        msg: to_binary(&InsuranceFundExecuteMessage::AddVamm {
+            vamm: vamm.to_string(),
+        })?,
+    }))
+}
+
+pub fn execute_insurance_fund_remove_vamm(
+    insurance_fund: &Addr,
+    vamm: &Addr,
+) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: insurance_fund.to_string(),
+        funds: vec![],
+        msg: to_binary(&InsuranceFundExecuteMessage::RemoveVamm {
+            vamm: vamm.to_string(),
+        })?,
+    }))
+}
+
+pub fn execute_vamm_set_open(vamm: &Addr, open: bool) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: vamm.to_string(),
+        funds: vec![],
+        msg: to_binary(&VammExecuteMessage::SetOpen { open })?,
+    }))
+}
+
+pub fn execute_vamm_update_owner(vamm: &Addr, owner: &Addr) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: vamm.to_string(),
+        funds: vec![],
+        msg: to_binary(&VammExecuteMessage::UpdateOwner {
+            owner: owner.to_string(),
+        })?,
+    }))
+}
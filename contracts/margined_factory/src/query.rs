@@ -0,0 +1,58 @@
+use cosmwasm_std::{Deps, StdError, StdResult};
+use margined_perp::margined_factory::{ConfigResponse, Market, MarketsResponse, OwnerResponse};
+
+use crate::{
+    contract::OWNER,
+    state::{read_config, read_market, read_markets, Config},
+};
+
+const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
+const MAX_PAGINATION_LIMIT: u32 = 30u32;
+
+/// Queries contract owner from the admin
+pub fn query_owner(deps: Deps) -> StdResult<OwnerResponse> {
+    if let Some(owner) = OWNER.get(deps)? {
+        Ok(OwnerResponse { owner })
+    } else {
+        Err(StdError::generic_err("No owner set"))
+    }
+}
+
+/// Queries contract config
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config: Config = read_config(deps.storage)?;
+
+    Ok(ConfigResponse {
+        vamm_code_id: config.vamm_code_id,
+        engine: config.engine,
+        insurance_fund: config.insurance_fund,
+        pricefeed: config.pricefeed,
+        template: config.template,
+    })
+}
+
+/// Queries the market of the vAMM
+pub fn query_market(deps: Deps, vamm: String) -> StdResult<Market> {
+    let vamm = deps.api.addr_validate(&vamm)?;
+
+    read_market(deps.storage, &vamm).map_err(|_| StdError::generic_err("Market not found"))
+}
+
+/// Queries the markets created by the factory ordered by vAMM address
+pub fn query_markets(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MarketsResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    let start_after = start_after
+        .map(|vamm| deps.api.addr_validate(&vamm))
+        .transpose()?;
+
+    let markets = read_markets(deps.storage, start_after, limit)?;
+
+    Ok(MarketsResponse { markets })
+}
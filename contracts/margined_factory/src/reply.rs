@@ -0,0 +1,52 @@
+use cosmwasm_std::{DepsMut, Env, Reply, Response, StdError, StdResult};
+use cw_utils::parse_reply_instantiate_data;
+use margined_perp::margined_factory::{Market, MarketStatus};
+
+use crate::{
+    messages::{execute_insurance_fund_add_vamm, execute_vamm_set_open},
+    state::{
+        read_config, read_tmp_market, remove_tmp_market, store_market, store_market_pair, Config,
+    },
+};
+
+/// Registers the market of the instantiated vAMM in the factory and the
+/// insurance fund, and opens it if requested
+pub fn instantiate_vamm_reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let tmp_market = read_tmp_market(deps.storage)?;
+
+    let response =
+        parse_reply_instantiate_data(msg).map_err(|e| StdError::generic_err(e.to_string()))?;
+    let vamm = deps.api.addr_validate(&response.contract_address)?;
+
+    let market = Market {
+        vamm: vamm.clone(),
+        base_asset: tmp_market.base_asset,
+        quote_asset: tmp_market.quote_asset,
+        created_at: env.block.time,
+        status: if tmp_market.open {
+            MarketStatus::Open
+        } else {
+            MarketStatus::Paused
+        },
+    };
+
+    store_market(deps.storage, &market)?;
+    store_market_pair(deps.storage, &market.base_asset, &market.quote_asset, &vamm)?;
+    remove_tmp_market(deps.storage);
+
+    let mut msgs = vec![execute_insurance_fund_add_vamm(
+        &config.insurance_fund,
+        &vamm,
+    )?];
+    if tmp_market.open {
+        msgs.push(execute_vamm_set_open(&vamm, true)?);
+    }
+
+    Ok(Response::default().add_messages(msgs).add_attributes(vec![
+        ("action", "create_market"),
+        ("vamm", vamm.as_str()),
+        ("base_asset", &market.base_asset),
+        ("quote_asset", &market.quote_asset),
+    ]))
+}
@@ -0,0 +1,92 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Order, StdResult, Storage};
+use cosmwasm_storage::{singleton, singleton_read, Singleton};
+use cw_storage_plus::{Bound, Map};
+use margined_perp::margined_factory::{Market, VammTemplate};
+
+pub static KEY_CONFIG: &[u8] = b"config";
+pub static KEY_TMP_MARKET: &[u8] = b"tmp-market";
+
+pub const MARKETS: Map<&Addr, Market> = Map::new("markets");
+pub const MARKET_PAIRS: Map<(&str, &str), Addr> = Map::new("market-pairs");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Config {
+    pub vamm_code_id: u64,
+    pub engine: Addr,
+    pub insurance_fund: Addr,
+    pub pricefeed: Addr,
+    pub template: VammTemplate,
+}
+
+pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
+    singleton(storage, KEY_CONFIG).save(config)
+}
+
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    singleton_read(storage, KEY_CONFIG).load()
+}
+
+/// Market that is waiting for its vAMM to be instantiated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TmpMarket {
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub open: bool,
+}
+
+pub fn store_tmp_market(storage: &mut dyn Storage, market: &TmpMarket) -> StdResult<()> {
+    singleton(storage, KEY_TMP_MARKET).save(market)
+}
+
+pub fn remove_tmp_market(storage: &mut dyn Storage) {
+    let mut store: Singleton<TmpMarket> = singleton(storage, KEY_TMP_MARKET);
+    store.remove()
+}
+
+pub fn read_tmp_market(storage: &dyn Storage) -> StdResult<TmpMarket> {
+    singleton_read(storage, KEY_TMP_MARKET).load()
+}
+
+pub fn store_market(storage: &mut dyn Storage, market: &Market) -> StdResult<()> {
+    MARKETS.save(storage, &market.vamm, market)
+}
+
+pub fn read_market(storage: &dyn Storage, vamm: &Addr) -> StdResult<Market> {
+    MARKETS.load(storage, vamm)
+}
+
+/// Reads markets ordered by vAMM address
+pub fn read_markets(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: usize,
+) -> StdResult<Vec<Market>> {
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    MARKETS
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, market)| market))
+        .collect()
+}
+
+/// Stores the vAMM of the asset pair, only one market per pair may be live
+pub fn store_market_pair(
+    storage: &mut dyn Storage,
+    base_asset: &str,
+    quote_asset: &str,
+    vamm: &Addr,
+) -> StdResult<()> {
+    MARKET_PAIRS.save(storage, (base_asset, quote_asset), vamm)
+}
+
+pub fn remove_market_pair(storage: &mut dyn Storage, base_asset: &str, quote_asset: &str) {
+    MARKET_PAIRS.remove(storage, (base_asset, quote_asset))
+}
+
+pub fn has_market_pair(storage: &dyn Storage, base_asset: &str, quote_asset: &str) -> bool {
+    MARKET_PAIRS.has(storage, (base_asset, quote_asset))
+}
@@ -0,0 +1,435 @@
+use crate::contract::{execute, instantiate, migrate, query};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, Addr, Uint128};
+use cw2::{get_contract_version, set_contract_version};
+use cw_multi_test::{AppResponse, Executor};
+use margined_perp::margined_factory::{
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MarketStatus, MigrateMsg, OwnerResponse, QueryMsg,
+    VammTemplate,
+};
+use margined_perp::margined_vamm::{OwnerResponse as VammOwnerResponse, QueryMsg as VammQueryMsg};
+use margined_utils::contracts::helpers::VammController;
+use margined_utils::scenarios::{to_decimals, FactoryScenario};
+
+fn template() -> VammTemplate {
+    VammTemplate {
+        decimals: 9u8,
+        quote_asset: "USD".to_string(),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::from(10_000_000u128),
+        spread_ratio: Uint128::from(10_000_000u128),
+        fluctuation_limit_ratio: Uint128::zero(),
+    }
+}
+
+fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        vamm_code_id: 1u64,
+        engine: "engine".to_string(),
+        insurance_fund: "insurance_fund".to_string(),
+        pricefeed: "pricefeed".to_string(),
+        template: template(),
+    }
+}
+
+fn parse_vamm(res: &AppResponse) -> VammController {
+    let vamm = res
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "vamm")
+        .unwrap()
+        .value
+        .clone();
+
+    VammController(Addr::unchecked(vamm))
+}
+
+#[test]
+fn test_instantiation() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, instantiate_msg()).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        config,
+        ConfigResponse {
+            vamm_code_id: 1u64,
+            engine: Addr::unchecked("engine"),
+            insurance_fund: Addr::unchecked("insurance_fund"),
+            pricefeed: Addr::unchecked("pricefeed"),
+            template: template(),
+        }
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwner {}).unwrap();
+    let resp: OwnerResponse = from_binary(&res).unwrap();
+    assert_eq!(resp.owner, Addr::unchecked("addr0000"));
+}
+
+#[test]
+fn test_instantiation_invalid_template() {
+    let mut deps = mock_dependencies();
+    let mut msg = instantiate_msg();
+    msg.template.toll_ratio = Uint128::from(10_000_000_000u128);
+
+    let info = mock_info("addr0000", &[]);
+    let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: Invalid ratio");
+}
+
+#[test]
+fn test_update_config() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, instantiate_msg()).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        vamm_code_id: Some(2u64),
+        engine: None,
+        insurance_fund: None,
+        pricefeed: Some("pricefeed0001".to_string()),
+        template: None,
+    };
+
+    // only the owner can update the config
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(err.to_string(), "Caller is not admin");
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.vamm_code_id, 2u64);
+    assert_eq!(config.pricefeed, Addr::unchecked("pricefeed0001"));
+
+    // templates are validated like the vamm instantiation
+    let mut invalid = template();
+    invalid.decimals = 5u8;
+    let msg = ExecuteMsg::UpdateConfig {
+        vamm_code_id: None,
+        engine: None,
+        insurance_fund: None,
+        pricefeed: None,
+        template: Some(invalid),
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Decimal places cannot be less than six"
+    );
+}
+
+#[test]
+fn test_update_owner() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, instantiate_msg()).unwrap();
+
+    let msg = ExecuteMsg::UpdateOwner {
+        owner: "addr0001".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetOwner {}).unwrap();
+    let resp: OwnerResponse = from_binary(&res).unwrap();
+    assert_eq!(resp.owner, Addr::unchecked("addr0001"));
+}
+
+#[test]
+fn test_create_market() {
+    let FactoryScenario {
+        mut router,
+        owner,
+        engine,
+        insurance_fund,
+        pricefeed,
+        factory,
+    } = FactoryScenario::new();
+
+    let msg = factory
+        .create_market(
+            "ETH".to_string(),
+            to_decimals(1_000),
+            to_decimals(100),
+            true,
+        )
+        .unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let vamm = parse_vamm(&res);
+
+    // the market is registered with its metadata
+    let market = factory.market(&router, vamm.addr().to_string()).unwrap();
+    assert_eq!(market.vamm, vamm.addr());
+    assert_eq!(market.base_asset, "ETH".to_string());
+    assert_eq!(market.quote_asset, "USD".to_string());
+    assert_eq!(market.created_at, router.block_info().time);
+    assert_eq!(market.status, MarketStatus::Open);
+
+    // the vamm is wired to the protocol contracts from the template
+    let config = vamm.config(&router).unwrap();
+    assert_eq!(config.margin_engine, engine.addr());
+    assert_eq!(config.insurance_fund, insurance_fund.addr());
+    assert_eq!(config.pricefeed, pricefeed.addr());
+    assert_eq!(config.toll_ratio, Uint128::zero());
+    assert_eq!(config.decimals, Uint128::from(1_000_000_000u128));
+
+    let state = vamm.state(&router).unwrap();
+    assert!(state.open);
+    assert_eq!(state.quote_asset_reserve, to_decimals(1_000));
+    assert_eq!(state.base_asset_reserve, to_decimals(100));
+
+    let res = insurance_fund
+        .is_vamm(vamm.addr().to_string(), &router)
+        .unwrap();
+    assert!(res.is_vamm);
+
+    // the factory owns the vamm
+    let res: VammOwnerResponse = router
+        .wrap()
+        .query_wasm_smart(vamm.addr(), &VammQueryMsg::GetOwner {})
+        .unwrap();
+    assert_eq!(res.owner, factory.addr());
+}
+
+#[test]
+fn test_create_market_unauthorized_and_duplicate() {
+    let FactoryScenario {
+        mut router,
+        owner,
+        factory,
+        ..
+    } = FactoryScenario::new();
+
+    let msg = factory
+        .create_market(
+            "ETH".to_string(),
+            to_decimals(1_000),
+            to_decimals(100),
+            false,
+        )
+        .unwrap();
+
+    let err = router
+        .execute(Addr::unchecked("alice"), msg.clone())
+        .unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Caller is not admin");
+
+    let res = router.execute(owner.clone(), msg.clone()).unwrap();
+    let vamm = parse_vamm(&res);
+
+    // markets that are not opened are paused
+    let market = factory.market(&router, vamm.addr().to_string()).unwrap();
+    assert_eq!(market.status, MarketStatus::Paused);
+    assert!(!vamm.state(&router).unwrap().open);
+
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Market already exists for ETH:USD"
+    );
+
+    // invalid reserves fail the vamm instantiation and nothing is registered
+    let msg = factory
+        .create_market(
+            "BTC".to_string(),
+            Uint128::from(1u128),
+            to_decimals(100),
+            true,
+        )
+        .unwrap();
+    router.execute(owner, msg).unwrap_err();
+
+    let res = factory.markets(&router, None, None).unwrap();
+    assert_eq!(res.markets.len(), 1usize);
+}
+
+#[test]
+fn test_set_market_status() {
+    let FactoryScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        factory,
+        ..
+    } = FactoryScenario::new();
+
+    let msg = factory
+        .create_market(
+            "ETH".to_string(),
+            to_decimals(1_000),
+            to_decimals(100),
+            true,
+        )
+        .unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let vamm = parse_vamm(&res);
+
+    // pause the market
+    let msg = factory
+        .set_market_status(vamm.addr().to_string(), MarketStatus::Paused)
+        .unwrap();
+    router.execute(owner.clone(), msg.clone()).unwrap();
+    assert!(!vamm.state(&router).unwrap().open);
+
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: Market status is unchanged"
+    );
+
+    // reopen the market
+    let msg = factory
+        .set_market_status(vamm.addr().to_string(), MarketStatus::Open)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    assert!(vamm.state(&router).unwrap().open);
+
+    // a shutdown by the insurance fund does not block closing the market
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = factory
+        .set_market_status(vamm.addr().to_string(), MarketStatus::Closed)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    assert!(!vamm.state(&router).unwrap().open);
+
+    let res = insurance_fund
+        .is_vamm(vamm.addr().to_string(), &router)
+        .unwrap();
+    assert!(!res.is_vamm);
+
+    let market = factory.market(&router, vamm.addr().to_string()).unwrap();
+    assert_eq!(market.status, MarketStatus::Closed);
+
+    // closed markets cannot be reopened
+    let msg = factory
+        .set_market_status(vamm.addr().to_string(), MarketStatus::Open)
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Market is closed");
+
+    // but the pair can be listed again
+    let msg = factory
+        .create_market(
+            "ETH".to_string(),
+            to_decimals(1_000),
+            to_decimals(100),
+            true,
+        )
+        .unwrap();
+    let res = router.execute(owner, msg).unwrap();
+    let relisted = parse_vamm(&res);
+    assert_ne!(relisted.addr(), vamm.addr());
+}
+
+#[test]
+fn test_update_market_owner() {
+    let FactoryScenario {
+        mut router,
+        owner,
+        factory,
+        ..
+    } = FactoryScenario::new();
+
+    let msg = factory
+        .create_market(
+            "ETH".to_string(),
+            to_decimals(1_000),
+            to_decimals(100),
+            true,
+        )
+        .unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let vamm = parse_vamm(&res);
+
+    // only markets of the factory can be handed over
+    let msg = factory
+        .update_market_owner("vamm".to_string(), owner.to_string())
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap_err();
+
+    let msg = factory
+        .update_market_owner(vamm.addr().to_string(), owner.to_string())
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let res: VammOwnerResponse = router
+        .wrap()
+        .query_wasm_smart(vamm.addr(), &VammQueryMsg::GetOwner {})
+        .unwrap();
+    assert_eq!(res.owner, owner);
+}
+
+#[test]
+fn test_get_markets() {
+    let FactoryScenario {
+        mut router,
+        owner,
+        factory,
+        ..
+    } = FactoryScenario::new();
+
+    let mut vamms = vec![];
+    for base_asset in ["ETH", "BTC", "ATOM"] {
+        let msg = factory
+            .create_market(
+                base_asset.to_string(),
+                to_decimals(1_000),
+                to_decimals(100),
+                true,
+            )
+            .unwrap();
+        let res = router.execute(owner.clone(), msg).unwrap();
+        vamms.push(parse_vamm(&res).addr());
+    }
+    vamms.sort();
+
+    let res = factory.markets(&router, None, Some(2u32)).unwrap();
+    let page: Vec<Addr> = res.markets.iter().map(|m| m.vamm.clone()).collect();
+    assert_eq!(page, vamms[..2].to_vec());
+
+    let res = factory
+        .markets(&router, Some(vamms[1].to_string()), Some(2u32))
+        .unwrap();
+    let page: Vec<Addr> = res.markets.iter().map(|m| m.vamm.clone()).collect();
+    assert_eq!(page, vamms[2..].to_vec());
+
+    let err = factory.market(&router, "vamm".to_string()).unwrap_err();
+    assert!(err.to_string().contains("Market not found"));
+}
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, instantiate_msg()).unwrap();
+
+    let config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+    // migrate from a previous release
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-factory", "0.0.1").unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(res, config);
+
+    // migrate from a different contract should fail
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "0.0.1").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a different contract".to_string()
+    );
+}
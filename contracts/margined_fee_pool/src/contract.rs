@@ -1,9 +1,16 @@
 #[cfg(not(feature = "library"))]
 use crate::error::ContractError;
 use crate::{
-    handle::{add_token, remove_token, send_token, update_owner},
-    query::{query_all_token, query_config, query_is_token, query_owner, query_token_list_length},
-    state::{store_config, Config},
+    handle::{
+        add_token, claim, deposit, receive_cw20, remove_token, send_token, unstake, update_config,
+        update_owner,
+    },
+    query::{
+        query_all_token, query_config, query_is_token, query_owner, query_pending_rewards,
+        query_staker, query_token_inflows, query_token_list_length, query_token_stats,
+        query_total_staked,
+    },
+    state::{migrate_token_list, store_config, Config},
 };
 
 use cosmwasm_std::{
@@ -11,7 +18,8 @@ use cosmwasm_std::{
 };
 use cw2::set_contract_version;
 use cw_controllers::Admin;
-use margined_perp::margined_fee_pool::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use margined_common::validate::validate_migration;
+use margined_perp::margined_fee_pool::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
 /// Contract name that is used for migration.
 const CONTRACT_NAME: &str = "crates.io:margined-fee-pool";
@@ -29,7 +37,10 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let config = Config {};
+    let config = Config {
+        staking_token: None,
+        unbonding_period: 0u64,
+    };
 
     store_config(deps.storage, &config)?;
 
@@ -41,24 +52,57 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
     match msg {
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::UpdateConfig {
+            staking_token,
+            unbonding_period,
+        } => update_config(deps, info, staking_token, unbonding_period),
         ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
         ExecuteMsg::AddToken { token } => add_token(deps, info, token),
-        ExecuteMsg::RemoveToken { token } => remove_token(deps, info, token),
+        ExecuteMsg::RemoveToken { token } => remove_token(deps, env, info, token),
         ExecuteMsg::SendToken {
             token,
             amount,
             recipient,
-        } => send_token(deps.as_ref(), env, info, token, amount, recipient),
+        } => send_token(deps, env, info, token, amount, recipient),
+        ExecuteMsg::Unstake { amount } => unstake(deps, env, info, amount),
+        ExecuteMsg::Claim {} => claim(deps, env, info),
+        ExecuteMsg::Deposit {} => deposit(deps, info),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
         QueryMsg::IsToken { token } => to_binary(&query_is_token(deps, token)?),
-        QueryMsg::GetTokenList { limit } => to_binary(&query_all_token(deps, limit)?),
+        QueryMsg::GetTokenList { start_after, limit } => {
+            to_binary(&query_all_token(deps, start_after, limit)?)
+        }
+        QueryMsg::TokenStats { token } => to_binary(&query_token_stats(deps, env, token)?),
+        QueryMsg::GetTokenInflows {
+            token,
+            start_after,
+            limit,
+        } => to_binary(&query_token_inflows(deps, token, start_after, limit)?),
         QueryMsg::GetTokenLength {} => to_binary(&query_token_list_length(deps)?),
+        QueryMsg::GetTotalStaked {} => to_binary(&query_total_staked(deps)?),
+        QueryMsg::GetStaker { address } => to_binary(&query_staker(deps, address)?),
+        QueryMsg::GetPendingRewards { address } => {
+            to_binary(&query_pending_rewards(deps, env, address)?)
+        }
     }
 }
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // move the tokens out of the legacy capped list and into the token map
+    migrate_token_list(deps.storage)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
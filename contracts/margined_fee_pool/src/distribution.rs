@@ -0,0 +1,72 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, StdResult, Uint128};
+use margined_common::asset::AssetInfo;
+use margined_perp::querier::query_token_balance;
+
+use crate::state::{
+    read_all_tokens, read_reward_state, read_staker_reward, read_total_staked, RewardState,
+    StakerReward, REWARD_STATES, STAKER_REWARDS,
+};
+
+/// Precision of the reward per share
+pub const REWARD_PRECISION: Uint128 = Uint128::new(1_000_000_000_000u128);
+
+/// Returns the reward state of the token after distributing the fees received
+/// since the last distribution to the current stakers. Fees received while
+/// nothing is staked are not distributed and remain with the owner.
+pub fn distribute(deps: Deps, env: &Env, token: &AssetInfo) -> StdResult<RewardState> {
+    let mut state = read_reward_state(deps.storage, token)?;
+    let total_staked = read_total_staked(deps.storage)?;
+
+    let balance = query_token_balance(deps, token.clone(), env.contract.address.clone())?;
+    let received = balance.saturating_sub(state.last_balance);
+
+    if !received.is_zero() && !total_staked.is_zero() {
+        state.reward_per_share += received.multiply_ratio(REWARD_PRECISION, total_staked);
+        state.reserved += received;
+    }
+
+    state.last_balance = balance;
+
+    Ok(state)
+}
+
+/// Accrues the rewards of the staker since its last settlement
+pub fn settle(state: &RewardState, mut reward: StakerReward, stake: Uint128) -> StakerReward {
+    let delta = state.reward_per_share - reward.reward_index;
+
+    reward.pending += stake.multiply_ratio(delta, REWARD_PRECISION);
+    reward.reward_index = state.reward_per_share;
+
+    reward
+}
+
+/// Distributes and settles the rewards of the staker in every fee token, must
+/// be called before the stake of the staker changes
+pub fn update_rewards(
+    deps: DepsMut,
+    env: &Env,
+    staker: &Addr,
+    stake: Uint128,
+) -> StdResult<Vec<(AssetInfo, RewardState, StakerReward)>> {
+    let rewards = read_all_tokens(deps.storage)?
+        .into_iter()
+        .map(|token| {
+            let state = distribute(deps.as_ref(), env, &token)?;
+            let reward = settle(
+                &state,
+                read_staker_reward(deps.storage, staker, &token)?,
+                stake,
+            );
+
+            Ok((token, state, reward))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (token, state, reward) in rewards.iter() {
+        let key = token.to_string();
+        REWARD_STATES.save(deps.storage, &key, state)?;
+        STAKER_REWARDS.save(deps.storage, (staker, &key), reward)?;
+    }
+
+    Ok(rewards)
+}
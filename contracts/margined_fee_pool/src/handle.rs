@@ -1,13 +1,63 @@
-use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
-use margined_common::validate::validate_eligible_collateral as validate_funds;
-use margined_perp::querier::query_token_balance;
+use cosmwasm_std::{
+    from_binary, Addr, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use margined_common::{asset::AssetInfo, validate::validate_eligible_collateral as validate_funds};
+use margined_perp::margined_fee_pool::{Cw20HookMsg, Unbonding};
 
 use crate::{
     contract::OWNER,
+    distribution::{distribute, update_rewards},
     messages::execute_transfer,
-    state::{is_token, remove_token as remove_token_from_list, save_token},
+    state::{
+        is_token, read_config, read_reward_state, read_stake, read_total_staked, read_unbonding,
+        record_outflow, record_sender_inflow, remove_token as remove_token_from_list, save_token,
+        store_config, Config, REWARD_STATES, STAKER_REWARDS, STAKES, TOTAL_STAKED, UNBONDING,
+    },
 };
 
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    staking_token: Option<String>,
+    unbonding_period: Option<u64>,
+) -> StdResult<Response> {
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut config: Config = read_config(deps.storage)?;
+
+    // set staking token, it cannot be changed once set
+    if let Some(staking_token) = staking_token {
+        if config.staking_token.is_some() {
+            return Err(StdError::generic_err("The staking token is already set"));
+        }
+
+        let staking_token = deps.api.addr_validate(&staking_token)?;
+        let asset = AssetInfo::Token {
+            contract_addr: staking_token.clone(),
+        };
+        if is_token(deps.storage, asset) {
+            return Err(StdError::generic_err(
+                "The staking token cannot be a fee token",
+            ));
+        }
+
+        config.staking_token = Some(staking_token);
+    }
+
+    // change unbonding period
+    if let Some(unbonding_period) = unbonding_period {
+        config.unbonding_period = unbonding_period;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
 pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResult<Response> {
     // validate the address
     let valid_owner = deps.api.addr_validate(&owner)?;
@@ -26,13 +76,30 @@ pub fn add_token(deps: DepsMut, info: MessageInfo, token: String) -> StdResult<R
     // validate address
     let valid_token = validate_funds(deps.as_ref(), token)?;
 
+    // the staked tokens are not fees
+    let config: Config = read_config(deps.storage)?;
+    if let (AssetInfo::Token { contract_addr }, Some(staking_token)) =
+        (&valid_token, &config.staking_token)
+    {
+        if contract_addr == staking_token {
+            return Err(StdError::generic_err(
+                "The staking token cannot be a fee token",
+            ));
+        }
+    }
+
     // add the token
     save_token(deps, valid_token)?;
 
     Ok(Response::default())
 }
 
-pub fn remove_token(deps: DepsMut, info: MessageInfo, token: String) -> StdResult<Response> {
+pub fn remove_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: String,
+) -> StdResult<Response> {
     // check permission
     if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
         return Err(StdError::generic_err("unauthorized"));
@@ -41,6 +108,16 @@ pub fn remove_token(deps: DepsMut, info: MessageInfo, token: String) -> StdResul
     // validate address
     let valid_token = validate_funds(deps.as_ref(), token)?;
 
+    // claims only pay out listed tokens, so the stakers must have claimed everything first
+    let state = if read_total_staked(deps.storage)?.is_zero() {
+        read_reward_state(deps.storage, &valid_token)?
+    } else {
+        distribute(deps.as_ref(), &env, &valid_token)?
+    };
+    if !state.reserved.is_zero() {
+        return Err(StdError::generic_err("Token has unclaimed rewards"));
+    }
+
     // remove token here
     remove_token_from_list(deps, valid_token)?;
 
@@ -48,7 +125,7 @@ pub fn remove_token(deps: DepsMut, info: MessageInfo, token: String) -> StdResul
 }
 
 pub fn send_token(
-    deps: Deps,
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
     token: String,
@@ -61,12 +138,12 @@ pub fn send_token(
     }
 
     // check permissions to send the message
-    if !OWNER.is_admin(deps, &info.sender)? {
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
         return Err(StdError::generic_err("unauthorized"));
     }
 
     // validate the token we want to send (this also tells us if it is native token or not)
-    let valid_token = validate_funds(deps, token)?;
+    let valid_token = validate_funds(deps.as_ref(), token)?;
 
     // validate the recipient address
     let valid_recipient = deps.api.addr_validate(&recipient)?;
@@ -76,13 +153,18 @@ pub fn send_token(
         return Err(StdError::generic_err("This token is not supported"));
     };
 
-    // query the balance of the given token that this contract holds
-    let balance = query_token_balance(deps, valid_token.clone(), env.contract.address)?;
+    // distribute the fees received so far, rewards of stakers cannot be sent
+    let mut state = distribute(deps.as_ref(), &env, &valid_token)?;
 
     // check that the balance is sufficient to pay the amount
-    if balance < amount {
+    if state.last_balance.checked_sub(state.reserved)? < amount {
         return Err(StdError::generic_err("Insufficient funds"));
     }
+
+    state.last_balance -= amount;
+    REWARD_STATES.save(deps.storage, &valid_token.to_string(), &state)?;
+    record_outflow(deps.storage, &valid_token, amount)?;
+
     Ok(
         Response::default().add_submessage(execute_transfer(
             valid_token,
@@ -91,3 +173,192 @@ pub fn send_token(
         )?),
     )
 }
+
+pub fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let is_staking_token = config.staking_token.as_ref() == Some(&info.sender);
+
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::Stake {} => {
+            // only the staking token can be staked
+            if !is_staking_token {
+                return Err(StdError::generic_err("unauthorized"));
+            }
+
+            stake(deps, env, cw20_msg.sender, cw20_msg.amount)
+        }
+        Cw20HookMsg::Deposit {} => {
+            // the staked tokens are not fees
+            if is_staking_token {
+                return Err(StdError::generic_err(
+                    "The staking token cannot be a fee token",
+                ));
+            }
+
+            let token = AssetInfo::Token {
+                contract_addr: info.sender,
+            };
+            let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+
+            record_deposit(deps, token, sender, cw20_msg.amount)
+        }
+    }
+}
+
+/// Records the native tokens sent along as fees deposited by the sender
+pub fn deposit(mut deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
+    if info.funds.is_empty() {
+        return Err(StdError::generic_err("Cannot deposit zero tokens"));
+    }
+
+    let mut response = Response::default();
+    for coin in info.funds.into_iter() {
+        let token = AssetInfo::NativeToken { denom: coin.denom };
+        let res = record_deposit(deps.branch(), token, info.sender.clone(), coin.amount)?;
+        response = response.add_attributes(res.attributes);
+    }
+
+    Ok(response)
+}
+
+// the deposited tokens are distributed to the stakers with any other fees received
+fn record_deposit(
+    deps: DepsMut,
+    token: AssetInfo,
+    sender: Addr,
+    amount: Uint128,
+) -> StdResult<Response> {
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Cannot deposit zero tokens"));
+    }
+
+    record_sender_inflow(deps.storage, &token, &sender, amount)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "deposit"),
+        ("sender", sender.as_str()),
+        ("token", &token.to_string()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+fn stake(mut deps: DepsMut, env: Env, staker: String, amount: Uint128) -> StdResult<Response> {
+    let staker = deps.api.addr_validate(&staker)?;
+
+    let stake = read_stake(deps.storage, &staker)?;
+    update_rewards(deps.branch(), &env, &staker, stake)?;
+
+    STAKES.save(deps.storage, &staker, &stake.checked_add(amount)?)?;
+    let total_staked = read_total_staked(deps.storage)?.checked_add(amount)?;
+    TOTAL_STAKED.save(deps.storage, &total_staked)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "stake"),
+        ("staker", staker.as_str()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+/// Stops the stake from earning rewards, the tokens can be claimed once the
+/// unbonding period has passed
+pub fn unstake(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+
+    let stake = read_stake(deps.storage, &info.sender)?;
+    if amount.is_zero() || amount > stake {
+        return Err(StdError::generic_err("Insufficient staked balance"));
+    }
+
+    update_rewards(deps.branch(), &env, &info.sender, stake)?;
+
+    STAKES.save(deps.storage, &info.sender, &(stake - amount))?;
+    let total_staked = read_total_staked(deps.storage)?.checked_sub(amount)?;
+    TOTAL_STAKED.save(deps.storage, &total_staked)?;
+
+    let release_at = env.block.time.plus_seconds(config.unbonding_period);
+
+    let mut unbonding = read_unbonding(deps.storage, &info.sender)?;
+    unbonding.push(Unbonding { amount, release_at });
+    UNBONDING.save(deps.storage, &info.sender, &unbonding)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "unstake"),
+        ("staker", info.sender.as_str()),
+        ("amount", &amount.to_string()),
+        ("release_at", &release_at.seconds().to_string()),
+    ]))
+}
+
+/// Pays out the pending rewards and the unbonded stake of the sender
+pub fn claim(mut deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+
+    let stake = read_stake(deps.storage, &info.sender)?;
+    let rewards = update_rewards(deps.branch(), &env, &info.sender, stake)?;
+
+    let mut response = Response::default()
+        .add_attributes(vec![("action", "claim"), ("staker", info.sender.as_str())]);
+
+    for (token, mut state, mut reward) in rewards {
+        if reward.pending.is_zero() {
+            continue;
+        }
+
+        let amount = reward.pending;
+        state.reserved = state.reserved.checked_sub(amount)?;
+        state.last_balance = state.last_balance.checked_sub(amount)?;
+        reward.pending = Uint128::zero();
+
+        let key = token.to_string();
+        REWARD_STATES.save(deps.storage, &key, &state)?;
+        STAKER_REWARDS.save(deps.storage, (&info.sender, &key), &reward)?;
+        record_outflow(deps.storage, &token, amount)?;
+
+        response = response
+            .add_submessage(execute_transfer(token, &info.sender, amount)?)
+            .add_attribute("reward", format!("{}{}", amount, key));
+    }
+
+    // release the unbonded stake
+    let (released, unbonding): (Vec<Unbonding>, Vec<Unbonding>) =
+        read_unbonding(deps.storage, &info.sender)?
+            .into_iter()
+            .partition(|unbonding| unbonding.release_at <= env.block.time);
+
+    let released = released
+        .iter()
+        .fold(Uint128::zero(), |acc, unbonding| acc + unbonding.amount);
+
+    if !released.is_zero() {
+        if unbonding.is_empty() {
+            UNBONDING.remove(deps.storage, &info.sender);
+        } else {
+            UNBONDING.save(deps.storage, &info.sender, &unbonding)?;
+        }
+
+        // the staking token is set when anything has been staked
+        let staking_token = AssetInfo::Token {
+            contract_addr: config.staking_token.unwrap(),
+        };
+
+        response = response
+            .add_submessage(execute_transfer(staking_token, &info.sender, released)?)
+            .add_attribute("unbonded", released.to_string());
+    }
+
+    if response.messages.is_empty() {
+        return Err(StdError::generic_err("Nothing to claim"));
+    }
+
+    Ok(response)
+}
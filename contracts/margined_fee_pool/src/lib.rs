@@ -1,4 +1,5 @@
 pub mod contract;
+mod distribution;
 mod error;
 mod handle;
 mod messages;
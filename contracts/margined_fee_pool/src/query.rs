@@ -1,20 +1,32 @@
-use cosmwasm_std::{Deps, StdError, StdResult};
-use margined_common::validate::validate_eligible_collateral as validate_funds;
+use cosmwasm_std::{Deps, Env, StdError, StdResult};
+use margined_common::{asset::AssetInfo, validate::validate_eligible_collateral as validate_funds};
 use margined_perp::margined_fee_pool::{
-    AllTokenResponse, ConfigResponse, OwnerResponse, TokenLengthResponse, TokenResponse,
+    AllTokenResponse, ConfigResponse, OwnerResponse, PendingReward, PendingRewardsResponse,
+    SenderInflow, StakerResponse, TokenInflowsResponse, TokenLengthResponse, TokenResponse,
+    TokenStatsResponse, TotalStakedResponse,
 };
 
 use crate::{
     contract::OWNER,
-    state::{is_token, read_token_list, TOKEN_LIMIT},
+    distribution::{distribute, settle},
+    state::{
+        is_token, read_all_tokens, read_config, read_sender_inflows, read_stake,
+        read_staker_reward, read_token_count, read_token_list, read_token_outflow,
+        read_total_staked, read_unbonding, Config,
+    },
 };
 
 const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
-const MAX_PAGINATION_LIMIT: u32 = TOKEN_LIMIT as u32;
+const MAX_PAGINATION_LIMIT: u32 = 30u32;
 
 /// Queries contract config
-pub fn query_config(_: Deps) -> StdResult<ConfigResponse> {
-    Ok(ConfigResponse {})
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config: Config = read_config(deps.storage)?;
+
+    Ok(ConfigResponse {
+        staking_token: config.staking_token,
+        unbonding_period: config.unbonding_period,
+    })
 }
 
 /// Queries contract owner from the admin
@@ -40,23 +52,131 @@ pub fn query_is_token(deps: Deps, token: String) -> StdResult<TokenResponse> {
 }
 
 /// Queries the list of tokens currently stored
-pub fn query_all_token(deps: Deps, limit: Option<u32>) -> StdResult<AllTokenResponse> {
+pub fn query_all_token(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllTokenResponse> {
     // set the limit for pagination
     let limit = limit
         .unwrap_or(DEFAULT_PAGINATION_LIMIT)
         .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|token| validate_funds(deps, token))
+        .transpose()?;
 
-    let list = read_token_list(deps, limit)?;
+    let list = read_token_list(deps, start_after, limit)?;
     Ok(AllTokenResponse { token_list: list })
 }
 
 /// Queries the length of the list of tokens currently stored
 pub fn query_token_list_length(deps: Deps) -> StdResult<TokenLengthResponse> {
+    Ok(TokenLengthResponse {
+        length: read_token_count(deps.storage),
+    })
+}
+
+/// Queries the balance and the cumulative inflow and outflow of the token
+pub fn query_token_stats(deps: Deps, env: Env, token: String) -> StdResult<TokenStatsResponse> {
+    let valid_token = validate_funds(deps, token)?;
+
+    // the balance of the staking token also holds the stakes
+    let config: Config = read_config(deps.storage)?;
+    if let (AssetInfo::Token { contract_addr }, Some(staking_token)) =
+        (&valid_token, &config.staking_token)
+    {
+        if contract_addr == staking_token {
+            return Err(StdError::generic_err(
+                "The staking token cannot be a fee token",
+            ));
+        }
+    }
+
+    // everything the fee pool holds or has paid out has been received
+    let state = distribute(deps, &env, &valid_token)?;
+    let outflow = read_token_outflow(deps.storage, &valid_token)?;
+
+    Ok(TokenStatsResponse {
+        token: valid_token,
+        balance: state.last_balance,
+        inflow: state.last_balance.checked_add(outflow)?,
+        outflow,
+        reserved: state.reserved,
+    })
+}
+
+/// Queries the cumulative deposits of the token by each sender, e.g. the engine
+pub fn query_token_inflows(
+    deps: Deps,
+    token: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokenInflowsResponse> {
+    let valid_token = validate_funds(deps, token)?;
+
     // set the limit for pagination
-    let limit = TOKEN_LIMIT;
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|sender| deps.api.addr_validate(&sender))
+        .transpose()?;
 
-    let list_length = read_token_list(deps, limit)?.len();
-    Ok(TokenLengthResponse {
-        length: list_length,
+    let inflows = read_sender_inflows(deps.storage, &valid_token, start_after, limit)?
+        .into_iter()
+        .map(|(sender, amount)| SenderInflow { sender, amount })
+        .collect();
+
+    Ok(TokenInflowsResponse {
+        token: valid_token,
+        inflows,
+    })
+}
+
+/// Queries the total amount staked
+pub fn query_total_staked(deps: Deps) -> StdResult<TotalStakedResponse> {
+    Ok(TotalStakedResponse {
+        total_staked: read_total_staked(deps.storage)?,
+    })
+}
+
+/// Queries the stake and unbonding stake of the address
+pub fn query_staker(deps: Deps, address: String) -> StdResult<StakerResponse> {
+    let address = deps.api.addr_validate(&address)?;
+
+    Ok(StakerResponse {
+        staked: read_stake(deps.storage, &address)?,
+        unbonding: read_unbonding(deps.storage, &address)?,
+        address,
     })
 }
+
+/// Queries the rewards the address can claim, including fees received since
+/// the last distribution
+pub fn query_pending_rewards(
+    deps: Deps,
+    env: Env,
+    address: String,
+) -> StdResult<PendingRewardsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let stake = read_stake(deps.storage, &address)?;
+
+    let rewards = read_all_tokens(deps.storage)?
+        .into_iter()
+        .map(|token| {
+            let state = distribute(deps, &env, &token)?;
+            let reward = settle(
+                &state,
+                read_staker_reward(deps.storage, &address, &token)?,
+                stake,
+            );
+
+            Ok(PendingReward {
+                token,
+                amount: reward.pending,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(PendingRewardsResponse { address, rewards })
+}
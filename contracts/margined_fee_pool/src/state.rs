@@ -1,99 +1,228 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Deps, DepsMut, StdError::GenericErr, StdResult, Storage};
-use cosmwasm_storage::singleton;
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Deps, DepsMut, Order, StdError::GenericErr, StdResult, Storage, Uint128};
+use cosmwasm_storage::{singleton, singleton_read};
+use cw_storage_plus::{Bound, Item, Map};
 use margined_common::asset::AssetInfo;
+use margined_perp::margined_fee_pool::Unbonding;
 
 pub static KEY_CONFIG: &[u8] = b"config";
+pub const TOKENS: Map<&str, AssetInfo> = Map::new("tokens");
+pub const TOKEN_OUTFLOWS: Map<&str, Uint128> = Map::new("token-outflows");
+pub const SENDER_INFLOWS: Map<(&str, &Addr), Uint128> = Map::new("sender-inflows");
+
+// legacy single-item list of tokens, only read when migrating into TOKENS
 pub const TOKEN_LIST: Item<Vec<AssetInfo>> = Item::new("token-list");
-pub const TOKEN_LIMIT: usize = 3usize;
+
+pub const TOTAL_STAKED: Item<Uint128> = Item::new("total-staked");
+pub const STAKES: Map<&Addr, Uint128> = Map::new("stakes");
+pub const UNBONDING: Map<&Addr, Vec<Unbonding>> = Map::new("unbonding");
+pub const REWARD_STATES: Map<&str, RewardState> = Map::new("reward-states");
+pub const STAKER_REWARDS: Map<(&Addr, &str), StakerReward> = Map::new("staker-rewards");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Config {}
+pub struct Config {
+    #[serde(default)]
+    pub staking_token: Option<Addr>,
+    #[serde(default)]
+    pub unbonding_period: u64,
+}
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
     singleton(storage, KEY_CONFIG).save(config)
 }
 
-// function checks if an addr is already added and adds it if not
-// We also check that we have not reached the limit of tokens here
-pub fn save_token(deps: DepsMut, input: AssetInfo) -> StdResult<()> {
-    // check if the list exists already
-    let mut token_list = match TOKEN_LIST.may_load(deps.storage)? {
-        None => vec![],
-        Some(list) => list,
-    };
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    singleton_read(storage, KEY_CONFIG).load()
+}
+
+/// Distribution state of a fee token
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct RewardState {
+    /// cumulative rewards per staked token, scaled by the reward precision
+    pub reward_per_share: Uint128,
+    /// balance of the fee pool at the last distribution
+    pub last_balance: Uint128,
+    /// rewards distributed to stakers that have not been claimed yet
+    pub reserved: Uint128,
+}
+
+/// Rewards of a staker in a fee token
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct StakerReward {
+    /// reward per share at the last settlement of the staker
+    pub reward_index: Uint128,
+    pub pending: Uint128,
+}
+
+pub fn read_total_staked(storage: &dyn Storage) -> StdResult<Uint128> {
+    Ok(TOTAL_STAKED.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn read_stake(storage: &dyn Storage, staker: &Addr) -> StdResult<Uint128> {
+    Ok(STAKES.may_load(storage, staker)?.unwrap_or_default())
+}
+
+pub fn read_unbonding(storage: &dyn Storage, staker: &Addr) -> StdResult<Vec<Unbonding>> {
+    Ok(UNBONDING.may_load(storage, staker)?.unwrap_or_default())
+}
+
+pub fn read_reward_state(storage: &dyn Storage, token: &AssetInfo) -> StdResult<RewardState> {
+    Ok(REWARD_STATES
+        .may_load(storage, &token.to_string())?
+        .unwrap_or_default())
+}
+
+pub fn read_staker_reward(
+    storage: &dyn Storage,
+    staker: &Addr,
+    token: &AssetInfo,
+) -> StdResult<StakerReward> {
+    Ok(STAKER_REWARDS
+        .may_load(storage, (staker, &token.to_string()))?
+        .unwrap_or_default())
+}
+
+pub fn read_token_outflow(storage: &dyn Storage, token: &AssetInfo) -> StdResult<Uint128> {
+    Ok(TOKEN_OUTFLOWS
+        .may_load(storage, &token.to_string())?
+        .unwrap_or_default())
+}
+
+// adds to the cumulative amount of the token paid out by the fee pool
+pub fn record_outflow(
+    storage: &mut dyn Storage,
+    token: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let outflow = read_token_outflow(storage, token)?.checked_add(amount)?;
+    TOKEN_OUTFLOWS.save(storage, &token.to_string(), &outflow)
+}
+
+// adds to the cumulative amount of the token deposited by the sender
+pub fn record_sender_inflow(
+    storage: &mut dyn Storage,
+    token: &AssetInfo,
+    sender: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let key = token.to_string();
+    let inflow = SENDER_INFLOWS
+        .may_load(storage, (&key, sender))?
+        .unwrap_or_default()
+        .checked_add(amount)?;
+    SENDER_INFLOWS.save(storage, (&key, sender), &inflow)
+}
+
+// reads a page of the senders that deposited the token with their cumulative deposits
+pub fn read_sender_inflows(
+    storage: &dyn Storage,
+    token: &AssetInfo,
+    start_after: Option<Addr>,
+    limit: usize,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    SENDER_INFLOWS
+        .prefix(&token.to_string())
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
 
+// reads the full token list, an empty list if no tokens are stored
+pub fn read_all_tokens(storage: &dyn Storage) -> StdResult<Vec<AssetInfo>> {
+    TOKENS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, token)| token))
+        .collect()
+}
+
+// function checks if a token is already added and adds it if not
+pub fn save_token(deps: DepsMut, input: AssetInfo) -> StdResult<()> {
     // check if we already added the token
-    if token_list.contains(&input) {
+    if TOKENS.has(deps.storage, &input.to_string()) {
         return Err(GenericErr {
             msg: "This token is already added".to_string(),
         });
     };
 
-    // check if we have reached the capacity
-    if token_list.len() >= TOKEN_LIMIT {
-        return Err(GenericErr {
-            msg: "The token capacity is already reached".to_string(),
-        });
-    };
-
     // add the token
-    token_list.push(input);
-    TOKEN_LIST.save(deps.storage, &token_list)
+    TOKENS.save(deps.storage, &input.to_string(), &input)
 }
 
-// this function reads Addrs stored in the TOKEN_LIST.
-// note that this function ONLY takes the first TOKEN_LIMIT terms
-pub fn read_token_list(deps: Deps, limit: usize) -> StdResult<Vec<AssetInfo>> {
-    match TOKEN_LIST.may_load(deps.storage)? {
-        None => Err(GenericErr {
+// this function reads a page of the tokens stored in TOKENS, ordered by denom or address
+pub fn read_token_list(
+    deps: Deps,
+    start_after: Option<AssetInfo>,
+    limit: usize,
+) -> StdResult<Vec<AssetInfo>> {
+    // an empty list is reported as an error as before
+    if start_after.is_none() && !has_tokens(deps.storage) {
+        return Err(GenericErr {
             msg: "No tokens are stored".to_string(),
-        }),
-        Some(list) => {
-            let take = limit.min(list.len());
-            Ok(list[..take].to_vec())
-        }
+        });
     }
+
+    let start_after = start_after.map(|token| token.to_string());
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    TOKENS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, token)| token))
+        .collect()
+}
+
+// this function returns the number of tokens stored
+pub fn read_token_count(storage: &dyn Storage) -> usize {
+    TOKENS
+        .keys_raw(storage, None, None, Order::Ascending)
+        .count()
 }
 
 // this function checks whether the token is stored already
 pub fn is_token(storage: &dyn Storage, token: AssetInfo) -> bool {
-    match TOKEN_LIST.may_load(storage).unwrap() {
-        None => false,
-        Some(list) => list.contains(&token),
-    }
+    TOKENS.has(storage, &token.to_string())
 }
 
 // this function deletes the entry under the given key
 pub fn remove_token(deps: DepsMut, token: AssetInfo) -> StdResult<()> {
-    // check if the list exists
-    let mut token_list = match TOKEN_LIST.may_load(deps.storage)? {
-        None => {
-            return Err(GenericErr {
-                msg: "No tokens are stored".to_string(),
-            })
-        }
-        Some(value) => value,
-    };
+    // check if there are any tokens stored
+    if !has_tokens(deps.storage) {
+        return Err(GenericErr {
+            msg: "No tokens are stored".to_string(),
+        });
+    }
 
     // check if the token is added
-    if !token_list.contains(&token) {
+    if !TOKENS.has(deps.storage, &token.to_string()) {
         return Err(GenericErr {
             msg: "This token has not been added".to_string(),
         });
     }
 
-    // change token_list
-    let index = token_list
-        .clone()
-        .iter()
-        .position(|x| x.eq(&token))
-        .unwrap();
-    token_list.swap_remove(index);
+    TOKENS.remove(deps.storage, &token.to_string());
+
+    Ok(())
+}
+
+// moves any tokens held in the legacy list into the token map
+pub fn migrate_token_list(storage: &mut dyn Storage) -> StdResult<()> {
+    if let Some(token_list) = TOKEN_LIST.may_load(storage)? {
+        for token in token_list.iter() {
+            TOKENS.save(storage, &token.to_string(), token)?;
+        }
+        TOKEN_LIST.remove(storage);
+    }
+
+    Ok(())
+}
 
-    // saves the updated token_list
-    TOKEN_LIST.save(deps.storage, &token_list)
+fn has_tokens(storage: &dyn Storage) -> bool {
+    TOKENS
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
 }
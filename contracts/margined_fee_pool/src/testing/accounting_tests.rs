@@ -0,0 +1,161 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, Uint128, WasmMsg};
+use cw20::Cw20ExecuteMsg;
+use cw_multi_test::Executor;
+use margined_common::asset::AssetInfo;
+use margined_perp::margined_fee_pool::{SenderInflow, TokenStatsResponse};
+use margined_utils::scenarios::{to_decimals, FeeStakingScenario};
+
+fn mint(env: &mut FeeStakingScenario, recipient: &Addr, amount: Uint128) {
+    let msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.usdc.addr().to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: recipient.to_string(),
+            amount,
+        })
+        .unwrap(),
+    });
+    env.router.execute(env.owner.clone(), msg).unwrap();
+}
+
+fn deposit(env: &mut FeeStakingScenario, sender: &Addr, amount: Uint128) {
+    let msg = env
+        .fee_pool
+        .deposit_cw20(env.usdc.addr().to_string(), amount)
+        .unwrap();
+    env.router.execute(sender.clone(), msg).unwrap();
+}
+
+#[test]
+fn test_token_stats() {
+    let mut env = FeeStakingScenario::new();
+    let (owner, alice, bob) = (env.owner.clone(), env.alice.clone(), env.bob.clone());
+    let fee_pool = env.fee_pool.addr();
+
+    // deposits are recorded against the sender, plain transfers only in the totals
+    mint(&mut env, &alice, to_decimals(100));
+    mint(&mut env, &bob, to_decimals(50));
+    deposit(&mut env, &alice, to_decimals(100));
+    deposit(&mut env, &bob, to_decimals(50));
+    mint(&mut env, &fee_pool, to_decimals(25));
+
+    let msg = env
+        .fee_pool
+        .send_token(
+            env.usdc.addr().to_string(),
+            to_decimals(40),
+            owner.to_string(),
+        )
+        .unwrap();
+    env.router.execute(owner.clone(), msg).unwrap();
+
+    let stats = env
+        .fee_pool
+        .token_stats(env.usdc.addr().to_string(), &env.router)
+        .unwrap();
+    assert_eq!(
+        stats,
+        TokenStatsResponse {
+            token: AssetInfo::Token {
+                contract_addr: env.usdc.addr(),
+            },
+            balance: to_decimals(135),
+            inflow: to_decimals(175),
+            outflow: to_decimals(40),
+            reserved: Uint128::zero(),
+        }
+    );
+
+    let res = env
+        .fee_pool
+        .token_inflows(env.usdc.addr().to_string(), None, None, &env.router)
+        .unwrap();
+    assert_eq!(
+        res.inflows,
+        vec![
+            SenderInflow {
+                sender: alice.clone(),
+                amount: to_decimals(100),
+            },
+            SenderInflow {
+                sender: bob.clone(),
+                amount: to_decimals(50),
+            },
+        ]
+    );
+
+    let res = env
+        .fee_pool
+        .token_inflows(
+            env.usdc.addr().to_string(),
+            Some(alice.to_string()),
+            None,
+            &env.router,
+        )
+        .unwrap();
+    assert_eq!(
+        res.inflows,
+        vec![SenderInflow {
+            sender: bob,
+            amount: to_decimals(50),
+        }]
+    );
+}
+
+#[test]
+fn test_claims_are_outflows() {
+    let mut env = FeeStakingScenario::new();
+    let alice = env.alice.clone();
+
+    let msg = env
+        .fee_pool
+        .stake(env.mrg.addr().to_string(), to_decimals(1_000))
+        .unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    mint(&mut env, &alice, to_decimals(80));
+    deposit(&mut env, &alice, to_decimals(80));
+
+    let stats = env
+        .fee_pool
+        .token_stats(env.usdc.addr().to_string(), &env.router)
+        .unwrap();
+    assert_eq!(stats.reserved, to_decimals(80));
+
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(alice, msg).unwrap();
+
+    let stats = env
+        .fee_pool
+        .token_stats(env.usdc.addr().to_string(), &env.router)
+        .unwrap();
+    assert_eq!(stats.balance, Uint128::zero());
+    assert_eq!(stats.inflow, to_decimals(80));
+    assert_eq!(stats.outflow, to_decimals(80));
+    assert_eq!(stats.reserved, Uint128::zero());
+}
+
+#[test]
+fn test_staking_token_cannot_be_deposited() {
+    let mut env = FeeStakingScenario::new();
+    let alice = env.alice.clone();
+
+    let msg = env
+        .fee_pool
+        .deposit_cw20(env.mrg.addr().to_string(), to_decimals(10))
+        .unwrap();
+    let err = env.router.execute(alice, msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().source().unwrap().to_string(),
+        "Generic error: The staking token cannot be a fee token"
+    );
+
+    let err = env
+        .fee_pool
+        .token_stats(env.mrg.addr().to_string(), &env.router)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Querier contract error: Generic error: The staking token cannot be a fee token"
+    );
+}
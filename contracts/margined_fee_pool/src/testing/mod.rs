@@ -1 +1,3 @@
+mod accounting_tests;
+mod staking_tests;
 mod tests;
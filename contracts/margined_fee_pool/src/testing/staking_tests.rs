@@ -0,0 +1,341 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, Empty, Uint128, WasmMsg};
+use cw20::Cw20ExecuteMsg;
+use cw_multi_test::Executor;
+use margined_common::asset::AssetInfo;
+use margined_perp::margined_fee_pool::{ConfigResponse, PendingReward};
+use margined_utils::scenarios::{to_decimals, FeeStakingScenario};
+
+fn stake(env: &mut FeeStakingScenario, staker: &Addr, amount: Uint128) {
+    let msg = env
+        .fee_pool
+        .stake(env.mrg.addr().to_string(), amount)
+        .unwrap();
+    env.router.execute(staker.clone(), msg).unwrap();
+}
+
+// mints usdc to the fee pool, as the engine does when fees are paid
+fn pay_fees(env: &mut FeeStakingScenario, amount: Uint128) {
+    let msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.usdc.addr().to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: env.fee_pool.addr().to_string(),
+            amount,
+        })
+        .unwrap(),
+    });
+    env.router.execute(env.owner.clone(), msg).unwrap();
+}
+
+fn pending(env: &FeeStakingScenario, staker: &Addr) -> Uint128 {
+    let res = env
+        .fee_pool
+        .pending_rewards(staker.to_string(), &env.router)
+        .unwrap();
+    assert_eq!(
+        res.rewards,
+        vec![PendingReward {
+            token: AssetInfo::Token {
+                contract_addr: env.usdc.addr(),
+            },
+            amount: res.rewards[0].amount,
+        }]
+    );
+
+    res.rewards[0].amount
+}
+
+#[test]
+fn test_update_config() {
+    let FeeStakingScenario {
+        mut router,
+        owner,
+        alice,
+        mrg,
+        usdc,
+        fee_pool,
+        ..
+    } = FeeStakingScenario::new();
+
+    let config = fee_pool.config(&router).unwrap();
+    assert_eq!(
+        config,
+        ConfigResponse {
+            staking_token: Some(mrg.addr()),
+            unbonding_period: 86_400u64,
+        }
+    );
+
+    // only the owner can update the config
+    let msg = fee_pool.update_config(None, Some(3_600u64)).unwrap();
+    let err = router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: unauthorized"
+    );
+
+    let msg = fee_pool.update_config(None, Some(3_600u64)).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+    assert_eq!(fee_pool.config(&router).unwrap().unbonding_period, 3_600u64);
+
+    // the staking token cannot be changed
+    let msg = fee_pool
+        .update_config(Some(usdc.addr().to_string()), None)
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: The staking token is already set"
+    );
+
+    // the staking token cannot be a fee token
+    let msg = fee_pool.add_token(mrg.addr().to_string()).unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: The staking token cannot be a fee token"
+    );
+}
+
+#[test]
+fn test_stake_only_staking_token() {
+    let mut env = FeeStakingScenario::new();
+    let alice = env.alice.clone();
+
+    // only the staking token can be staked
+    let msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.usdc.addr().to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: alice.to_string(),
+            amount: to_decimals(100),
+        })
+        .unwrap(),
+    });
+    env.router.execute(env.owner.clone(), msg).unwrap();
+
+    let msg = env
+        .fee_pool
+        .stake(env.usdc.addr().to_string(), to_decimals(100))
+        .unwrap();
+    let err = env.router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().source().unwrap().to_string(),
+        "Generic error: unauthorized"
+    );
+
+    stake(&mut env, &alice, to_decimals(1_000));
+
+    let staker = env.fee_pool.staker(alice.to_string(), &env.router).unwrap();
+    assert_eq!(staker.staked, to_decimals(1_000));
+    assert!(staker.unbonding.is_empty());
+    let total = env.fee_pool.total_staked(&env.router).unwrap();
+    assert_eq!(total.total_staked, to_decimals(1_000));
+}
+
+#[test]
+fn test_pro_rata_rewards() {
+    let mut env = FeeStakingScenario::new();
+    let (alice, bob, carol) = (env.alice.clone(), env.bob.clone(), env.carol.clone());
+
+    stake(&mut env, &alice, to_decimals(1_000));
+    stake(&mut env, &bob, to_decimals(3_000));
+
+    pay_fees(&mut env, to_decimals(400));
+    assert_eq!(pending(&env, &alice), to_decimals(100));
+    assert_eq!(pending(&env, &bob), to_decimals(300));
+    assert_eq!(pending(&env, &carol), Uint128::zero());
+
+    // a new staker only earns fees paid after staking
+    stake(&mut env, &carol, to_decimals(4_000));
+    pay_fees(&mut env, to_decimals(800));
+
+    assert_eq!(pending(&env, &alice), to_decimals(200));
+    assert_eq!(pending(&env, &bob), to_decimals(600));
+    assert_eq!(pending(&env, &carol), to_decimals(400));
+}
+
+#[test]
+fn test_claim() {
+    let mut env = FeeStakingScenario::new();
+    let (alice, bob) = (env.alice.clone(), env.bob.clone());
+
+    stake(&mut env, &alice, to_decimals(1_000));
+    stake(&mut env, &bob, to_decimals(1_000));
+    pay_fees(&mut env, to_decimals(500));
+
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    let balance = env
+        .usdc
+        .balance::<_, _, Empty>(&env.router, alice.clone())
+        .unwrap();
+    assert_eq!(balance, to_decimals(250));
+    assert_eq!(pending(&env, &alice), Uint128::zero());
+
+    // nothing left to claim
+    let msg = env.fee_pool.claim().unwrap();
+    let err = env.router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: Nothing to claim"
+    );
+
+    // the claim does not affect the rewards of other stakers
+    pay_fees(&mut env, to_decimals(100));
+    assert_eq!(pending(&env, &alice), to_decimals(50));
+    assert_eq!(pending(&env, &bob), to_decimals(300));
+
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(bob.clone(), msg).unwrap();
+    let balance = env
+        .usdc
+        .balance::<_, _, Empty>(&env.router, bob.clone())
+        .unwrap();
+    assert_eq!(balance, to_decimals(300));
+
+    let balance = env
+        .usdc
+        .balance::<_, _, Empty>(&env.router, env.fee_pool.addr())
+        .unwrap();
+    assert_eq!(balance, to_decimals(50));
+}
+
+#[test]
+fn test_unstake_with_unbonding() {
+    let mut env = FeeStakingScenario::new();
+    let (alice, bob) = (env.alice.clone(), env.bob.clone());
+
+    stake(&mut env, &alice, to_decimals(1_000));
+    stake(&mut env, &bob, to_decimals(1_000));
+    pay_fees(&mut env, to_decimals(200));
+
+    let msg = env.fee_pool.unstake(to_decimals(1_001)).unwrap();
+    let err = env.router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: Insufficient staked balance"
+    );
+
+    let msg = env.fee_pool.unstake(to_decimals(1_000)).unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    let staker = env.fee_pool.staker(alice.to_string(), &env.router).unwrap();
+    assert_eq!(staker.staked, Uint128::zero());
+    assert_eq!(staker.unbonding.len(), 1usize);
+    assert_eq!(staker.unbonding[0].amount, to_decimals(1_000));
+
+    // unbonding stake earns no rewards
+    pay_fees(&mut env, to_decimals(200));
+    assert_eq!(pending(&env, &alice), to_decimals(100));
+    assert_eq!(pending(&env, &bob), to_decimals(300));
+
+    // rewards are paid but the stake is still unbonding
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+    let balance = env
+        .mrg
+        .balance::<_, _, Empty>(&env.router, alice.clone())
+        .unwrap();
+    assert_eq!(balance, to_decimals(4_000));
+
+    let msg = env.fee_pool.claim().unwrap();
+    let err = env.router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: Nothing to claim"
+    );
+
+    env.router.update_block(|block| {
+        block.time = block.time.plus_seconds(86_400u64);
+        block.height += 1;
+    });
+
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+    let balance = env
+        .mrg
+        .balance::<_, _, Empty>(&env.router, alice.clone())
+        .unwrap();
+    assert_eq!(balance, to_decimals(5_000));
+
+    let staker = env.fee_pool.staker(alice.to_string(), &env.router).unwrap();
+    assert!(staker.unbonding.is_empty());
+}
+
+#[test]
+fn test_send_token_keeps_rewards() {
+    let mut env = FeeStakingScenario::new();
+    let (owner, alice) = (env.owner.clone(), env.alice.clone());
+
+    // fees paid while nothing is staked remain with the owner
+    pay_fees(&mut env, to_decimals(100));
+    stake(&mut env, &alice, to_decimals(1_000));
+    pay_fees(&mut env, to_decimals(300));
+    assert_eq!(pending(&env, &alice), to_decimals(300));
+
+    let msg = env
+        .fee_pool
+        .send_token(
+            env.usdc.addr().to_string(),
+            to_decimals(101),
+            owner.to_string(),
+        )
+        .unwrap();
+    let err = env.router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: Insufficient funds"
+    );
+
+    let msg = env
+        .fee_pool
+        .send_token(
+            env.usdc.addr().to_string(),
+            to_decimals(100),
+            owner.to_string(),
+        )
+        .unwrap();
+    env.router.execute(owner.clone(), msg).unwrap();
+
+    // the stakers rewards are unaffected
+    assert_eq!(pending(&env, &alice), to_decimals(300));
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    let balance = env
+        .usdc
+        .balance::<_, _, Empty>(&env.router, alice.clone())
+        .unwrap();
+    assert_eq!(balance, to_decimals(300));
+}
+
+#[test]
+fn test_remove_token_with_unclaimed_rewards() {
+    let mut env = FeeStakingScenario::new();
+    let (owner, alice) = (env.owner.clone(), env.alice.clone());
+
+    stake(&mut env, &alice, to_decimals(1_000));
+    pay_fees(&mut env, to_decimals(100));
+
+    // the rewards could no longer be claimed once the token is removed
+    let msg = env
+        .fee_pool
+        .remove_token(env.usdc.addr().to_string())
+        .unwrap();
+    let err = env.router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: Token has unclaimed rewards"
+    );
+
+    let msg = env.fee_pool.claim().unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    let msg = env
+        .fee_pool
+        .remove_token(env.usdc.addr().to_string())
+        .unwrap();
+    env.router.execute(owner.clone(), msg).unwrap();
+}
@@ -1,14 +1,16 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::TOKEN_LIST;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{
     from_binary, to_binary, Addr, BankMsg, Coin, CosmosMsg, Empty, StdError, Uint128, WasmMsg,
 };
+use cw2::{get_contract_version, set_contract_version};
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
 use margined_common::asset::AssetInfo;
 use margined_perp::margined_fee_pool::{
-    AllTokenResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, OwnerResponse, QueryMsg,
-    TokenLengthResponse, TokenResponse,
+    AllTokenResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, OwnerResponse,
+    QueryMsg, TokenLengthResponse, TokenResponse,
 };
 use margined_utils::scenarios::{NativeTokenScenario, SimpleScenario};
 
@@ -22,7 +24,13 @@ fn test_instantiation() {
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
     let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!(config, ConfigResponse {});
+    assert_eq!(
+        config,
+        ConfigResponse {
+            staking_token: None,
+            unbonding_period: 0u64,
+        }
+    );
 }
 
 #[test]
@@ -93,7 +101,10 @@ fn test_query_all_token() {
     let res = query(
         deps.as_ref(),
         mock_env(),
-        QueryMsg::GetTokenList { limit: None },
+        QueryMsg::GetTokenList {
+            start_after: None,
+            limit: None,
+        },
     )
     .unwrap_err();
 
@@ -119,7 +130,10 @@ fn test_query_all_token() {
     let res = query(
         deps.as_ref(),
         mock_env(),
-        QueryMsg::GetTokenList { limit: None },
+        QueryMsg::GetTokenList {
+            start_after: None,
+            limit: None,
+        },
     )
     .unwrap();
 
@@ -397,9 +411,7 @@ fn test_remove_non_existed_token() {
 }
 
 #[test]
-fn test_token_capacity() {
-    // for the purpose of this test, TOKEN_LIMIT is set to 3 (so four exceeds it!)
-
+fn test_token_list_pagination() {
     // instantiate contract here
     let mut deps = mock_dependencies();
     let msg = InstantiateMsg {};
@@ -407,76 +419,54 @@ fn test_token_capacity() {
 
     instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    ////////////////////////////////////////////////////////
-    // Test exceeding TOKEN_LIMIT by adding a single vAMM //
-    ////////////////////////////////////////////////////////
-
-    let tokens: Vec<String> = vec![
-        "token1".to_string(),
-        "token2".to_string(),
-        "token3".to_string(),
-        "token4".to_string(),
-    ];
-
-    // add three tokens
-    for n in 1..4 {
+    // add more tokens than the previous capacity of three
+    for n in 1..6 {
         let info = mock_info("owner", &[]);
         let msg = ExecuteMsg::AddToken {
-            token: tokens[n - 1].clone(),
+            token: format!("token{}", n),
         };
 
         execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     }
 
-    // try to add a fourth token
-    let info = mock_info("owner", &[]);
-    let msg = ExecuteMsg::AddToken {
-        token: "token4".to_string(),
-    };
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTokenLength {}).unwrap();
+    let res: TokenLengthResponse = from_binary(&res).unwrap();
+    assert_eq!(res.length, 5usize);
+
+    // query the tokens two at a time
+    let mut start_after: Option<String> = None;
+    let mut pages: Vec<Vec<AssetInfo>> = vec![];
+    loop {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTokenList {
+                start_after: start_after.clone(),
+                limit: Some(2u32),
+            },
+        )
+        .unwrap();
+        let res: AllTokenResponse = from_binary(&res).unwrap();
+        if res.token_list.is_empty() {
+            break;
+        }
 
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        start_after = res.token_list.last().map(|token| token.to_string());
+        pages.push(res.token_list);
+    }
 
     assert_eq!(
-        res.to_string(),
-        "Generic error: The token capacity is already reached"
+        pages.iter().map(|page| page.len()).collect::<Vec<usize>>(),
+        vec![2, 2, 1]
+    );
+    assert_eq!(
+        pages.concat(),
+        (1..6)
+            .map(|n| AssetInfo::Token {
+                contract_addr: Addr::unchecked(format!("token{}", n)),
+            })
+            .collect::<Vec<AssetInfo>>()
     );
-
-    ////////////////////////////////////////////
-    // Test exceeding VAMM_LIMIT via for loop //
-    ////////////////////////////////////////////
-
-    // instantiate contract here
-    let mut deps = mock_dependencies();
-    let msg = InstantiateMsg {};
-    let info = mock_info("owner", &[]);
-
-    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-    let tokens: Vec<String> = vec![
-        "token1".to_string(),
-        "token2".to_string(),
-        "token3".to_string(),
-        "token4".to_string(),
-    ];
-
-    // add four vamms
-    for n in 1..5 {
-        let info = mock_info("owner", &[]);
-        let msg = ExecuteMsg::AddToken {
-            token: tokens[n - 1].clone(),
-        };
-
-        if n == 4 {
-            let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-
-            assert_eq!(
-                res.to_string(),
-                "Generic error: The token capacity is already reached"
-            );
-            break;
-        }
-        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    }
 }
 
 #[test]
@@ -911,3 +901,79 @@ fn test_not_owner() {
         res.downcast().unwrap()
     );
 }
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {};
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+    // migrate from a previous release
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:margined-fee-pool",
+        "0.0.1",
+    )
+    .unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(res, config);
+
+    // migrate from a different contract should fail
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "0.0.1").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a different contract".to_string()
+    );
+}
+
+#[test]
+fn test_migrate_token_list() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {};
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // store the tokens as a previous release did
+    let legacy = vec![
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("token2"),
+        },
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("token1"),
+        },
+    ];
+    TOKEN_LIST.save(deps.as_mut().storage, &legacy).unwrap();
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:margined-fee-pool",
+        "0.0.1",
+    )
+    .unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetTokenList {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: AllTokenResponse = from_binary(&res).unwrap();
+    assert_eq!(res.token_list, vec![legacy[1].clone(), legacy[0].clone()]);
+    assert!(TOKEN_LIST
+        .may_load(deps.as_ref().storage)
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,117 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+};
+use cw2::set_contract_version;
+use margined_common::validate::{validate_migration, validate_ratio};
+use margined_perp::margined_governance::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+
+use crate::{
+    error::ContractError,
+    handle::{
+        execute_proposal, propose, receive_cw20, unstake, update_config, validate_voting_period,
+        vote,
+    },
+    query::{query_config, query_proposal, query_proposals, query_staker, query_state, query_vote},
+    state::{store_config, Config, TOTAL_STAKED},
+};
+
+/// Contract name that is used for migration.
+const CONTRACT_NAME: &str = "crates.io:margined-governance";
+/// Contract version that is used for migration.
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Decimals of the quorum and threshold ratios
+pub const RATIO_DECIMALS: Uint128 = Uint128::new(1_000_000u128);
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    validate_ratio(msg.quorum, RATIO_DECIMALS)?;
+    validate_ratio(msg.threshold, RATIO_DECIMALS)?;
+    validate_voting_period(msg.voting_period)?;
+
+    let config = Config {
+        staking_token: deps.api.addr_validate(&msg.staking_token)?,
+        quorum: msg.quorum,
+        threshold: msg.threshold,
+        voting_period: msg.voting_period,
+        timelock_period: msg.timelock_period,
+        proposal_min_stake: msg.proposal_min_stake,
+    };
+
+    store_config(deps.storage, &config)?;
+
+    TOTAL_STAKED.save(deps.storage, &Uint128::zero(), env.block.height)?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::Unstake { amount } => unstake(deps, env, info, amount),
+        ExecuteMsg::Propose {
+            title,
+            description,
+            msgs,
+        } => propose(deps, env, info, title, description, msgs),
+        ExecuteMsg::Vote {
+            proposal_id,
+            vote: option,
+        } => vote(deps, env, info, proposal_id, option),
+        ExecuteMsg::Execute { proposal_id } => execute_proposal(deps, env, proposal_id),
+        ExecuteMsg::UpdateConfig {
+            quorum,
+            threshold,
+            voting_period,
+            timelock_period,
+            proposal_min_stake,
+        } => update_config(
+            deps,
+            env,
+            info,
+            quorum,
+            threshold,
+            voting_period,
+            timelock_period,
+            proposal_min_stake,
+        ),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::Staker { address } => to_binary(&query_staker(deps, address)?),
+        QueryMsg::Proposal { proposal_id } => to_binary(&query_proposal(deps, env, proposal_id)?),
+        QueryMsg::Proposals { start_after, limit } => {
+            to_binary(&query_proposals(deps, env, start_after, limit)?)
+        }
+        QueryMsg::Vote { proposal_id, voter } => to_binary(&query_vote(deps, proposal_id, voter)?),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
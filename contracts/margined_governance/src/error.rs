@@ -0,0 +1,38 @@
+use cosmwasm_std::{OverflowError, StdError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Insufficient stake to create a proposal")]
+    InsufficientProposalStake {},
+
+    #[error("Insufficient staked balance")]
+    InsufficientStake {},
+
+    #[error("Proposal has no messages")]
+    EmptyProposal {},
+
+    #[error("Voting period has ended")]
+    VotingEnded {},
+
+    #[error("Already voted on proposal")]
+    AlreadyVoted {},
+
+    #[error("No voting power at the start of the proposal")]
+    NoVotingPower {},
+
+    #[error("Proposal has not passed")]
+    ProposalNotPassed {},
+
+    #[error("Timelock has not elapsed")]
+    TimelockNotElapsed {},
+}
@@ -0,0 +1,280 @@
+use cosmwasm_std::{
+    from_binary, to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError, Uint128,
+    WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use margined_common::validate::validate_ratio;
+use margined_perp::margined_governance::{Cw20HookMsg, ProposalStatus, VoteOption};
+
+use crate::{
+    contract::RATIO_DECIMALS,
+    error::ContractError,
+    state::{
+        next_proposal_id, read_config, read_proposal, read_stake, read_stake_at_height,
+        read_total_staked, read_total_staked_at_height, store_config, store_proposal, store_stake,
+        Config, Proposal, Vote, VOTES,
+    },
+};
+
+pub fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    // only the staking token can be staked
+    if info.sender != config.staking_token {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::Stake {} => stake(deps, env, cw20_msg.sender, cw20_msg.amount),
+    }
+}
+
+fn stake(
+    deps: DepsMut,
+    env: Env,
+    staker: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let staker = deps.api.addr_validate(&staker)?;
+
+    let stake = read_stake(deps.storage, &staker)?.checked_add(amount)?;
+    let total_staked = read_total_staked(deps.storage)?.checked_add(amount)?;
+
+    store_stake(deps.storage, &staker, stake, total_staked, env.block.height)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "stake"),
+        ("staker", staker.as_str()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+/// Withdraws staked tokens, votes already cast keep their weight
+pub fn unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    let stake = read_stake(deps.storage, &info.sender)?;
+    if amount.is_zero() || amount > stake {
+        return Err(ContractError::InsufficientStake {});
+    }
+
+    let total_staked = read_total_staked(deps.storage)?.checked_sub(amount)?;
+
+    store_stake(
+        deps.storage,
+        &info.sender,
+        stake - amount,
+        total_staked,
+        env.block.height,
+    )?;
+
+    let msg = WasmMsg::Execute {
+        contract_addr: config.staking_token.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: info.sender.to_string(),
+            amount,
+        })?,
+    };
+
+    Ok(Response::default().add_message(msg).add_attributes(vec![
+        ("action", "unstake"),
+        ("staker", info.sender.as_str()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+/// Creates a proposal, voting power is the stake at the block of creation
+pub fn propose(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    title: String,
+    description: String,
+    msgs: Vec<WasmMsg>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+
+    if read_stake(deps.storage, &info.sender)? < config.proposal_min_stake {
+        return Err(ContractError::InsufficientProposalStake {});
+    }
+
+    if msgs.is_empty() {
+        return Err(ContractError::EmptyProposal {});
+    }
+
+    let id = next_proposal_id(deps.storage)?;
+    let end_time = env.block.time.plus_seconds(config.voting_period);
+
+    let proposal = Proposal {
+        id,
+        proposer: info.sender.clone(),
+        title,
+        description,
+        msgs,
+        start_height: env.block.height,
+        end_time,
+        execute_after: end_time.plus_seconds(config.timelock_period),
+        quorum: config.quorum,
+        threshold: config.threshold,
+        total_power: read_total_staked_at_height(deps.storage, env.block.height)?,
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::zero(),
+        abstain_votes: Uint128::zero(),
+        executed: false,
+    };
+
+    store_proposal(deps.storage, &proposal)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "propose"),
+        ("proposal_id", &id.to_string()),
+        ("proposer", info.sender.as_str()),
+    ]))
+}
+
+pub fn vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+    vote: VoteOption,
+) -> Result<Response, ContractError> {
+    let mut proposal = read_proposal(deps.storage, proposal_id)?;
+
+    if proposal.status(&env.block)? != ProposalStatus::Open {
+        return Err(ContractError::VotingEnded {});
+    }
+
+    if VOTES.has(deps.storage, (proposal_id, &info.sender)) {
+        return Err(ContractError::AlreadyVoted {});
+    }
+
+    let power = read_stake_at_height(deps.storage, &info.sender, proposal.start_height)?;
+    if power.is_zero() {
+        return Err(ContractError::NoVotingPower {});
+    }
+
+    match vote {
+        VoteOption::Yes => proposal.yes_votes = proposal.yes_votes.checked_add(power)?,
+        VoteOption::No => proposal.no_votes = proposal.no_votes.checked_add(power)?,
+        VoteOption::Abstain => {
+            proposal.abstain_votes = proposal.abstain_votes.checked_add(power)?
+        }
+    }
+
+    store_proposal(deps.storage, &proposal)?;
+    VOTES.save(
+        deps.storage,
+        (proposal_id, &info.sender),
+        &Vote { vote, power },
+    )?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "vote"),
+        ("proposal_id", &proposal_id.to_string()),
+        ("voter", info.sender.as_str()),
+        ("vote", &format!("{:?}", vote).to_lowercase()),
+        ("power", &power.to_string()),
+    ]))
+}
+
+/// Executes the messages of a passed proposal once the timelock has elapsed,
+/// callable by anyone
+pub fn execute_proposal(
+    deps: DepsMut,
+    env: Env,
+    proposal_id: u64,
+) -> Result<Response, ContractError> {
+    let mut proposal = read_proposal(deps.storage, proposal_id)?;
+
+    if proposal.status(&env.block)? != ProposalStatus::Passed {
+        return Err(ContractError::ProposalNotPassed {});
+    }
+
+    if env.block.time < proposal.execute_after {
+        return Err(ContractError::TimelockNotElapsed {});
+    }
+
+    proposal.executed = true;
+    store_proposal(deps.storage, &proposal)?;
+
+    let msgs: Vec<CosmosMsg> = proposal.msgs.into_iter().map(CosmosMsg::Wasm).collect();
+
+    Ok(Response::default().add_messages(msgs).add_attributes(vec![
+        ("action", "execute_proposal"),
+        ("proposal_id", &proposal_id.to_string()),
+    ]))
+}
+
+/// Updates the voting parameters, only callable through a proposal
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    quorum: Option<Uint128>,
+    threshold: Option<Uint128>,
+    voting_period: Option<u64>,
+    timelock_period: Option<u64>,
+    proposal_min_stake: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    // check permission
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut config: Config = read_config(deps.storage)?;
+
+    // change quorum
+    if let Some(quorum) = quorum {
+        validate_ratio(quorum, RATIO_DECIMALS)?;
+        config.quorum = quorum;
+    }
+
+    // change threshold
+    if let Some(threshold) = threshold {
+        validate_ratio(threshold, RATIO_DECIMALS)?;
+        config.threshold = threshold;
+    }
+
+    // change voting period
+    if let Some(voting_period) = voting_period {
+        validate_voting_period(voting_period)?;
+        config.voting_period = voting_period;
+    }
+
+    // change timelock period
+    if let Some(timelock_period) = timelock_period {
+        config.timelock_period = timelock_period;
+    }
+
+    // change minimum stake to create proposals
+    if let Some(proposal_min_stake) = proposal_min_stake {
+        config.proposal_min_stake = proposal_min_stake;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "update_config"))
+}
+
+pub fn validate_voting_period(voting_period: u64) -> Result<(), ContractError> {
+    if voting_period == 0u64 {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Voting period cannot be zero",
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,8 @@
+pub mod contract;
+mod error;
+mod handle;
+mod query;
+mod state;
+
+#[cfg(test)]
+mod testing;
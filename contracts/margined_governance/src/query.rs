@@ -0,0 +1,101 @@
+use cosmwasm_std::{Deps, Env, StdResult};
+use margined_perp::margined_governance::{
+    ConfigResponse, ProposalResponse, ProposalsResponse, StakerResponse, StateResponse,
+    VoteResponse,
+};
+
+use crate::state::{
+    read_config, read_proposal, read_proposal_count, read_proposals, read_stake, read_total_staked,
+    Config, Proposal, VOTES,
+};
+
+const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
+const MAX_PAGINATION_LIMIT: u32 = 30u32;
+
+/// Queries contract config
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config: Config = read_config(deps.storage)?;
+
+    Ok(ConfigResponse {
+        staking_token: config.staking_token,
+        quorum: config.quorum,
+        threshold: config.threshold,
+        voting_period: config.voting_period,
+        timelock_period: config.timelock_period,
+        proposal_min_stake: config.proposal_min_stake,
+    })
+}
+
+/// Queries the total stake and number of proposals
+pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
+    Ok(StateResponse {
+        total_staked: read_total_staked(deps.storage)?,
+        proposal_count: read_proposal_count(deps.storage)?,
+    })
+}
+
+/// Queries the current stake of the address
+pub fn query_staker(deps: Deps, address: String) -> StdResult<StakerResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let staked = read_stake(deps.storage, &address)?;
+
+    Ok(StakerResponse { address, staked })
+}
+
+/// Queries the proposal with its status at the current block
+pub fn query_proposal(deps: Deps, env: Env, proposal_id: u64) -> StdResult<ProposalResponse> {
+    let proposal = read_proposal(deps.storage, proposal_id)?;
+
+    to_proposal_response(&env, proposal)
+}
+
+/// Queries proposals in ascending order of id
+pub fn query_proposals(
+    deps: Deps,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ProposalsResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
+
+    let proposals = read_proposals(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|proposal| to_proposal_response(&env, proposal))
+        .collect::<StdResult<_>>()?;
+
+    Ok(ProposalsResponse { proposals })
+}
+
+/// Queries the vote of the voter on the proposal
+pub fn query_vote(deps: Deps, proposal_id: u64, voter: String) -> StdResult<VoteResponse> {
+    let voter = deps.api.addr_validate(&voter)?;
+    let vote = VOTES.load(deps.storage, (proposal_id, &voter))?;
+
+    Ok(VoteResponse {
+        voter,
+        vote: vote.vote,
+        power: vote.power,
+    })
+}
+
+fn to_proposal_response(env: &Env, proposal: Proposal) -> StdResult<ProposalResponse> {
+    let status = proposal.status(&env.block)?;
+
+    Ok(ProposalResponse {
+        id: proposal.id,
+        proposer: proposal.proposer,
+        title: proposal.title,
+        description: proposal.description,
+        msgs: proposal.msgs,
+        status,
+        start_height: proposal.start_height,
+        end_time: proposal.end_time,
+        execute_after: proposal.execute_after,
+        total_power: proposal.total_power,
+        yes_votes: proposal.yes_votes,
+        no_votes: proposal.no_votes,
+        abstain_votes: proposal.abstain_votes,
+    })
+}
@@ -0,0 +1,183 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, BlockInfo, Order, StdResult, Storage, Timestamp, Uint128, WasmMsg};
+use cosmwasm_storage::{singleton, singleton_read};
+use cw_storage_plus::{Bound, Item, Map, SnapshotItem, SnapshotMap, Strategy};
+use margined_perp::margined_governance::{ProposalStatus, VoteOption};
+
+use crate::contract::RATIO_DECIMALS;
+
+pub static KEY_CONFIG: &[u8] = b"config";
+
+pub const PROPOSAL_COUNT: Item<u64> = Item::new("proposal-count");
+pub const PROPOSALS: Map<u64, Proposal> = Map::new("proposals");
+pub const VOTES: Map<(u64, &Addr), Vote> = Map::new("votes");
+/// Staked balances, snapshotted so that votes use the stake at the start of a proposal
+pub const STAKES: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "stakes",
+    "stakes__checkpoints",
+    "stakes__changelog",
+    Strategy::EveryBlock,
+);
+pub const TOTAL_STAKED: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total-staked",
+    "total-staked__checkpoints",
+    "total-staked__changelog",
+    Strategy::EveryBlock,
+);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Config {
+    pub staking_token: Addr,
+    pub quorum: Uint128,    // share of the total stake that has to vote
+    pub threshold: Uint128, // share of the yes and no votes that has to be yes
+    pub voting_period: u64,
+    pub timelock_period: u64,
+    pub proposal_min_stake: Uint128,
+}
+
+pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
+    singleton(storage, KEY_CONFIG).save(config)
+}
+
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    singleton_read(storage, KEY_CONFIG).load()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Addr,
+    pub title: String,
+    pub description: String,
+    pub msgs: Vec<WasmMsg>,
+    pub start_height: u64,
+    pub end_time: Timestamp,
+    pub execute_after: Timestamp,
+    pub quorum: Uint128,
+    pub threshold: Uint128,
+    pub total_power: Uint128,
+    pub yes_votes: Uint128,
+    pub no_votes: Uint128,
+    pub abstain_votes: Uint128,
+    pub executed: bool,
+}
+
+impl Proposal {
+    /// Status of the proposal at the given block
+    pub fn status(&self, block: &BlockInfo) -> StdResult<ProposalStatus> {
+        if self.executed {
+            return Ok(ProposalStatus::Executed);
+        }
+
+        if block.time < self.end_time {
+            return Ok(ProposalStatus::Open);
+        }
+
+        if self.is_passed()? {
+            Ok(ProposalStatus::Passed)
+        } else {
+            Ok(ProposalStatus::Rejected)
+        }
+    }
+
+    /// Returns true if the quorum is reached and the yes votes exceed the threshold
+    fn is_passed(&self) -> StdResult<bool> {
+        if self.total_power.is_zero() {
+            return Ok(false);
+        }
+
+        let votes = self.yes_votes + self.no_votes + self.abstain_votes;
+        if votes.checked_mul(RATIO_DECIMALS)? < self.quorum.checked_mul(self.total_power)? {
+            return Ok(false);
+        }
+
+        let decisive_votes = self.yes_votes + self.no_votes;
+        if decisive_votes.is_zero() {
+            return Ok(false);
+        }
+
+        Ok(self.yes_votes.checked_mul(RATIO_DECIMALS)?
+            > self.threshold.checked_mul(decisive_votes)?)
+    }
+}
+
+pub fn store_proposal(storage: &mut dyn Storage, proposal: &Proposal) -> StdResult<()> {
+    PROPOSALS.save(storage, proposal.id, proposal)
+}
+
+pub fn read_proposal(storage: &dyn Storage, proposal_id: u64) -> StdResult<Proposal> {
+    PROPOSALS.load(storage, proposal_id)
+}
+
+/// Reads proposals in ascending order of id
+pub fn read_proposals(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<Proposal>> {
+    let start = start_after.map(Bound::exclusive);
+
+    PROPOSALS
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, proposal)| proposal))
+        .collect()
+}
+
+/// Increments the proposal count and returns the id of the next proposal
+pub fn next_proposal_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let id = PROPOSAL_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    PROPOSAL_COUNT.save(storage, &id)?;
+
+    Ok(id)
+}
+
+pub fn read_proposal_count(storage: &dyn Storage) -> StdResult<u64> {
+    Ok(PROPOSAL_COUNT.may_load(storage)?.unwrap_or_default())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Vote {
+    pub vote: VoteOption,
+    pub power: Uint128,
+}
+
+pub fn read_stake(storage: &dyn Storage, staker: &Addr) -> StdResult<Uint128> {
+    Ok(STAKES.may_load(storage, staker)?.unwrap_or_default())
+}
+
+/// Reads the stake at the start of the given block
+pub fn read_stake_at_height(
+    storage: &dyn Storage,
+    staker: &Addr,
+    height: u64,
+) -> StdResult<Uint128> {
+    Ok(STAKES
+        .may_load_at_height(storage, staker, height)?
+        .unwrap_or_default())
+}
+
+pub fn read_total_staked(storage: &dyn Storage) -> StdResult<Uint128> {
+    Ok(TOTAL_STAKED.may_load(storage)?.unwrap_or_default())
+}
+
+/// Reads the total stake at the start of the given block
+pub fn read_total_staked_at_height(storage: &dyn Storage, height: u64) -> StdResult<Uint128> {
+    Ok(TOTAL_STAKED
+        .may_load_at_height(storage, height)?
+        .unwrap_or_default())
+}
+
+/// Stores the new stake of the staker and the total stake at the height
+pub fn store_stake(
+    storage: &mut dyn Storage,
+    staker: &Addr,
+    stake: Uint128,
+    total_staked: Uint128,
+    height: u64,
+) -> StdResult<()> {
+    STAKES.save(storage, staker, &stake, height)?;
+    TOTAL_STAKED.save(storage, &total_staked, height)
+}
@@ -0,0 +1,408 @@
+use crate::contract::{instantiate, migrate, query};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{from_binary, to_binary, Addr, Empty, Uint128, WasmMsg};
+use cw2::{get_contract_version, set_contract_version};
+use cw_multi_test::Executor;
+use margined_perp::margined_fee_pool::ExecuteMsg as FeePoolExecuteMsg;
+use margined_perp::margined_governance::{
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, ProposalStatus, QueryMsg, VoteOption,
+};
+use margined_utils::scenarios::{to_decimals, GovernanceScenario};
+
+fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        staking_token: "mrg".to_string(),
+        quorum: Uint128::from(400_000u128),
+        threshold: Uint128::from(500_000u128),
+        voting_period: 86_400u64,
+        timelock_period: 3_600u64,
+        proposal_min_stake: Uint128::from(1_000u128),
+    }
+}
+
+fn add_token_msg(env: &GovernanceScenario) -> WasmMsg {
+    WasmMsg::Execute {
+        contract_addr: env.fee_pool.addr().to_string(),
+        msg: to_binary(&FeePoolExecuteMsg::AddToken {
+            token: env.mrg.addr().to_string(),
+        })
+        .unwrap(),
+        funds: vec![],
+    }
+}
+
+fn stake(env: &mut GovernanceScenario, staker: &Addr, amount: Uint128) {
+    let msg = env
+        .governance
+        .stake(env.mrg.addr().to_string(), amount)
+        .unwrap();
+    env.router.execute(staker.clone(), msg).unwrap();
+}
+
+fn propose(env: &mut GovernanceScenario, proposer: &Addr, msgs: Vec<WasmMsg>) {
+    let msg = env
+        .governance
+        .propose("title".to_string(), "description".to_string(), msgs)
+        .unwrap();
+    env.router.execute(proposer.clone(), msg).unwrap();
+}
+
+fn vote(env: &mut GovernanceScenario, voter: &Addr, proposal_id: u64, option: VoteOption) {
+    let msg = env.governance.vote(proposal_id, option).unwrap();
+    env.router.execute(voter.clone(), msg).unwrap();
+}
+
+fn next_block(env: &mut GovernanceScenario, seconds: u64) {
+    env.router.update_block(|block| {
+        block.time = block.time.plus_seconds(seconds);
+        block.height += 1;
+    });
+}
+
+#[test]
+fn test_instantiation() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, instantiate_msg()).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        config,
+        ConfigResponse {
+            staking_token: Addr::unchecked("mrg"),
+            quorum: Uint128::from(400_000u128),
+            threshold: Uint128::from(500_000u128),
+            voting_period: 86_400u64,
+            timelock_period: 3_600u64,
+            proposal_min_stake: Uint128::from(1_000u128),
+        }
+    );
+
+    // ratios cannot exceed one
+    let mut msg = instantiate_msg();
+    msg.threshold = Uint128::from(1_000_001u128);
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: Invalid ratio");
+
+    let mut msg = instantiate_msg();
+    msg.voting_period = 0u64;
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Voting period cannot be zero"
+    );
+}
+
+#[test]
+fn test_stake_and_unstake() {
+    let mut env = GovernanceScenario::new();
+    let alice = env.alice.clone();
+
+    stake(&mut env, &alice, to_decimals(2_000));
+
+    let staker = env
+        .governance
+        .staker(&env.router, alice.to_string())
+        .unwrap();
+    assert_eq!(staker.staked, to_decimals(2_000));
+    let state = env.governance.state(&env.router).unwrap();
+    assert_eq!(state.total_staked, to_decimals(2_000));
+
+    // only the staking token can be received
+    let msg = ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+        sender: alice.to_string(),
+        amount: to_decimals(1_000),
+        msg: to_binary(&margined_perp::margined_governance::Cw20HookMsg::Stake {}).unwrap(),
+    });
+    let err = env
+        .router
+        .execute_contract(alice.clone(), env.governance.addr(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Unauthorized");
+
+    // cannot unstake more than staked
+    let msg = env.governance.unstake(to_decimals(2_001)).unwrap();
+    let err = env.router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Insufficient staked balance"
+    );
+
+    let msg = env.governance.unstake(to_decimals(500)).unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    let balance = env
+        .mrg
+        .balance::<_, _, Empty>(&env.router, alice.clone())
+        .unwrap();
+    assert_eq!(balance, to_decimals(3_500));
+    let state = env.governance.state(&env.router).unwrap();
+    assert_eq!(state.total_staked, to_decimals(1_500));
+}
+
+#[test]
+fn test_propose() {
+    let mut env = GovernanceScenario::new();
+    let (alice, carol) = (env.alice.clone(), env.carol.clone());
+
+    stake(&mut env, &alice, to_decimals(2_000));
+    stake(&mut env, &carol, to_decimals(500));
+    next_block(&mut env, 5u64);
+
+    // carol does not have the minimum stake
+    let msg = env
+        .governance
+        .propose(
+            "title".to_string(),
+            "description".to_string(),
+            vec![add_token_msg(&env)],
+        )
+        .unwrap();
+    let err = env.router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Insufficient stake to create a proposal"
+    );
+
+    let msg = env
+        .governance
+        .propose("title".to_string(), "description".to_string(), vec![])
+        .unwrap();
+    let err = env.router.execute(alice.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Proposal has no messages"
+    );
+
+    let msgs = vec![add_token_msg(&env)];
+    propose(&mut env, &alice, msgs.clone());
+
+    let proposal = env.governance.proposal(&env.router, 1u64).unwrap();
+    assert_eq!(proposal.proposer, alice);
+    assert_eq!(proposal.msgs, msgs);
+    assert_eq!(proposal.status, ProposalStatus::Open);
+    assert_eq!(proposal.total_power, to_decimals(2_500));
+    assert_eq!(
+        proposal.execute_after,
+        proposal.end_time.plus_seconds(3_600u64)
+    );
+
+    propose(&mut env, &alice, msgs);
+    let proposals = env
+        .governance
+        .proposals(&env.router, Some(1u64), None)
+        .unwrap();
+    assert_eq!(proposals.proposals.len(), 1usize);
+    assert_eq!(proposals.proposals[0].id, 2u64);
+    assert_eq!(
+        env.governance.state(&env.router).unwrap().proposal_count,
+        2u64
+    );
+}
+
+#[test]
+fn test_vote_uses_snapshot_power() {
+    let mut env = GovernanceScenario::new();
+    let (alice, bob, carol) = (env.alice.clone(), env.bob.clone(), env.carol.clone());
+
+    stake(&mut env, &alice, to_decimals(2_000));
+    stake(&mut env, &bob, to_decimals(1_000));
+    next_block(&mut env, 5u64);
+
+    let msgs = vec![add_token_msg(&env)];
+    propose(&mut env, &alice, msgs);
+
+    // stake added after the proposal has no voting power
+    stake(&mut env, &carol, to_decimals(2_000));
+    next_block(&mut env, 5u64);
+    let msg = env.governance.vote(1u64, VoteOption::Yes).unwrap();
+    let err = env.router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "No voting power at the start of the proposal"
+    );
+
+    // unstaking after the proposal does not reduce voting power
+    let msg = env.governance.unstake(to_decimals(1_000)).unwrap();
+    env.router.execute(bob.clone(), msg).unwrap();
+    vote(&mut env, &bob, 1u64, VoteOption::No);
+
+    let vote_of = env
+        .governance
+        .vote_of(&env.router, 1u64, bob.to_string())
+        .unwrap();
+    assert_eq!(vote_of.vote, VoteOption::No);
+    assert_eq!(vote_of.power, to_decimals(1_000));
+
+    let msg = env.governance.vote(1u64, VoteOption::Yes).unwrap();
+    let err = env.router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Already voted on proposal"
+    );
+
+    vote(&mut env, &alice, 1u64, VoteOption::Yes);
+
+    let proposal = env.governance.proposal(&env.router, 1u64).unwrap();
+    assert_eq!(proposal.yes_votes, to_decimals(2_000));
+    assert_eq!(proposal.no_votes, to_decimals(1_000));
+
+    // voting closes at the end of the voting period
+    next_block(&mut env, 86_400u64);
+    let msg = env.governance.vote(1u64, VoteOption::Abstain).unwrap();
+    let err = env.router.execute(carol.clone(), msg).unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Voting period has ended");
+
+    let proposal = env.governance.proposal(&env.router, 1u64).unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Passed);
+}
+
+#[test]
+fn test_quorum_and_threshold() {
+    let mut env = GovernanceScenario::new();
+    let (alice, bob, carol) = (env.alice.clone(), env.bob.clone(), env.carol.clone());
+
+    stake(&mut env, &alice, to_decimals(5_000));
+    stake(&mut env, &bob, to_decimals(3_000));
+    stake(&mut env, &carol, to_decimals(2_000));
+    next_block(&mut env, 5u64);
+
+    // proposal 1: 30% turnout misses the 40% quorum
+    let msgs = vec![add_token_msg(&env)];
+    propose(&mut env, &alice, msgs.clone());
+    vote(&mut env, &bob, 1u64, VoteOption::Yes);
+
+    // proposal 2: abstain counts towards quorum, but yes must exceed half of yes and no
+    propose(&mut env, &alice, msgs);
+    vote(&mut env, &alice, 2u64, VoteOption::Abstain);
+    vote(&mut env, &bob, 2u64, VoteOption::No);
+    vote(&mut env, &carol, 2u64, VoteOption::Yes);
+
+    next_block(&mut env, 86_400u64);
+
+    for proposal_id in [1u64, 2u64] {
+        let proposal = env.governance.proposal(&env.router, proposal_id).unwrap();
+        assert_eq!(proposal.status, ProposalStatus::Rejected);
+
+        next_block(&mut env, 3_600u64);
+        let msg = env.governance.execute(proposal_id).unwrap();
+        let err = env.router.execute(carol.clone(), msg).unwrap_err();
+        assert_eq!(err.source().unwrap().to_string(), "Proposal has not passed");
+    }
+}
+
+#[test]
+fn test_execute_after_timelock() {
+    let mut env = GovernanceScenario::new();
+    let (alice, bob) = (env.alice.clone(), env.bob.clone());
+
+    stake(&mut env, &alice, to_decimals(2_000));
+    next_block(&mut env, 5u64);
+
+    let msgs = vec![add_token_msg(&env)];
+    propose(&mut env, &alice, msgs);
+    vote(&mut env, &alice, 1u64, VoteOption::Yes);
+
+    // cannot execute while the vote is open
+    let msg = env.governance.execute(1u64).unwrap();
+    let err = env.router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Proposal has not passed");
+
+    next_block(&mut env, 86_400u64);
+    let msg = env.governance.execute(1u64).unwrap();
+    let err = env.router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Timelock has not elapsed"
+    );
+
+    // anyone can execute once the timelock has elapsed
+    next_block(&mut env, 3_600u64);
+    let msg = env.governance.execute(1u64).unwrap();
+    env.router.execute(bob.clone(), msg).unwrap();
+
+    let is_token = env
+        .fee_pool
+        .is_token(env.mrg.addr().to_string(), &env.router)
+        .unwrap();
+    assert!(is_token.is_token);
+
+    let proposal = env.governance.proposal(&env.router, 1u64).unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Executed);
+
+    // proposals execute only once
+    let msg = env.governance.execute(1u64).unwrap();
+    let err = env.router.execute(bob.clone(), msg).unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Proposal has not passed");
+}
+
+#[test]
+fn test_update_config_through_proposal() {
+    let mut env = GovernanceScenario::new();
+    let alice = env.alice.clone();
+
+    // only governance itself can update its config
+    let msg = ExecuteMsg::UpdateConfig {
+        quorum: None,
+        threshold: None,
+        voting_period: Some(600u64),
+        timelock_period: None,
+        proposal_min_stake: None,
+    };
+    let err = env
+        .router
+        .execute_contract(env.owner.clone(), env.governance.addr(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(err.source().unwrap().to_string(), "Unauthorized");
+
+    stake(&mut env, &alice, to_decimals(2_000));
+    next_block(&mut env, 5u64);
+
+    let update = WasmMsg::Execute {
+        contract_addr: env.governance.addr().to_string(),
+        msg: to_binary(&msg).unwrap(),
+        funds: vec![],
+    };
+    propose(&mut env, &alice, vec![update]);
+    vote(&mut env, &alice, 1u64, VoteOption::Yes);
+    next_block(&mut env, 86_400u64 + 3_600u64);
+
+    let msg = env.governance.execute(1u64).unwrap();
+    env.router.execute(alice.clone(), msg).unwrap();
+
+    let config = env.governance.config(&env.router).unwrap();
+    assert_eq!(config.voting_period, 600u64);
+}
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, instantiate_msg()).unwrap();
+
+    let config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+    // migrate from a previous release
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:margined-governance",
+        "0.0.1",
+    )
+    .unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(res, config);
+
+    // migrate from a different contract should fail
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "0.0.1").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a different contract".to_string()
+    );
+}
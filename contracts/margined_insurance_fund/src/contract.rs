@@ -1,19 +1,20 @@
 #[cfg(not(feature = "library"))]
 use crate::error::ContractError;
 use crate::{
-    handle::{add_vamm, remove_vamm, shutdown_all_vamm, update_owner, withdraw},
+    handle::{add_vamm, remove_vamm, set_factory, shutdown_all_vamm, update_owner, withdraw},
     query::{
         query_all_vamm, query_config, query_is_vamm, query_owner, query_status_all_vamm,
         query_vamm_status,
     },
-    state::{store_config, Config},
+    state::{migrate_vammlist, store_config, Config},
 };
 use cosmwasm_std::{
     entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
 };
 use cw2::set_contract_version;
 use cw_controllers::Admin;
-use margined_perp::margined_insurance_fund::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use margined_common::validate::validate_migration;
+use margined_perp::margined_insurance_fund::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
 /// Contract name that is used for migration.
 const CONTRACT_NAME: &str = "crates.io:margined-insurance-fund";
@@ -33,6 +34,7 @@ pub fn instantiate(
 
     let config = Config {
         engine: deps.api.addr_validate(&msg.engine)?,
+        factory: None,
     };
 
     store_config(deps.storage, &config)?;
@@ -49,7 +51,10 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::AddVamm { vamm } => add_vamm(deps, info, vamm),
         ExecuteMsg::RemoveVamm { vamm } => remove_vamm(deps, info, vamm),
         ExecuteMsg::Withdraw { token, amount } => withdraw(deps, info, token, amount),
-        ExecuteMsg::ShutdownVamms {} => shutdown_all_vamm(deps, env, info),
+        ExecuteMsg::ShutdownVamms { start_after, limit } => {
+            shutdown_all_vamm(deps, env, info, start_after, limit)
+        }
+        ExecuteMsg::SetFactory { factory } => set_factory(deps, info, factory),
     }
 }
 
@@ -59,8 +64,24 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
         QueryMsg::IsVamm { vamm } => to_binary(&query_is_vamm(deps, vamm)?),
-        QueryMsg::GetAllVamm { limit } => to_binary(&query_all_vamm(deps, limit)?),
+        QueryMsg::GetAllVamm { start_after, limit } => {
+            to_binary(&query_all_vamm(deps, start_after, limit)?)
+        }
         QueryMsg::GetVammStatus { vamm } => to_binary(&query_vamm_status(deps, vamm)?),
-        QueryMsg::GetAllVammStatus { limit } => to_binary(&query_status_all_vamm(deps, limit)?),
+        QueryMsg::GetAllVammStatus { start_after, limit } => {
+            to_binary(&query_status_all_vamm(deps, start_after, limit)?)
+        }
     }
 }
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // move the vAMMs out of the legacy capped list and into the registry
+    migrate_vammlist(deps.storage)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
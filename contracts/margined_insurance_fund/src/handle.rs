@@ -9,7 +9,9 @@ use crate::{
     contract::OWNER,
     messages::execute_vamm_shutdown,
     querier::{query_engine_decimals, query_vamm_decimals},
-    state::{read_config, read_vammlist, remove_vamm as remove_amm, save_vamm, Config, VAMM_LIMIT},
+    state::{
+        read_config, read_vammlist, remove_vamm as remove_amm, save_vamm, store_config, Config,
+    },
 };
 
 pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResult<Response> {
@@ -21,11 +23,33 @@ pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResul
         .map_err(|error| StdError::generic_err(format!("{}", error)))
 }
 
+pub fn set_factory(
+    deps: DepsMut,
+    info: MessageInfo,
+    factory: Option<String>,
+) -> StdResult<Response> {
+    let mut config: Config = read_config(deps.storage)?;
+
+    // check permission
+    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // validate address
+    config.factory = factory
+        .map(|factory| deps.api.addr_validate(&factory))
+        .transpose()?;
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "set_factory"))
+}
+
 pub fn add_vamm(deps: DepsMut, info: MessageInfo, vamm: String) -> StdResult<Response> {
     let config: Config = read_config(deps.storage)?;
 
     // check permission
-    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+    if !is_owner_or_factory(&deps, &info, &config)? {
         return Err(StdError::generic_err("unauthorized"));
     }
 
@@ -50,8 +74,10 @@ pub fn add_vamm(deps: DepsMut, info: MessageInfo, vamm: String) -> StdResult<Res
 }
 
 pub fn remove_vamm(deps: DepsMut, info: MessageInfo, vamm: String) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+
     // check permission
-    if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
+    if !is_owner_or_factory(&deps, &info, &config)? {
         return Err(StdError::generic_err("unauthorized"));
     }
 
@@ -64,7 +90,17 @@ pub fn remove_vamm(deps: DepsMut, info: MessageInfo, vamm: String) -> StdResult<
     Ok(Response::default())
 }
 
-pub fn shutdown_all_vamm(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+const DEFAULT_SHUTDOWN_LIMIT: u32 = 10u32;
+const MAX_SHUTDOWN_LIMIT: u32 = 30u32;
+
+// shuts down one page of vAMMs, the last vAMM is returned so the caller can resume after it
+pub fn shutdown_all_vamm(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Response> {
     // check permission
     if !OWNER.is_admin(deps.as_ref(), &info.sender)? && info.sender != env.contract.address {
         return Err(StdError::generic_err("unauthorized"));
@@ -73,14 +109,29 @@ pub fn shutdown_all_vamm(deps: DepsMut, env: Env, info: MessageInfo) -> StdResul
     // initialise the submsgs vec
     let mut msgs = vec![];
 
-    // construct all the shutdown messages
-    let keys = read_vammlist(deps.as_ref(), VAMM_LIMIT)?;
+    // set the limit for pagination
+    let limit = limit
+        .unwrap_or(DEFAULT_SHUTDOWN_LIMIT)
+        .min(MAX_SHUTDOWN_LIMIT) as usize;
+    let start_after = start_after
+        .map(|vamm| deps.api.addr_validate(&vamm))
+        .transpose()?;
+
+    // construct the shutdown messages for this page
+    let keys = read_vammlist(deps.as_ref(), start_after, limit)?;
 
     for vamm in keys.iter() {
         msgs.push(execute_vamm_shutdown(vamm.clone())?);
     }
 
-    Ok(Response::default().add_submessages(msgs))
+    let last_vamm = keys.last().map(|vamm| vamm.to_string()).unwrap_or_default();
+
+    Ok(Response::default()
+        .add_submessages(msgs)
+        .add_attributes(vec![
+            ("action", "shutdown_vamms"),
+            ("last_vamm", &last_vamm),
+        ]))
 }
 
 pub fn withdraw(
@@ -126,3 +177,9 @@ pub fn withdraw(
             ("amount", &amount.to_string()),
         ]))
 }
+
+// the factory registers the vAMMs it deploys alongside the owner
+fn is_owner_or_factory(deps: &DepsMut, info: &MessageInfo, config: &Config) -> StdResult<bool> {
+    Ok(OWNER.is_admin(deps.as_ref(), &info.sender)?
+        || config.factory.as_ref() == Some(&info.sender))
+}
@@ -7,11 +7,11 @@ use margined_perp::margined_insurance_fund::{
 use crate::{
     contract::OWNER,
     querier::query_vamm_open,
-    state::{is_vamm, read_config, read_vammlist, Config, VAMM_LIMIT},
+    state::{is_vamm, read_config, read_vammlist, Config},
 };
 
 const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
-const MAX_PAGINATION_LIMIT: u32 = VAMM_LIMIT as u32;
+const MAX_PAGINATION_LIMIT: u32 = 30u32;
 
 /// Queries contract owner from the admin
 pub fn query_owner(deps: Deps) -> StdResult<OwnerResponse> {
@@ -28,6 +28,7 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
 
     Ok(ConfigResponse {
         engine: config.engine,
+        factory: config.factory,
     })
 }
 
@@ -43,13 +44,20 @@ pub fn query_is_vamm(deps: Deps, vamm: String) -> StdResult<VammResponse> {
 }
 
 /// Queries the list of vAMMs currently stored (not necessarily on)
-pub fn query_all_vamm(deps: Deps, limit: Option<u32>) -> StdResult<AllVammResponse> {
+pub fn query_all_vamm(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllVammResponse> {
     // set the limit for pagination
     let limit = limit
         .unwrap_or(DEFAULT_PAGINATION_LIMIT)
         .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|vamm| deps.api.addr_validate(&vamm))
+        .transpose()?;
 
-    let list = read_vammlist(deps, limit)?;
+    let list = read_vammlist(deps, start_after, limit)?;
     Ok(AllVammResponse { vamm_list: list })
 }
 
@@ -67,16 +75,23 @@ pub fn query_vamm_status(deps: Deps, vamm: String) -> StdResult<VammStatusRespon
 }
 
 /// Queries the status of multiple vAMMs, returning the vAMM address and whether it is on/off
-pub fn query_status_all_vamm(deps: Deps, limit: Option<u32>) -> StdResult<AllVammStatusResponse> {
+pub fn query_status_all_vamm(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllVammStatusResponse> {
     // set the limit for pagination
     let limit = limit
         .unwrap_or(DEFAULT_PAGINATION_LIMIT)
         .min(MAX_PAGINATION_LIMIT) as usize;
+    let start_after = start_after
+        .map(|vamm| deps.api.addr_validate(&vamm))
+        .transpose()?;
 
     let mut status_list: Vec<(Addr, bool)> = vec![];
 
     // iterate through the vamm list and query the status one by one
-    for vamm in read_vammlist(deps, limit)?.iter() {
+    for vamm in read_vammlist(deps, start_after, limit)?.iter() {
         let vamm_bool = query_vamm_open(&deps, vamm.to_string())?;
         status_list.push((vamm.clone(), vamm_bool));
     }
@@ -1,94 +1,95 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Deps, DepsMut, StdError, StdResult, Storage};
+use cosmwasm_std::{Addr, Deps, DepsMut, Empty, Order, StdError, StdResult, Storage};
 use cosmwasm_storage::{singleton, singleton_read};
-use cw_storage_plus::Item;
+use cw_storage_plus::{Bound, Item, Map};
 
 pub static KEY_CONFIG: &[u8] = b"config";
+pub const VAMMS: Map<&Addr, Empty> = Map::new("vamms");
+
+// legacy single-item list of vAMMs, only read when migrating into VAMMS
 pub const VAMM_LIST: Item<Vec<Addr>> = Item::new("vamm-list");
-pub const VAMM_LIMIT: usize = 3usize;
 
 // function checks if an addr is already added and adds it if not
-// We also check that we have not reached the limit of vAMMs here
 pub fn save_vamm(deps: DepsMut, input: Addr) -> StdResult<()> {
-    // check if there is a vector
-    let mut vamm_list = match VAMM_LIST.may_load(deps.storage)? {
-        None => vec![],
-        Some(value) => value,
-    };
-
     // check if we already added the vamm
-    if vamm_list.contains(&input) {
+    if VAMMS.has(deps.storage, &input) {
         return Err(StdError::GenericErr {
             msg: "This vAMM is already added".to_string(),
         });
     };
 
-    // check if we have reached the capacity
-    if vamm_list.len() >= VAMM_LIMIT {
-        return Err(StdError::GenericErr {
-            msg: "The vAMM capacity is already reached".to_string(),
-        });
-    };
-
-    // add the vamm to the vector
-    vamm_list.push(input);
-    VAMM_LIST.save(deps.storage, &vamm_list)
+    VAMMS.save(deps.storage, &input, &Empty {})
 }
 
-// this function reads Addrs stored in the VAMM_LIST.
-// note that this function ONLY takes the first VAMM_LIMIT terms
-pub fn read_vammlist(deps: Deps, limit: usize) -> StdResult<Vec<Addr>> {
-    match VAMM_LIST.may_load(deps.storage)? {
-        None => Err(StdError::GenericErr {
+// this function reads a page of Addrs stored in VAMMS, ordered by address
+pub fn read_vammlist(deps: Deps, start_after: Option<Addr>, limit: usize) -> StdResult<Vec<Addr>> {
+    // an empty registry is reported as an error as before
+    if start_after.is_none() && !has_vamms(deps.storage) {
+        return Err(StdError::GenericErr {
             msg: "No vAMMs are stored".to_string(),
-        }),
-        Some(value) => {
-            let take = limit.min(value.len());
-            Ok(value[..take].to_vec())
-        }
+        });
     }
+
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    VAMMS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
 }
 
 // this function checks whether the vamm is stored already
 pub fn is_vamm(storage: &dyn Storage, input: Addr) -> bool {
-    match VAMM_LIST.may_load(storage).unwrap() {
-        None => false,
-        Some(value) => value.contains(&input),
-    }
+    VAMMS.has(storage, &input)
 }
 
 // this function deletes the entry under the given key
 pub fn remove_vamm(deps: DepsMut, input: Addr) -> StdResult<()> {
     // check if there are any vamms stored
-    let mut vamm_list = match VAMM_LIST.may_load(deps.storage)? {
-        None => {
-            return Err(StdError::GenericErr {
-                msg: "No vAMMs are stored".to_string(),
-            })
-        }
-        Some(value) => value,
-    };
+    if !has_vamms(deps.storage) {
+        return Err(StdError::GenericErr {
+            msg: "No vAMMs are stored".to_string(),
+        });
+    }
 
     // check if the vamm is added
-    if !vamm_list.contains(&input) {
+    if !VAMMS.has(deps.storage, &input) {
         return Err(StdError::GenericErr {
             msg: "This vAMM has not been added".to_string(),
         });
     }
 
-    // change vamm_list
-    let index = vamm_list.clone().iter().position(|x| x.eq(&input)).unwrap();
-    vamm_list.swap_remove(index);
+    VAMMS.remove(deps.storage, &input);
+
+    Ok(())
+}
+
+// moves any vAMMs held in the legacy list into the registry
+pub fn migrate_vammlist(storage: &mut dyn Storage) -> StdResult<()> {
+    if let Some(vamm_list) = VAMM_LIST.may_load(storage)? {
+        for vamm in vamm_list.iter() {
+            VAMMS.save(storage, vamm, &Empty {})?;
+        }
+        VAMM_LIST.remove(storage);
+    }
+
+    Ok(())
+}
 
-    // saves the updated vamm_list
-    VAMM_LIST.save(deps.storage, &vamm_list)
+fn has_vamms(storage: &dyn Storage) -> bool {
+    VAMMS
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Config {
     pub engine: Addr,
+    #[serde(default)]
+    pub factory: Option<Addr>, // factory that is allowed to register vAMMs
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -1,11 +1,15 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::state::VAMM_LIST;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{from_binary, Addr, StdError};
+use cw2::{get_contract_version, set_contract_version};
 use cw_multi_test::Executor;
 use margined_perp::margined_insurance_fund::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, OwnerResponse, QueryMsg,
+    AllVammResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, OwnerResponse,
+    QueryMsg,
 };
 use margined_utils::scenarios::ShutdownScenario;
+use margined_vamm::error::ContractError as VammError;
 
 const ENGINE: &str = "engine";
 
@@ -24,6 +28,7 @@ fn test_instantiation() {
         config,
         ConfigResponse {
             engine: Addr::unchecked(ENGINE.to_string()),
+            factory: None,
         }
     );
 }
@@ -53,6 +58,56 @@ fn test_update_owner() {
     assert_eq!(owner, Addr::unchecked("addr0001".to_string()));
 }
 
+#[test]
+fn test_set_factory() {
+    let ShutdownScenario {
+        mut router,
+        owner,
+        insurance_fund,
+        vamm1,
+        ..
+    } = ShutdownScenario::new();
+    let factory = Addr::unchecked("factory");
+
+    // only the owner can set the factory
+    let msg = insurance_fund
+        .set_factory(Some(factory.to_string()))
+        .unwrap();
+    let err = router.execute(factory.clone(), msg.clone()).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: unauthorized"
+    );
+    router.execute(owner.clone(), msg).unwrap();
+
+    let config = insurance_fund.config(&router).unwrap();
+    assert_eq!(config.factory, Some(factory.clone()));
+
+    // the factory can register and remove vamms
+    let msg = insurance_fund.add_vamm(vamm1.addr().to_string()).unwrap();
+    router.execute(factory.clone(), msg).unwrap();
+    let res = insurance_fund
+        .is_vamm(vamm1.addr().to_string(), &router)
+        .unwrap();
+    assert!(res.is_vamm);
+
+    let msg = insurance_fund
+        .remove_vamm(vamm1.addr().to_string())
+        .unwrap();
+    router.execute(factory.clone(), msg).unwrap();
+
+    // an unset factory can no longer register vamms
+    let msg = insurance_fund.set_factory(None).unwrap();
+    router.execute(owner, msg).unwrap();
+
+    let msg = insurance_fund.add_vamm(vamm1.addr().to_string()).unwrap();
+    let err = router.execute(factory, msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Generic error: unauthorized"
+    );
+}
+
 #[test]
 fn test_query_vamm() {
     let ShutdownScenario {
@@ -88,7 +143,7 @@ fn test_query_all_vamm() {
     } = ShutdownScenario::new();
 
     // check to see that there are no vAMMs
-    let res = insurance_fund.all_vamms(None, &router).unwrap_err();
+    let res = insurance_fund.all_vamms(None, None, &router).unwrap_err();
 
     assert_eq!(
         res.to_string(),
@@ -104,7 +159,7 @@ fn test_query_all_vamm() {
     router.execute(owner, msg).unwrap();
 
     // check for the added vAMMs
-    let res = insurance_fund.all_vamms(None, &router).unwrap();
+    let res = insurance_fund.all_vamms(None, None, &router).unwrap();
     let list = res.vamm_list;
 
     assert_eq!(list, vec![vamm1.addr(), vamm2.addr()]);
@@ -314,18 +369,13 @@ fn test_off_vamm_off_again() {
     router.execute(owner.clone(), msg).unwrap();
 
     //turn vamm off
-    let msg = insurance_fund.shutdown_vamms().unwrap();
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
     //turn vamm off again (note the unauthorized error comes from state.open == open)
-    let msg = insurance_fund.shutdown_vamms().unwrap();
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
     let err = router.execute(owner, msg).unwrap_err();
-    assert_eq!(
-        StdError::GenericErr {
-            msg: "unauthorized".to_string(),
-        },
-        err.downcast().unwrap()
-    );
+    assert_eq!(VammError::Unauthorized {}, err.downcast().unwrap());
 }
 
 #[test]
@@ -353,7 +403,7 @@ fn test_vamm_shutdown() {
     router.execute(owner.clone(), msg).unwrap();
 
     // query all vamms' status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -366,11 +416,11 @@ fn test_vamm_shutdown() {
     );
 
     // shutdown all vamms
-    let msg = insurance_fund.shutdown_vamms().unwrap();
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
     router.execute(owner, msg).unwrap();
 
     // query all vamms' status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -408,7 +458,7 @@ fn test_vamm_shutdown_from_insurance() {
     router.execute(owner.clone(), msg).unwrap();
 
     // query all vamms' status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -421,11 +471,11 @@ fn test_vamm_shutdown_from_insurance() {
     );
 
     // shutdown all vamms
-    let msg = insurance_fund.shutdown_vamms().unwrap();
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
     router.execute(insurance_fund.addr(), msg).unwrap();
 
     // query all vamms' status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -461,7 +511,7 @@ fn test_query_vamm_status() {
     assert_eq!(status, true);
 
     // shutdown vamm
-    let msg = insurance_fund.shutdown_vamms().unwrap();
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
     // query vamm status
@@ -485,7 +535,9 @@ fn test_all_vamm_status() {
     } = ShutdownScenario::new();
 
     // query all vamms' status (there aren't any yet)
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap_err();
+    let res = insurance_fund
+        .all_vamm_status(None, None, &router)
+        .unwrap_err();
 
     assert_eq!(
         res.to_string(),
@@ -501,7 +553,7 @@ fn test_all_vamm_status() {
     router.execute(owner.clone(), msg).unwrap();
 
     // query all vamms' status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -510,11 +562,11 @@ fn test_all_vamm_status() {
     );
 
     // switch first vamm off
-    let msg = insurance_fund.shutdown_vamms().unwrap();
+    let msg = insurance_fund.shutdown_vamms(None, None).unwrap();
     router.execute(owner.clone(), msg).unwrap();
 
     // query all vamms' status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -545,15 +597,15 @@ fn test_pagination() {
     router.execute(owner.clone(), msg).unwrap();
 
     //query only the first vamm (because we gave it limit of 1)
-    let res = insurance_fund.all_vamm_status(Some(1u32), &router).unwrap();
+    let res = insurance_fund
+        .all_vamm_status(None, Some(1u32), &router)
+        .unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(vamms_status, vec![(vamm1.addr(), true)]);
 }
 #[test]
 fn test_pagination_limit() {
-    // for the purpose of this test, VAMM_LIMIT is set to 3 (so four exceeds it!)
-
     let ShutdownScenario {
         mut router,
         owner,
@@ -577,7 +629,7 @@ fn test_pagination_limit() {
     }
 
     // query all vamms status
-    let res = insurance_fund.all_vamm_status(None, &router).unwrap();
+    let res = insurance_fund.all_vamm_status(None, None, &router).unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -590,7 +642,9 @@ fn test_pagination_limit() {
     );
 
     //query only the first two vamms
-    let res = insurance_fund.all_vamm_status(Some(2u32), &router).unwrap();
+    let res = insurance_fund
+        .all_vamm_status(None, Some(2u32), &router)
+        .unwrap();
     let vamms_status = res.vamm_list_status;
 
     assert_eq!(
@@ -600,9 +654,7 @@ fn test_pagination_limit() {
 }
 
 #[test]
-fn test_vamm_capacity() {
-    // for the purpose of this test, VAMM_LIMIT is set to 3 (so four exceeds it!)
-
+fn test_vamm_registry_is_unbounded() {
     let ShutdownScenario {
         mut router,
         owner,
@@ -614,10 +666,6 @@ fn test_vamm_capacity() {
         ..
     } = ShutdownScenario::new();
 
-    ///////////////////////////////////////////////////////
-    // Test exceeding VAMM_LIMIT by adding a single vAMM //
-    ///////////////////////////////////////////////////////
-
     let vamms: Vec<String> = vec![
         vamm1.addr().to_string(),
         vamm2.addr().to_string(),
@@ -625,26 +673,56 @@ fn test_vamm_capacity() {
         vamm4.addr().to_string(),
     ];
 
-    //add three vamms
-    for n in 1..4 {
-        let msg = insurance_fund.add_vamm(vamms[n - 1].clone()).unwrap();
+    // add four vamms, more than the old capacity of three
+    for vamm in vamms.iter() {
+        let msg = insurance_fund.add_vamm(vamm.clone()).unwrap();
         router.execute(owner.clone(), msg).unwrap();
     }
 
-    //try to add a fourth vamm
+    let res = insurance_fund.all_vamms(None, None, &router).unwrap();
+    assert_eq!(res.vamm_list.len(), 4);
+
+    // page through the registry two at a time
+    let first = insurance_fund
+        .all_vamms(None, Some(2u32), &router)
+        .unwrap()
+        .vamm_list;
+    let second = insurance_fund
+        .all_vamms(Some(first[1].to_string()), Some(2u32), &router)
+        .unwrap()
+        .vamm_list;
+    let last = insurance_fund
+        .all_vamms(Some(second[1].to_string()), Some(2u32), &router)
+        .unwrap()
+        .vamm_list;
+
+    assert_eq!(first.len(), 2);
+    assert_eq!(second.len(), 2);
+    assert!(last.is_empty());
+
+    let mut paged: Vec<String> = first
+        .iter()
+        .chain(second.iter())
+        .map(|a| a.to_string())
+        .collect();
+    let mut expected = vamms.clone();
+    paged.sort();
+    expected.sort();
+    assert_eq!(paged, expected);
+
+    // adding the same vamm twice still fails
     let msg = insurance_fund.add_vamm(vamm4.addr().to_string()).unwrap();
     let err = router.execute(owner, msg).unwrap_err();
     assert_eq!(
         StdError::GenericErr {
-            msg: "The vAMM capacity is already reached".to_string(),
+            msg: "This vAMM is already added".to_string(),
         },
         err.downcast().unwrap()
     );
+}
 
-    ////////////////////////////////////////////
-    // Test exceeding VAMM_LIMIT via for loop //
-    ////////////////////////////////////////////
-
+#[test]
+fn test_resumable_shutdown() {
     let ShutdownScenario {
         mut router,
         owner,
@@ -656,30 +734,40 @@ fn test_vamm_capacity() {
         ..
     } = ShutdownScenario::new();
 
-    let vamms: Vec<String> = vec![
-        vamm1.addr().to_string(),
-        vamm2.addr().to_string(),
-        vamm3.addr().to_string(),
-        vamm4.addr().to_string(),
-    ];
+    for vamm in [&vamm1, &vamm2, &vamm3, &vamm4] {
+        let msg = insurance_fund.add_vamm(vamm.addr().to_string()).unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+    }
 
-    // add four vamms
-    for n in 1..5 {
-        let msg = insurance_fund.add_vamm(vamms[n - 1].clone()).unwrap();
+    // shut down the first page of three vamms
+    let msg = insurance_fund.shutdown_vamms(None, Some(3u32)).unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+    let last_vamm = res
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "last_vamm")
+        .map(|attr| attr.value.clone())
+        .unwrap();
 
-        if n == 4 {
-            let err = router.execute(owner.clone(), msg).unwrap_err();
+    let status = insurance_fund
+        .all_vamm_status(None, None, &router)
+        .unwrap()
+        .vamm_list_status;
+    assert_eq!(status.iter().filter(|(_, open)| !open).count(), 3);
+    assert!(status[3].1);
 
-            assert_eq!(
-                StdError::GenericErr {
-                    msg: "The vAMM capacity is already reached".to_string(),
-                },
-                err.downcast().unwrap()
-            );
-            break;
-        }
-        router.execute(owner.clone(), msg).unwrap();
-    }
+    // resume after the last vamm of the previous page
+    let msg = insurance_fund
+        .shutdown_vamms(Some(last_vamm), Some(3u32))
+        .unwrap();
+    router.execute(owner, msg).unwrap();
+
+    let status = insurance_fund
+        .all_vamm_status(None, None, &router)
+        .unwrap()
+        .vamm_list_status;
+    assert!(status.iter().all(|(_, open)| !open));
 }
 
 #[test]
@@ -726,7 +814,10 @@ fn test_not_owner() {
 
     //try to shutdown all vamms
     let info = mock_info("not_the_owner", &[]);
-    let msg = ExecuteMsg::ShutdownVamms {};
+    let msg = ExecuteMsg::ShutdownVamms {
+        start_after: None,
+        limit: None,
+    };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
 
@@ -752,3 +843,84 @@ fn test_incompatible_decimals() {
         err.downcast().unwrap()
     );
 }
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        engine: ENGINE.to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+    // migrate from a previous release
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:margined-insurance-fund",
+        "0.0.1",
+    )
+    .unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(res, config);
+
+    // migrate from a different contract should fail
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "0.0.1").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a different contract".to_string()
+    );
+}
+
+#[test]
+fn test_migrate_vamm_list() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        engine: ENGINE.to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // store the vamms as a previous release did
+    let legacy = vec![
+        Addr::unchecked("vamm2"),
+        Addr::unchecked("vamm1"),
+        Addr::unchecked("vamm3"),
+    ];
+    VAMM_LIST.save(deps.as_mut().storage, &legacy).unwrap();
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:margined-insurance-fund",
+        "0.0.1",
+    )
+    .unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetAllVamm {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let res: AllVammResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        res.vamm_list,
+        vec![
+            Addr::unchecked("vamm1"),
+            Addr::unchecked("vamm2"),
+            Addr::unchecked("vamm3"),
+        ]
+    );
+    assert!(VAMM_LIST.may_load(deps.as_ref().storage).unwrap().is_none());
+}
@@ -0,0 +1,73 @@
+// Contains the aggregation of reporter submissions into a price round
+use cosmwasm_std::{StdResult, Uint128};
+use margined_perp::margined_pricefeed::Submission;
+
+use crate::contract::RATIO_DECIMALS;
+
+/// Price aggregated from the submissions of a round
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregatedPrice {
+    pub price: Uint128,
+    pub timestamp: u64,
+}
+
+/// Returns the median of the submissions that deviate at most `max_deviation` from
+/// the median of all submissions, or `None` if fewer than `quorum` are accepted.
+/// The aggregated timestamp is that of the latest accepted submission.
+pub fn aggregate_submissions(
+    submissions: &[Submission],
+    quorum: u64,
+    max_deviation: Uint128,
+) -> StdResult<Option<AggregatedPrice>> {
+    if submissions.is_empty() || (submissions.len() as u64) < quorum {
+        return Ok(None);
+    }
+
+    let median = calc_median(submissions.iter().map(|s| s.price).collect())?;
+
+    let mut accepted = vec![];
+    for submission in submissions {
+        if calc_deviation(submission.price, median)? <= max_deviation {
+            accepted.push(submission);
+        }
+    }
+
+    if (accepted.len() as u64) < quorum {
+        return Ok(None);
+    }
+
+    let price = calc_median(accepted.iter().map(|s| s.price).collect())?;
+    let timestamp = accepted
+        .iter()
+        .map(|s| s.timestamp)
+        .max()
+        .unwrap_or_default();
+
+    Ok(Some(AggregatedPrice { price, timestamp }))
+}
+
+fn calc_median(mut prices: Vec<Uint128>) -> StdResult<Uint128> {
+    prices.sort();
+
+    let mid = prices.len() / 2;
+    if prices.len().is_multiple_of(2) {
+        Ok(prices[mid - 1]
+            .checked_add(prices[mid])?
+            .checked_div(Uint128::from(2u128))?)
+    } else {
+        Ok(prices[mid])
+    }
+}
+
+/// Deviation of the price from the median as a ratio with `RATIO_DECIMALS`
+fn calc_deviation(price: Uint128, median: Uint128) -> StdResult<Uint128> {
+    let difference = if price > median {
+        price - median
+    } else {
+        median - price
+    };
+
+    Ok(difference
+        .checked_mul(RATIO_DECIMALS)?
+        .checked_div(median)?)
+}
@@ -1,18 +1,24 @@
 use crate::error::ContractError;
 use crate::{
-    handle::{append_multiple_price, append_price, update_owner},
+    handle::{
+        add_reporter, append_multiple_price, append_price, migrate_prices, remove_oracle,
+        remove_reporter, set_aggregation, set_oracle, set_retention_period, submit_price,
+        update_owner,
+    },
     query::{
-        query_config, query_get_previous_price, query_get_price, query_get_twap_price, query_owner,
+        query_config, query_get_oracle, query_get_previous_price, query_get_price,
+        query_get_price_history, query_get_submissions, query_get_twap_price, query_owner,
     },
-    state::{store_config, Config},
+    state::{migrate_config, store_config, Config},
 };
 use cw2::set_contract_version;
-use cw_controllers::Admin;
+use cw_controllers::{Admin, Hooks};
+use margined_common::validate::validate_migration;
 
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
 };
-use margined_perp::margined_pricefeed::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use margined_perp::margined_pricefeed::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
 /// Contract name that is used for migration.
 const CONTRACT_NAME: &str = "crates.io:margined-pricefeed";
@@ -20,17 +26,26 @@ const CONTRACT_NAME: &str = "crates.io:margined-pricefeed";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Owner admin
 pub const OWNER: Admin = Admin::new("owner");
+/// Hooks controller for the whitelisted price reporters
+pub const REPORTERS: Hooks = Hooks::new("reporters");
+/// Decimals of the max deviation ratio
+pub const RATIO_DECIMALS: Uint128 = Uint128::new(1_000_000u128);
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let config = Config {};
+    let config = Config {
+        oracle_hub_contract: deps.api.addr_validate(&msg.oracle_hub_contract)?,
+        retention_period: None,
+        quorum: 1u64,
+        max_deviation: RATIO_DECIMALS,
+    };
 
     store_config(deps.storage, &config)?;
 
@@ -58,6 +73,41 @@ pub fn execute(
             timestamps,
         } => append_multiple_price(deps, info, key, prices, timestamps),
         ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
+        ExecuteMsg::SetOracle {
+            key,
+            oracle_type,
+            contract,
+            symbol,
+            decimals,
+            max_staleness,
+            max_confidence_ratio,
+        } => set_oracle(
+            deps,
+            info,
+            key,
+            oracle_type,
+            contract,
+            symbol,
+            decimals,
+            max_staleness,
+            max_confidence_ratio,
+        ),
+        ExecuteMsg::RemoveOracle { key } => remove_oracle(deps, info, key),
+        ExecuteMsg::SetRetentionPeriod { retention_period } => {
+            set_retention_period(deps, info, retention_period)
+        }
+        ExecuteMsg::AddReporter { address } => add_reporter(deps, info, address),
+        ExecuteMsg::RemoveReporter { address } => remove_reporter(deps, info, address),
+        ExecuteMsg::SetAggregation {
+            quorum,
+            max_deviation,
+        } => set_aggregation(deps, info, quorum, max_deviation),
+        ExecuteMsg::SubmitPrice {
+            key,
+            price,
+            timestamp,
+        } => submit_price(deps, info, key, price, timestamp),
+        ExecuteMsg::MigratePrices { limit } => migrate_prices(deps, info, limit),
     }
 }
 
@@ -66,7 +116,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::GetOwner {} => to_binary(&query_owner(deps)?),
-        QueryMsg::GetPrice { key } => to_binary(&query_get_price(deps, key)?),
+        QueryMsg::GetPrice { key } => to_binary(&query_get_price(deps, env, key)?),
         QueryMsg::GetPreviousPrice {
             key,
             num_round_back,
@@ -74,5 +124,30 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::GetTwapPrice { key, interval } => {
             to_binary(&query_get_twap_price(deps, env, key, interval)?)
         }
+        QueryMsg::GetOracle { key } => to_binary(&query_get_oracle(deps, key)?),
+        QueryMsg::GetPriceHistory {
+            key,
+            start_after,
+            limit,
+        } => to_binary(&query_get_price_history(deps, key, start_after, limit)?),
+        QueryMsg::IsReporter { address } => to_binary(&REPORTERS.query_hook(deps, address)?),
+        QueryMsg::GetReporters {} => to_binary(&REPORTERS.query_hooks(deps)?),
+        QueryMsg::GetSubmissions { key } => to_binary(&query_get_submissions(deps, key)?),
     }
 }
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // transform any state stored in a previous layout
+    let oracle_hub_contract = msg
+        .oracle_hub_contract
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    migrate_config(deps.storage, oracle_hub_contract)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
@@ -1,5 +1,5 @@
 use cosmwasm_std::StdError;
-use cw_controllers::AdminError;
+use cw_controllers::{AdminError, HookError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,9 +10,15 @@ pub enum ContractError {
     #[error("{0}")]
     Admin(#[from] AdminError),
 
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Unable to retrieve price data for key: {0}")]
     NoPriceData(String),
+
+    #[error("Price history of key {0} must be migrated first")]
+    PendingMigration(String),
 }
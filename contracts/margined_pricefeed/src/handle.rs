@@ -1,6 +1,20 @@
 use cosmwasm_std::{DepsMut, MessageInfo, Response, StdError, Uint128};
+use margined_common::validate::{validate_decimal_places, validate_ratio};
+use margined_perp::margined_pricefeed::{OracleType, Submission};
 
-use crate::{contract::OWNER, error::ContractError, state::store_price_data};
+use crate::{
+    aggregation::aggregate_submissions,
+    contract::{OWNER, RATIO_DECIMALS, REPORTERS},
+    error::ContractError,
+    state::{
+        clear_submissions, is_pending_migration, migrate_price_data, prune_price_data, read_config,
+        read_submissions, remove_oracle_config, store_config, store_oracle_config,
+        store_price_data, store_submission, OracleConfig,
+    },
+};
+
+const DEFAULT_MIGRATION_LIMIT: u32 = 30u32;
+const MAX_MIGRATION_LIMIT: u32 = 100u32;
 
 pub fn update_owner(
     deps: DepsMut,
@@ -13,9 +27,9 @@ pub fn update_owner(
     Ok(OWNER.execute_update_admin(deps, info, Some(valid_owner))?)
 }
 
-/// this is a mock function that enables storage of data
-/// by the contract owner will be replaced by integration
-/// with on-chain price oracles in the future.
+/// stores a price pushed by the contract owner, pushed prices
+/// are used when no oracle adapter is set for the key or when
+/// the oracle price is unavailable, stale or not confident enough.
 pub fn append_price(
     deps: DepsMut,
     info: MessageInfo,
@@ -26,16 +40,16 @@ pub fn append_price(
     // check permission
     OWNER.assert_admin(deps.as_ref(), &info.sender)?;
 
-    store_price_data(deps.storage, key, price, timestamp)?;
+    append_price_data(deps, key, price, timestamp)?;
 
     Ok(Response::default().add_attribute("action", "append_price"))
 }
 
-/// this is a mock function that enables storage of data
-/// by the contract owner will be replaced by integration
-/// with on-chain price oracles in the future.
+/// stores a batch of pushed prices for the key, pairing each price
+/// with the timestamp at the same index. The lists must be the same
+/// length and each entry is appended in order as by `append_price`.
 pub fn append_multiple_price(
-    deps: DepsMut,
+    mut deps: DepsMut,
     info: MessageInfo,
     key: String,
     prices: Vec<Uint128>,
@@ -52,8 +66,257 @@ pub fn append_multiple_price(
     }
 
     for index in 0..prices.len() {
-        store_price_data(deps.storage, key.clone(), prices[index], timestamps[index])?;
+        append_price_data(deps.branch(), key.clone(), prices[index], timestamps[index])?;
     }
 
     Ok(Response::default())
 }
+
+/// Sets the oracle adapter used to pull prices for the key,
+/// the pushed prices are used as a fallback
+#[allow(clippy::too_many_arguments)]
+pub fn set_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    oracle_type: OracleType,
+    contract: Option<String>,
+    symbol: String,
+    decimals: u8,
+    max_staleness: u64,
+    max_confidence_ratio: Uint128,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    // default to the oracle hub if no contract is given
+    let contract = match contract {
+        Some(contract) => deps.api.addr_validate(&contract)?,
+        None => read_config(deps.storage)?.oracle_hub_contract,
+    };
+
+    let decimals = validate_decimal_places(decimals)?;
+    validate_ratio(max_confidence_ratio, decimals)?;
+
+    let oracle = OracleConfig {
+        oracle_type,
+        contract,
+        symbol,
+        decimals,
+        max_staleness,
+        max_confidence_ratio,
+    };
+
+    store_oracle_config(deps.storage, key.clone(), &oracle)?;
+
+    Ok(Response::default().add_attributes(vec![("action", "set_oracle"), ("key", &key)]))
+}
+
+/// Removes the oracle adapter of the key so that only pushed prices are used
+pub fn remove_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    remove_oracle_config(deps.storage, key.clone());
+
+    Ok(Response::default().add_attributes(vec![("action", "remove_oracle"), ("key", &key)]))
+}
+
+/// Sets the period of price history that is retained, older rounds are
+/// pruned as new prices are appended
+pub fn set_retention_period(
+    deps: DepsMut,
+    info: MessageInfo,
+    retention_period: Option<u64>,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut config = read_config(deps.storage)?;
+    config.retention_period = retention_period;
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attribute("action", "set_retention_period"))
+}
+
+/// Adds an address to the whitelisted price reporters
+pub fn add_reporter(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    // validate the address
+    let reporter = deps.api.addr_validate(&address)?;
+
+    REPORTERS.add_hook(deps.storage, reporter.clone())?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "add_reporter"),
+        ("reporter", reporter.as_str()),
+    ]))
+}
+
+/// Removes an address from the whitelisted price reporters, pending
+/// submissions of the reporter are ignored from then on
+pub fn remove_reporter(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    // validate the address
+    let reporter = deps.api.addr_validate(&address)?;
+
+    REPORTERS.remove_hook(deps.storage, reporter.clone())?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "remove_reporter"),
+        ("reporter", reporter.as_str()),
+    ]))
+}
+
+/// Sets the number of submissions required to aggregate a round and the
+/// max deviation from the median for a submission to be accepted
+pub fn set_aggregation(
+    deps: DepsMut,
+    info: MessageInfo,
+    quorum: u64,
+    max_deviation: Uint128,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if quorum == 0u64 {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Quorum cannot be zero",
+        )));
+    }
+    validate_ratio(max_deviation, RATIO_DECIMALS)?;
+
+    let mut config = read_config(deps.storage)?;
+    config.quorum = quorum;
+    config.max_deviation = max_deviation;
+
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default().add_attributes(vec![
+        ("action", "set_aggregation"),
+        ("quorum", &quorum.to_string()),
+        ("max_deviation", &max_deviation.to_string()),
+    ]))
+}
+
+/// Stores the submission of a reporter for the pending round of the key, the
+/// round is aggregated into a new price once enough submissions agree
+pub fn submit_price(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+    price: Uint128,
+    timestamp: u64,
+) -> Result<Response, ContractError> {
+    // check permission
+    if !REPORTERS.query_hook(deps.as_ref(), info.sender.to_string())? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if price.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "Price cannot be zero",
+        )));
+    }
+
+    let submission = Submission {
+        reporter: info.sender.clone(),
+        price,
+        timestamp,
+    };
+    store_submission(deps.storage, &key, &submission)?;
+
+    let mut response = Response::default().add_attributes(vec![
+        ("action", "submit_price"),
+        ("key", &key),
+        ("reporter", info.sender.as_str()),
+        ("price", &price.to_string()),
+    ]);
+
+    // only submissions of current reporters count towards the round
+    let reporters = REPORTERS.query_hooks(deps.as_ref())?.hooks;
+    let submissions: Vec<Submission> = read_submissions(deps.storage, &key)?
+        .into_iter()
+        .filter(|s| reporters.contains(&s.reporter.to_string()))
+        .collect();
+
+    let config = read_config(deps.storage)?;
+    if let Some(aggregated) =
+        aggregate_submissions(&submissions, config.quorum, config.max_deviation)?
+    {
+        let round_id = append_price_data(
+            deps.branch(),
+            key.clone(),
+            aggregated.price,
+            aggregated.timestamp,
+        )?;
+        clear_submissions(deps.storage, &key)?;
+
+        response = response.add_attributes(vec![
+            ("round_id", round_id.to_string()),
+            ("aggregated_price", aggregated.price.to_string()),
+        ]);
+    }
+
+    Ok(response)
+}
+
+/// Moves a batch of prices stored by a previous release into one entry per
+/// round, can be called repeatedly until nothing is left to migrate
+pub fn migrate_prices(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    // check permission
+    OWNER.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_MIGRATION_LIMIT)
+        .min(MAX_MIGRATION_LIMIT) as usize;
+
+    let migrated = migrate_price_data(deps.storage, limit)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate_prices"),
+        ("migrated", &migrated.to_string()),
+    ]))
+}
+
+/// Stores the price as a new round and prunes rounds outside the retention period
+fn append_price_data(
+    deps: DepsMut,
+    key: String,
+    price: Uint128,
+    timestamp: u64,
+) -> Result<u64, ContractError> {
+    // new rounds would collide with the rounds of the legacy history
+    if is_pending_migration(deps.storage, &key) {
+        return Err(ContractError::PendingMigration(key));
+    }
+
+    let round_id = store_price_data(deps.storage, key.clone(), price, timestamp)?;
+
+    if let Some(retention_period) = read_config(deps.storage)?.retention_period {
+        prune_price_data(deps.storage, &key, retention_period)?;
+    }
+
+    Ok(round_id)
+}
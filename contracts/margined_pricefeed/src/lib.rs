@@ -1,6 +1,8 @@
+mod aggregation;
 pub mod contract;
 mod error;
 mod handle;
+mod oracle;
 mod query;
 mod state;
 
@@ -0,0 +1,109 @@
+// Contains the adapters for external oracle contracts
+use cosmwasm_std::{to_binary, Deps, Env, QueryRequest, StdError, StdResult, Uint128, WasmQuery};
+use margined_perp::margined_pricefeed::{
+    BandQueryMsg, BandReferenceData, OracleType, PythPriceFeedResponse, PythQueryMsg,
+};
+
+use crate::state::OracleConfig;
+
+/// Band reference rates are always returned with 18 decimals
+const BAND_DECIMALS: u32 = 18;
+/// Quote symbol used for all Band reference data queries
+const BAND_QUOTE_SYMBOL: &str = "USD";
+
+/// Price returned by an oracle adapter, scaled to the decimals of the feed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: Uint128,
+    pub timestamp: u64,
+}
+
+/// Queries the external oracle and validates the price against the staleness
+/// and confidence limits of the oracle config
+pub fn query_oracle_price(deps: Deps, env: &Env, oracle: &OracleConfig) -> StdResult<OraclePrice> {
+    let oracle_price = match oracle.oracle_type {
+        OracleType::Band => query_band_price(deps, oracle)?,
+        OracleType::Pyth => query_pyth_price(deps, oracle)?,
+    };
+
+    if oracle_price.price.is_zero() {
+        return Err(StdError::generic_err("Oracle price is zero"));
+    }
+
+    let age = env
+        .block
+        .time
+        .seconds()
+        .saturating_sub(oracle_price.timestamp);
+    if age > oracle.max_staleness {
+        return Err(StdError::generic_err("Oracle price is stale"));
+    }
+
+    Ok(oracle_price)
+}
+
+fn query_band_price(deps: Deps, oracle: &OracleConfig) -> StdResult<OraclePrice> {
+    let data: BandReferenceData = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle.contract.to_string(),
+        msg: to_binary(&BandQueryMsg::GetReferenceData {
+            base_symbol: oracle.symbol.clone(),
+            quote_symbol: BAND_QUOTE_SYMBOL.to_string(),
+        })?,
+    }))?;
+
+    let price = data
+        .rate
+        .checked_mul(oracle.decimals)?
+        .checked_div(Uint128::from(10u128.pow(BAND_DECIMALS)))?;
+
+    // the reference is only as fresh as the oldest of its two legs
+    let timestamp = data.last_updated_base.min(data.last_updated_quote);
+
+    Ok(OraclePrice { price, timestamp })
+}
+
+fn query_pyth_price(deps: Deps, oracle: &OracleConfig) -> StdResult<OraclePrice> {
+    let response: PythPriceFeedResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: oracle.contract.to_string(),
+            msg: to_binary(&PythQueryMsg::PriceFeed {
+                id: oracle.symbol.clone(),
+            })?,
+        }))?;
+    let pyth_price = response.price_feed.price;
+
+    if pyth_price.price <= 0 {
+        return Err(StdError::generic_err("Oracle price is not positive"));
+    }
+
+    let price = scale_pyth_value(pyth_price.price as u128, pyth_price.expo, oracle.decimals)?;
+    if price.is_zero() {
+        return Err(StdError::generic_err("Oracle price is zero"));
+    }
+
+    // the width of the confidence interval relative to the price
+    let conf = scale_pyth_value(pyth_price.conf as u128, pyth_price.expo, oracle.decimals)?;
+    let confidence_ratio = conf.checked_mul(oracle.decimals)?.checked_div(price)?;
+    if confidence_ratio > oracle.max_confidence_ratio {
+        return Err(StdError::generic_err("Oracle price confidence is too wide"));
+    }
+
+    Ok(OraclePrice {
+        price,
+        timestamp: pyth_price.publish_time.max(0) as u64,
+    })
+}
+
+/// Converts a value expressed as `value * 10^expo` into the decimals of the feed
+fn scale_pyth_value(value: u128, expo: i32, decimals: Uint128) -> StdResult<Uint128> {
+    let exponent = 10u128
+        .checked_pow(expo.unsigned_abs())
+        .ok_or_else(|| StdError::generic_err("Oracle price exponent is out of range"))?;
+
+    let value = Uint128::from(value).checked_mul(decimals)?;
+    if expo >= 0 {
+        Ok(value.checked_mul(Uint128::from(exponent))?)
+    } else {
+        Ok(value.checked_div(Uint128::from(exponent))?)
+    }
+}
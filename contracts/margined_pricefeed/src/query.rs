@@ -1,14 +1,30 @@
 use cosmwasm_std::{Deps, Env, StdError, StdResult, Uint128};
-use margined_perp::margined_pricefeed::{ConfigResponse, OwnerResponse};
+use margined_perp::margined_pricefeed::{
+    ConfigResponse, OracleResponse, OwnerResponse, PriceHistoryResponse, SubmissionsResponse,
+};
 
 use crate::{
     contract::OWNER,
-    state::{read_price_data, PriceData},
+    oracle::query_oracle_price,
+    state::{
+        read_config, read_latest_price_data, read_oracle_config, read_price_data,
+        read_price_history, read_rounds, read_submissions, PriceData,
+    },
 };
 
+const DEFAULT_PAGINATION_LIMIT: u32 = 10u32;
+const MAX_PAGINATION_LIMIT: u32 = 30u32;
+
 /// Queries contract Config
-pub fn query_config(_deps: Deps) -> StdResult<ConfigResponse> {
-    Ok(ConfigResponse {})
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = read_config(deps.storage)?;
+
+    Ok(ConfigResponse {
+        oracle_hub_contract: config.oracle_hub_contract,
+        retention_period: config.retention_period,
+        quorum: config.quorum,
+        max_deviation: config.max_deviation,
+    })
 }
 
 /// Queries contract owner from the admin
@@ -20,14 +36,34 @@ pub fn query_owner(deps: Deps) -> StdResult<OwnerResponse> {
     }
 }
 
-/// Queries latest price for pair stored with key
-pub fn query_get_price(deps: Deps, key: String) -> StdResult<PriceData> {
-    let prices_response = read_price_data(deps.storage, key);
+/// Queries latest price for pair stored with key, the price is pulled from the
+/// oracle adapter if one is set and falls back to the pushed prices if the
+/// oracle price is unavailable, stale or not confident enough
+pub fn query_get_price(deps: Deps, env: Env, key: String) -> StdResult<Uint128> {
+    if let Some(oracle) = read_oracle_config(deps.storage, key.clone())? {
+        if let Ok(oracle_price) = query_oracle_price(deps, &env, &oracle) {
+            return Ok(oracle_price.price);
+        }
+    }
 
-    let prices = prices_response.unwrap();
-    let price = prices.last().unwrap();
+    let price = read_latest_price_data(deps.storage, &key)?;
 
-    Ok(price.clone())
+    Ok(price.price)
+}
+
+/// Queries the oracle adapter set for the key
+pub fn query_get_oracle(deps: Deps, key: String) -> StdResult<OracleResponse> {
+    let oracle = read_oracle_config(deps.storage, key)?
+        .ok_or_else(|| StdError::generic_err("No oracle set for key"))?;
+
+    Ok(OracleResponse {
+        oracle_type: oracle.oracle_type,
+        contract: oracle.contract,
+        symbol: oracle.symbol,
+        decimals: oracle.decimals,
+        max_staleness: oracle.max_staleness,
+        max_confidence_ratio: oracle.max_confidence_ratio,
+    })
 }
 
 /// Queries previous price for pair stored with key
@@ -36,32 +72,47 @@ pub fn query_get_previous_price(
     key: String,
     num_round_back: Uint128,
 ) -> StdResult<PriceData> {
-    let prices_response = read_price_data(deps.storage, key);
-
-    let prices = prices_response.unwrap();
-    let latest_price = prices.last().unwrap();
+    let rounds = read_rounds(deps.storage, &key)?;
 
-    if num_round_back > latest_price.round_id {
+    if num_round_back > Uint128::from(rounds.latest_round) {
         return Err(StdError::generic_err("Not enough history"));
     }
 
-    let mut previous_prices = prices.clone();
+    let round_id = rounds.latest_round - num_round_back.u128() as u64;
 
-    // obvs not the most efficient way to do this but this is
-    // just a placeholder while we build the twap logic and
-    // do the integration with the rest of the project.
-    let mut i = 0;
-    while i < num_round_back.u128() {
-        previous_prices.pop();
-        i += 1;
+    match read_price_data(deps.storage, &key, round_id)? {
+        Some(price_data) => Ok(price_data),
+        None if round_id == 0u64 => Ok(PriceData::default()),
+        // the round has been pruned
+        None => Err(StdError::generic_err("Not enough history")),
     }
+}
+
+/// Queries the retained price rounds of the key in ascending order
+pub fn query_get_price_history(
+    deps: Deps,
+    key: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PriceHistoryResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PAGINATION_LIMIT)
+        .min(MAX_PAGINATION_LIMIT) as usize;
 
-    let previous_price = previous_prices.last().unwrap();
+    let prices = read_price_history(deps.storage, &key, start_after, limit)?;
 
-    Ok(previous_price.clone())
+    Ok(PriceHistoryResponse { prices })
 }
 
-/// Queries contract Config
+/// Queries the reporter submissions of the pending round of the key
+pub fn query_get_submissions(deps: Deps, key: String) -> StdResult<SubmissionsResponse> {
+    let submissions = read_submissions(deps.storage, &key)?;
+
+    Ok(SubmissionsResponse { submissions })
+}
+
+/// Queries the time weighted average price of the key over the interval, the price of the
+/// oracle adapter is the most recent sample if it is newer than the latest pushed round
 pub fn query_get_twap_price(
     deps: Deps,
     env: Env,
@@ -72,63 +123,89 @@ pub fn query_get_twap_price(
         return Err(StdError::generic_err("Interval can't be zero"));
     }
 
-    let base_timestamp = env.block.time.seconds().checked_sub(interval).unwrap();
-    let prices_response = read_price_data(deps.storage, key);
+    let now = env.block.time.seconds();
+    let mut twap = TwapAccumulator::new(now, now.saturating_sub(interval));
 
-    // get the current data
-    let mut prices = prices_response.unwrap();
-    let mut latest_round = prices.last().unwrap();
-    let mut timestamp = latest_round.timestamp.seconds();
+    let rounds = read_rounds(deps.storage, &key)?;
+    let latest_round = read_latest_price_data(deps.storage, &key)?;
 
-    if latest_round.round_id == Uint128::zero() {
-        return Err(StdError::generic_err("Insufficient history"));
+    if let Some(oracle) = read_oracle_config(deps.storage, key.clone())? {
+        if let Ok(oracle_price) = query_oracle_price(deps, &env, &oracle) {
+            if (latest_round.round_id.is_zero()
+                || oracle_price.timestamp > latest_round.timestamp.seconds())
+                && twap.add(oracle_price.price, oracle_price.timestamp)?
+            {
+                return twap.average();
+            }
+        }
     }
 
-    // if latest updated timestamp is earlier than target timestamp, return the latest price.
-    if latest_round.timestamp.seconds() < base_timestamp
-        || latest_round.round_id == Uint128::from(1u128)
-    {
-        return Ok(latest_round.price);
+    if latest_round.round_id.is_zero() {
+        return twap.average();
     }
 
-    let mut cumulative_time =
-        Uint128::from(env.block.time.seconds().checked_sub(timestamp).unwrap());
-
-    let mut weighted_price = latest_round.price.checked_mul(cumulative_time)?;
-
+    let mut round_id = rounds.latest_round;
     loop {
-        if latest_round.round_id == Uint128::from(1u128) {
-            let twap = weighted_price.checked_div(cumulative_time).unwrap();
-            return Ok(twap);
+        let price_data = read_price_data(deps.storage, &key, round_id)?
+            .ok_or_else(|| StdError::generic_err("Insufficient history"))?;
+
+        // stop once the interval is covered or the earliest retained round is reached
+        if twap.add(price_data.price, price_data.timestamp.seconds())?
+            || round_id == rounds.first_round
+        {
+            return twap.average();
         }
 
-        prices.pop();
-        latest_round = prices.last().unwrap();
-
-        if latest_round.timestamp.seconds() <= base_timestamp {
-            let delta_timestamp = Uint128::from(timestamp.checked_sub(base_timestamp).unwrap());
+        round_id -= 1;
+    }
+}
 
-            weighted_price = weighted_price
-                .checked_add(latest_round.price.checked_mul(delta_timestamp).unwrap())
-                .unwrap();
+/// Weights price samples, from the most recent backwards, by the time each was the
+/// latest price within the interval
+struct TwapAccumulator {
+    base_timestamp: u64,
+    next_timestamp: u64,
+    weighted_price: Uint128,
+    cumulative_time: Uint128,
+    latest_price: Option<Uint128>,
+}
 
-            break;
+impl TwapAccumulator {
+    fn new(now: u64, base_timestamp: u64) -> Self {
+        TwapAccumulator {
+            base_timestamp,
+            next_timestamp: now,
+            weighted_price: Uint128::zero(),
+            cumulative_time: Uint128::zero(),
+            latest_price: None,
         }
+    }
 
-        let delta_timestamp = Uint128::from(
-            timestamp
-                .checked_sub(latest_round.timestamp.seconds())
-                .unwrap(),
+    /// Adds a sample older than those added before, returns whether the interval is covered
+    fn add(&mut self, price: Uint128, timestamp: u64) -> StdResult<bool> {
+        let delta = Uint128::from(
+            self.next_timestamp
+                .saturating_sub(timestamp.max(self.base_timestamp)),
         );
-        weighted_price = weighted_price
-            .checked_add(latest_round.price.checked_mul(delta_timestamp).unwrap())
-            .unwrap();
 
-        cumulative_time = cumulative_time.checked_add(delta_timestamp).unwrap();
-        timestamp = latest_round.timestamp.seconds();
+        self.weighted_price = self.weighted_price.checked_add(price.checked_mul(delta)?)?;
+        self.cumulative_time = self.cumulative_time.checked_add(delta)?;
+        self.latest_price.get_or_insert(price);
+        self.next_timestamp = self.next_timestamp.min(timestamp);
+
+        Ok(timestamp <= self.base_timestamp)
     }
 
-    let twap = weighted_price.checked_div(Uint128::from(interval)).unwrap();
+    fn average(&self) -> StdResult<Uint128> {
+        let latest_price = self
+            .latest_price
+            .ok_or_else(|| StdError::generic_err("Insufficient history"))?;
 
-    Ok(twap)
+        // every sample is from the current block
+        if self.cumulative_time.is_zero() {
+            return Ok(latest_price);
+        }
+
+        Ok(self.weighted_price.checked_div(self.cumulative_time)?)
+    }
 }
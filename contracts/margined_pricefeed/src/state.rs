@@ -1,57 +1,313 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{StdResult, Storage, Timestamp, Uint128};
-use cosmwasm_storage::singleton;
-use cw_storage_plus::Map;
+use cosmwasm_std::{Addr, Order, StdError, StdResult, Storage, Timestamp, Uint128};
+use cosmwasm_storage::{singleton, singleton_read};
+use cw_storage_plus::{Bound, Map};
+use margined_perp::margined_pricefeed::{OracleType, Submission};
+
+use crate::contract::RATIO_DECIMALS;
+
+pub use margined_perp::margined_pricefeed::PriceData;
 
 pub static KEY_CONFIG: &[u8] = b"config";
 
-pub const PRICES: Map<String, Vec<PriceData>> = Map::new("prices");
+/// Maximum number of rounds removed by a single prune
+pub const MAX_PRUNED_ROUNDS: u64 = 10;
+
+pub const PRICES: Map<(&str, u64), PriceData> = Map::new("price-rounds");
+pub const ROUNDS: Map<&str, Rounds> = Map::new("rounds");
+pub const SUBMISSIONS: Map<(&str, &Addr), Submission> = Map::new("submissions");
+/// Storage layout used before prices were stored per round
+pub const LEGACY_PRICES: Map<String, Vec<PriceData>> = Map::new("prices");
+/// Number of legacy entries of a key that are yet to be migrated
+pub const MIGRATION_CURSORS: Map<&str, u64> = Map::new("migration-cursors");
+pub const ORACLES: Map<String, OracleConfig> = Map::new("oracles");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Config {}
+pub struct Config {
+    pub oracle_hub_contract: Addr,
+    #[serde(default)]
+    pub retention_period: Option<u64>, // seconds of price history kept, everything is kept if unset
+    #[serde(default = "default_quorum")]
+    pub quorum: u64, // number of reporter submissions required to aggregate a round
+    #[serde(default = "default_max_deviation")]
+    pub max_deviation: Uint128, // max deviation of a submission from the median
+}
+
+fn default_quorum() -> u64 {
+    1u64
+}
+
+fn default_max_deviation() -> Uint128 {
+    RATIO_DECIMALS
+}
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
     singleton(storage, KEY_CONFIG).save(config)
 }
 
-#[derive(Serialize, Default, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct PriceData {
-    pub round_id: Uint128,
-    pub price: Uint128,
-    pub timestamp: Timestamp,
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    singleton_read(storage, KEY_CONFIG).load()
+}
+
+/// Config layout used before the oracle hub was stored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LegacyConfig {}
+
+/// Transforms a config stored in the legacy layout into the current one,
+/// configs already in the current layout are left untouched
+pub fn migrate_config(
+    storage: &mut dyn Storage,
+    oracle_hub_contract: Option<Addr>,
+) -> StdResult<()> {
+    if read_config(storage).is_ok() {
+        return Ok(());
+    }
+
+    let _legacy: LegacyConfig = singleton_read(storage, KEY_CONFIG).load()?;
+
+    let oracle_hub_contract = oracle_hub_contract.ok_or_else(|| {
+        StdError::generic_err("Oracle hub contract is required to migrate the config")
+    })?;
+
+    store_config(
+        storage,
+        &Config {
+            oracle_hub_contract,
+            retention_period: None,
+            quorum: default_quorum(),
+            max_deviation: default_max_deviation(),
+        },
+    )
+}
+
+/// Moves a batch of prices stored as a single vector per key into one entry
+/// per round and returns the number of legacy entries processed. Rounds are
+/// moved from the latest backwards so the latest price of a key is available
+/// as soon as its first batch is moved, can be called repeatedly until nothing
+/// is left to migrate.
+pub fn migrate_price_data(storage: &mut dyn Storage, limit: usize) -> StdResult<usize> {
+    let mut migrated = 0usize;
+
+    while migrated < limit {
+        let (key, prices) = match LEGACY_PRICES
+            .range(storage, None, None, Order::Ascending)
+            .next()
+        {
+            Some(item) => item?,
+            None => break,
+        };
+
+        // entries below the cursor are yet to be moved
+        let end = match MIGRATION_CURSORS.may_load(storage, &key)? {
+            Some(cursor) => cursor as usize,
+            None => prices.len(),
+        };
+        let start = end.saturating_sub(limit - migrated);
+
+        let mut rounds = match ROUNDS.may_load(storage, &key)? {
+            Some(rounds) => rounds,
+            None => {
+                let latest_round = prices.last().map_or(0u64, |p| p.round_id.u128() as u64);
+                Rounds {
+                    first_round: latest_round + 1,
+                    latest_round,
+                }
+            }
+        };
+
+        // the first entry of the legacy layout is an empty round zero
+        for price_data in prices[start..end]
+            .iter()
+            .rev()
+            .filter(|p| !p.round_id.is_zero())
+        {
+            rounds.first_round = price_data.round_id.u128() as u64;
+            PRICES.save(storage, (&key, rounds.first_round), price_data)?;
+        }
+
+        store_rounds(storage, &key, &rounds)?;
+
+        if start == 0 {
+            MIGRATION_CURSORS.remove(storage, &key);
+            LEGACY_PRICES.remove(storage, key);
+        } else {
+            MIGRATION_CURSORS.save(storage, &key, &(start as u64))?;
+        }
+
+        migrated += end - start;
+    }
+
+    Ok(migrated)
+}
+
+/// Returns whether prices of the key are stored in the legacy layout and
+/// none of them have been moved yet
+pub fn is_pending_migration(storage: &dyn Storage, key: &str) -> bool {
+    !ROUNDS.has(storage, key) && LEGACY_PRICES.has(storage, key.to_string())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct OracleConfig {
+    pub oracle_type: OracleType,
+    pub contract: Addr,
+    pub symbol: String,
+    pub decimals: Uint128,
+    pub max_staleness: u64,
+    pub max_confidence_ratio: Uint128,
+}
+
+pub fn store_oracle_config(
+    storage: &mut dyn Storage,
+    key: String,
+    oracle: &OracleConfig,
+) -> StdResult<()> {
+    ORACLES.save(storage, key, oracle)
+}
+
+pub fn remove_oracle_config(storage: &mut dyn Storage, key: String) {
+    ORACLES.remove(storage, key)
+}
+
+pub fn read_oracle_config(storage: &dyn Storage, key: String) -> StdResult<Option<OracleConfig>> {
+    ORACLES.may_load(storage, key)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Rounds {
+    pub first_round: u64,
+    pub latest_round: u64,
+}
+
+impl Default for Rounds {
+    fn default() -> Rounds {
+        Rounds {
+            first_round: 1u64,
+            latest_round: 0u64,
+        }
+    }
+}
+
+pub fn store_rounds(storage: &mut dyn Storage, key: &str, rounds: &Rounds) -> StdResult<()> {
+    ROUNDS.save(storage, key, rounds)
+}
+
+pub fn read_rounds(storage: &dyn Storage, key: &str) -> StdResult<Rounds> {
+    Ok(ROUNDS.may_load(storage, key)?.unwrap_or_default())
+}
+
+/// Stores the price as the next round of the key and returns its round id
 pub fn store_price_data(
     storage: &mut dyn Storage,
     key: String,
     price: Uint128,
     timestamp: u64,
-) -> StdResult<()> {
-    // load the existing data
-    let mut prices = read_price_data(storage, key.clone()).unwrap();
+) -> StdResult<u64> {
+    let mut rounds = read_rounds(storage, &key)?;
+    rounds.latest_round += 1;
 
     let price_data: PriceData = PriceData {
-        round_id: Uint128::from(prices.len() as u64),
+        round_id: Uint128::from(rounds.latest_round),
         price,
         timestamp: Timestamp::from_seconds(timestamp),
     };
 
-    prices.push(price_data);
+    PRICES.save(storage, (&key, rounds.latest_round), &price_data)?;
+    store_rounds(storage, &key, &rounds)?;
+
+    Ok(rounds.latest_round)
+}
+
+/// Reads the price stored for the round, if it is still retained
+pub fn read_price_data(
+    storage: &dyn Storage,
+    key: &str,
+    round_id: u64,
+) -> StdResult<Option<PriceData>> {
+    PRICES.may_load(storage, (key, round_id))
+}
+
+/// Reads the latest price of the key, or an empty round if none was stored
+pub fn read_latest_price_data(storage: &dyn Storage, key: &str) -> StdResult<PriceData> {
+    let rounds = read_rounds(storage, key)?;
+
+    Ok(read_price_data(storage, key, rounds.latest_round)?.unwrap_or_default())
+}
+
+/// Reads the retained rounds of the key in ascending order
+pub fn read_price_history(
+    storage: &dyn Storage,
+    key: &str,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<PriceData>> {
+    let start = start_after.map(Bound::exclusive);
+
+    PRICES
+        .prefix(key)
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, price_data)| price_data))
+        .collect()
+}
+
+/// Removes rounds older than the retention period, at most `MAX_PRUNED_ROUNDS`
+/// are removed per call so that the cost of appending a price stays constant.
+/// The latest round is always retained.
+pub fn prune_price_data(
+    storage: &mut dyn Storage,
+    key: &str,
+    retention_period: u64,
+) -> StdResult<u64> {
+    let mut rounds = read_rounds(storage, key)?;
+    let latest = read_latest_price_data(storage, key)?;
+    let cutoff = latest.timestamp.seconds().saturating_sub(retention_period);
+
+    let mut pruned = 0u64;
+    while pruned < MAX_PRUNED_ROUNDS && rounds.first_round < rounds.latest_round {
+        match read_price_data(storage, key, rounds.first_round)? {
+            Some(price_data) if price_data.timestamp.seconds() >= cutoff => break,
+            Some(_) => PRICES.remove(storage, (key, rounds.first_round)),
+            None => {}
+        }
+
+        rounds.first_round += 1;
+        pruned += 1;
+    }
+
+    store_rounds(storage, key, &rounds)?;
+
+    Ok(pruned)
+}
+
+/// Stores the submission of the reporter for the pending round of the key,
+/// any previous submission of the reporter is replaced
+pub fn store_submission(
+    storage: &mut dyn Storage,
+    key: &str,
+    submission: &Submission,
+) -> StdResult<()> {
+    SUBMISSIONS.save(storage, (key, &submission.reporter), submission)
+}
 
-    PRICES.save(storage, key, &prices)
+pub fn read_submissions(storage: &dyn Storage, key: &str) -> StdResult<Vec<Submission>> {
+    SUBMISSIONS
+        .prefix(key)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, submission)| submission))
+        .collect()
 }
 
-pub fn read_price_data(storage: &dyn Storage, key: String) -> StdResult<Vec<PriceData>> {
-    let prices = PRICES.may_load(storage, key)?;
-    let mut result = Vec::new();
+pub fn clear_submissions(storage: &mut dyn Storage, key: &str) -> StdResult<()> {
+    let reporters: Vec<Addr> = SUBMISSIONS
+        .prefix(key)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
 
-    if let Some(prices) = prices {
-        result = prices;
-    } else {
-        result.push(PriceData::default());
+    for reporter in reporters {
+        SUBMISSIONS.remove(storage, (key, &reporter));
     }
 
-    Ok(result)
+    Ok(())
 }
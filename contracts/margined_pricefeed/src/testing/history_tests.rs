@@ -0,0 +1,275 @@
+use crate::{
+    contract::{execute, instantiate, migrate, query},
+    state::{PriceData, LEGACY_PRICES},
+};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{from_binary, OwnedDeps, StdError, Timestamp, Uint128};
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, PriceHistoryResponse, QueryMsg,
+};
+
+const KEY: &str = "ETHUSD";
+
+fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps
+}
+
+fn price_data(round_id: u64, price: u128, timestamp: u64) -> PriceData {
+    PriceData {
+        round_id: Uint128::from(round_id),
+        price: Uint128::from(price),
+        timestamp: Timestamp::from_seconds(timestamp),
+    }
+}
+
+fn query_history(
+    deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Vec<PriceData> {
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPriceHistory {
+            key: KEY.to_string(),
+            start_after,
+            limit,
+        },
+    )
+    .unwrap();
+    let history: PriceHistoryResponse = from_binary(&res).unwrap();
+
+    history.prices
+}
+
+#[test]
+fn test_get_price_history() {
+    let mut deps = setup();
+
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: KEY.to_string(),
+        prices: vec![
+            Uint128::from(500u128),
+            Uint128::from(600u128),
+            Uint128::from(700u128),
+            Uint128::from(800u128),
+            Uint128::from(900u128),
+        ],
+        timestamps: vec![1_000, 1_001, 1_002, 1_003, 1_004],
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let prices = query_history(&deps, None, Some(2u32));
+    assert_eq!(
+        prices,
+        vec![price_data(1, 500, 1_000), price_data(2, 600, 1_001)]
+    );
+
+    let prices = query_history(&deps, Some(2u64), None);
+    assert_eq!(
+        prices,
+        vec![
+            price_data(3, 700, 1_002),
+            price_data(4, 800, 1_003),
+            price_data(5, 900, 1_004),
+        ]
+    );
+
+    let prices = query_history(&deps, Some(5u64), None);
+    assert!(prices.is_empty());
+}
+
+#[test]
+fn test_prune_price_history() {
+    let mut deps = setup();
+
+    // only the owner can set the retention period
+    let msg = ExecuteMsg::SetRetentionPeriod {
+        retention_period: Some(100u64),
+    };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+    assert_eq!(err.to_string(), "Caller is not admin");
+
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: KEY.to_string(),
+        prices: vec![
+            Uint128::from(500u128),
+            Uint128::from(600u128),
+            Uint128::from(700u128),
+            Uint128::from(800u128),
+        ],
+        timestamps: vec![1_000, 1_050, 1_100, 1_200],
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // rounds older than the retention period are removed
+    let prices = query_history(&deps, None, None);
+    assert_eq!(
+        prices,
+        vec![price_data(3, 700, 1_100), price_data(4, 800, 1_200)]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPreviousPrice {
+            key: KEY.to_string(),
+            num_round_back: Uint128::from(1u128),
+        },
+    )
+    .unwrap();
+    let price: PriceData = from_binary(&res).unwrap();
+    assert_eq!(price, price_data(3, 700, 1_100));
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPreviousPrice {
+            key: KEY.to_string(),
+            num_round_back: Uint128::from(2u128),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("Not enough history"));
+
+    // the latest round is always retained
+    let msg = ExecuteMsg::AppendPrice {
+        key: KEY.to_string(),
+        price: Uint128::from(900u128),
+        timestamp: 2_000,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let prices = query_history(&deps, None, None);
+    assert_eq!(prices, vec![price_data(5, 900, 2_000)]);
+}
+
+#[test]
+fn test_migrate_legacy_price_data() {
+    let mut deps = setup();
+
+    // store prices with the layout of the previous release
+    let legacy = vec![
+        PriceData::default(),
+        price_data(1, 500, 1_000),
+        price_data(2, 600, 1_001),
+        price_data(3, 550, 1_002),
+        price_data(4, 650, 1_003),
+        price_data(5, 700, 1_004),
+    ];
+    LEGACY_PRICES
+        .save(deps.as_mut().storage, KEY.to_string(), &legacy)
+        .unwrap();
+
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            oracle_hub_contract: None,
+        },
+    )
+    .unwrap();
+
+    // prices cannot be appended before the history is migrated
+    let msg = ExecuteMsg::AppendPrice {
+        key: KEY.to_string(),
+        price: Uint128::from(800u128),
+        timestamp: 1_005,
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Price history of key ETHUSD must be migrated first"
+    );
+
+    // only the owner can migrate
+    let msg = ExecuteMsg::MigratePrices { limit: Some(2) };
+    let info = mock_info("addr0001", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err.to_string(), "Caller is not admin");
+
+    // the latest rounds are moved first
+    let msg = ExecuteMsg::MigratePrices { limit: Some(2) };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.attributes[1].value, "2");
+    assert_eq!(query_history(&deps, None, None), legacy[4..].to_vec());
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPrice {
+            key: KEY.to_string(),
+        },
+    )
+    .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(700u128));
+
+    // new rounds continue from the legacy history while it is migrated
+    let msg = ExecuteMsg::AppendPrice {
+        key: KEY.to_string(),
+        price: Uint128::from(800u128),
+        timestamp: 1_005,
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::MigratePrices { limit: Some(2) };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.attributes[1].value, "2");
+    assert!(LEGACY_PRICES
+        .may_load(deps.as_ref().storage, KEY.to_string())
+        .unwrap()
+        .is_some());
+
+    // the last batch includes the empty round zero
+    let msg = ExecuteMsg::MigratePrices { limit: Some(2) };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.attributes[1].value, "2");
+    assert!(LEGACY_PRICES
+        .may_load(deps.as_ref().storage, KEY.to_string())
+        .unwrap()
+        .is_none());
+
+    // nothing is left to migrate
+    let msg = ExecuteMsg::MigratePrices { limit: Some(2) };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.attributes[1].value, "0");
+
+    let mut expected = legacy[1..].to_vec();
+    expected.push(price_data(6, 800, 1_005));
+    assert_eq!(query_history(&deps, None, None), expected);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPreviousPrice {
+            key: KEY.to_string(),
+            num_round_back: Uint128::from(5u128),
+        },
+    )
+    .unwrap();
+    let price: PriceData = from_binary(&res).unwrap();
+    assert_eq!(price, price_data(1, 500, 1_000));
+}
@@ -1 +1,5 @@
+#[cfg(test)]
+mod history_tests;
+mod oracle_tests;
+mod reporter_tests;
 mod tests;
@@ -0,0 +1,325 @@
+use cosmwasm_std::{Addr, StdError, Uint128};
+use cw_multi_test::Executor;
+use margined_perp::margined_pricefeed::{OracleResponse, OracleType};
+use margined_utils::scenarios::{to_decimals, OracleScenario};
+use mock_oracle::contract::ExecuteMsg as OracleExecuteMsg;
+
+const KEY: &str = "ETH";
+const PYTH_ID: &str = "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace";
+
+#[test]
+fn test_set_and_remove_oracle() {
+    let OracleScenario {
+        mut router,
+        owner,
+        oracle,
+        pricefeed,
+        ..
+    } = OracleScenario::new();
+
+    // only the owner can set an oracle
+    let msg = pricefeed
+        .set_oracle(
+            KEY.to_string(),
+            OracleType::Band,
+            None,
+            KEY.to_string(),
+            9u8,
+            60u64,
+            Uint128::zero(),
+        )
+        .unwrap();
+    let err = router
+        .execute(Addr::unchecked("alice"), msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "Caller is not admin".to_string()
+    );
+
+    // the oracle hub is used when no contract is given
+    router.execute(owner.clone(), msg).unwrap();
+
+    let res = pricefeed.get_oracle(&router, KEY.to_string()).unwrap();
+    assert_eq!(
+        res,
+        OracleResponse {
+            oracle_type: OracleType::Band,
+            contract: oracle,
+            symbol: KEY.to_string(),
+            decimals: Uint128::from(1_000_000_000u128),
+            max_staleness: 60u64,
+            max_confidence_ratio: Uint128::zero(),
+        }
+    );
+
+    let msg = pricefeed.remove_oracle(KEY.to_string()).unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let err = pricefeed.get_oracle(&router, KEY.to_string()).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::GenericErr {
+            msg: "Querier contract error: Generic error: No oracle set for key".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_band_oracle_price() {
+    let OracleScenario {
+        mut router,
+        owner,
+        oracle,
+        vamm,
+        pricefeed,
+    } = OracleScenario::new();
+
+    let now = router.block_info().time.seconds();
+
+    // band rates have 18 decimals
+    let msg = OracleExecuteMsg::SetBandRate {
+        symbol: KEY.to_string(),
+        rate: Uint128::from(1_500_250_000_000_000_000_000u128),
+        last_updated: now,
+    };
+    router
+        .execute_contract(owner.clone(), oracle.clone(), &msg, &[])
+        .unwrap();
+
+    let msg = pricefeed
+        .set_oracle(
+            KEY.to_string(),
+            OracleType::Band,
+            Some(oracle.to_string()),
+            KEY.to_string(),
+            9u8,
+            60u64,
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let price = pricefeed.get_price(&router, KEY.to_string()).unwrap();
+    assert_eq!(price, Uint128::from(1_500_250_000_000u128));
+
+    // the vamm reads the oracle price through the pricefeed
+    let underlying_price = vamm.underlying_price(&router).unwrap();
+    assert_eq!(underlying_price, Uint128::from(1_500_250_000_000u128));
+}
+
+#[test]
+fn test_pyth_oracle_price_and_confidence() {
+    let OracleScenario {
+        mut router,
+        owner,
+        oracle,
+        pricefeed,
+        ..
+    } = OracleScenario::new();
+
+    let now = router.block_info().time.seconds();
+
+    let msg = pricefeed
+        .append_price(KEY.to_string(), to_decimals(1_400u64), now)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // pyth prices are expressed as price * 10^expo
+    let msg = OracleExecuteMsg::SetPythPrice {
+        id: PYTH_ID.to_string(),
+        price: 150_000_000_000i64,
+        conf: 100_000_000u64,
+        expo: -8i32,
+        publish_time: now as i64,
+    };
+    router
+        .execute_contract(owner.clone(), oracle.clone(), &msg, &[])
+        .unwrap();
+
+    let msg = pricefeed
+        .set_oracle(
+            KEY.to_string(),
+            OracleType::Pyth,
+            None,
+            PYTH_ID.to_string(),
+            9u8,
+            60u64,
+            Uint128::from(10_000_000u128), // 0.01
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let price = pricefeed.get_price(&router, KEY.to_string()).unwrap();
+    assert_eq!(price, to_decimals(1_500u64));
+
+    // a confidence interval wider than 1% of the price falls back to the pushed price
+    let msg = OracleExecuteMsg::SetPythPrice {
+        id: PYTH_ID.to_string(),
+        price: 150_000_000_000i64,
+        conf: 3_000_000_000u64,
+        expo: -8i32,
+        publish_time: now as i64,
+    };
+    router
+        .execute_contract(owner.clone(), oracle.clone(), &msg, &[])
+        .unwrap();
+
+    let price = pricefeed.get_price(&router, KEY.to_string()).unwrap();
+    assert_eq!(price, to_decimals(1_400u64));
+
+    // as does a price that is not positive
+    let msg = OracleExecuteMsg::SetPythPrice {
+        id: PYTH_ID.to_string(),
+        price: -150_000_000_000i64,
+        conf: 0u64,
+        expo: -8i32,
+        publish_time: now as i64,
+    };
+    router
+        .execute_contract(owner.clone(), oracle, &msg, &[])
+        .unwrap();
+
+    let price = pricefeed.get_price(&router, KEY.to_string()).unwrap();
+    assert_eq!(price, to_decimals(1_400u64));
+}
+
+#[test]
+fn test_stale_oracle_price_falls_back_to_pushed_price() {
+    let OracleScenario {
+        mut router,
+        owner,
+        oracle,
+        vamm,
+        pricefeed,
+    } = OracleScenario::new();
+
+    let now = router.block_info().time.seconds();
+
+    let msg = pricefeed
+        .append_price(KEY.to_string(), to_decimals(1_400u64), now)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = OracleExecuteMsg::SetBandRate {
+        symbol: KEY.to_string(),
+        rate: Uint128::from(1_500_000_000_000_000_000_000u128),
+        last_updated: now,
+    };
+    router
+        .execute_contract(owner.clone(), oracle, &msg, &[])
+        .unwrap();
+
+    let msg = pricefeed
+        .set_oracle(
+            KEY.to_string(),
+            OracleType::Band,
+            None,
+            KEY.to_string(),
+            9u8,
+            60u64,
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let price = vamm.underlying_price(&router).unwrap();
+    assert_eq!(price, to_decimals(1_500u64));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(61);
+        block.height += 1;
+    });
+
+    let price = vamm.underlying_price(&router).unwrap();
+    assert_eq!(price, to_decimals(1_400u64));
+
+    // an unknown symbol also falls back to the pushed price
+    let msg = pricefeed
+        .set_oracle(
+            KEY.to_string(),
+            OracleType::Band,
+            None,
+            "BTC".to_string(),
+            9u8,
+            60u64,
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let price = vamm.underlying_price(&router).unwrap();
+    assert_eq!(price, to_decimals(1_400u64));
+}
+
+#[test]
+fn test_twap_includes_oracle_price() {
+    let OracleScenario {
+        mut router,
+        owner,
+        oracle,
+        pricefeed,
+        ..
+    } = OracleScenario::new();
+
+    let now = router.block_info().time.seconds();
+
+    let msg = pricefeed
+        .set_oracle(
+            KEY.to_string(),
+            OracleType::Band,
+            Some(oracle.to_string()),
+            KEY.to_string(),
+            9u8,
+            120u64,
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = OracleExecuteMsg::SetBandRate {
+        symbol: KEY.to_string(),
+        rate: Uint128::from(1_500_000_000_000_000_000_000u128),
+        last_updated: now,
+    };
+    router
+        .execute_contract(owner.clone(), oracle.clone(), &msg, &[])
+        .unwrap();
+
+    // a market priced by the adapter alone has a twap
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(30);
+        block.height += 1;
+    });
+
+    let twap = pricefeed
+        .twap_price(&router, KEY.to_string(), 60u64)
+        .unwrap();
+    assert_eq!(twap, to_decimals(1_500u64));
+
+    // the adapter price is weighted from its update after the pushed rounds
+    let msg = pricefeed
+        .append_price(KEY.to_string(), to_decimals(1_400u64), now + 30)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let msg = OracleExecuteMsg::SetBandRate {
+        symbol: KEY.to_string(),
+        rate: Uint128::from(1_600_000_000_000_000_000_000u128),
+        last_updated: now + 60,
+    };
+    router
+        .execute_contract(owner.clone(), oracle, &msg, &[])
+        .unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(60);
+        block.height += 1;
+    });
+
+    // 1400 for 30 seconds then 1600 for 30 seconds
+    let twap = pricefeed
+        .twap_price(&router, KEY.to_string(), 60u64)
+        .unwrap();
+    assert_eq!(twap, to_decimals(1_500u64));
+}
@@ -0,0 +1,272 @@
+use crate::contract::{execute, instantiate, query};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{from_binary, OwnedDeps, Response, Uint128};
+use cw_controllers::HooksResponse;
+use margined_perp::margined_pricefeed::{
+    ExecuteMsg, InstantiateMsg, QueryMsg, Submission, SubmissionsResponse,
+};
+
+const KEY: &str = "ETHUSD";
+const OWNER: &str = "addr0000";
+const REPORTERS: [&str; 3] = ["reporter0000", "reporter0001", "reporter0002"];
+
+fn setup(quorum: u64, max_deviation: u128) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+    for reporter in REPORTERS {
+        let msg = ExecuteMsg::AddReporter {
+            address: reporter.to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::SetAggregation {
+        quorum,
+        max_deviation: Uint128::from(max_deviation),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+    deps
+}
+
+fn submit(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    reporter: &str,
+    price: u128,
+    timestamp: u64,
+) -> Response {
+    let msg = ExecuteMsg::SubmitPrice {
+        key: KEY.to_string(),
+        price: Uint128::from(price),
+        timestamp,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(reporter, &[]), msg).unwrap()
+}
+
+fn query_price(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>) -> Uint128 {
+    let msg = QueryMsg::GetPrice {
+        key: KEY.to_string(),
+    };
+    from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+}
+
+fn query_submissions(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>) -> Vec<Submission> {
+    let msg = QueryMsg::GetSubmissions {
+        key: KEY.to_string(),
+    };
+    let res: SubmissionsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+
+    res.submissions
+}
+
+fn attribute(res: &Response, key: &str) -> Option<String> {
+    res.attributes
+        .iter()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.clone())
+}
+
+#[test]
+fn test_add_remove_reporter() {
+    let mut deps = setup(1u64, 1_000_000u128);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetReporters {}).unwrap();
+    let reporters: HooksResponse = from_binary(&res).unwrap();
+    assert_eq!(reporters.hooks, REPORTERS.to_vec());
+
+    // only the owner can manage reporters
+    let msg = ExecuteMsg::AddReporter {
+        address: "reporter0003".to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("reporter0000", &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Caller is not admin");
+
+    let msg = ExecuteMsg::AddReporter {
+        address: "reporter0000".to_string(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Given address already registered as a hook"
+    );
+
+    let msg = ExecuteMsg::RemoveReporter {
+        address: "reporter0001".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    assert_eq!(
+        attribute(&res, "reporter"),
+        Some("reporter0001".to_string())
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::IsReporter {
+            address: "reporter0001".to_string(),
+        },
+    )
+    .unwrap();
+    let is_reporter: bool = from_binary(&res).unwrap();
+    assert!(!is_reporter);
+
+    // removed reporters cannot submit prices
+    let msg = ExecuteMsg::SubmitPrice {
+        key: KEY.to_string(),
+        price: Uint128::from(100u128),
+        timestamp: 1u64,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("reporter0001", &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Unauthorized");
+}
+
+#[test]
+fn test_set_aggregation() {
+    let mut deps = setup(1u64, 1_000_000u128);
+
+    let msg = ExecuteMsg::SetAggregation {
+        quorum: 0u64,
+        max_deviation: Uint128::from(50_000u128),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+    assert_eq!(err.to_string(), "Generic error: Quorum cannot be zero");
+
+    let msg = ExecuteMsg::SetAggregation {
+        quorum: 2u64,
+        max_deviation: Uint128::from(50_000u128),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("reporter0000", &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Caller is not admin");
+}
+
+#[test]
+fn test_quorum_and_median() {
+    let mut deps = setup(3u64, 1_000_000u128);
+    let now = mock_env().block.time.seconds();
+
+    // the round is pending until the quorum is met
+    let res = submit(&mut deps, "reporter0000", 110u128, now - 20);
+    assert_eq!(attribute(&res, "round_id"), None);
+    let res = submit(&mut deps, "reporter0001", 100u128, now - 15);
+    assert_eq!(attribute(&res, "round_id"), None);
+    assert_eq!(query_submissions(&deps).len(), 2usize);
+    assert_eq!(query_price(&deps), Uint128::zero());
+
+    // a resubmission replaces the previous one of the reporter
+    submit(&mut deps, "reporter0001", 104u128, now - 12);
+    assert_eq!(query_submissions(&deps).len(), 2usize);
+
+    let res = submit(&mut deps, "reporter0002", 90u128, now - 10);
+    assert_eq!(attribute(&res, "round_id"), Some("1".to_string()));
+    assert_eq!(attribute(&res, "aggregated_price"), Some("104".to_string()));
+    assert_eq!(query_price(&deps), Uint128::from(104u128));
+    assert!(query_submissions(&deps).is_empty());
+
+    // the round uses the timestamp of the latest submission
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetTwapPrice {
+            key: KEY.to_string(),
+            interval: 5u64,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(104u128));
+}
+
+#[test]
+fn test_even_quorum_averages_median() {
+    let mut deps = setup(2u64, 1_000_000u128);
+    let now = mock_env().block.time.seconds();
+
+    submit(&mut deps, "reporter0000", 100u128, now - 20);
+    let res = submit(&mut deps, "reporter0001", 110u128, now - 10);
+    assert_eq!(attribute(&res, "aggregated_price"), Some("105".to_string()));
+
+    submit(&mut deps, "reporter0002", 200u128, now - 8);
+    let res = submit(&mut deps, "reporter0000", 220u128, now - 5);
+    assert_eq!(attribute(&res, "round_id"), Some("2".to_string()));
+    assert_eq!(query_price(&deps), Uint128::from(210u128));
+
+    // 105 for 5 seconds, then 210 for 5 seconds
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetTwapPrice {
+            key: KEY.to_string(),
+            interval: 10u64,
+        },
+    )
+    .unwrap();
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(157u128));
+}
+
+#[test]
+fn test_deviation_filter() {
+    // submissions may deviate at most 5% from the median
+    let mut deps = setup(2u64, 50_000u128);
+    let now = mock_env().block.time.seconds();
+
+    // with two submissions 100 and 200 the median is 150, both are outliers
+    submit(&mut deps, "reporter0000", 100u128, now - 20);
+    let res = submit(&mut deps, "reporter0001", 200u128, now - 15);
+    assert_eq!(attribute(&res, "round_id"), None);
+    assert_eq!(query_submissions(&deps).len(), 2usize);
+
+    // the outlier is filtered once a third submission agrees with the first
+    let res = submit(&mut deps, "reporter0002", 102u128, now - 10);
+    assert_eq!(attribute(&res, "round_id"), Some("1".to_string()));
+    assert_eq!(attribute(&res, "aggregated_price"), Some("101".to_string()));
+    assert!(query_submissions(&deps).is_empty());
+}
+
+#[test]
+fn test_removed_reporter_submission_ignored() {
+    let mut deps = setup(2u64, 1_000_000u128);
+    let now = mock_env().block.time.seconds();
+
+    submit(&mut deps, "reporter0000", 100u128, now - 20);
+
+    let msg = ExecuteMsg::RemoveReporter {
+        address: "reporter0000".to_string(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+
+    let res = submit(&mut deps, "reporter0001", 120u128, now - 10);
+    assert_eq!(attribute(&res, "round_id"), None);
+
+    let res = submit(&mut deps, "reporter0002", 130u128, now - 5);
+    assert_eq!(attribute(&res, "aggregated_price"), Some("125".to_string()));
+
+    // the submission of the removed reporter is cleared with the round
+    assert!(query_submissions(&deps).is_empty());
+    assert_eq!(query_price(&deps), Uint128::from(125u128));
+}
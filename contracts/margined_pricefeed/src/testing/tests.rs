@@ -1,14 +1,16 @@
 use crate::{
-    contract::{execute, instantiate, query},
-    state::PriceData,
+    contract::{execute, instantiate, migrate, query},
+    state::{LegacyConfig, PriceData, KEY_CONFIG},
 };
 use cosmwasm_std::{from_binary, Addr, Uint128};
 use cosmwasm_std::{
     testing::{mock_dependencies, mock_env, mock_info},
     Timestamp,
 };
+use cosmwasm_storage::singleton;
+use cw2::{get_contract_version, set_contract_version};
 use margined_perp::margined_pricefeed::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, OwnerResponse, QueryMsg,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, OwnerResponse, QueryMsg,
 };
 
 #[test]
@@ -22,7 +24,15 @@ fn test_instantiation() {
 
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
     let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!(config, ConfigResponse {});
+    assert_eq!(
+        config,
+        ConfigResponse {
+            oracle_hub_contract: Addr::unchecked("oracle_hub0000"),
+            retention_period: None,
+            quorum: 1u64,
+            max_deviation: Uint128::from(1_000_000u128),
+        }
+    );
 }
 
 #[test]
@@ -92,6 +102,18 @@ fn test_set_and_get_price() {
         },
     )
     .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(500_000_000u128));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPreviousPrice {
+            key: "ETHUSD".to_string(),
+            num_round_back: Uint128::zero(),
+        },
+    )
+    .unwrap();
     let price: PriceData = from_binary(&res).unwrap();
     assert_eq!(
         price,
@@ -120,6 +142,18 @@ fn test_set_and_get_price() {
         },
     )
     .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(600_000_000u128));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPreviousPrice {
+            key: "ETHUSD".to_string(),
+            num_round_back: Uint128::zero(),
+        },
+    )
+    .unwrap();
     let price: PriceData = from_binary(&res).unwrap();
     assert_eq!(
         price,
@@ -171,6 +205,18 @@ fn test_set_multiple_price() {
         },
     )
     .unwrap();
+    let price: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(price, Uint128::from(700_000_000u128));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::GetPreviousPrice {
+            key: "ETHUSD".to_string(),
+            num_round_back: Uint128::zero(),
+        },
+    )
+    .unwrap();
     let price: PriceData = from_binary(&res).unwrap();
     assert_eq!(
         price,
@@ -440,6 +486,42 @@ fn test_get_twap_latest_price_update_is_earlier_than_request() {
     assert_eq!(twap, Uint128::from(410_000_000u128));
 }
 
+#[test]
+fn test_get_twap_rounds_of_the_current_block() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let _msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), env.clone(), info, _msg).unwrap();
+
+    // every retained round has the timestamp of the block
+    let msg = ExecuteMsg::AppendMultiplePrice {
+        key: "ETHUSD".to_string(),
+        prices: vec![
+            Uint128::from(400_000_000u128),
+            Uint128::from(405_000_000u128),
+        ],
+        timestamps: vec![env.block.time.seconds(), env.block.time.seconds()],
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::GetTwapPrice {
+            key: "ETHUSD".to_string(),
+            interval: 45,
+        },
+    )
+    .unwrap();
+
+    let twap: Uint128 = from_binary(&res).unwrap();
+    assert_eq!(twap, Uint128::from(405_000_000u128));
+}
+
 #[test]
 fn test_get_twap_no_rounds() {
     let mut deps = mock_dependencies();
@@ -516,3 +598,102 @@ fn test_get_twap_error_zero_interval() {
     );
     assert!(res.is_err());
 }
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+    // migrate from a previous release
+    set_contract_version(
+        deps.as_mut().storage,
+        "crates.io:margined-pricefeed",
+        "0.0.1",
+    )
+    .unwrap();
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            oracle_hub_contract: None,
+        },
+    )
+    .unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(res, config);
+
+    // migrate from a different contract should fail
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-engine", "0.0.1").unwrap();
+    let err = migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            oracle_hub_contract: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a different contract".to_string()
+    );
+}
+
+#[test]
+fn test_migrate_legacy_config() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        oracle_hub_contract: "oracle_hub0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // overwrite the config with the layout of the previous release
+    singleton(deps.as_mut().storage, KEY_CONFIG)
+        .save(&LegacyConfig {})
+        .unwrap();
+
+    // the oracle hub must be provided to migrate the legacy config
+    let err = migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            oracle_hub_contract: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Oracle hub contract is required to migrate the config".to_string()
+    );
+
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            oracle_hub_contract: Some("oracle_hub0001".to_string()),
+        },
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        config,
+        ConfigResponse {
+            oracle_hub_contract: Addr::unchecked("oracle_hub0001"),
+            retention_period: None,
+            quorum: 1u64,
+            max_deviation: Uint128::from(1_000_000u128),
+        }
+    );
+}
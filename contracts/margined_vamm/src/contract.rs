@@ -7,9 +7,12 @@ use cw2::set_contract_version;
 use cw_controllers::Admin;
 use margined_common::{
     integer::Integer,
-    validate::{validate_assets, validate_decimal_places, validate_non_fraction, validate_ratio},
+    validate::{
+        validate_assets, validate_decimal_places, validate_migration, validate_non_fraction,
+        validate_ratio,
+    },
 };
-use margined_perp::margined_vamm::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use margined_perp::margined_vamm::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
 use crate::error::ContractError;
 use crate::querier::{query_underlying_price, query_underlying_twap_price};
@@ -70,6 +73,9 @@ pub fn instantiate(
         spot_price_twap_interval: ONE_HOUR_IN_SECONDS,
         funding_period: msg.funding_period,
         funding_buffer_period: msg.funding_period / 2u64,
+        max_funding_rate: Uint128::zero(),
+        interest_rate: Integer::zero(),
+        clamp_band: Uint128::zero(),
     };
 
     // set and update margin engine
@@ -96,6 +102,7 @@ pub fn instantiate(
         quote_asset_reserve: msg.quote_asset_reserve,
         total_position_size: Integer::zero(),
         funding_rate: Integer::zero(),
+        raw_funding_rate: Integer::zero(),
         next_funding_time: 0u64,
     };
 
@@ -116,7 +123,12 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::UpdateConfig {
             base_asset_holding_cap,
@@ -128,6 +140,9 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             insurance_fund,
             pricefeed,
             spot_price_twap_interval,
+            max_funding_rate,
+            interest_rate,
+            clamp_band,
         } => update_config(
             deps,
             info,
@@ -140,6 +155,9 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             insurance_fund,
             pricefeed,
             spot_price_twap_interval,
+            max_funding_rate,
+            interest_rate,
+            clamp_band,
         ),
         ExecuteMsg::UpdateOwner { owner } => update_owner(deps, info, owner),
         ExecuteMsg::SwapInput {
@@ -218,3 +236,12 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         )?),
     }
 }
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    validate_migration(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::default())
+}
@@ -1,11 +1,51 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{DivideByZeroError, OverflowError, StdError, Uint128};
+use cw_controllers::AdminError;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    DivideByZero(#[from] DivideByZeroError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("sender not margin engine")]
+    NotMarginEngine {},
+
+    #[error("amm is closed")]
+    Closed {},
+
+    #[error("spot_price_twap_interval should be between one minute and one week")]
+    InvalidTwapInterval { interval: u64 },
+
+    #[error("Less than minimum base asset amount limit")]
+    BaseAssetBelowLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("Greater than maximum base asset amount limit")]
+    BaseAssetAboveLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("Less than minimum quote asset amount limit")]
+    QuoteAssetBelowLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("Greater than maximum quote asset amount limit")]
+    QuoteAssetAboveLimit { amount: Uint128, limit: Uint128 },
+
+    #[error("settle funding called too early")]
+    FundingTooEarly { next_funding_time: u64 },
+
+    #[error("price is already over fluctuation limit")]
+    AlreadyOverFluctuationLimit { price: Uint128 },
+
+    #[error("price is over fluctuation limit")]
+    OverFluctuationLimit { price: Uint128 },
 }
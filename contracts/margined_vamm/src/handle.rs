@@ -1,20 +1,22 @@
-use cosmwasm_std::{
-    Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128,
-};
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128};
 
 use margined_common::{integer::Integer, validate::validate_ratio};
-use margined_perp::margined_vamm::Direction;
+use margined_perp::{
+    amm::{calc_input_amount, calc_output_amount},
+    margined_vamm::Direction,
+};
 
 use crate::{
     contract::{
         ONE_DAY_IN_SECONDS, ONE_HOUR_IN_SECONDS, ONE_MINUTE_IN_SECONDS, ONE_WEEK_IN_SECONDS, OWNER,
     },
+    error::ContractError,
     querier::query_underlying_twap_price,
     query::query_twap_price,
     state::{read_config, read_state, store_config, store_state, Config, State},
     utils::{
-        add_reserve_snapshot, check_is_over_block_fluctuation_limit, modulo, require_margin_engine,
-        require_open,
+        add_reserve_snapshot, check_is_over_block_fluctuation_limit, clamp_funding_rate,
+        require_margin_engine, require_open,
     },
 };
 
@@ -31,12 +33,15 @@ pub fn update_config(
     insurance_fund: Option<String>,
     pricefeed: Option<String>,
     spot_price_twap_interval: Option<u64>,
-) -> StdResult<Response> {
+    max_funding_rate: Option<Uint128>,
+    interest_rate: Option<Integer>,
+    clamp_band: Option<Uint128>,
+) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
 
     // check permission
     if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
 
     // change base asset holding cap
@@ -84,28 +89,53 @@ pub fn update_config(
     // change spot price twap interval - check that the twap interval is between 1 min and 1 week
     if let Some(spot_price_twap_interval) = spot_price_twap_interval {
         if !(ONE_MINUTE_IN_SECONDS..=ONE_WEEK_IN_SECONDS).contains(&spot_price_twap_interval) {
-            return Err(StdError::generic_err(
-                "spot_price_twap_interval should be between one minute and one week",
-            ));
+            return Err(ContractError::InvalidTwapInterval {
+                interval: spot_price_twap_interval,
+            });
         }
         config.spot_price_twap_interval = spot_price_twap_interval;
     }
 
+    // change max funding rate per funding period, zero disables the cap
+    if let Some(max_funding_rate) = max_funding_rate {
+        validate_ratio(max_funding_rate, config.decimals)?;
+        config.max_funding_rate = max_funding_rate;
+    }
+
+    // change interest rate per funding period
+    if let Some(interest_rate) = interest_rate {
+        validate_ratio(interest_rate.value, config.decimals)?;
+        config.interest_rate = interest_rate;
+    }
+
+    // change clamp band around the interest rate
+    if let Some(clamp_band) = clamp_band {
+        validate_ratio(clamp_band, config.decimals)?;
+        config.clamp_band = clamp_band;
+    }
+
     store_config(deps.storage, &config)?;
 
     Ok(Response::default().add_attribute("action", "update_config"))
 }
 
-pub fn update_owner(deps: DepsMut, info: MessageInfo, owner: String) -> StdResult<Response> {
+pub fn update_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: String,
+) -> Result<Response, ContractError> {
     // validate the address
     let valid_owner = deps.api.addr_validate(&owner)?;
 
-    OWNER
-        .execute_update_admin(deps, info, Some(valid_owner))
-        .map_err(|error| StdError::generic_err(format!("{}", error)))
+    Ok(OWNER.execute_update_admin(deps, info, Some(valid_owner))?)
 }
 
-pub fn set_open(deps: DepsMut, env: Env, info: MessageInfo, open: bool) -> StdResult<Response> {
+pub fn set_open(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    open: bool,
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let mut state: State = read_state(deps.storage)?;
 
@@ -113,7 +143,7 @@ pub fn set_open(deps: DepsMut, env: Env, info: MessageInfo, open: bool) -> StdRe
     if (!OWNER.is_admin(deps.as_ref(), &info.sender)? && info.sender != config.insurance_fund)
         || state.open == open
     {
-        return Err(StdError::generic_err("unauthorized"));
+        return Err(ContractError::Unauthorized {});
     }
 
     state.open = open;
@@ -138,7 +168,7 @@ pub fn swap_input(
     quote_asset_amount: Uint128,
     base_asset_limit: Uint128,
     can_go_over_fluctuation: bool,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let state: State = read_state(deps.storage)?;
     let config: Config = read_config(deps.storage)?;
 
@@ -159,14 +189,16 @@ pub fn swap_input(
         // In RemoveFromAmm case, more position means more debt so should not be larger than base_asset_limit
         if !base_asset_limit.is_zero() {
             if direction == Direction::AddToAmm && base_asset_amount < base_asset_limit {
-                return Err(StdError::generic_err(
-                    "Less than minimum base asset amount limit",
-                ));
+                return Err(ContractError::BaseAssetBelowLimit {
+                    amount: base_asset_amount,
+                    limit: base_asset_limit,
+                });
             } else if direction == Direction::RemoveFromAmm && base_asset_amount > base_asset_limit
             {
-                return Err(StdError::generic_err(
-                    "Greater than maximum base asset amount limit",
-                ));
+                return Err(ContractError::BaseAssetAboveLimit {
+                    amount: base_asset_amount,
+                    limit: base_asset_limit,
+                });
             }
         }
 
@@ -201,7 +233,7 @@ pub fn swap_output(
     direction: Direction,
     base_asset_amount: Uint128,
     quote_asset_limit: Uint128,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let state: State = read_state(deps.storage)?;
     let config: Config = read_config(deps.storage)?;
 
@@ -230,15 +262,17 @@ pub fn swap_output(
             if update_direction == Direction::RemoveFromAmm
                 && quote_asset_amount < quote_asset_limit
             {
-                return Err(StdError::generic_err(
-                    "Less than minimum quote asset amount limit",
-                ));
+                return Err(ContractError::QuoteAssetBelowLimit {
+                    amount: quote_asset_amount,
+                    limit: quote_asset_limit,
+                });
             } else if update_direction == Direction::AddToAmm
                 && quote_asset_amount > quote_asset_limit
             {
-                return Err(StdError::generic_err(
-                    "Greater than maximum quote asset amount limit",
-                ));
+                return Err(ContractError::QuoteAssetAboveLimit {
+                    amount: quote_asset_amount,
+                    limit: quote_asset_limit,
+                });
             }
         }
 
@@ -265,15 +299,21 @@ pub fn swap_output(
     ]))
 }
 
-pub fn settle_funding(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+pub fn settle_funding(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let mut state: State = read_state(deps.storage)?;
 
     require_open(state.open)?;
-    require_margin_engine(info.sender, config.margin_engine)?;
+    require_margin_engine(info.sender, config.margin_engine.clone())?;
 
     if env.block.time.seconds() < state.next_funding_time {
-        return Err(StdError::generic_err("settle funding called too early"));
+        return Err(ContractError::FundingTooEarly {
+            next_funding_time: state.next_funding_time,
+        });
     }
 
     // twap price from oracle
@@ -291,11 +331,25 @@ pub fn settle_funding(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<R
         .checked_mul(Integer::new_positive(config.funding_period))?
         .checked_div(Integer::new_positive(ONE_DAY_IN_SECONDS))?;
 
-    // update funding rate = premiumFraction / twapIndexPrice
-    state.funding_rate = premium_fraction
+    // raw funding rate = premiumFraction / twapIndexPrice
+    let raw_funding_rate = premium_fraction
         .checked_mul(Integer::new_positive(config.decimals))?
         .checked_div(Integer::new_positive(underlying_price))?;
 
+    // the funding rate is the clamped raw funding rate, the premium fraction
+    // paid by the engine follows the clamped rate
+    let funding_rate = clamp_funding_rate(&config, raw_funding_rate)?;
+    let clamped_premium_fraction = if funding_rate == raw_funding_rate {
+        premium_fraction
+    } else {
+        funding_rate
+            .checked_mul(Integer::new_positive(underlying_price))?
+            .checked_div(Integer::new_positive(config.decimals))?
+    };
+
+    state.funding_rate = funding_rate;
+    state.raw_funding_rate = raw_funding_rate;
+
     // in order to prevent multiple funding settlement during very short time after network congestion
     let min_next_funding_time = env.block.time.plus_seconds(config.funding_buffer_period);
 
@@ -315,7 +369,10 @@ pub fn settle_funding(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<R
 
     Ok(Response::new().add_attributes(vec![
         ("action", "settle_funding"),
-        ("premium_fraction", &premium_fraction.to_string()),
+        ("premium_fraction", &clamped_premium_fraction.to_string()),
+        ("raw_premium_fraction", &premium_fraction.to_string()),
+        ("funding_rate", &funding_rate.to_string()),
+        ("raw_funding_rate", &raw_funding_rate.to_string()),
         ("underlying_price", &underlying_price.to_string()),
         ("index_price", &index_price.to_string()),
     ]))
@@ -330,40 +387,13 @@ pub fn get_input_price_with_reserves(
 ) -> StdResult<Uint128> {
     let config: Config = read_config(deps.storage)?;
 
-    if quote_asset_amount == Uint128::zero() {
-        return Ok(Uint128::zero());
-    }
-
-    // k = x * y (divided by decimal places)
-    let invariant_k = quote_asset_reserve
-        .checked_mul(base_asset_reserve)?
-        .checked_div(config.decimals)?;
-
-    let quote_asset_after: Uint128 = match direction {
-        Direction::AddToAmm => quote_asset_reserve.checked_add(quote_asset_amount)?,
-        Direction::RemoveFromAmm => quote_asset_reserve.checked_sub(quote_asset_amount)?,
-    };
-
-    let base_asset_after: Uint128 = invariant_k
-        .checked_mul(config.decimals)?
-        .checked_div(quote_asset_after)?;
-
-    let mut base_asset_bought = if base_asset_after > base_asset_reserve {
-        base_asset_after - base_asset_reserve
-    } else {
-        base_asset_reserve - base_asset_after
-    };
-
-    let remainder = modulo(invariant_k, quote_asset_after, config.decimals);
-    if remainder != Uint128::zero() {
-        if *direction == Direction::AddToAmm {
-            base_asset_bought = base_asset_bought.checked_sub(Uint128::new(1u128))?;
-        } else {
-            base_asset_bought = base_asset_bought.checked_add(Uint128::from(1u128))?;
-        }
-    }
-
-    Ok(base_asset_bought)
+    calc_input_amount(
+        direction,
+        quote_asset_amount,
+        quote_asset_reserve,
+        base_asset_reserve,
+        config.decimals,
+    )
 }
 
 pub fn get_output_price_with_reserves(
@@ -375,38 +405,13 @@ pub fn get_output_price_with_reserves(
 ) -> StdResult<Uint128> {
     let config: Config = read_config(deps.storage)?;
 
-    if base_asset_amount == Uint128::zero() {
-        return Ok(Uint128::zero());
-    }
-
-    let invariant_k = quote_asset_reserve
-        .checked_mul(base_asset_reserve)?
-        .checked_div(config.decimals)?;
-
-    let base_asset_after: Uint128 = match direction {
-        Direction::AddToAmm => base_asset_reserve.checked_add(base_asset_amount)?,
-        Direction::RemoveFromAmm => base_asset_reserve.checked_sub(base_asset_amount)?,
-    };
-
-    let quote_asset_after: Uint128 = invariant_k
-        .checked_mul(config.decimals)?
-        .checked_div(base_asset_after)?;
-
-    let mut quote_asset_sold = if quote_asset_after > quote_asset_reserve {
-        quote_asset_after - quote_asset_reserve
-    } else {
-        quote_asset_reserve - quote_asset_after
-    };
-
-    let remainder = modulo(invariant_k, base_asset_after, config.decimals);
-    if remainder != Uint128::zero() {
-        if *direction == Direction::AddToAmm {
-            quote_asset_sold = quote_asset_sold.checked_sub(Uint128::from(1u128))?;
-        } else {
-            quote_asset_sold = quote_asset_sold.checked_add(Uint128::new(1u128))?;
-        }
-    }
-    Ok(quote_asset_sold)
+    calc_output_amount(
+        direction,
+        base_asset_amount,
+        quote_asset_reserve,
+        base_asset_reserve,
+        config.decimals,
+    )
 }
 
 pub fn update_reserve(
@@ -416,7 +421,7 @@ pub fn update_reserve(
     quote_asset_amount: Uint128,
     base_asset_amount: Uint128,
     can_go_over_fluctuation: bool,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let mut state: State = read_state(storage)?;
 
     check_is_over_block_fluctuation_limit(
@@ -1,5 +1,5 @@
 pub mod contract;
-mod error;
+pub mod error;
 mod handle;
 mod querier;
 mod query;
@@ -35,6 +35,9 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         pricefeed: config.pricefeed,
         funding_period: config.funding_period,
         spot_price_twap_interval: config.spot_price_twap_interval,
+        max_funding_rate: config.max_funding_rate,
+        interest_rate: config.interest_rate,
+        clamp_band: config.clamp_band,
     })
 }
 
@@ -48,6 +51,7 @@ pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
         base_asset_reserve: state.base_asset_reserve,
         total_position_size: state.total_position_size,
         funding_rate: state.funding_rate,
+        raw_funding_rate: state.raw_funding_rate,
         next_funding_time: state.next_funding_time,
     })
 }
@@ -27,6 +27,12 @@ pub struct Config {
     pub spot_price_twap_interval: u64,
     pub funding_period: u64,
     pub funding_buffer_period: u64,
+    #[serde(default)]
+    pub max_funding_rate: Uint128,
+    #[serde(default)]
+    pub interest_rate: Integer,
+    #[serde(default)]
+    pub clamp_band: Uint128,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -44,6 +50,8 @@ pub struct State {
     pub base_asset_reserve: Uint128,
     pub total_position_size: Integer,
     pub funding_rate: Integer,
+    #[serde(default)]
+    pub raw_funding_rate: Integer,
     pub next_funding_time: u64,
 }
 
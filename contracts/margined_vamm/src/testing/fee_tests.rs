@@ -1,4 +1,5 @@
-use cosmwasm_std::{StdError, Uint128};
+use crate::error::ContractError;
+use cosmwasm_std::Uint128;
 use cw_multi_test::Executor;
 use margined_perp::margined_vamm::CalcFeeResponse;
 use margined_utils::scenarios::{to_decimals, SimpleScenario};
@@ -137,9 +138,7 @@ fn test_update_not_owner() {
         .unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "unauthorized".to_string(),
-        },
-        err.downcast().unwrap()
+        err.source().unwrap().to_string(),
+        ContractError::Unauthorized {}.to_string()
     );
 }
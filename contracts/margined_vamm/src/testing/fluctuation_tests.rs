@@ -1,4 +1,5 @@
 use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
 use cosmwasm_std::testing::{
     mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
 };
@@ -193,10 +194,7 @@ fn test_force_error_swap_input_price_down_over_limit() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is over fluctuation limit"
-    );
+    assert!(matches!(result, ContractError::OverFluctuationLimit { .. }));
 }
 
 #[test]
@@ -225,10 +223,10 @@ fn test_force_error_swap_input_can_go_over_limit_but_fails_second_time() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is already over fluctuation limit"
-    );
+    assert!(matches!(
+        result,
+        ContractError::AlreadyOverFluctuationLimit { .. }
+    ));
 }
 
 #[test]
@@ -257,10 +255,10 @@ fn test_force_error_swap_input_short_can_go_over_limit_but_fails_second_time() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is already over fluctuation limit"
-    );
+    assert!(matches!(
+        result,
+        ContractError::AlreadyOverFluctuationLimit { .. }
+    ));
 }
 
 #[test]
@@ -286,10 +284,10 @@ fn test_force_error_swap_output_can_go_over_limit_but_fails_second_time() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env, info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is already over fluctuation limit"
-    );
+    assert!(matches!(
+        result,
+        ContractError::AlreadyOverFluctuationLimit { .. }
+    ));
 }
 
 #[test]
@@ -315,10 +313,10 @@ fn test_force_error_swap_output_short_can_go_over_limit_but_fails_second_time()
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env, info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is already over fluctuation limit"
-    );
+    assert!(matches!(
+        result,
+        ContractError::AlreadyOverFluctuationLimit { .. }
+    ));
 }
 
 #[test]
@@ -344,10 +342,10 @@ fn test_force_error_swap_output_short_can_go_over_limit_but_fails_larger_price()
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is already over fluctuation limit"
-    );
+    assert!(matches!(
+        result,
+        ContractError::AlreadyOverFluctuationLimit { .. }
+    ));
 }
 
 #[test]
@@ -421,10 +419,7 @@ fn test_force_error_compare_price_fluctuation_with_previous_blocks() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is over fluctuation limit"
-    );
+    assert!(matches!(result, ContractError::OverFluctuationLimit { .. }));
 
     // BUY 30, reserve will be 1040 : 96.15, price is 1040 / 96.15 = 10.82
     let swap_msg = ExecuteMsg::SwapInput {
@@ -436,10 +431,7 @@ fn test_force_error_compare_price_fluctuation_with_previous_blocks() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env.clone(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is over fluctuation limit"
-    );
+    assert!(matches!(result, ContractError::OverFluctuationLimit { .. }));
 
     // should revert as well if BUY 30 separately
     let swap_msg = ExecuteMsg::SwapInput {
@@ -462,10 +454,7 @@ fn test_force_error_compare_price_fluctuation_with_previous_blocks() {
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env, info, swap_msg).unwrap_err();
 
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is over fluctuation limit"
-    );
+    assert!(matches!(result, ContractError::OverFluctuationLimit { .. }));
 }
 
 #[test]
@@ -499,10 +488,7 @@ fn test_force_error_value_of_fluctuation_is_same_even_no_trading_for_multiple_bl
     let info = mock_info("addr0000", &[]);
     let result = execute(app.deps.as_mut(), app.env, info, swap_msg).unwrap_err();
 
-    assert_eq!(
-        result.to_string(),
-        "Generic error: price is over fluctuation limit"
-    );
+    assert!(matches!(result, ContractError::OverFluctuationLimit { .. }));
 }
 
 #[test]
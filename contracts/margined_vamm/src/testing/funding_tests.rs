@@ -1,5 +1,7 @@
+use crate::error::ContractError;
 use cosmwasm_std::{StdError, Uint128};
-use cw_multi_test::Executor;
+use cw_multi_test::{AppResponse, Executor};
+use margined_common::integer::Integer;
 use margined_utils::scenarios::VammScenario;
 
 #[test]
@@ -97,10 +99,8 @@ fn test_force_error_caller_is_not_couterparty_or_owner() {
     let msg = vamm.settle_funding().unwrap();
     let err = router.execute(alice.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "sender not margin engine".to_string(),
-        },
-        err.downcast().unwrap()
+        err.source().unwrap().to_string(),
+        ContractError::NotMarginEngine {}.to_string()
     );
 }
 
@@ -131,9 +131,191 @@ fn test_cant_settle_funding_multiple_times_at_once_even_settle_funding_delay() {
     let msg = vamm.settle_funding().unwrap();
     let err = router.execute(owner.clone(), msg).unwrap_err();
     assert_eq!(
-        StdError::GenericErr {
-            msg: "settle funding called too early".to_string(),
-        },
-        err.downcast().unwrap()
+        err.source().unwrap().to_string(),
+        ContractError::FundingTooEarly {
+            next_funding_time: 1571801019,
+        }
+        .to_string()
     );
 }
+
+fn settle_funding_attribute(res: &AppResponse, key: &str) -> String {
+    res.events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == key)
+        .unwrap()
+        .value
+        .clone()
+}
+
+#[test]
+fn test_funding_rate_capped_at_max_funding_rate() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        pricefeed,
+        ..
+    } = VammScenario::new();
+
+    // index price is 0.5 while the vamm trades at 10
+    let price: Uint128 = Uint128::from(500_000_000u128);
+    let timestamp: u64 = 1_000_000_000;
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), price, timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // max funding rate of 1% per funding period
+    let msg = vamm
+        .set_funding_params(
+            Uint128::from(10_000_000u128),
+            Integer::zero(),
+            Uint128::zero(),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600u64);
+        block.height += 1;
+    });
+
+    let msg = vamm.settle_funding().unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+
+    // premium of 9.5 for 1/24th of a day relative to 0.5
+    assert_eq!(
+        settle_funding_attribute(&res, "raw_funding_rate"),
+        "791666666"
+    );
+    assert_eq!(
+        settle_funding_attribute(&res, "raw_premium_fraction"),
+        "395833333"
+    );
+    assert_eq!(settle_funding_attribute(&res, "funding_rate"), "10000000");
+    assert_eq!(
+        settle_funding_attribute(&res, "premium_fraction"),
+        "5000000"
+    );
+
+    let state = vamm.state(&router).unwrap();
+    assert_eq!(state.funding_rate, Integer::new_positive(10_000_000u128));
+    assert_eq!(
+        state.raw_funding_rate,
+        Integer::new_positive(791_666_666u128)
+    );
+}
+
+#[test]
+fn test_interest_rate_within_clamp_band() {
+    let VammScenario {
+        mut router,
+        owner,
+        vamm,
+        pricefeed,
+        ..
+    } = VammScenario::new();
+
+    // index price equals the vamm price, there is no premium
+    let price: Uint128 = Uint128::from(10_000_000_000u128);
+    let timestamp: u64 = 1_000_000_000;
+
+    let msg = pricefeed
+        .append_price("ETH".to_string(), price, timestamp)
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    // interest of 0.01% per funding period within a 0.05% band
+    let msg = vamm
+        .set_funding_params(
+            Uint128::zero(),
+            Integer::new_positive(100_000u128),
+            Uint128::from(500_000u128),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600u64);
+        block.height += 1;
+    });
+
+    let msg = vamm.settle_funding().unwrap();
+    let res = router.execute(owner.clone(), msg).unwrap();
+
+    assert_eq!(settle_funding_attribute(&res, "raw_funding_rate"), "0");
+    assert_eq!(settle_funding_attribute(&res, "funding_rate"), "100000");
+    assert_eq!(
+        settle_funding_attribute(&res, "premium_fraction"),
+        "1000000"
+    );
+
+    // the interest component is limited by the clamp band
+    let msg = vamm
+        .set_funding_params(
+            Uint128::zero(),
+            Integer::new_positive(100_000u128),
+            Uint128::from(50_000u128),
+        )
+        .unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(3_600u64);
+        block.height += 1;
+    });
+
+    let msg = vamm.settle_funding().unwrap();
+    router.execute(owner.clone(), msg).unwrap();
+
+    let state = vamm.state(&router).unwrap();
+    assert_eq!(state.funding_rate, Integer::new_positive(50_000u128));
+    assert_eq!(state.raw_funding_rate, Integer::zero());
+}
+
+#[test]
+fn test_set_funding_params_validates_ratios() {
+    let VammScenario {
+        mut router,
+        owner,
+        alice,
+        vamm,
+        ..
+    } = VammScenario::new();
+
+    let msg = vamm
+        .set_funding_params(
+            Uint128::from(1_000_000_001u128),
+            Integer::zero(),
+            Uint128::zero(),
+        )
+        .unwrap();
+    let err = router.execute(owner.clone(), msg).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::Std(StdError::generic_err("Invalid ratio")).to_string()
+    );
+
+    let msg = vamm
+        .set_funding_params(
+            Uint128::from(10_000_000u128),
+            Integer::new_negative(100_000u128),
+            Uint128::from(500_000u128),
+        )
+        .unwrap();
+    let err = router.execute(alice.clone(), msg.clone()).unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::Unauthorized {}.to_string()
+    );
+
+    router.execute(owner.clone(), msg).unwrap();
+
+    let config = vamm.config(&router).unwrap();
+    assert_eq!(config.max_funding_rate, Uint128::from(10_000_000u128));
+    assert_eq!(config.interest_rate, Integer::new_negative(100_000u128));
+    assert_eq!(config.clamp_band, Uint128::from(500_000u128));
+}
@@ -0,0 +1,50 @@
+use crate::contract::{instantiate, migrate, query};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::Uint128;
+use cw2::{get_contract_version, set_contract_version};
+use margined_perp::margined_vamm::{InstantiateMsg, MigrateMsg, QueryMsg};
+use margined_utils::scenarios::to_decimals;
+
+#[test]
+fn test_migrate() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        decimals: 9u8,
+        quote_asset: "ETH".to_string(),
+        base_asset: "USD".to_string(),
+        quote_asset_reserve: to_decimals(100),
+        base_asset_reserve: to_decimals(10_000),
+        funding_period: 3_600_u64,
+        toll_ratio: Uint128::zero(),
+        spread_ratio: Uint128::zero(),
+        fluctuation_limit_ratio: Uint128::zero(),
+        margin_engine: Some("addr0000".to_string()),
+        insurance_fund: Some("insurance_fund".to_string()),
+        pricefeed: "oracle".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let state = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+
+    // migrate from a previous release
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-vamm", "0.0.1").unwrap();
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION").to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    assert_eq!(res, config);
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap();
+    assert_eq!(res, state);
+
+    // downgrading the contract should fail
+    set_contract_version(deps.as_mut().storage, "crates.io:margined-vamm", "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot migrate from a newer contract version".to_string()
+    );
+}
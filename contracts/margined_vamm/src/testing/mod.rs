@@ -3,6 +3,7 @@ mod fee_tests;
 mod fluctuation_tests;
 mod funding_tests;
 mod get_price_tests;
+mod migrate_tests;
 mod set_open_tests;
 mod spread_limit_tests;
 mod swap_input_output_tests;
@@ -1,4 +1,5 @@
 use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{from_binary, Uint128};
 use margined_perp::margined_vamm::{
@@ -151,10 +152,7 @@ fn test_set_open_cant_do_anything_when_its_beginning() {
     let msg = ExecuteMsg::SettleFunding {};
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
+    assert_eq!(result, ContractError::Closed {});
 
     let msg = ExecuteMsg::SwapInput {
         direction: Direction::AddToAmm,
@@ -164,10 +162,7 @@ fn test_set_open_cant_do_anything_when_its_beginning() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
+    assert_eq!(result, ContractError::Closed {});
 
     let msg = ExecuteMsg::SwapOutput {
         direction: Direction::AddToAmm,
@@ -176,10 +171,7 @@ fn test_set_open_cant_do_anything_when_its_beginning() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
+    assert_eq!(result, ContractError::Closed {});
 }
 
 #[test]
@@ -214,10 +206,7 @@ fn test_set_open_cant_do_anything_when_closed() {
     let msg = ExecuteMsg::SettleFunding {};
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
+    assert_eq!(result, ContractError::Closed {});
 
     let msg = ExecuteMsg::SwapInput {
         direction: Direction::AddToAmm,
@@ -227,10 +216,7 @@ fn test_set_open_cant_do_anything_when_closed() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
+    assert_eq!(result, ContractError::Closed {});
 
     let msg = ExecuteMsg::SwapOutput {
         direction: Direction::AddToAmm,
@@ -239,8 +225,5 @@ fn test_set_open_cant_do_anything_when_closed() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: amm is closed".to_string()
-    );
+    assert_eq!(result, ContractError::Closed {});
 }
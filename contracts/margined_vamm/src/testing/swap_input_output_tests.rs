@@ -386,6 +386,7 @@ fn test_swap_input_twice_short_long() {
             base_asset_reserve: Uint128::from(100_000_000_001u128),
             total_position_size: Integer::new_negative(1u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -452,6 +453,7 @@ fn test_swap_input_twice_long_short() {
             base_asset_reserve: Uint128::from(100_000_000_001u128),
             total_position_size: Integer::new_negative(1u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -516,6 +518,7 @@ fn test_swap_output_twice_short_long() {
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::default(),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -580,6 +583,7 @@ fn test_swap_output_twice_long_short() {
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::default(),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
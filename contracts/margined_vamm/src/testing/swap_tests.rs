@@ -1,6 +1,7 @@
 use crate::contract::{
     execute, instantiate, query, ONE_HOUR_IN_SECONDS, ONE_MINUTE_IN_SECONDS, ONE_WEEK_IN_SECONDS,
 };
+use crate::error::ContractError;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{from_binary, Addr, Uint128};
 use margined_common::integer::Integer;
@@ -47,6 +48,9 @@ fn test_instantiation() {
             pricefeed: Addr::unchecked("oracle".to_string()),
             funding_period: 3_600u64,
             spot_price_twap_interval: ONE_HOUR_IN_SECONDS,
+            max_funding_rate: Uint128::zero(),
+            interest_rate: Integer::zero(),
+            clamp_band: Uint128::zero(),
         }
     );
 
@@ -60,6 +64,7 @@ fn test_instantiation() {
             base_asset_reserve: Uint128::from(10_000_000_000_000u128),
             total_position_size: Integer::default(),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 0u64,
         }
     );
@@ -256,15 +261,15 @@ fn test_bad_twap_interval() {
         insurance_fund: None,
         pricefeed: None,
         spot_price_twap_interval: Some(59u64),
+        max_funding_rate: None,
+        interest_rate: None,
+        clamp_band: None,
     };
 
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
 
-    assert_eq!(
-        res.to_string(),
-        "Generic error: spot_price_twap_interval should be between one minute and one week"
-    );
+    assert_eq!(res, ContractError::InvalidTwapInterval { interval: 59 });
 
     // Update the config with twap_price above range
     let msg = ExecuteMsg::UpdateConfig {
@@ -277,14 +282,17 @@ fn test_bad_twap_interval() {
         insurance_fund: None,
         pricefeed: None,
         spot_price_twap_interval: Some(ONE_WEEK_IN_SECONDS + 1),
+        max_funding_rate: None,
+        interest_rate: None,
+        clamp_band: None,
     };
 
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
 
     assert_eq!(
-        res.to_string(),
-        "Generic error: spot_price_twap_interval should be between one minute and one week"
+        res,
+        ContractError::InvalidTwapInterval { interval: 604_801 }
     );
 }
 
@@ -319,6 +327,9 @@ fn test_update_config() {
         insurance_fund: Some("new_insurance_fund".to_string()),
         pricefeed: None,
         spot_price_twap_interval: Some(ONE_MINUTE_IN_SECONDS),
+        max_funding_rate: None,
+        interest_rate: None,
+        clamp_band: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -342,6 +353,9 @@ fn test_update_config() {
             pricefeed: Addr::unchecked("oracle".to_string()),
             funding_period: 3_600u64,
             spot_price_twap_interval: ONE_MINUTE_IN_SECONDS,
+            max_funding_rate: Uint128::zero(),
+            interest_rate: Integer::zero(),
+            clamp_band: Uint128::zero(),
         }
     );
 }
@@ -377,6 +391,9 @@ fn test_update_config_fail() {
         insurance_fund: None,
         pricefeed: None,
         spot_price_twap_interval: None,
+        max_funding_rate: None,
+        interest_rate: None,
+        clamp_band: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -437,6 +454,7 @@ fn test_swap_input_zero_amount() {
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::zero(),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -491,6 +509,7 @@ fn test_swap_output_zero_amount() {
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::zero(),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -546,6 +565,7 @@ fn test_swap_input_long() {
             base_asset_reserve: Uint128::from(62_500_000_000u128),
             total_position_size: Integer::new_positive(37_500_000_000u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -601,6 +621,7 @@ fn test_swap_input_short() {
             base_asset_reserve: to_decimals(250),
             total_position_size: Integer::new_negative(to_decimals(150)),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -655,6 +676,7 @@ fn test_swap_output_short() {
             base_asset_reserve: to_decimals(250),
             total_position_size: Integer::new_negative(to_decimals(150)),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -709,6 +731,7 @@ fn test_swap_output_long() {
             base_asset_reserve: to_decimals(50),
             total_position_size: Integer::new_positive(to_decimals(50)),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -765,6 +788,7 @@ fn test_swap_input_short_long() {
             base_asset_reserve: Uint128::from(192_307_692_308u128),
             total_position_size: Integer::new_negative(92_307_692_308u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -789,6 +813,7 @@ fn test_swap_input_short_long() {
             base_asset_reserve: Uint128::from(67_567_567_568u128),
             total_position_size: Integer::new_positive(32_432_432_432u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -844,6 +869,7 @@ fn test_swap_input_short_long_long() {
             base_asset_reserve: to_decimals(125),
             total_position_size: Integer::new_negative(25_000_000_000u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -868,6 +894,7 @@ fn test_swap_input_short_long_long() {
             base_asset_reserve: Uint128::from(111_111_111_112u128),
             total_position_size: Integer::new_negative(11_111_111_112u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -892,6 +919,7 @@ fn test_swap_input_short_long_long() {
             base_asset_reserve: Uint128::from(90_909_090_910u128),
             total_position_size: Integer::new_positive(90_909_090_90u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -947,6 +975,7 @@ fn test_swap_input_short_long_short() {
             base_asset_reserve: to_decimals(125),
             total_position_size: Integer::new_negative(25_000_000_000u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -971,6 +1000,7 @@ fn test_swap_input_short_long_short() {
             base_asset_reserve: to_decimals(80),
             total_position_size: Integer::new_positive(20_000_000_000u128),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -995,6 +1025,7 @@ fn test_swap_input_short_long_short() {
             base_asset_reserve: to_decimals(100),
             total_position_size: Integer::default(),
             funding_rate: Integer::zero(),
+            raw_funding_rate: Integer::zero(),
             next_funding_time: 1_571_801_019u64,
         }
     );
@@ -1233,10 +1264,7 @@ fn test_force_error_swapinput_long_but_less_than_min_base_amount() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: Less than minimum base asset amount limit"
-    );
+    assert!(matches!(result, ContractError::BaseAssetBelowLimit { .. }));
 }
 
 #[test]
@@ -1279,10 +1307,7 @@ fn test_force_error_swapinput_short_but_more_than_min_base_amount() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: Greater than maximum base asset amount limit"
-    );
+    assert!(matches!(result, ContractError::BaseAssetAboveLimit { .. }));
 }
 
 #[test]
@@ -1410,10 +1435,7 @@ fn test_swapoutput_short_force_error_min_quote_251() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: Less than minimum quote asset amount limit"
-    );
+    assert!(matches!(result, ContractError::QuoteAssetBelowLimit { .. }));
 }
 
 #[test]
@@ -1453,10 +1475,7 @@ fn test_swapoutput_short_force_error_min_quote_400() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: Less than minimum quote asset amount limit"
-    );
+    assert!(matches!(result, ContractError::QuoteAssetBelowLimit { .. }));
 }
 
 #[test]
@@ -1584,10 +1603,7 @@ fn test_swapoutput_long_force_error_min_quote_199() {
     };
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: Greater than maximum quote asset amount limit"
-    );
+    assert!(matches!(result, ContractError::QuoteAssetAboveLimit { .. }));
 }
 
 #[test]
@@ -1628,8 +1644,5 @@ fn test_swapoutput_long_force_error_min_quote_100() {
 
     let info = mock_info("addr0000", &[]);
     let result = execute(deps.as_mut(), mock_env(), info, swap_msg).unwrap_err();
-    assert_eq!(
-        result.to_string(),
-        "Generic error: Greater than maximum quote asset amount limit"
-    );
+    assert!(matches!(result, ContractError::QuoteAssetAboveLimit { .. }));
 }
@@ -1,7 +1,9 @@
-use cosmwasm_std::{Addr, Deps, Env, Response, StdError, StdResult, Storage, Uint128};
+use cosmwasm_std::{Addr, Deps, Env, Response, StdResult, Storage, Uint128};
+use margined_common::integer::Integer;
 use margined_perp::margined_vamm::Direction;
 
 use crate::{
+    error::ContractError,
     handle::{get_input_price_with_reserves, get_output_price_with_reserves},
     state::{
         read_config, read_reserve_snapshot, read_reserve_snapshot_counter, read_state,
@@ -29,24 +31,45 @@ pub struct TwapPriceCalcParams {
     pub asset: Option<TwapInputAsset>,
 }
 
-pub fn require_margin_engine(sender: Addr, margin_engine: Addr) -> StdResult<Response> {
+pub fn require_margin_engine(sender: Addr, margin_engine: Addr) -> Result<Response, ContractError> {
     // check that sender is the margin engine
     if sender != margin_engine {
-        return Err(StdError::generic_err("sender not margin engine"));
+        return Err(ContractError::NotMarginEngine {});
     }
 
     Ok(Response::new())
 }
 
-pub fn require_open(open: bool) -> StdResult<Response> {
+pub fn require_open(open: bool) -> Result<Response, ContractError> {
     // check that the vamm is open
     if !open {
-        return Err(StdError::generic_err("amm is closed"));
+        return Err(ContractError::Closed {});
     }
 
     Ok(Response::new())
 }
 
+/// Clamps the raw funding rate of a funding period. The interest rate is added
+/// to the premium, limited by the clamp band, i.e.
+/// premium + clamp(interest - premium, -band, band)
+/// and the result is capped at the max funding rate unless it is zero.
+pub fn clamp_funding_rate(config: &Config, raw_funding_rate: Integer) -> StdResult<Integer> {
+    let mut funding_rate = raw_funding_rate;
+
+    let mut interest_component = config.interest_rate.checked_sub(raw_funding_rate)?;
+    interest_component.value = interest_component.value.min(config.clamp_band);
+
+    if !interest_component.is_zero() {
+        funding_rate = funding_rate.checked_add(interest_component)?;
+    }
+
+    if !config.max_funding_rate.is_zero() {
+        funding_rate.value = funding_rate.value.min(config.max_funding_rate);
+    }
+
+    Ok(funding_rate)
+}
+
 pub fn check_is_over_block_fluctuation_limit(
     storage: &mut dyn Storage,
     env: Env,
@@ -54,7 +77,7 @@ pub fn check_is_over_block_fluctuation_limit(
     quote_asset_amount: Uint128,
     base_asset_amount: Uint128,
     can_go_over_limit: bool,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let config = read_config(storage)?;
     let state = read_state(storage)?;
 
@@ -72,9 +95,9 @@ pub fn check_is_over_block_fluctuation_limit(
     // ensure that the latest price isn't over the limit which would restrict any further
     // swaps from occurring in this block
     if current_price > upper_limit || current_price < lower_limit {
-        return Err(StdError::generic_err(
-            "price is already over fluctuation limit",
-        ));
+        return Err(ContractError::AlreadyOverFluctuationLimit {
+            price: current_price,
+        });
     }
 
     if !can_go_over_limit {
@@ -92,7 +115,7 @@ pub fn check_is_over_block_fluctuation_limit(
                 .checked_div(state.base_asset_reserve.checked_add(base_asset_amount)?)
         }?;
         if price > upper_limit || price < lower_limit {
-            return Err(StdError::generic_err("price is over fluctuation limit"));
+            return Err(ContractError::OverFluctuationLimit { price });
         }
     }
 
@@ -270,12 +293,3 @@ pub fn calc_twap(
 
     Ok(weighted_price.checked_div(Uint128::from(interval))?)
 }
-
-/// Does the modulus (%) operator on Uint128.
-/// However it follows the design of the perpetual protocol decimals
-/// https://github.com/perpetual-protocol/perpetual-protocol/blob/release/v2.1.x/src/utils/Decimal.sol
-pub(crate) fn modulo(a: Uint128, b: Uint128, decimals: Uint128) -> Uint128 {
-    let a_decimals = a.checked_mul(decimals).unwrap();
-    let integral = a_decimals / b;
-    a_decimals - (b * integral)
-}
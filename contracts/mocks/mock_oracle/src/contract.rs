@@ -0,0 +1,139 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{bucket, bucket_read};
+use margined_perp::margined_pricefeed::{
+    BandReferenceData, PythPrice, PythPriceFeed, PythPriceFeedResponse,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub static KEY_BAND_RATES: &[u8] = b"band-rates";
+pub static KEY_PYTH_PRICES: &[u8] = b"pyth-prices";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct InstantiateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    SetBandRate {
+        symbol: String,
+        rate: Uint128,
+        last_updated: u64,
+    },
+    SetPythPrice {
+        id: String,
+        price: i64,
+        conf: u64,
+        expo: i32,
+        publish_time: i64,
+    },
+}
+
+/// Answers both the Band and the Pyth query interfaces
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    GetReferenceData {
+        base_symbol: String,
+        quote_symbol: String,
+    },
+    PriceFeed {
+        id: String,
+    },
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> StdResult<Response> {
+    Ok(Response::default())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetBandRate {
+            symbol,
+            rate,
+            last_updated,
+        } => {
+            let data = BandReferenceData {
+                rate,
+                last_updated_base: last_updated,
+                last_updated_quote: last_updated,
+            };
+            store_band_rate(deps.storage, symbol, &data)?;
+        }
+        ExecuteMsg::SetPythPrice {
+            id,
+            price,
+            conf,
+            expo,
+            publish_time,
+        } => {
+            let price = PythPrice {
+                price,
+                conf,
+                expo,
+                publish_time,
+            };
+            store_pyth_price(deps.storage, id, &price)?;
+        }
+    }
+
+    Ok(Response::default())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetReferenceData { base_symbol, .. } => {
+            to_binary(&read_band_rate(deps.storage, base_symbol)?)
+        }
+        QueryMsg::PriceFeed { id } => to_binary(&PythPriceFeedResponse {
+            price_feed: PythPriceFeed {
+                id: id.clone(),
+                price: read_pyth_price(deps.storage, id)?,
+            },
+        }),
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn store_band_rate(
+    storage: &mut dyn Storage,
+    symbol: String,
+    data: &BandReferenceData,
+) -> StdResult<()> {
+    bucket(storage, KEY_BAND_RATES).save(symbol.as_bytes(), data)
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn read_band_rate(storage: &dyn Storage, symbol: String) -> StdResult<BandReferenceData> {
+    bucket_read(storage, KEY_BAND_RATES).load(symbol.as_bytes())
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn store_pyth_price(storage: &mut dyn Storage, id: String, price: &PythPrice) -> StdResult<()> {
+    bucket(storage, KEY_PYTH_PRICES).save(id.as_bytes(), price)
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn read_pyth_price(storage: &dyn Storage, id: String) -> StdResult<PythPrice> {
+    bucket_read(storage, KEY_PYTH_PRICES).load(id.as_bytes())
+}
@@ -1,5 +1,7 @@
 use crate::asset::AssetInfo;
-use cosmwasm_std::{Deps, Response, StdError, StdResult, Uint128};
+use cosmwasm_std::{Deps, Response, StdError, StdResult, Storage, Uint128};
+use cw2::get_contract_version;
+use semver::Version;
 
 /// Validates that the decimals aren't zero and returns the decimal placeholder accordinglys
 pub fn validate_decimal_places(decimal_places: u8) -> StdResult<Uint128> {
@@ -78,3 +80,36 @@ pub fn validate_eligible_collateral(deps: Deps, input: String) -> StdResult<Asse
 
     Ok(response)
 }
+
+/// Validates that the stored contract can be migrated to the given name and version,
+/// returns the version that was previously stored
+pub fn validate_migration(
+    storage: &dyn Storage,
+    contract_name: &str,
+    contract_version: &str,
+) -> StdResult<Version> {
+    let stored = get_contract_version(storage)?;
+
+    // check that we are not migrating from a different contract
+    if stored.contract != contract_name {
+        return Err(StdError::generic_err(
+            "Cannot migrate from a different contract",
+        ));
+    }
+
+    let stored_version = parse_version(&stored.version)?;
+    let new_version = parse_version(contract_version)?;
+
+    // check that we are not downgrading the contract
+    if stored_version > new_version {
+        return Err(StdError::generic_err(
+            "Cannot migrate from a newer contract version",
+        ));
+    }
+
+    Ok(stored_version)
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    Version::parse(version).map_err(|_| StdError::generic_err("Invalid contract version"))
+}
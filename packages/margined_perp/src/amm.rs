@@ -0,0 +1,99 @@
+use cosmwasm_std::{StdResult, Uint128};
+
+use crate::margined_vamm::Direction;
+
+/// Returns the base asset exchanged for the quote asset amount against the
+/// given reserves, rounding in favour of the vamm
+pub fn calc_input_amount(
+    direction: &Direction,
+    quote_asset_amount: Uint128,
+    quote_asset_reserve: Uint128,
+    base_asset_reserve: Uint128,
+    decimals: Uint128,
+) -> StdResult<Uint128> {
+    if quote_asset_amount.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    // k = x * y (divided by decimal places)
+    let invariant_k = quote_asset_reserve
+        .checked_mul(base_asset_reserve)?
+        .checked_div(decimals)?;
+
+    let quote_asset_after: Uint128 = match direction {
+        Direction::AddToAmm => quote_asset_reserve.checked_add(quote_asset_amount)?,
+        Direction::RemoveFromAmm => quote_asset_reserve.checked_sub(quote_asset_amount)?,
+    };
+
+    let base_asset_after: Uint128 = invariant_k
+        .checked_mul(decimals)?
+        .checked_div(quote_asset_after)?;
+
+    let mut base_asset_bought = if base_asset_after > base_asset_reserve {
+        base_asset_after - base_asset_reserve
+    } else {
+        base_asset_reserve - base_asset_after
+    };
+
+    let remainder = modulo(invariant_k, quote_asset_after, decimals)?;
+    if !remainder.is_zero() {
+        base_asset_bought = match direction {
+            Direction::AddToAmm => base_asset_bought.checked_sub(Uint128::one())?,
+            Direction::RemoveFromAmm => base_asset_bought.checked_add(Uint128::one())?,
+        };
+    }
+
+    Ok(base_asset_bought)
+}
+
+/// Returns the quote asset exchanged for the base asset amount against the
+/// given reserves, rounding in favour of the vamm
+pub fn calc_output_amount(
+    direction: &Direction,
+    base_asset_amount: Uint128,
+    quote_asset_reserve: Uint128,
+    base_asset_reserve: Uint128,
+    decimals: Uint128,
+) -> StdResult<Uint128> {
+    if base_asset_amount.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let invariant_k = quote_asset_reserve
+        .checked_mul(base_asset_reserve)?
+        .checked_div(decimals)?;
+
+    let base_asset_after: Uint128 = match direction {
+        Direction::AddToAmm => base_asset_reserve.checked_add(base_asset_amount)?,
+        Direction::RemoveFromAmm => base_asset_reserve.checked_sub(base_asset_amount)?,
+    };
+
+    let quote_asset_after: Uint128 = invariant_k
+        .checked_mul(decimals)?
+        .checked_div(base_asset_after)?;
+
+    let mut quote_asset_sold = if quote_asset_after > quote_asset_reserve {
+        quote_asset_after - quote_asset_reserve
+    } else {
+        quote_asset_reserve - quote_asset_after
+    };
+
+    let remainder = modulo(invariant_k, base_asset_after, decimals)?;
+    if !remainder.is_zero() {
+        quote_asset_sold = match direction {
+            Direction::AddToAmm => quote_asset_sold.checked_sub(Uint128::one())?,
+            Direction::RemoveFromAmm => quote_asset_sold.checked_add(Uint128::one())?,
+        };
+    }
+
+    Ok(quote_asset_sold)
+}
+
+/// Does the modulus (%) operator on Uint128.
+/// However it follows the design of the perpetual protocol decimals
+/// https://github.com/perpetual-protocol/perpetual-protocol/blob/release/v2.1.x/src/utils/Decimal.sol
+fn modulo(a: Uint128, b: Uint128, decimals: Uint128) -> StdResult<Uint128> {
+    let a_decimals = a.checked_mul(decimals)?;
+    let integral = a_decimals.checked_div(b)?;
+    Ok(a_decimals.checked_sub(b.checked_mul(integral)?)?)
+}
@@ -1,5 +1,8 @@
+pub mod amm;
 pub mod margined_engine;
+pub mod margined_factory;
 pub mod margined_fee_pool;
+pub mod margined_governance;
 pub mod margined_insurance_fund;
 pub mod margined_pricefeed;
 pub mod margined_vamm;
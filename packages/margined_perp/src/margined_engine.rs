@@ -1,5 +1,6 @@
 use crate::margined_vamm::Direction;
 use cosmwasm_std::{Addr, SubMsg, Uint128};
+use cw20::Cw20ReceiveMsg;
 use margined_common::{asset::AssetInfo, integer::Integer};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -19,6 +20,46 @@ pub enum PnlCalcOption {
     Oracle,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    Limit,
+    StopLoss,
+    TakeProfit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerPriceOption {
+    SpotPrice,
+    Twap,
+}
+
+/// A single operation of a batch, executed on behalf of the sender of the batch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EngineOp {
+    OpenPosition {
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+    },
+    ClosePosition {
+        vamm: String,
+        quote_asset_limit: Uint128,
+    },
+    DepositMargin {
+        vamm: String,
+        amount: Uint128,
+    },
+    WithdrawMargin {
+        vamm: String,
+        amount: Uint128,
+    },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct InstantiateMsg {
     pub pauser: String,
@@ -31,8 +72,12 @@ pub struct InstantiateMsg {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
     UpdateConfig {
         owner: Option<String>,
         insurance_fund: Option<String>,
@@ -41,6 +86,7 @@ pub enum ExecuteMsg {
         maintenance_margin_ratio: Option<Uint128>,
         partial_liquidation_ratio: Option<Uint128>,
         liquidation_fee: Option<Uint128>,
+        keeper_fee: Option<Uint128>,
     },
     UpdatePauser {
         pauser: String,
@@ -81,6 +127,107 @@ pub enum ExecuteMsg {
     SetPause {
         pause: bool,
     },
+    SetCrossMargin {
+        enabled: bool,
+    },
+    PlaceLimitOrder {
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+        trigger_price: Uint128,
+        trigger_option: TriggerPriceOption,
+    },
+    PlaceTriggerOrder {
+        vamm: String,
+        order_type: OrderType,
+        quote_asset_limit: Uint128,
+        trigger_price: Uint128,
+        trigger_option: TriggerPriceOption,
+    },
+    CancelOrder {
+        vamm: String,
+        order_id: u64,
+    },
+    ExecuteOrder {
+        vamm: String,
+        order_id: u64,
+    },
+    MigratePositions {
+        limit: Option<u32>,
+    },
+    Batch {
+        ops: Vec<EngineOp>,
+    },
+    /// Executes the next operation of a batch, can only be called by the engine itself
+    BatchStep {
+        batch_id: u64,
+    },
+    RegisterCollateral {
+        asset: AssetInfo,
+        weight: Uint128,
+        pricefeed: String,
+        price_key: String,
+        price_decimals: u8,
+    },
+    RemoveCollateral {
+        asset: AssetInfo,
+    },
+    /// Deposits the registered native collateral sent
+    DepositCollateral {},
+    WithdrawCollateral {
+        asset: AssetInfo,
+        amount: Uint128,
+    },
+    LiquidateCollateral {
+        vamm: String,
+        trader: String,
+        asset: AssetInfo,
+        amount: Uint128,
+    },
+    /// Sets the risk parameters of the vamm, parameters not given use the engine's
+    SetRiskParams {
+        vamm: String,
+        initial_margin_ratio: Option<Uint128>,
+        maintenance_margin_ratio: Option<Uint128>,
+        partial_liquidation_ratio: Option<Uint128>,
+        liquidation_fee: Option<Uint128>,
+    },
+    /// Sets the leverage tiers of the vamm, replacing any set before
+    SetLeverageTiers {
+        vamm: String,
+        tiers: Vec<LeverageTier>,
+    },
+    /// Closes a bankrupt position the insurance fund cannot cover against the supplied
+    /// profitable opposing positions, in decreasing score, at the bankruptcy price
+    AutoDeleverage {
+        vamm: String,
+        trader: String,
+        limit: Option<u32>,
+    },
+}
+
+/// Messages sent along with the cw20 collateral, the amount sent must exactly cover
+/// the margin and fees required
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    OpenPosition {
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+    },
+    DepositMargin {
+        vamm: String,
+    },
+    Batch {
+        ops: Vec<EngineOp>,
+    },
+    /// Deposits the registered collateral token sent, any amount is accepted
+    DepositCollateral {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -123,6 +270,94 @@ pub enum QueryMsg {
         vamm: String,
         trader: String,
     },
+    IsCrossMargin {
+        trader: String,
+    },
+    Order {
+        vamm: String,
+        order_id: u64,
+    },
+    Orders {
+        vamm: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    TraderOrders {
+        vamm: String,
+        trader: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    AllPositionsForVamm {
+        vamm: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    AllTraders {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    LiquidatablePositions {
+        vamm: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    LiquidationPrice {
+        vamm: String,
+        trader: String,
+    },
+    AccountSummary {
+        trader: String,
+    },
+    TradeHistory {
+        vamm: String,
+        trader: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    TraderStats {
+        trader: String,
+        vamm: Option<String>,
+    },
+    Collaterals {},
+    TraderCollateral {
+        trader: String,
+    },
+    RiskParams {
+        vamm: String,
+    },
+    LeverageTiers {
+        vamm: String,
+    },
+    AdlRanking {
+        vamm: String,
+        trader: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    SimulateOpenPosition {
+        vamm: String,
+        trader: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+    },
+    SimulateClosePosition {
+        vamm: String,
+        trader: String,
+        quote_asset_limit: Uint128,
+    },
+    SimulateDepositMargin {
+        vamm: String,
+        trader: String,
+        amount: Uint128,
+    },
+    SimulateWithdrawMargin {
+        vamm: String,
+        trader: String,
+        amount: Uint128,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -136,6 +371,52 @@ pub struct ConfigResponse {
     pub maintenance_margin_ratio: Uint128,
     pub partial_liquidation_ratio: Uint128,
     pub liquidation_fee: Uint128,
+    pub keeper_fee: Uint128,
+}
+
+/// Risk parameters of a vamm overriding those of the engine config
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct RiskParams {
+    pub initial_margin_ratio: Option<Uint128>,
+    pub maintenance_margin_ratio: Option<Uint128>,
+    pub partial_liquidation_ratio: Option<Uint128>,
+    pub liquidation_fee: Option<Uint128>,
+}
+
+/// The risk parameters in effect for a vamm, with `overrides` set for the vamm
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RiskParamsResponse {
+    pub vamm: Addr,
+    pub initial_margin_ratio: Uint128,
+    pub maintenance_margin_ratio: Uint128,
+    pub partial_liquidation_ratio: Uint128,
+    pub liquidation_fee: Uint128,
+    pub overrides: RiskParams,
+}
+
+/// Margin ratios required of positions with an open notional above `notional`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LeverageTier {
+    pub notional: Uint128,
+    pub initial_margin_ratio: Uint128,
+    pub maintenance_margin_ratio: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LeverageTiersResponse {
+    pub vamm: Addr,
+    pub tiers: Vec<LeverageTier>,
+}
+
+/// The score of a position and the number of positions of a page ahead of it in the
+/// auto-deleveraging queue of its side, `last_trader` is where the next page starts
+/// and is none after the last page. Positions that are not profitable are not in the queue
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AdlRankingResponse {
+    pub trader: Addr,
+    pub score: Integer,
+    pub ahead: u32,
+    pub last_trader: Option<Addr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -176,6 +457,161 @@ impl Default for Position {
     }
 }
 
+/// A resting conditional order, `escrow` holds the margin and trading fees
+/// and `keeper_fee` is paid to whoever executes the order
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Order {
+    pub order_id: u64,
+    pub vamm: Addr,
+    pub trader: Addr,
+    pub order_type: OrderType,
+    pub side: Side,
+    pub margin_amount: Uint128,
+    pub leverage: Uint128,
+    pub asset_limit: Uint128,
+    pub trigger_price: Uint128,
+    pub trigger_option: TriggerPriceOption,
+    pub escrow: Uint128,
+    pub keeper_fee: Uint128,
+    pub block_number: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct OrdersResponse {
+    pub orders: Vec<Order>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PositionsResponse {
+    pub positions: Vec<Position>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TradersResponse {
+    pub traders: Vec<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LiquidationType {
+    Partial,
+    Full,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LiquidatablePosition {
+    pub trader: Addr,
+    pub margin_ratio: Integer,
+    pub shortfall: Uint128, // margin missing to reach the maintenance margin ratio
+    pub liquidation_type: LiquidationType,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LiquidatablePositionsResponse {
+    pub positions: Vec<LiquidatablePosition>,
+    pub last_trader: Option<Addr>, // last trader evaluated, to continue from
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Trade {
+    pub id: u64,
+    pub vamm: Addr,
+    pub trader: Addr,
+    pub size: Integer, // exchanged position size, negative if sold
+    pub price: Uint128,
+    pub notional: Uint128,
+    pub spread_fee: Uint128,
+    pub toll_fee: Uint128,
+    pub realized_pnl: Integer,
+    pub funding_paid: Integer, // negative if received by the trader
+    pub liquidation: bool,
+    pub block_height: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TradesResponse {
+    pub trades: Vec<Trade>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct TraderStats {
+    pub trade_count: u64,
+    pub liquidation_count: u64,
+    pub volume: Uint128,
+    pub realized_pnl: Integer,
+    pub spread_fees: Uint128,
+    pub toll_fees: Uint128,
+    pub funding_paid: Integer,
+}
+
+/// An asset accepted as collateral besides the eligible collateral, valued at the price
+/// of `price_key` in `pricefeed`, quoted with `price_decimals`, and discounted by `weight`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CollateralAsset {
+    pub asset: AssetInfo,
+    pub decimals: Uint128,
+    pub weight: Uint128,
+    pub pricefeed: Addr,
+    pub price_key: String,
+    pub price_decimals: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CollateralsResponse {
+    pub collaterals: Vec<CollateralAsset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CollateralBalance {
+    pub asset: AssetInfo,
+    pub amount: Uint128,
+    pub value: Uint128, // weighted value in the eligible collateral
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TraderCollateralResponse {
+    pub trader: Addr,
+    pub balances: Vec<CollateralBalance>,
+    pub total_value: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PositionSummary {
+    pub position: Position,
+    pub position_notional: Uint128, // at the spot price
+    pub spot_unrealized_pnl: Integer,
+    pub twap_unrealized_pnl: Integer,
+    pub oracle_unrealized_pnl: Integer,
+    pub pending_funding: Integer, // negative if the trader pays
+    pub margin_ratio: Integer,
+    pub free_collateral: Integer,
+    pub liquidation_price: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AccountSummaryResponse {
+    pub trader: Addr,
+    pub cross_margin: bool,
+    pub positions: Vec<PositionSummary>,
+    pub free_collateral: Integer,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SimulationResponse {
+    pub position: Position, // position after the simulated action
+    pub exchanged_position_size: Integer,
+    pub exchanged_quote_amount: Uint128,
+    pub entry_price: Uint128,
+    pub spread_fee: Uint128,
+    pub toll_fee: Uint128,
+    pub realized_pnl: Integer,
+    pub funding_payment: Integer,
+    pub margin_to_vault: Integer, // positive if paid by the trader, negative if returned
+    pub margin_ratio: Integer,
+    pub bad_debt: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct SwapResponse {
     pub vamm: String,
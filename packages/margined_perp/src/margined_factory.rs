@@ -0,0 +1,106 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Timestamp, Uint128};
+
+/// Parameters shared by all vAMMs deployed by the factory
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct VammTemplate {
+    pub decimals: u8,
+    pub quote_asset: String,
+    pub funding_period: u64,
+    pub toll_ratio: Uint128,
+    pub spread_ratio: Uint128,
+    pub fluctuation_limit_ratio: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub vamm_code_id: u64,
+    pub engine: String,
+    pub insurance_fund: String,
+    pub pricefeed: String,
+    pub template: VammTemplate,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    UpdateConfig {
+        vamm_code_id: Option<u64>,
+        engine: Option<String>,
+        insurance_fund: Option<String>,
+        pricefeed: Option<String>,
+        template: Option<VammTemplate>,
+    },
+    UpdateOwner {
+        owner: String,
+    },
+    CreateMarket {
+        base_asset: String,
+        quote_asset_reserve: Uint128,
+        base_asset_reserve: Uint128,
+        open: bool,
+    },
+    SetMarketStatus {
+        vamm: String,
+        status: MarketStatus,
+    },
+    UpdateMarketOwner {
+        vamm: String,
+        owner: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    GetOwner {},
+    GetMarket {
+        vamm: String,
+    },
+    GetMarkets {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketStatus {
+    Open,
+    Paused,
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Market {
+    pub vamm: Addr,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub created_at: Timestamp,
+    pub status: MarketStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConfigResponse {
+    pub vamm_code_id: u64,
+    pub engine: Addr,
+    pub insurance_fund: Addr,
+    pub pricefeed: Addr,
+    pub template: VammTemplate,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct OwnerResponse {
+    pub owner: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MarketsResponse {
+    pub markets: Vec<Market>,
+}
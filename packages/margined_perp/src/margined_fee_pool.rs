@@ -1,14 +1,24 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
 use margined_common::asset::AssetInfo;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct InstantiateMsg {}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
+    UpdateConfig {
+        staking_token: Option<String>,
+        unbonding_period: Option<u64>,
+    },
     UpdateOwner {
         owner: String,
     },
@@ -23,6 +33,19 @@ pub enum ExecuteMsg {
         amount: Uint128,
         recipient: String,
     },
+    Unstake {
+        amount: Uint128,
+    },
+    Claim {},
+    Deposit {},
+}
+
+/// Messages sent along with the staking token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    Stake {},
+    Deposit {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -30,13 +53,36 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Config {},
     GetOwner {},
-    IsToken { token: String },
+    IsToken {
+        token: String,
+    },
     GetTokenLength {},
-    GetTokenList { limit: Option<u32> },
+    GetTokenList {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    TokenStats {
+        token: String,
+    },
+    GetTokenInflows {
+        token: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    GetTotalStaked {},
+    GetStaker {
+        address: String,
+    },
+    GetPendingRewards {
+        address: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct ConfigResponse {}
+pub struct ConfigResponse {
+    pub staking_token: Option<Addr>,
+    pub unbonding_period: u64,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct OwnerResponse {
@@ -57,3 +103,57 @@ pub struct AllTokenResponse {
 pub struct TokenLengthResponse {
     pub length: usize,
 }
+
+/// Cumulative accounting of a fee token, the inflow is every amount received
+/// by the fee pool and the outflow every amount sent or claimed from it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TokenStatsResponse {
+    pub token: AssetInfo,
+    pub balance: Uint128,
+    pub inflow: Uint128,
+    pub outflow: Uint128,
+    pub reserved: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SenderInflow {
+    pub sender: Addr,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TokenInflowsResponse {
+    pub token: AssetInfo,
+    pub inflows: Vec<SenderInflow>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TotalStakedResponse {
+    pub total_staked: Uint128,
+}
+
+/// Stake withdrawn with `Unstake` that can be claimed once released
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub release_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StakerResponse {
+    pub address: Addr,
+    pub staked: Uint128,
+    pub unbonding: Vec<Unbonding>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingReward {
+    pub token: AssetInfo,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingRewardsResponse {
+    pub address: Addr,
+    pub rewards: Vec<PendingReward>,
+}
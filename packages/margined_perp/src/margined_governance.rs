@@ -0,0 +1,142 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Timestamp, Uint128, WasmMsg};
+use cw20::Cw20ReceiveMsg;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub staking_token: String,
+    pub quorum: Uint128,
+    pub threshold: Uint128,
+    pub voting_period: u64,
+    pub timelock_period: u64,
+    pub proposal_min_stake: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
+    Unstake {
+        amount: Uint128,
+    },
+    Propose {
+        title: String,
+        description: String,
+        msgs: Vec<WasmMsg>,
+    },
+    Vote {
+        proposal_id: u64,
+        vote: VoteOption,
+    },
+    Execute {
+        proposal_id: u64,
+    },
+    UpdateConfig {
+        quorum: Option<Uint128>,
+        threshold: Option<Uint128>,
+        voting_period: Option<u64>,
+        timelock_period: Option<u64>,
+        proposal_min_stake: Option<Uint128>,
+    },
+}
+
+/// Messages sent along with the staking token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    Stake {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    State {},
+    Staker {
+        address: String,
+    },
+    Proposal {
+        proposal_id: u64,
+    },
+    Proposals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    Vote {
+        proposal_id: u64,
+        voter: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Open,
+    Passed,
+    Rejected,
+    Executed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConfigResponse {
+    pub staking_token: Addr,
+    pub quorum: Uint128,
+    pub threshold: Uint128,
+    pub voting_period: u64,
+    pub timelock_period: u64,
+    pub proposal_min_stake: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StateResponse {
+    pub total_staked: Uint128,
+    pub proposal_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StakerResponse {
+    pub address: Addr,
+    pub staked: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ProposalResponse {
+    pub id: u64,
+    pub proposer: Addr,
+    pub title: String,
+    pub description: String,
+    pub msgs: Vec<WasmMsg>,
+    pub status: ProposalStatus,
+    pub start_height: u64,
+    pub end_time: Timestamp,
+    pub execute_after: Timestamp,
+    pub total_power: Uint128,
+    pub yes_votes: Uint128,
+    pub no_votes: Uint128,
+    pub abstain_votes: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ProposalsResponse {
+    pub proposals: Vec<ProposalResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct VoteResponse {
+    pub voter: Addr,
+    pub vote: VoteOption,
+    pub power: Uint128,
+}
@@ -8,14 +8,32 @@ pub struct InstantiateMsg {
     pub engine: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    UpdateOwner { owner: String },
-    AddVamm { vamm: String },
-    RemoveVamm { vamm: String },
-    Withdraw { token: AssetInfo, amount: Uint128 },
-    ShutdownVamms {},
+    UpdateOwner {
+        owner: String,
+    },
+    AddVamm {
+        vamm: String,
+    },
+    RemoveVamm {
+        vamm: String,
+    },
+    Withdraw {
+        token: AssetInfo,
+        amount: Uint128,
+    },
+    ShutdownVamms {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    SetFactory {
+        factory: Option<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -23,15 +41,26 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     Config {},
     GetOwner {},
-    IsVamm { vamm: String },
-    GetAllVamm { limit: Option<u32> },
-    GetAllVammStatus { limit: Option<u32> },
-    GetVammStatus { vamm: String },
+    IsVamm {
+        vamm: String,
+    },
+    GetAllVamm {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    GetAllVammStatus {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    GetVammStatus {
+        vamm: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct ConfigResponse {
     pub engine: Addr,
+    pub factory: Option<Addr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
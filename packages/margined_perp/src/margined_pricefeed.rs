@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Timestamp, Uint128};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -15,6 +15,11 @@ pub struct InstantiateMsg {
     pub oracle_hub_contract: String, // address of the oracle hub we are using
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {
+    pub oracle_hub_contract: Option<String>, // required when migrating a config without an oracle hub
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -31,6 +36,39 @@ pub enum ExecuteMsg {
     UpdateOwner {
         owner: String,
     },
+    SetOracle {
+        key: String,
+        oracle_type: OracleType,
+        contract: Option<String>, // defaults to the oracle hub
+        symbol: String,
+        decimals: u8,
+        max_staleness: u64,
+        max_confidence_ratio: Uint128,
+    },
+    RemoveOracle {
+        key: String,
+    },
+    SetRetentionPeriod {
+        retention_period: Option<u64>,
+    },
+    AddReporter {
+        address: String,
+    },
+    RemoveReporter {
+        address: String,
+    },
+    SetAggregation {
+        quorum: u64,
+        max_deviation: Uint128,
+    },
+    SubmitPrice {
+        key: String,
+        price: Uint128,
+        timestamp: u64,
+    },
+    MigratePrices {
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -49,12 +87,118 @@ pub enum QueryMsg {
         key: String,
         interval: u64,
     },
+    GetOracle {
+        key: String,
+    },
+    GetPriceHistory {
+        key: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    IsReporter {
+        address: String,
+    },
+    GetReporters {},
+    GetSubmissions {
+        key: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConfigResponse {
+    pub oracle_hub_contract: Addr,
+    pub retention_period: Option<u64>,
+    pub quorum: u64,
+    pub max_deviation: Uint128,
+}
+
+#[derive(Serialize, Default, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PriceData {
+    pub round_id: Uint128,
+    pub price: Uint128,
+    pub timestamp: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct ConfigResponse {}
+pub struct Submission {
+    pub reporter: Addr,
+    pub price: Uint128,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubmissionsResponse {
+    pub submissions: Vec<Submission>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PriceHistoryResponse {
+    pub prices: Vec<PriceData>,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct OwnerResponse {
     pub owner: Addr,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleType {
+    Band,
+    Pyth,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct OracleResponse {
+    pub oracle_type: OracleType,
+    pub contract: Addr,
+    pub symbol: String,
+    pub decimals: Uint128,
+    pub max_staleness: u64,
+    pub max_confidence_ratio: Uint128,
+}
+
+/// Query interface of Band Protocol style standard reference contracts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BandQueryMsg {
+    GetReferenceData {
+        base_symbol: String,
+        quote_symbol: String,
+    },
+}
+
+/// Band reference data, the rate is always expressed with 18 decimals
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BandReferenceData {
+    pub rate: Uint128,
+    pub last_updated_base: u64,
+    pub last_updated_quote: u64,
+}
+
+/// Query interface of Pyth Network style price feed contracts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PythQueryMsg {
+    PriceFeed { id: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PythPriceFeedResponse {
+    pub price_feed: PythPriceFeed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PythPriceFeed {
+    pub id: String,
+    pub price: PythPrice,
+}
+
+/// Pyth price, the real value is `price * 10^expo` with a confidence interval of `conf * 10^expo`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PythPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
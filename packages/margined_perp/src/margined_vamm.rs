@@ -29,6 +29,9 @@ pub struct InstantiateMsg {
     pub fluctuation_limit_ratio: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 #[allow(clippy::large_enum_variant)]
@@ -43,6 +46,9 @@ pub enum ExecuteMsg {
         insurance_fund: Option<String>,
         pricefeed: Option<String>,
         spot_price_twap_interval: Option<u64>,
+        max_funding_rate: Option<Uint128>,
+        interest_rate: Option<Integer>,
+        clamp_band: Option<Uint128>,
     },
     UpdateOwner {
         owner: String,
@@ -127,6 +133,9 @@ pub struct ConfigResponse {
     pub decimals: Uint128,
     pub funding_period: u64,
     pub spot_price_twap_interval: u64,
+    pub max_funding_rate: Uint128,
+    pub interest_rate: Integer,
+    pub clamp_band: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -141,6 +150,7 @@ pub struct StateResponse {
     pub base_asset_reserve: Uint128,
     pub total_position_size: Integer,
     pub funding_rate: Integer,
+    pub raw_funding_rate: Integer,
     pub next_funding_time: u64,
 }
 
@@ -1,6 +1,10 @@
 use margined_perp::margined_engine::{
-    ConfigResponse, ExecuteMsg, PnlCalcOption, Position, PositionUnrealizedPnlResponse, QueryMsg,
-    Side, StateResponse,
+    AccountSummaryResponse, AdlRankingResponse, CollateralsResponse, ConfigResponse, Cw20HookMsg,
+    EngineOp, ExecuteMsg, LeverageTier, LeverageTiersResponse, LiquidatablePositionsResponse,
+    Order, OrderType, OrdersResponse, PnlCalcOption, Position, PositionUnrealizedPnlResponse,
+    PositionsResponse, QueryMsg, RiskParamsResponse, Side, SimulationResponse, StateResponse,
+    Trade, TraderCollateralResponse, TraderStats, TradersResponse, TradesResponse,
+    TriggerPriceOption,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -9,8 +13,9 @@ use cosmwasm_std::{
     to_binary, Addr, Coin, CosmosMsg, Empty, Querier, QuerierWrapper, StdResult, Uint128, WasmMsg,
     WasmQuery,
 };
+use cw20::Cw20ExecuteMsg;
 use cw_controllers::HooksResponse;
-use margined_common::integer::Integer;
+use margined_common::{asset::AssetInfo, integer::Integer};
 
 /// EngineController is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
@@ -42,6 +47,7 @@ impl EngineController {
         maintenance_margin_ratio: Option<Uint128>,
         partial_liquidation_ratio: Option<Uint128>,
         liquidation_fee: Option<Uint128>,
+        keeper_fee: Option<Uint128>,
     ) -> StdResult<CosmosMsg> {
         let msg = ExecuteMsg::UpdateConfig {
             owner,
@@ -51,6 +57,7 @@ impl EngineController {
             maintenance_margin_ratio,
             partial_liquidation_ratio,
             liquidation_fee,
+            keeper_fee,
         };
         self.call(msg, vec![])
     }
@@ -64,6 +71,7 @@ impl EngineController {
             maintenance_margin_ratio: None,
             partial_liquidation_ratio: None,
             liquidation_fee: None,
+            keeper_fee: None,
         };
         self.call(msg, vec![])
     }
@@ -80,6 +88,7 @@ impl EngineController {
             maintenance_margin_ratio: Some(maintenance_margin_ratio),
             partial_liquidation_ratio: None,
             liquidation_fee: None,
+            keeper_fee: None,
         };
         self.call(msg, vec![])
     }
@@ -93,6 +102,7 @@ impl EngineController {
             maintenance_margin_ratio: Some(margin_ratio),
             partial_liquidation_ratio: None,
             liquidation_fee: None,
+            keeper_fee: None,
         };
         self.call(msg, vec![])
     }
@@ -109,6 +119,7 @@ impl EngineController {
             maintenance_margin_ratio: None,
             partial_liquidation_ratio: Some(partial_liquidation_ratio),
             liquidation_fee: None,
+            keeper_fee: None,
         };
         self.call(msg, vec![])
     }
@@ -122,6 +133,21 @@ impl EngineController {
             maintenance_margin_ratio: None,
             partial_liquidation_ratio: None,
             liquidation_fee: Some(liquidation_fee),
+            keeper_fee: None,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_keeper_fee(&self, keeper_fee: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            owner: None,
+            insurance_fund: None,
+            fee_pool: None,
+            initial_margin_ratio: None,
+            maintenance_margin_ratio: None,
+            partial_liquidation_ratio: None,
+            liquidation_fee: None,
+            keeper_fee: Some(keeper_fee),
         };
         self.call(msg, vec![])
     }
@@ -192,6 +218,226 @@ impl EngineController {
         self.call(msg, vec![])
     }
 
+    pub fn set_cross_margin(&self, enabled: bool) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetCrossMargin { enabled };
+        self.call(msg, vec![])
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn place_limit_order(
+        &self,
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+        trigger_price: Uint128,
+        trigger_option: TriggerPriceOption,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::PlaceLimitOrder {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+            trigger_price,
+            trigger_option,
+        };
+        self.call(msg, funds)
+    }
+
+    pub fn place_trigger_order(
+        &self,
+        vamm: String,
+        order_type: OrderType,
+        quote_asset_limit: Uint128,
+        trigger_price: Uint128,
+        trigger_option: TriggerPriceOption,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::PlaceTriggerOrder {
+            vamm,
+            order_type,
+            quote_asset_limit,
+            trigger_price,
+            trigger_option,
+        };
+        self.call(msg, funds)
+    }
+
+    pub fn cancel_order(&self, vamm: String, order_id: u64) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::CancelOrder { vamm, order_id };
+        self.call(msg, vec![])
+    }
+
+    pub fn execute_order(&self, vamm: String, order_id: u64) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ExecuteOrder { vamm, order_id };
+        self.call(msg, vec![])
+    }
+
+    pub fn migrate_positions(&self, limit: Option<u32>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::MigratePositions { limit };
+        self.call(msg, vec![])
+    }
+
+    pub fn batch(&self, ops: Vec<EngineOp>, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Batch { ops };
+        self.call(msg, funds)
+    }
+
+    /// sends the cw20 collateral to open a position, executed against the token
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_position_cw20(
+        &self,
+        token: String,
+        amount: Uint128,
+        vamm: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = Cw20HookMsg::OpenPosition {
+            vamm,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+        };
+        self.send_cw20(token, amount, msg)
+    }
+
+    /// sends the cw20 collateral as margin, executed against the token
+    pub fn deposit_margin_cw20(
+        &self,
+        token: String,
+        amount: Uint128,
+        vamm: String,
+    ) -> StdResult<CosmosMsg> {
+        self.send_cw20(token, amount, Cw20HookMsg::DepositMargin { vamm })
+    }
+
+    /// sends the cw20 collateral to pay for a batch, executed against the token
+    pub fn batch_cw20(
+        &self,
+        token: String,
+        amount: Uint128,
+        ops: Vec<EngineOp>,
+    ) -> StdResult<CosmosMsg> {
+        self.send_cw20(token, amount, Cw20HookMsg::Batch { ops })
+    }
+
+    /// sends a registered collateral token to deposit it, executed against the token
+    pub fn deposit_collateral_cw20(&self, token: String, amount: Uint128) -> StdResult<CosmosMsg> {
+        self.send_cw20(token, amount, Cw20HookMsg::DepositCollateral {})
+    }
+
+    fn send_cw20(&self, token: String, amount: Uint128, msg: Cw20HookMsg) -> StdResult<CosmosMsg> {
+        let msg = Cw20ExecuteMsg::Send {
+            contract: self.addr().to_string(),
+            amount,
+            msg: to_binary(&msg)?,
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: token,
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    pub fn register_collateral(
+        &self,
+        asset: AssetInfo,
+        weight: Uint128,
+        pricefeed: String,
+        price_key: String,
+        price_decimals: u8,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RegisterCollateral {
+            asset,
+            weight,
+            pricefeed,
+            price_key,
+            price_decimals,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn remove_collateral(&self, asset: AssetInfo) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RemoveCollateral { asset };
+        self.call(msg, vec![])
+    }
+
+    pub fn deposit_collateral(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::DepositCollateral {};
+        self.call(msg, funds)
+    }
+
+    pub fn withdraw_collateral(&self, asset: AssetInfo, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::WithdrawCollateral { asset, amount };
+        self.call(msg, vec![])
+    }
+
+    pub fn liquidate_collateral(
+        &self,
+        vamm: String,
+        trader: String,
+        asset: AssetInfo,
+        amount: Uint128,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::LiquidateCollateral {
+            vamm,
+            trader,
+            asset,
+            amount,
+        };
+        self.call(msg, funds)
+    }
+
+    pub fn set_risk_params(
+        &self,
+        vamm: String,
+        initial_margin_ratio: Option<Uint128>,
+        maintenance_margin_ratio: Option<Uint128>,
+        partial_liquidation_ratio: Option<Uint128>,
+        liquidation_fee: Option<Uint128>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetRiskParams {
+            vamm,
+            initial_margin_ratio,
+            maintenance_margin_ratio,
+            partial_liquidation_ratio,
+            liquidation_fee,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_leverage_tiers(
+        &self,
+        vamm: String,
+        tiers: Vec<LeverageTier>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetLeverageTiers { vamm, tiers };
+        self.call(msg, vec![])
+    }
+
+    pub fn auto_deleverage(
+        &self,
+        vamm: String,
+        trader: String,
+        limit: Option<u32>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::AutoDeleverage {
+            vamm,
+            trader,
+            limit,
+        };
+        self.call(msg, vec![])
+    }
+
     pub fn add_whitelist(&self, address: String) -> StdResult<CosmosMsg> {
         let msg = ExecuteMsg::AddWhitelist { address };
         self.call(msg, vec![])
@@ -402,4 +648,409 @@ impl EngineController {
         let res: Integer = QuerierWrapper::<Empty>::new(querier).query(&query)?;
         Ok(res)
     }
+
+    /// checks if the trader's positions are cross margined
+    pub fn is_cross_margin<Q: Querier>(&self, querier: &Q, trader: String) -> StdResult<bool> {
+        let msg = QueryMsg::IsCrossMargin { trader };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: bool = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get an open order
+    pub fn order<Q: Querier>(&self, querier: &Q, vamm: String, order_id: u64) -> StdResult<Order> {
+        let msg = QueryMsg::Order { vamm, order_id };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: Order = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the open orders for a vamm
+    pub fn orders<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Order>> {
+        let msg = QueryMsg::Orders {
+            vamm,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: OrdersResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res.orders)
+    }
+
+    /// get the open orders of a trader for a vamm
+    pub fn trader_orders<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Order>> {
+        let msg = QueryMsg::TraderOrders {
+            vamm,
+            trader,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: OrdersResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res.orders)
+    }
+
+    /// get the positions in a vamm
+    pub fn all_positions_for_vamm<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Position>> {
+        let msg = QueryMsg::AllPositionsForVamm {
+            vamm,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: PositionsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res.positions)
+    }
+
+    /// get the traders with a position in any vamm
+    pub fn all_traders<Q: Querier>(
+        &self,
+        querier: &Q,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Addr>> {
+        let msg = QueryMsg::AllTraders { start_after, limit };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TradersResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res.traders)
+    }
+
+    /// get the liquidatable positions in a vamm
+    pub fn liquidatable_positions<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<LiquidatablePositionsResponse> {
+        let msg = QueryMsg::LiquidatablePositions {
+            vamm,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: LiquidatablePositionsResponse =
+            QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// simulate opening a position
+    #[allow(clippy::too_many_arguments)]
+    pub fn simulate_open_position<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        side: Side,
+        margin_amount: Uint128,
+        leverage: Uint128,
+        base_asset_limit: Uint128,
+    ) -> StdResult<SimulationResponse> {
+        let msg = QueryMsg::SimulateOpenPosition {
+            vamm,
+            trader,
+            side,
+            margin_amount,
+            leverage,
+            base_asset_limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: SimulationResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// simulate closing a position
+    pub fn simulate_close_position<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        quote_asset_limit: Uint128,
+    ) -> StdResult<SimulationResponse> {
+        let msg = QueryMsg::SimulateClosePosition {
+            vamm,
+            trader,
+            quote_asset_limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: SimulationResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// simulate depositing margin into a position
+    pub fn simulate_deposit_margin<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        amount: Uint128,
+    ) -> StdResult<SimulationResponse> {
+        let msg = QueryMsg::SimulateDepositMargin {
+            vamm,
+            trader,
+            amount,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: SimulationResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// simulate withdrawing margin from a position
+    pub fn simulate_withdraw_margin<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        amount: Uint128,
+    ) -> StdResult<SimulationResponse> {
+        let msg = QueryMsg::SimulateWithdrawMargin {
+            vamm,
+            trader,
+            amount,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: SimulationResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the vamm spot price at which the position is liquidated
+    pub fn liquidation_price<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+    ) -> StdResult<Uint128> {
+        let msg = QueryMsg::LiquidationPrice { vamm, trader };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: Uint128 = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the summary of every position of a trader
+    pub fn account_summary<Q: Querier>(
+        &self,
+        querier: &Q,
+        trader: String,
+    ) -> StdResult<AccountSummaryResponse> {
+        let msg = QueryMsg::AccountSummary { trader };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: AccountSummaryResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the trades of a trader in a vamm
+    pub fn trade_history<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<Trade>> {
+        let msg = QueryMsg::TradeHistory {
+            vamm,
+            trader,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TradesResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res.trades)
+    }
+
+    /// get the running totals of a trader
+    pub fn trader_stats<Q: Querier>(
+        &self,
+        querier: &Q,
+        trader: String,
+        vamm: Option<String>,
+    ) -> StdResult<TraderStats> {
+        let msg = QueryMsg::TraderStats { trader, vamm };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TraderStats = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the assets registered as collateral
+    pub fn collaterals<Q: Querier>(&self, querier: &Q) -> StdResult<CollateralsResponse> {
+        let msg = QueryMsg::Collaterals {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: CollateralsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the collateral deposited by a trader
+    pub fn trader_collateral<Q: Querier>(
+        &self,
+        querier: &Q,
+        trader: String,
+    ) -> StdResult<TraderCollateralResponse> {
+        let msg = QueryMsg::TraderCollateral { trader };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TraderCollateralResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the risk parameters in effect for a vamm
+    pub fn risk_params<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+    ) -> StdResult<RiskParamsResponse> {
+        let msg = QueryMsg::RiskParams { vamm };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: RiskParamsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the leverage tiers of a vamm
+    pub fn leverage_tiers<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+    ) -> StdResult<LeverageTiersResponse> {
+        let msg = QueryMsg::LeverageTiers { vamm };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: LeverageTiersResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the number of positions of a page ahead of a position in the auto-deleveraging queue
+    pub fn adl_ranking<Q: Querier>(
+        &self,
+        querier: &Q,
+        vamm: String,
+        trader: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<AdlRankingResponse> {
+        let msg = QueryMsg::AdlRanking {
+            vamm,
+            trader,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: AdlRankingResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
 }
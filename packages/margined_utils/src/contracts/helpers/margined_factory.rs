@@ -0,0 +1,124 @@
+use margined_perp::margined_factory::{
+    ConfigResponse, ExecuteMsg, Market, MarketStatus, MarketsResponse, QueryMsg, VammTemplate,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, Empty, Querier, QuerierWrapper, StdResult, Uint128, WasmMsg,
+    WasmQuery,
+};
+
+/// FactoryController is a wrapper around Addr that provides a lot of helpers
+/// for working with this.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FactoryController(pub Addr);
+
+impl FactoryController {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = to_binary(&msg.into())?;
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg,
+            funds,
+        }
+        .into())
+    }
+
+    pub fn update_config(
+        &self,
+        vamm_code_id: Option<u64>,
+        engine: Option<String>,
+        insurance_fund: Option<String>,
+        pricefeed: Option<String>,
+        template: Option<VammTemplate>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            vamm_code_id,
+            engine,
+            insurance_fund,
+            pricefeed,
+            template,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn update_owner(&self, owner: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateOwner { owner };
+        self.call(msg, vec![])
+    }
+
+    pub fn create_market(
+        &self,
+        base_asset: String,
+        quote_asset_reserve: Uint128,
+        base_asset_reserve: Uint128,
+        open: bool,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::CreateMarket {
+            base_asset,
+            quote_asset_reserve,
+            base_asset_reserve,
+            open,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_market_status(&self, vamm: String, status: MarketStatus) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetMarketStatus { vamm, status };
+        self.call(msg, vec![])
+    }
+
+    pub fn update_market_owner(&self, vamm: String, owner: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateMarketOwner { vamm, owner };
+        self.call(msg, vec![])
+    }
+
+    /// get factory configuration
+    pub fn config<Q: Querier>(&self, querier: &Q) -> StdResult<ConfigResponse> {
+        let msg = QueryMsg::Config {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: ConfigResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get market of the vamm
+    pub fn market<Q: Querier>(&self, querier: &Q, vamm: String) -> StdResult<Market> {
+        let msg = QueryMsg::GetMarket { vamm };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: Market = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get paginated markets
+    pub fn markets<Q: Querier>(
+        &self,
+        querier: &Q,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<MarketsResponse> {
+        let msg = QueryMsg::GetMarkets { start_after, limit };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: MarketsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+}
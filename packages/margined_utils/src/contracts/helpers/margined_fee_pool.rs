@@ -1,5 +1,8 @@
+use cw20::Cw20ExecuteMsg;
 use margined_perp::margined_fee_pool::{
-    AllTokenResponse, ConfigResponse, ExecuteMsg, QueryMsg, TokenLengthResponse, TokenResponse,
+    AllTokenResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, PendingRewardsResponse, QueryMsg,
+    StakerResponse, TokenInflowsResponse, TokenLengthResponse, TokenResponse, TokenStatsResponse,
+    TotalStakedResponse,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -39,6 +42,18 @@ impl FeePoolController {
         self.call(msg, vec![])
     }
 
+    pub fn update_config(
+        &self,
+        staking_token: Option<String>,
+        unbonding_period: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            staking_token,
+            unbonding_period,
+        };
+        self.call(msg, vec![])
+    }
+
     pub fn add_token(&self, token: String) -> StdResult<CosmosMsg> {
         let msg = ExecuteMsg::AddToken { token };
         self.call(msg, vec![])
@@ -63,6 +78,52 @@ impl FeePoolController {
         self.call(msg, vec![])
     }
 
+    /// sends the staking token to the fee pool, executed against the token
+    pub fn stake(&self, staking_token: String, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = Cw20ExecuteMsg::Send {
+            contract: self.addr().to_string(),
+            amount,
+            msg: to_binary(&Cw20HookMsg::Stake {})?,
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: staking_token,
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    pub fn unstake(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Unstake { amount };
+        self.call(msg, vec![])
+    }
+
+    pub fn claim(&self) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Claim {};
+        self.call(msg, vec![])
+    }
+
+    /// deposits the native tokens as fees
+    pub fn deposit(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Deposit {};
+        self.call(msg, funds)
+    }
+
+    /// deposits the cw20 token as fees, executed against the token
+    pub fn deposit_cw20(&self, token: String, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = Cw20ExecuteMsg::Send {
+            contract: self.addr().to_string(),
+            amount,
+            msg: to_binary(&Cw20HookMsg::Deposit {})?,
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: token,
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
     //////////////////////
     /// Query Messages ///
     //////////////////////
@@ -96,10 +157,11 @@ impl FeePoolController {
     /// get all the tokens in a list
     pub fn all_tokens_list<Q: Querier>(
         &self,
+        start_after: Option<String>,
         limit: Option<u32>,
         querier: &Q,
     ) -> StdResult<AllTokenResponse> {
-        let msg = QueryMsg::GetTokenList { limit };
+        let msg = QueryMsg::GetTokenList { start_after, limit };
         let query = WasmQuery::Smart {
             contract_addr: self.addr().into(),
             msg: to_binary(&msg)?,
@@ -122,4 +184,87 @@ impl FeePoolController {
         let res: TokenResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
         Ok(res)
     }
+
+    /// get the total amount staked
+    pub fn total_staked<Q: Querier>(&self, querier: &Q) -> StdResult<TotalStakedResponse> {
+        let msg = QueryMsg::GetTotalStaked {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TotalStakedResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the stake and unbonding stake of the address
+    pub fn staker<Q: Querier>(&self, address: String, querier: &Q) -> StdResult<StakerResponse> {
+        let msg = QueryMsg::GetStaker { address };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: StakerResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the rewards the address can claim
+    pub fn pending_rewards<Q: Querier>(
+        &self,
+        address: String,
+        querier: &Q,
+    ) -> StdResult<PendingRewardsResponse> {
+        let msg = QueryMsg::GetPendingRewards { address };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: PendingRewardsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the balance and cumulative inflow and outflow of the token
+    pub fn token_stats<Q: Querier>(
+        &self,
+        token: String,
+        querier: &Q,
+    ) -> StdResult<TokenStatsResponse> {
+        let msg = QueryMsg::TokenStats { token };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TokenStatsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get the cumulative deposits of the token by sender
+    pub fn token_inflows<Q: Querier>(
+        &self,
+        token: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        querier: &Q,
+    ) -> StdResult<TokenInflowsResponse> {
+        let msg = QueryMsg::GetTokenInflows {
+            token,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: TokenInflowsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
 }
@@ -0,0 +1,169 @@
+use cw20::Cw20ExecuteMsg;
+use margined_perp::margined_governance::{
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, ProposalResponse, ProposalsResponse, QueryMsg,
+    StakerResponse, StateResponse, VoteOption, VoteResponse,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, Empty, Querier, QuerierWrapper, StdResult, Uint128, WasmMsg,
+    WasmQuery,
+};
+
+/// GovernanceController is a wrapper around Addr that provides a lot of helpers
+/// for working with this.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct GovernanceController(pub Addr);
+
+impl GovernanceController {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = to_binary(&msg.into())?;
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg,
+            funds,
+        }
+        .into())
+    }
+
+    /// sends the staking token to governance, executed against the token
+    pub fn stake(&self, staking_token: String, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = Cw20ExecuteMsg::Send {
+            contract: self.addr().to_string(),
+            amount,
+            msg: to_binary(&Cw20HookMsg::Stake {})?,
+        };
+        Ok(WasmMsg::Execute {
+            contract_addr: staking_token,
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    pub fn unstake(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Unstake { amount };
+        self.call(msg, vec![])
+    }
+
+    pub fn propose(
+        &self,
+        title: String,
+        description: String,
+        msgs: Vec<WasmMsg>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Propose {
+            title,
+            description,
+            msgs,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn vote(&self, proposal_id: u64, vote: VoteOption) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Vote { proposal_id, vote };
+        self.call(msg, vec![])
+    }
+
+    pub fn execute(&self, proposal_id: u64) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Execute { proposal_id };
+        self.call(msg, vec![])
+    }
+
+    /// get governance configuration
+    pub fn config<Q: Querier>(&self, querier: &Q) -> StdResult<ConfigResponse> {
+        let msg = QueryMsg::Config {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: ConfigResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get total staked and number of proposals
+    pub fn state<Q: Querier>(&self, querier: &Q) -> StdResult<StateResponse> {
+        let msg = QueryMsg::State {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: StateResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get stake of the address
+    pub fn staker<Q: Querier>(&self, querier: &Q, address: String) -> StdResult<StakerResponse> {
+        let msg = QueryMsg::Staker { address };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: StakerResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get proposal by id
+    pub fn proposal<Q: Querier>(
+        &self,
+        querier: &Q,
+        proposal_id: u64,
+    ) -> StdResult<ProposalResponse> {
+        let msg = QueryMsg::Proposal { proposal_id };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: ProposalResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get paginated proposals
+    pub fn proposals<Q: Querier>(
+        &self,
+        querier: &Q,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ProposalsResponse> {
+        let msg = QueryMsg::Proposals { start_after, limit };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: ProposalsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get vote of the voter on a proposal
+    pub fn vote_of<Q: Querier>(
+        &self,
+        querier: &Q,
+        proposal_id: u64,
+        voter: String,
+    ) -> StdResult<VoteResponse> {
+        let msg = QueryMsg::Vote { proposal_id, voter };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: VoteResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+}
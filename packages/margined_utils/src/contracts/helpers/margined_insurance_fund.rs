@@ -49,8 +49,17 @@ impl InsuranceFundController {
         self.call(msg, vec![])
     }
 
-    pub fn shutdown_vamms(&self) -> StdResult<CosmosMsg> {
-        let msg = ExecuteMsg::ShutdownVamms {};
+    pub fn shutdown_vamms(
+        &self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::ShutdownVamms { start_after, limit };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_factory(&self, factory: Option<String>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetFactory { factory };
         self.call(msg, vec![])
     }
 
@@ -91,10 +100,11 @@ impl InsuranceFundController {
     /// get all the vamms status'
     pub fn all_vamm_status<Q: Querier>(
         &self,
+        start_after: Option<String>,
         limit: Option<u32>,
         querier: &Q,
     ) -> StdResult<AllVammStatusResponse> {
-        let msg = QueryMsg::GetAllVammStatus { limit };
+        let msg = QueryMsg::GetAllVammStatus { start_after, limit };
         let query = WasmQuery::Smart {
             contract_addr: self.addr().into(),
             msg: to_binary(&msg)?,
@@ -108,10 +118,11 @@ impl InsuranceFundController {
     /// get a list of all the vamms
     pub fn all_vamms<Q: Querier>(
         &self,
+        start_after: Option<String>,
         limit: Option<u32>,
         querier: &Q,
     ) -> StdResult<AllVammResponse> {
-        let msg = QueryMsg::GetAllVamm { limit };
+        let msg = QueryMsg::GetAllVamm { start_after, limit };
         let query = WasmQuery::Smart {
             contract_addr: self.addr().into(),
             msg: to_binary(&msg)?,
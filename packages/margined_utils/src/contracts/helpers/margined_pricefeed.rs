@@ -1,7 +1,12 @@
-use margined_perp::margined_pricefeed::{ConfigResponse, ExecuteMsg, QueryMsg};
+use margined_perp::margined_pricefeed::{
+    ConfigResponse, ExecuteMsg, OracleResponse, OracleType, PriceHistoryResponse, QueryMsg,
+    SubmissionsResponse,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use cw_controllers::HooksResponse;
+
 use cosmwasm_std::{
     to_binary, Addr, Coin, CosmosMsg, Empty, Querier, QuerierWrapper, StdResult, Uint128, WasmMsg,
     WasmQuery,
@@ -55,6 +60,76 @@ impl PricefeedController {
         self.call(msg, vec![])
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_oracle(
+        &self,
+        key: String,
+        oracle_type: OracleType,
+        contract: Option<String>,
+        symbol: String,
+        decimals: u8,
+        max_staleness: u64,
+        max_confidence_ratio: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetOracle {
+            key,
+            oracle_type,
+            contract,
+            symbol,
+            decimals,
+            max_staleness,
+            max_confidence_ratio,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn remove_oracle(&self, key: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RemoveOracle { key };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_retention_period(&self, retention_period: Option<u64>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetRetentionPeriod { retention_period };
+        self.call(msg, vec![])
+    }
+
+    pub fn add_reporter(&self, address: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::AddReporter { address };
+        self.call(msg, vec![])
+    }
+
+    pub fn remove_reporter(&self, address: String) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::RemoveReporter { address };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_aggregation(&self, quorum: u64, max_deviation: Uint128) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SetAggregation {
+            quorum,
+            max_deviation,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn submit_price(
+        &self,
+        key: String,
+        price: Uint128,
+        timestamp: u64,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::SubmitPrice {
+            key,
+            price,
+            timestamp,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn migrate_prices(&self, limit: Option<u32>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::MigratePrices { limit };
+        self.call(msg, vec![])
+    }
+
     /// get margined pricefeed configuration
     pub fn config<Q: Querier>(&self, querier: &Q) -> StdResult<ConfigResponse> {
         let msg = QueryMsg::Config {};
@@ -119,4 +194,83 @@ impl PricefeedController {
         let res: Uint128 = QuerierWrapper::<Empty>::new(querier).query(&query)?;
         Ok(res)
     }
+
+    /// get oracle adapter set for key
+    pub fn get_oracle<Q: Querier>(&self, querier: &Q, key: String) -> StdResult<OracleResponse> {
+        let msg = QueryMsg::GetOracle { key };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: OracleResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get paginated price history
+    pub fn price_history<Q: Querier>(
+        &self,
+        querier: &Q,
+        key: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PriceHistoryResponse> {
+        let msg = QueryMsg::GetPriceHistory {
+            key,
+            start_after,
+            limit,
+        };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: PriceHistoryResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get whether the address is a whitelisted reporter
+    pub fn is_reporter<Q: Querier>(&self, querier: &Q, address: String) -> StdResult<bool> {
+        let msg = QueryMsg::IsReporter { address };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: bool = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get whitelisted reporters
+    pub fn reporters<Q: Querier>(&self, querier: &Q) -> StdResult<HooksResponse> {
+        let msg = QueryMsg::GetReporters {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: HooksResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
+    /// get pending reporter submissions for key
+    pub fn submissions<Q: Querier>(
+        &self,
+        querier: &Q,
+        key: String,
+    ) -> StdResult<SubmissionsResponse> {
+        let msg = QueryMsg::GetSubmissions { key };
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: SubmissionsResponse = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
 }
@@ -1,3 +1,4 @@
+use margined_common::integer::Integer;
 use margined_perp::margined_vamm::{
     CalcFeeResponse, ConfigResponse, Direction, ExecuteMsg, QueryMsg, StateResponse,
 };
@@ -52,6 +53,9 @@ impl VammController {
             insurance_fund,
             pricefeed,
             spot_price_twap_interval,
+            max_funding_rate: None,
+            interest_rate: None,
+            clamp_band: None,
         };
         self.call(msg, vec![])
     }
@@ -72,6 +76,9 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
+            max_funding_rate: None,
+            interest_rate: None,
+            clamp_band: None,
         };
         self.call(msg, vec![])
     }
@@ -87,6 +94,9 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
+            max_funding_rate: None,
+            interest_rate: None,
+            clamp_band: None,
         };
         self.call(msg, vec![])
     }
@@ -105,6 +115,9 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
+            max_funding_rate: None,
+            interest_rate: None,
+            clamp_band: None,
         };
         self.call(msg, vec![])
     }
@@ -123,6 +136,9 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
+            max_funding_rate: None,
+            interest_rate: None,
+            clamp_band: None,
         };
         self.call(msg, vec![])
     }
@@ -141,6 +157,32 @@ impl VammController {
             insurance_fund: None,
             pricefeed: None,
             spot_price_twap_interval: None,
+            max_funding_rate: None,
+            interest_rate: None,
+            clamp_band: None,
+        };
+        self.call(msg, vec![])
+    }
+
+    pub fn set_funding_params(
+        &self,
+        max_funding_rate: Uint128,
+        interest_rate: Integer,
+        clamp_band: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::UpdateConfig {
+            base_asset_holding_cap: None,
+            open_interest_notional_cap: None,
+            toll_ratio: None,
+            spread_ratio: None,
+            fluctuation_limit_ratio: None,
+            margin_engine: None,
+            insurance_fund: None,
+            pricefeed: None,
+            spot_price_twap_interval: None,
+            max_funding_rate: Some(max_funding_rate),
+            interest_rate: Some(interest_rate),
+            clamp_band: Some(clamp_band),
         };
         self.call(msg, vec![])
     }
@@ -255,6 +297,19 @@ impl VammController {
         Ok(res)
     }
 
+    /// get underlying price provided by the pricefeed
+    pub fn underlying_price<Q: Querier>(&self, querier: &Q) -> StdResult<Uint128> {
+        let msg = QueryMsg::UnderlyingPrice {};
+        let query = WasmQuery::Smart {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg)?,
+        }
+        .into();
+
+        let res: Uint128 = QuerierWrapper::<Empty>::new(querier).query(&query)?;
+        Ok(res)
+    }
+
     /// get swap fees
     pub fn calc_fee<Q: Querier>(
         &self,
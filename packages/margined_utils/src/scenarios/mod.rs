@@ -1,12 +1,15 @@
 use crate::contracts::helpers::{
-    margined_engine::EngineController, margined_fee_pool::FeePoolController,
+    margined_engine::EngineController, margined_factory::FactoryController,
+    margined_fee_pool::FeePoolController, margined_governance::GovernanceController,
     margined_insurance_fund::InsuranceFundController, margined_pricefeed::PricefeedController,
     margined_vamm::VammController,
 };
 use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Empty, Response, Uint128};
 use cw20::{Cw20Coin, Cw20Contract, Cw20ExecuteMsg, MinterResponse};
 use margined_perp::margined_engine::{ExecuteMsg, InstantiateMsg, Side};
+use margined_perp::margined_factory::{InstantiateMsg as FactoryInstantiateMsg, VammTemplate};
 use margined_perp::margined_fee_pool::InstantiateMsg as FeePoolInstantiateMsg;
+use margined_perp::margined_governance::InstantiateMsg as GovernanceInstantiateMsg;
 use margined_perp::margined_insurance_fund::InstantiateMsg as InsuranceFundInstantiateMsg;
 use margined_perp::margined_pricefeed::{
     ExecuteMsg as PricefeedExecuteMsg, InstantiateMsg as PricefeedInstantiateMsg,
@@ -145,6 +148,7 @@ impl NativeTokenScenario {
                     maintenance_margin_ratio: None,
                     partial_liquidation_ratio: None,
                     liquidation_fee: None,
+                    keeper_fee: None,
                 },
                 &[],
             )
@@ -204,6 +208,9 @@ impl NativeTokenScenario {
                     insurance_fund: None,
                     pricefeed: None,
                     spot_price_twap_interval: None,
+                    max_funding_rate: None,
+                    interest_rate: None,
+                    clamp_band: None,
                 },
                 &[],
             )
@@ -416,6 +423,7 @@ impl SimpleScenario {
                     maintenance_margin_ratio: None,
                     partial_liquidation_ratio: None,
                     liquidation_fee: None,
+                    keeper_fee: None,
                 },
                 &[],
             )
@@ -487,6 +495,9 @@ impl SimpleScenario {
                     insurance_fund: None,
                     pricefeed: None,
                     spot_price_twap_interval: None,
+                    max_funding_rate: None,
+                    interest_rate: None,
+                    clamp_band: None,
                 },
                 &[],
             )
@@ -599,6 +610,114 @@ impl SimpleScenario {
             });
         }
     }
+
+    /// instantiates another vamm with the same parameters as the default one,
+    /// registered with the insurance fund and open for trading
+    pub fn add_vamm(&mut self) -> VammController {
+        let vamm_id = self.router.store_code(contract_vamm());
+
+        let vamm_addr = self
+            .router
+            .instantiate_contract(
+                vamm_id,
+                self.owner.clone(),
+                &VammInstantiateMsg {
+                    decimals: 9u8,
+                    quote_asset: "ETH".to_string(),
+                    base_asset: "USD".to_string(),
+                    quote_asset_reserve: to_decimals(1_000),
+                    base_asset_reserve: to_decimals(100),
+                    funding_period: 86_400_u64,
+                    toll_ratio: Uint128::zero(),
+                    spread_ratio: Uint128::zero(),
+                    fluctuation_limit_ratio: Uint128::zero(),
+                    pricefeed: self.pricefeed.addr().to_string(),
+                    margin_engine: Some(self.engine.addr().to_string()),
+                    insurance_fund: Some(self.insurance_fund.addr().to_string()),
+                },
+                &[],
+                "vamm",
+                None,
+            )
+            .unwrap();
+        let vamm = VammController(vamm_addr);
+
+        let msg = vamm.set_open(true).unwrap();
+        self.router.execute(self.owner.clone(), msg).unwrap();
+
+        let msg = self
+            .insurance_fund
+            .add_vamm(vamm.addr().to_string())
+            .unwrap();
+        self.router.execute(self.owner.clone(), msg).unwrap();
+
+        vamm
+    }
+
+    /// instantiates another cw20 token, alice and bob are each given 1000 tokens
+    pub fn add_token(&mut self, symbol: &str, decimals: u8) -> Cw20Contract {
+        let token_id = self.router.store_code(contract_cw20());
+        let amount = Uint128::from(1_000u128 * 10u128.pow(decimals as u32));
+
+        let token_addr = self
+            .router
+            .instantiate_contract(
+                token_id,
+                self.owner.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: symbol.to_string(),
+                    symbol: symbol.to_string(),
+                    decimals,
+                    initial_balances: vec![
+                        Cw20Coin {
+                            address: self.alice.to_string(),
+                            amount,
+                        },
+                        Cw20Coin {
+                            address: self.bob.to_string(),
+                            amount,
+                        },
+                    ],
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "cw20",
+                None,
+            )
+            .unwrap();
+
+        Cw20Contract(token_addr)
+    }
+
+    /// instantiates another mock pricefeed, as the mock returns the same price for
+    /// every key each asset priced independently needs its own
+    pub fn add_pricefeed(&mut self, key: &str, price: Uint128) -> PricefeedController {
+        let pricefeed_id = self.router.store_code(contract_mock_pricefeed());
+
+        let pricefeed_addr = self
+            .router
+            .instantiate_contract(
+                pricefeed_id,
+                self.owner.clone(),
+                &PricefeedInstantiateMsg {
+                    oracle_hub_contract: "oracle_hub0000".to_string(),
+                },
+                &[],
+                "pricefeed",
+                None,
+            )
+            .unwrap();
+        let pricefeed = PricefeedController(pricefeed_addr);
+
+        let timestamp = self.router.block_info().time.seconds();
+        let msg = pricefeed
+            .append_price(key.to_string(), price, timestamp)
+            .unwrap();
+        self.router.execute(self.owner.clone(), msg).unwrap();
+
+        pricefeed
+    }
 }
 
 impl Default for SimpleScenario {
@@ -954,6 +1073,464 @@ impl Default for ShutdownScenario {
     }
 }
 
+pub struct OracleScenario {
+    pub router: App,
+    pub owner: Addr,
+    pub oracle: Addr,
+    pub vamm: VammController,
+    pub pricefeed: PricefeedController,
+}
+
+impl OracleScenario {
+    pub fn new() -> Self {
+        let mut router = AppBuilder::new().build(|_router, _, _storage| {});
+
+        let owner = Addr::unchecked("owner");
+
+        let oracle_id = router.store_code(contract_mock_oracle());
+        let pricefeed_id = router.store_code(contract_pricefeed());
+        let vamm_id = router.store_code(contract_vamm());
+
+        let oracle = router
+            .instantiate_contract(
+                oracle_id,
+                owner.clone(),
+                &mock_oracle::contract::InstantiateMsg {},
+                &[],
+                "oracle",
+                None,
+            )
+            .unwrap();
+
+        let pricefeed_addr = router
+            .instantiate_contract(
+                pricefeed_id,
+                owner.clone(),
+                &PricefeedInstantiateMsg {
+                    oracle_hub_contract: oracle.to_string(),
+                },
+                &[],
+                "pricefeed",
+                None,
+            )
+            .unwrap();
+        let pricefeed = PricefeedController(pricefeed_addr.clone());
+
+        let vamm_addr = router
+            .instantiate_contract(
+                vamm_id,
+                owner.clone(),
+                &VammInstantiateMsg {
+                    decimals: 9u8,
+                    quote_asset: "USD".to_string(),
+                    base_asset: "ETH".to_string(),
+                    quote_asset_reserve: to_decimals(1_000),
+                    base_asset_reserve: to_decimals(100),
+                    funding_period: 3_600_u64,
+                    toll_ratio: Uint128::zero(),
+                    spread_ratio: Uint128::zero(),
+                    fluctuation_limit_ratio: Uint128::zero(),
+                    pricefeed: pricefeed_addr.to_string(),
+                    margin_engine: Some(owner.to_string()),
+                    insurance_fund: Some("insurance_fund".to_string()),
+                },
+                &[],
+                "vamm",
+                None,
+            )
+            .unwrap();
+        let vamm = VammController(vamm_addr);
+
+        Self {
+            router,
+            owner,
+            oracle,
+            vamm,
+            pricefeed,
+        }
+    }
+}
+
+impl Default for OracleScenario {
+    fn default() -> Self {
+        OracleScenario::new()
+    }
+}
+
+pub struct FactoryScenario {
+    pub router: App,
+    pub owner: Addr,
+    pub engine: EngineController,
+    pub insurance_fund: InsuranceFundController,
+    pub pricefeed: PricefeedController,
+    pub factory: FactoryController,
+}
+
+impl FactoryScenario {
+    pub fn new() -> Self {
+        let mut router = AppBuilder::new().build(|_router, _, _storage| {});
+
+        let owner = Addr::unchecked("owner");
+
+        let fee_pool_id = router.store_code(contract_fee_pool());
+        let usdc_id = router.store_code(contract_cw20());
+        let engine_id = router.store_code(contract_engine());
+        let vamm_id = router.store_code(contract_vamm());
+        let insurance_fund_id = router.store_code(contract_insurance_fund());
+        let pricefeed_id = router.store_code(contract_mock_pricefeed());
+        let factory_id = router.store_code(contract_factory());
+
+        let fee_pool_addr = router
+            .instantiate_contract(
+                fee_pool_id,
+                owner.clone(),
+                &FeePoolInstantiateMsg {},
+                &[],
+                "fee_pool",
+                None,
+            )
+            .unwrap();
+
+        let usdc_addr = router
+            .instantiate_contract(
+                usdc_id,
+                owner.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "USDC".to_string(),
+                    symbol: "USDC".to_string(),
+                    decimals: 9,
+                    initial_balances: vec![],
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "cw20",
+                None,
+            )
+            .unwrap();
+
+        let engine_addr = router
+            .instantiate_contract(
+                engine_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    pauser: owner.to_string(),
+                    insurance_fund: "insurance_fund".to_string(),
+                    fee_pool: fee_pool_addr.to_string(),
+                    eligible_collateral: usdc_addr.to_string(),
+                    initial_margin_ratio: Uint128::from(50_000_000u128), // 0.05
+                    maintenance_margin_ratio: Uint128::from(50_000_000u128), // 0.05
+                    liquidation_fee: Uint128::from(50_000_000u128),      // 0.05
+                },
+                &[],
+                "engine",
+                None,
+            )
+            .unwrap();
+        let engine = EngineController(engine_addr.clone());
+
+        let insurance_fund_addr = router
+            .instantiate_contract(
+                insurance_fund_id,
+                owner.clone(),
+                &InsuranceFundInstantiateMsg {
+                    engine: engine_addr.to_string(),
+                },
+                &[],
+                "insurance_fund",
+                None,
+            )
+            .unwrap();
+        let insurance_fund = InsuranceFundController(insurance_fund_addr.clone());
+
+        let pricefeed_addr = router
+            .instantiate_contract(
+                pricefeed_id,
+                owner.clone(),
+                &PricefeedInstantiateMsg {
+                    oracle_hub_contract: "oracle_hub0000".to_string(),
+                },
+                &[],
+                "pricefeed",
+                None,
+            )
+            .unwrap();
+        let pricefeed = PricefeedController(pricefeed_addr.clone());
+
+        let factory_addr = router
+            .instantiate_contract(
+                factory_id,
+                owner.clone(),
+                &FactoryInstantiateMsg {
+                    vamm_code_id: vamm_id,
+                    engine: engine_addr.to_string(),
+                    insurance_fund: insurance_fund_addr.to_string(),
+                    pricefeed: pricefeed_addr.to_string(),
+                    template: VammTemplate {
+                        decimals: 9u8,
+                        quote_asset: "USD".to_string(),
+                        funding_period: 3_600_u64,
+                        toll_ratio: Uint128::zero(),
+                        spread_ratio: Uint128::zero(),
+                        fluctuation_limit_ratio: Uint128::zero(),
+                    },
+                },
+                &[],
+                "factory",
+                None,
+            )
+            .unwrap();
+        let factory = FactoryController(factory_addr.clone());
+
+        // allow the factory to register vamms in the insurance fund
+        let msg = insurance_fund
+            .set_factory(Some(factory_addr.to_string()))
+            .unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+
+        Self {
+            router,
+            owner,
+            engine,
+            insurance_fund,
+            pricefeed,
+            factory,
+        }
+    }
+}
+
+impl Default for FactoryScenario {
+    fn default() -> Self {
+        FactoryScenario::new()
+    }
+}
+
+pub struct GovernanceScenario {
+    pub router: App,
+    pub owner: Addr,
+    pub alice: Addr,
+    pub bob: Addr,
+    pub carol: Addr,
+    pub mrg: Cw20Contract,
+    pub governance: GovernanceController,
+    pub fee_pool: FeePoolController,
+}
+
+impl GovernanceScenario {
+    pub fn new() -> Self {
+        let mut router = AppBuilder::new().build(|_router, _, _storage| {});
+
+        let owner = Addr::unchecked("owner");
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+        let carol = Addr::unchecked("carol");
+
+        let mrg_id = router.store_code(contract_cw20());
+        let governance_id = router.store_code(contract_governance());
+        let fee_pool_id = router.store_code(contract_fee_pool());
+
+        let mrg_addr = router
+            .instantiate_contract(
+                mrg_id,
+                owner.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "Margined".to_string(),
+                    symbol: "MRG".to_string(),
+                    decimals: 9,
+                    initial_balances: vec![
+                        Cw20Coin {
+                            address: alice.to_string(),
+                            amount: to_decimals(5_000),
+                        },
+                        Cw20Coin {
+                            address: bob.to_string(),
+                            amount: to_decimals(3_000),
+                        },
+                        Cw20Coin {
+                            address: carol.to_string(),
+                            amount: to_decimals(2_000),
+                        },
+                    ],
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "mrg",
+                None,
+            )
+            .unwrap();
+        let mrg = Cw20Contract(mrg_addr.clone());
+
+        let governance_addr = router
+            .instantiate_contract(
+                governance_id,
+                owner.clone(),
+                &GovernanceInstantiateMsg {
+                    staking_token: mrg_addr.to_string(),
+                    quorum: Uint128::from(400_000u128),    // 0.4
+                    threshold: Uint128::from(500_000u128), // 0.5
+                    voting_period: 86_400u64,
+                    timelock_period: 3_600u64,
+                    proposal_min_stake: to_decimals(1_000),
+                },
+                &[],
+                "governance",
+                None,
+            )
+            .unwrap();
+        let governance = GovernanceController(governance_addr.clone());
+
+        let fee_pool_addr = router
+            .instantiate_contract(
+                fee_pool_id,
+                owner.clone(),
+                &FeePoolInstantiateMsg {},
+                &[],
+                "fee_pool",
+                None,
+            )
+            .unwrap();
+        let fee_pool = FeePoolController(fee_pool_addr);
+
+        // hand the fee pool over to governance
+        let msg = fee_pool.update_owner(governance_addr.to_string()).unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+
+        Self {
+            router,
+            owner,
+            alice,
+            bob,
+            carol,
+            mrg,
+            governance,
+            fee_pool,
+        }
+    }
+}
+
+impl Default for GovernanceScenario {
+    fn default() -> Self {
+        GovernanceScenario::new()
+    }
+}
+
+pub struct FeeStakingScenario {
+    pub router: App,
+    pub owner: Addr,
+    pub alice: Addr,
+    pub bob: Addr,
+    pub carol: Addr,
+    pub mrg: Cw20Contract,
+    pub usdc: Cw20Contract,
+    pub fee_pool: FeePoolController,
+}
+
+impl FeeStakingScenario {
+    pub fn new() -> Self {
+        let mut router = AppBuilder::new().build(|_router, _, _storage| {});
+
+        let owner = Addr::unchecked("owner");
+        let alice = Addr::unchecked("alice");
+        let bob = Addr::unchecked("bob");
+        let carol = Addr::unchecked("carol");
+
+        let cw20_id = router.store_code(contract_cw20());
+        let fee_pool_id = router.store_code(contract_fee_pool());
+
+        let mrg_addr = router
+            .instantiate_contract(
+                cw20_id,
+                owner.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "Margined".to_string(),
+                    symbol: "MRG".to_string(),
+                    decimals: 9,
+                    initial_balances: vec![
+                        Cw20Coin {
+                            address: alice.to_string(),
+                            amount: to_decimals(5_000),
+                        },
+                        Cw20Coin {
+                            address: bob.to_string(),
+                            amount: to_decimals(5_000),
+                        },
+                        Cw20Coin {
+                            address: carol.to_string(),
+                            amount: to_decimals(5_000),
+                        },
+                    ],
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "mrg",
+                None,
+            )
+            .unwrap();
+        let mrg = Cw20Contract(mrg_addr.clone());
+
+        let usdc_addr = router
+            .instantiate_contract(
+                cw20_id,
+                owner.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "USDC".to_string(),
+                    symbol: "USDC".to_string(),
+                    decimals: 9,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: owner.to_string(),
+                        cap: None,
+                    }),
+                    marketing: None,
+                },
+                &[],
+                "usdc",
+                None,
+            )
+            .unwrap();
+        let usdc = Cw20Contract(usdc_addr.clone());
+
+        let fee_pool_addr = router
+            .instantiate_contract(
+                fee_pool_id,
+                owner.clone(),
+                &FeePoolInstantiateMsg {},
+                &[],
+                "fee_pool",
+                None,
+            )
+            .unwrap();
+        let fee_pool = FeePoolController(fee_pool_addr);
+
+        let msg = fee_pool
+            .update_config(Some(mrg_addr.to_string()), Some(86_400u64))
+            .unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+
+        let msg = fee_pool.add_token(usdc_addr.to_string()).unwrap();
+        router.execute(owner.clone(), msg).unwrap();
+
+        Self {
+            router,
+            owner,
+            alice,
+            bob,
+            carol,
+            mrg,
+            usdc,
+            fee_pool,
+        }
+    }
+}
+
+impl Default for FeeStakingScenario {
+    fn default() -> Self {
+        FeeStakingScenario::new()
+    }
+}
+
 pub const DECIMAL_MULTIPLIER: Uint128 = Uint128::new(1_000_000_000);
 
 fn contract_cw20() -> Box<dyn Contract<Empty>> {
@@ -970,7 +1547,8 @@ fn contract_vamm() -> Box<dyn Contract<Empty>> {
         margined_vamm::contract::execute,
         margined_vamm::contract::instantiate,
         margined_vamm::contract::query,
-    );
+    )
+    .with_migrate(margined_vamm::contract::migrate);
     Box::new(contract)
 }
 
@@ -979,7 +1557,8 @@ fn contract_insurance_fund() -> Box<dyn Contract<Empty>> {
         margined_insurance_fund::contract::execute,
         margined_insurance_fund::contract::instantiate,
         margined_insurance_fund::contract::query,
-    );
+    )
+    .with_migrate(margined_insurance_fund::contract::migrate);
     Box::new(contract)
 }
 
@@ -988,7 +1567,8 @@ fn contract_fee_pool() -> Box<dyn Contract<Empty>> {
         margined_fee_pool::contract::execute,
         margined_fee_pool::contract::instantiate,
         margined_fee_pool::contract::query,
-    );
+    )
+    .with_migrate(margined_fee_pool::contract::migrate);
     Box::new(contract)
 }
 
@@ -998,16 +1578,47 @@ fn contract_engine() -> Box<dyn Contract<Empty>> {
         margined_engine::contract::instantiate,
         margined_engine::contract::query,
     )
-    .with_reply(margined_engine::contract::reply);
+    .with_reply(margined_engine::contract::reply)
+    .with_migrate(margined_engine::contract::migrate);
     Box::new(contract)
 }
 
-// note this is unused as it will take a lot of work to have the pricefeed full of data
-fn _contract_pricefeed() -> Box<dyn Contract<Empty>> {
+fn contract_factory() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new_with_empty(
+        margined_factory::contract::execute,
+        margined_factory::contract::instantiate,
+        margined_factory::contract::query,
+    )
+    .with_reply(margined_factory::contract::reply)
+    .with_migrate(margined_factory::contract::migrate);
+    Box::new(contract)
+}
+
+fn contract_governance() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new_with_empty(
+        margined_governance::contract::execute,
+        margined_governance::contract::instantiate,
+        margined_governance::contract::query,
+    )
+    .with_migrate(margined_governance::contract::migrate);
+    Box::new(contract)
+}
+
+fn contract_pricefeed() -> Box<dyn Contract<Empty>> {
     let contract = ContractWrapper::new_with_empty(
         margined_pricefeed::contract::execute,
         margined_pricefeed::contract::instantiate,
         margined_pricefeed::contract::query,
+    )
+    .with_migrate(margined_pricefeed::contract::migrate);
+    Box::new(contract)
+}
+
+fn contract_mock_oracle() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new_with_empty(
+        mock_oracle::contract::execute,
+        mock_oracle::contract::instantiate,
+        mock_oracle::contract::query,
     );
     Box::new(contract)
 }